@@ -5,6 +5,30 @@ use std::collections::HashMap;
 use std::pin::Pin;
 use futures::Stream;
 
+/// Split an assistant message into chat entries: the prose as an assistant
+/// entry carrying `tool_calls`, followed by a `ToolCall` entry for each call.
+/// The call entries are linked to the prose entry by tool call id.
+pub fn assistant_message_entries(message: &GrokMessage) -> Vec<ChatEntry> {
+    let tool_calls = message.tool_calls.clone().filter(|calls| !calls.is_empty());
+    let content = match message.content.as_deref().map(str::trim) {
+        Some(text) if !text.is_empty() => text.to_string(),
+        _ if tool_calls.is_some() => "Using tools to help you...".to_string(),
+        _ => String::new(),
+    };
+
+    let mut entries = vec![ChatEntry {
+        entry_type: ChatEntryType::Assistant,
+        content,
+        timestamp: chrono::Utc::now(),
+        tool_calls: tool_calls.clone(),
+        tool_call: None,
+        tool_result: None,
+        is_streaming: None,
+    }];
+    entries.extend(tool_calls.iter().flatten().map(ChatEntry::tool_call_entry));
+    entries
+}
+
 #[derive(Clone)]
 pub struct GrokAgent {
     grok_client: GrokClient,
@@ -134,6 +158,11 @@ Current working directory: ".to_string() + &std::env::current_dir()?.to_string_l
         })
     }
 
+    /// Permit file tools to write outside the project root for this session
+    pub fn set_allow_outside(&mut self, allow: bool) {
+        self.text_editor.set_allow_outside(allow);
+    }
+
     pub async fn process_user_message(&mut self, message: &str) -> Result<Vec<ChatEntry>, Box<dyn std::error::Error>> {
         // Add user message to conversation
         let user_entry = ChatEntry {
@@ -261,18 +290,11 @@ Current working directory: ".to_string() + &std::env::current_dir()?.to_string_l
                     last_tool_signature = current_signature;
                 }
 
-                // Add assistant message with tool calls
-                let assistant_entry = ChatEntry {
-                    entry_type: ChatEntryType::Assistant,
-                    content: assistant_message.content.clone().unwrap_or_else(|| "Using tools to help you...".to_string()),
-                    timestamp: chrono::Utc::now(),
-                    tool_calls: Some(tool_calls.clone()),
-                    tool_call: None,
-                    tool_result: None,
-                    is_streaming: None,
-                };
-                self.chat_history.push(assistant_entry.clone());
-                new_entries.push(assistant_entry);
+                // Add the assistant's prose followed by one entry per tool call
+                for entry in assistant_message_entries(assistant_message) {
+                    self.chat_history.push(entry.clone());
+                    new_entries.push(entry);
+                }
 
                 // Add assistant message to conversation
                 self.messages.push(assistant_message.clone());
@@ -404,8 +426,9 @@ Current working directory: ".to_string() + &std::env::current_dir()?.to_string_l
             "create_file" => {
                 let path = args.get("path").and_then(|v| v.as_str()).ok_or("Missing 'path' argument")?;
                 let content = args.get("content").and_then(|v| v.as_str()).ok_or("Missing 'content' argument")?;
+                let overwrite = args.get("overwrite").and_then(|v| v.as_bool()).unwrap_or(false);
 
-                match self.text_editor.create(path, content).await {
+                match self.text_editor.create(path, content, overwrite).await {
                     Ok(result) => Ok(result),
                     Err(e) => Ok(ToolResult {
                         success: false,
@@ -640,6 +663,10 @@ Current working directory: ".to_string() + &std::env::current_dir()?.to_string_l
                                 "type": "string",
                                 "description": "Content to write to the file"
                             }));
+                            props.insert("overwrite".to_string(), serde_json::json!({
+                                "type": "boolean",
+                                "description": "Replace the file if it already exists (default: false)"
+                            }));
                             props
                         },
                         required: vec!["path".to_string(), "content".to_string()],
@@ -798,7 +825,7 @@ Current working directory: ".to_string() + &std::env::current_dir()?.to_string_l
                             }));
                             props.insert("regex".to_string(), serde_json::json!({
                                 "type": "boolean",
-                                "description": "Whether to treat query as a regular expression",
+                                "description": "Treat query as a regular expression (default false: query is matched literally)",
                                 "default": false
                             }));
                             props.insert("max_results".to_string(), serde_json::json!({
@@ -1073,4 +1100,71 @@ Current working directory: ".to_string() + &std::env::current_dir()?.to_string_l
     pub fn get_chat_history(&self) -> &Vec<ChatEntry> {
         &self.chat_history
     }
-}
\ No newline at end of file
+}
+
+impl crate::commands::commit::CommitMessageGenerator for GrokAgent {
+    /// One-off completion outside the conversation history
+    async fn generate_commit_message(&self, diff: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let prompt = crate::commands::commit::commit_message_prompt(diff);
+        let response = self.grok_client.chat(
+            vec![GrokMessage {
+                role: "user".to_string(),
+                content: Some(prompt),
+                tool_calls: None,
+                tool_call_id: None,
+            }],
+            None,
+            None,
+            None,
+        ).await?;
+
+        response.choices.first()
+            .and_then(|choice| choice.message.content.clone())
+            .ok_or_else(|| "Model returned no commit message".into())
+    }
+}
+
+#[cfg(test)]
+mod message_entry_tests {
+    use super::*;
+
+    fn call(id: &str, name: &str, arguments: &str) -> GrokToolCall {
+        GrokToolCall {
+            id: id.to_string(),
+            call_type: "function".to_string(),
+            function: GrokToolCallFunction {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_prose_and_tool_calls_are_all_kept() {
+        let message = GrokMessage {
+            role: "assistant".to_string(),
+            content: Some("Let me look at the manifest and run the tests.".to_string()),
+            tool_calls: Some(vec![
+                call("call_1", "view_file", r#"{"path":"Cargo.toml"}"#),
+                call("call_2", "bash", r#"{"command":"cargo test"}"#),
+            ]),
+            tool_call_id: None,
+        };
+
+        let entries = assistant_message_entries(&message);
+        assert_eq!(entries.len(), 3);
+
+        let prose = &entries[0];
+        assert_eq!(prose.entry_type, ChatEntryType::Assistant);
+        assert_eq!(prose.content, "Let me look at the manifest and run the tests.");
+
+        for (entry, name) in entries[1..].iter().zip(["view_file", "bash"]) {
+            assert_eq!(entry.entry_type, ChatEntryType::ToolCall);
+            assert_eq!(entry.tool_call.as_ref().unwrap().function.name, name);
+            assert!(entry.belongs_to(prose));
+        }
+
+        let unrelated = ChatEntry::tool_call_entry(&call("call_9", "bash", "{}"));
+        assert!(!unrelated.belongs_to(prose));
+    }
+}
@@ -0,0 +1,185 @@
+//! Implementation of the `/commit-and-push` chat command.
+//!
+//! The flow is split in two so the UI can ask for confirmation in between:
+//! [`commit_all`] stages everything, asks the model for a commit message and
+//! commits; [`push`] is only run once the user has confirmed.
+
+use std::path::Path;
+use std::process::Command;
+
+/// Diffs larger than this are truncated before being sent to the model
+const MAX_DIFF_CHARS: usize = 12_000;
+
+/// Produces a commit message for a staged diff
+pub trait CommitMessageGenerator {
+    async fn generate_commit_message(&self, diff: &str) -> Result<String, Box<dyn std::error::Error>>;
+}
+
+/// Outcome of the staging + commit half of `/commit-and-push`
+#[derive(Debug, Clone)]
+pub struct CommitReport {
+    pub message: String,
+    pub steps: Vec<String>,
+}
+
+impl CommitReport {
+    pub fn summary(&self) -> String {
+        self.steps.join("\n")
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<std::process::Output, Box<dyn std::error::Error>> {
+    Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e).into())
+}
+
+fn git_checked(dir: &Path, args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    let output = git(dir, args)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git {} failed: {}", args.join(" "), stderr.trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Build the prompt sent to the model for a staged diff
+pub fn commit_message_prompt(diff: &str) -> String {
+    let diff = if diff.len() > MAX_DIFF_CHARS {
+        let mut end = MAX_DIFF_CHARS;
+        while !diff.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!("{}\n... (diff truncated)", &diff[..end])
+    } else {
+        diff.to_string()
+    };
+
+    format!(
+        "Write a concise git commit message for the following staged changes. \
+        Use a short imperative subject line (max 72 characters), optionally followed by \
+        a blank line and a brief body. Respond with the commit message only.\n\n{}",
+        diff
+    )
+}
+
+/// Strip code fences and surrounding quotes the model sometimes adds
+fn clean_commit_message(message: &str) -> String {
+    message
+        .trim()
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim()
+        .trim_matches('"')
+        .trim()
+        .to_string()
+}
+
+/// Stage all changes, generate a commit message and commit. Does not push.
+pub async fn commit_all<G: CommitMessageGenerator>(
+    dir: &Path,
+    generator: &G,
+) -> Result<CommitReport, Box<dyn std::error::Error>> {
+    let inside = git(dir, &["rev-parse", "--is-inside-work-tree"])?;
+    if !inside.status.success() {
+        return Err(format!("{} is not a git repository", dir.display()).into());
+    }
+
+    let mut steps = Vec::new();
+
+    git_checked(dir, &["add", "-A"])?;
+    steps.push("✓ Staged all changes (git add -A)".to_string());
+
+    let staged = git(dir, &["diff", "--cached", "--quiet"])?;
+    if staged.status.success() {
+        return Err("No changes to commit".into());
+    }
+
+    let diff = git_checked(dir, &["diff", "--cached"])?;
+    let message = clean_commit_message(&generator.generate_commit_message(&diff).await?);
+    if message.is_empty() {
+        return Err("Generated commit message was empty".into());
+    }
+    steps.push(format!("✓ Generated commit message:\n{}", message));
+
+    git_checked(dir, &["commit", "-m", &message])?;
+    let hash = git_checked(dir, &["rev-parse", "--short", "HEAD"])?;
+    steps.push(format!("✓ Committed {}", hash));
+
+    Ok(CommitReport { message, steps })
+}
+
+/// Push the current branch to its remote
+pub fn push(dir: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let output = git(dir, &["push"])?;
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if !output.status.success() {
+        return Err(format!("git push failed: {}", stderr).into());
+    }
+    // git reports push progress on stderr
+    Ok(format!("✓ Pushed to remote\n{}", stderr).trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeGenerator;
+
+    impl CommitMessageGenerator for FakeGenerator {
+        async fn generate_commit_message(&self, diff: &str) -> Result<String, Box<dyn std::error::Error>> {
+            assert!(diff.contains("hello.txt"));
+            Ok("```\nAdd hello file\n```".to_string())
+        }
+    }
+
+    fn temp_repo(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "grok_commit_{}_{}",
+            name,
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        git_checked(&dir, &["init", "-q"]).unwrap();
+        git_checked(&dir, &["config", "user.name", "Test"]).unwrap();
+        git_checked(&dir, &["config", "user.email", "test@example.com"]).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_commit_all_uses_generated_message() {
+        let dir = temp_repo("commit");
+        std::fs::write(dir.join("hello.txt"), "hello").unwrap();
+
+        let report = commit_all(&dir, &FakeGenerator).await.unwrap();
+        assert_eq!(report.message, "Add hello file");
+
+        let subject = git_checked(&dir, &["log", "-1", "--format=%s"]).unwrap();
+        assert_eq!(subject, "Add hello file");
+
+        // Nothing left to commit
+        let err = commit_all(&dir, &FakeGenerator).await.unwrap_err();
+        assert!(err.to_string().contains("No changes"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_commit_all_rejects_non_git_directory() {
+        let dir = std::env::temp_dir().join("grok_commit_not_a_repo");
+        std::fs::create_dir_all(&dir).unwrap();
+        // Guard against temp_dir itself living inside a repository
+        if git(&dir, &["rev-parse", "--is-inside-work-tree"]).unwrap().status.success() {
+            return;
+        }
+
+        let err = commit_all(&dir, &FakeGenerator).await.unwrap_err();
+        assert!(err.to_string().contains("not a git repository"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
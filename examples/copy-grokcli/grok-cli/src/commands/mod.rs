@@ -1,3 +1,5 @@
+pub mod commit;
+
 pub mod mcp {
     use clap::Subcommand;
     use serde::{Deserialize, Serialize};
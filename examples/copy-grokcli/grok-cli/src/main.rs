@@ -47,10 +47,18 @@ struct CliArgs {
     #[arg(long = "prompt")]
     prompt: Option<String>,
 
+    /// Output format for headless mode: json (JSON lines, default) or text
+    #[arg(long = "output", value_enum, default_value = "json")]
+    output: utils::output_sink::OutputFormat,
+
     /// Maximum number of tool execution rounds (default: 400)
     #[arg(long = "max-tool-rounds", default_value = "400")]
     max_tool_rounds: u32,
 
+    /// Allow file tools to modify files outside the working directory
+    #[arg(long = "allow-outside")]
+    allow_outside: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -119,19 +127,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
 
         let mut agent = agent::GrokAgent::new(&api_key, base_url, model, Some(args.max_tool_rounds), is_openai_compatible).await?;
+        agent.set_allow_outside(args.allow_outside);
 
         // Process the prompt
         let chat_entries = agent.process_user_message(&prompt).await?;
 
         // Output results
-        for entry in chat_entries {
-            println!("{}", serde_json::to_string(&entry)?);
+        let mut sink = utils::output_sink::stdout_sink(args.output);
+        for entry in &chat_entries {
+            sink.emit(entry)?;
         }
+        sink.finish()?;
     } else {
         // Interactive mode: launch UI
         println!("🤖 Starting Grok CLI Conversational Assistant...\n");
 
-        let agent = agent::GrokAgent::new(&api_key, base_url, model, Some(args.max_tool_rounds), is_openai_compatible).await?;
+        let mut agent = agent::GrokAgent::new(&api_key, base_url, model, Some(args.max_tool_rounds), is_openai_compatible).await?;
+        agent.set_allow_outside(args.allow_outside);
         let initial_message = args.message.join(" ");
 
         ui::run_app(agent, initial_message).await?;
@@ -1,8 +1,7 @@
 use crate::types::{EditorCommand, EditorCommandType, ToolResult};
-use std::process::Command;
 use serde::{Deserialize, Serialize};
 use tokio::fs;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TodoItem {
@@ -180,16 +179,76 @@ pub struct TodoUpdate {
     pub priority: Option<String>,
 }
 
+/// Lines longer than this (in chars) are truncated in `view` output
+pub const MAX_VIEW_LINE_CHARS: usize = 2000;
+
+/// Number a line for display, cutting it at `MAX_VIEW_LINE_CHARS` with a
+/// `…(N chars)` marker giving how many chars were left out
+fn format_view_line(number: usize, line: &str, truncated: &mut usize) -> String {
+    match line.char_indices().nth(MAX_VIEW_LINE_CHARS) {
+        Some((cut, _)) => {
+            *truncated += 1;
+            format!("{}: {}…({} chars)", number, &line[..cut], line[cut..].chars().count())
+        }
+        None => format!("{}: {}", number, line),
+    }
+}
+
+/// Warning appended to `view` output when long lines were shortened
+fn truncation_note(truncated: usize) -> String {
+    if truncated == 0 {
+        String::new()
+    } else {
+        format!(
+            "\n[{} line(s) longer than {} chars truncated for display; the file itself is unchanged]",
+            truncated, MAX_VIEW_LINE_CHARS
+        )
+    }
+}
+
 #[derive(Clone)]
 pub struct TextEditorTool {
     edit_history: Vec<EditorCommand>,
+    project_root: PathBuf,
+    allow_outside: bool,
 }
 
 impl TextEditorTool {
     pub fn new() -> Self {
+        let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
         Self {
             edit_history: Vec::new(),
+            project_root: cwd.canonicalize().unwrap_or(cwd),
+            allow_outside: false,
+        }
+    }
+
+    pub fn with_project_root(mut self, root: impl AsRef<Path>) -> Self {
+        self.project_root = resolve_path(root.as_ref());
+        self
+    }
+
+    /// Allow writes outside the project root (the --allow-outside flag)
+    pub fn set_allow_outside(&mut self, allow: bool) {
+        self.allow_outside = allow;
+    }
+
+    /// Returns a failing ToolResult if `file_path` resolves outside the project root
+    fn check_confined(&self, file_path: &str) -> Option<ToolResult> {
+        if self.allow_outside || resolve_path(Path::new(file_path)).starts_with(&self.project_root) {
+            return None;
         }
+
+        Some(ToolResult {
+            success: false,
+            output: None,
+            error: Some(format!(
+                "Refusing to modify {}: path is outside the project root {} (use --allow-outside to permit)",
+                file_path,
+                self.project_root.display()
+            )),
+            data: None,
+        })
     }
 
     pub async fn view(&self, file_path: &str, view_range: Option<(usize, usize)>) -> Result<ToolResult, Box<dyn std::error::Error>> {
@@ -218,15 +277,16 @@ impl TextEditorTool {
                 let lines: Vec<&str> = content.lines().collect();
                 if start > 0 && end <= lines.len() {
                     let selected_lines: Vec<&str> = lines[start - 1..end].to_vec();
+                    let mut truncated = 0;
                     let numbered_lines: Vec<String> = selected_lines
                         .iter()
                         .enumerate()
-                        .map(|(idx, line)| format!("{}: {}", start + idx, line))
+                        .map(|(idx, line)| format_view_line(start + idx, line, &mut truncated))
                         .collect();
 
                     Ok(ToolResult {
                         success: true,
-                        output: Some(format!("Lines {}-{} of {}:\n{}", start, end, file_path, numbered_lines.join("\n"))),
+                        output: Some(format!("Lines {}-{} of {}:\n{}{}", start, end, file_path, numbered_lines.join("\n"), truncation_note(truncated))),
                         error: None,
                         data: None,
                     })
@@ -247,10 +307,11 @@ impl TextEditorTool {
                 } else {
                     &lines
                 };
+                let mut truncated = 0;
                 let numbered_lines: Vec<String> = display_lines
                     .iter()
                     .enumerate()
-                    .map(|(idx, line)| format!("{}: {}", idx + 1, line))
+                    .map(|(idx, line)| format_view_line(idx + 1, line, &mut truncated))
                     .collect();
 
                 let additional_message = if total_lines > 10 {
@@ -261,7 +322,7 @@ impl TextEditorTool {
 
                 Ok(ToolResult {
                     success: true,
-                    output: Some(format!("Contents of {}:\n{}{}", file_path, numbered_lines.join("\n"), additional_message)),
+                    output: Some(format!("Contents of {}:\n{}{}{}", file_path, numbered_lines.join("\n"), additional_message, truncation_note(truncated))),
                     error: None,
                     data: None,
                 })
@@ -276,6 +337,10 @@ impl TextEditorTool {
         new_str: &str,
         replace_all: bool,
     ) -> Result<ToolResult, Box<dyn std::error::Error>> {
+        if let Some(blocked) = self.check_confined(file_path) {
+            return Ok(blocked);
+        }
+
         let resolved_path = std::path::Path::new(file_path).canonicalize()?;
 
         if !resolved_path.exists() {
@@ -324,18 +389,81 @@ impl TextEditorTool {
         })
     }
 
-    pub async fn create(&mut self, file_path: &str, content: &str) -> Result<ToolResult, Box<dyn std::error::Error>> {
+    /// Create a file. An existing file is only replaced when `overwrite` is set,
+    /// in which case its original content is kept so `undo_edit` can restore it.
+    pub async fn create(&mut self, file_path: &str, content: &str, overwrite: bool) -> Result<ToolResult, Box<dyn std::error::Error>> {
+        if let Some(blocked) = self.check_confined(file_path) {
+            return Ok(blocked);
+        }
+
         let path = Path::new(file_path);
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent).await?;
+
+        let original = if path.exists() {
+            if !overwrite {
+                return Ok(ToolResult {
+                    success: false,
+                    output: None,
+                    error: Some(format!(
+                        "File already exists: {}. Use str_replace_editor to edit it, or pass overwrite: true to replace it",
+                        file_path
+                    )),
+                    data: None,
+                });
+            }
+            match fs::read_to_string(path).await {
+                Ok(original) => Some(original),
+                Err(e) => {
+                    return Ok(ToolResult {
+                        success: false,
+                        output: None,
+                        error: Some(format!("Couldn't back up existing file {}: {}", file_path, e)),
+                        data: None,
+                    });
+                }
+            }
+        } else {
+            None
+        };
+
+        // Remember the outermost directory we create so a failed write can undo it
+        let mut created_root = None;
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            created_root = parent
+                .ancestors()
+                .take_while(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+                .last()
+                .map(Path::to_path_buf);
+
+            if let Err(e) = fs::create_dir_all(parent).await {
+                remove_created_dirs(parent, created_root.as_deref()).await;
+                return Ok(ToolResult {
+                    success: false,
+                    output: None,
+                    error: Some(format!("Couldn't create parent directory {}: {}", parent.display(), e)),
+                    data: None,
+                });
+            }
         }
 
-        fs::write(path, content).await?;
+        if let Err(e) = fs::write(path, content).await {
+            if let Some(parent) = path.parent() {
+                remove_created_dirs(parent, created_root.as_deref()).await;
+            }
+            return Ok(ToolResult {
+                success: false,
+                output: None,
+                error: Some(format!("Couldn't write file {}: {}", file_path, e)),
+                data: None,
+            });
+        }
 
+        // Only successful creates are recorded in the edit history.
+        // For an overwrite, old_str holds the replaced content.
+        let replaced = original.is_some();
         let command = EditorCommand {
             command: EditorCommandType::Create,
             path: file_path.to_string(),
-            old_str: None,
+            old_str: original,
             new_str: None,
             content: Some(content.to_string()),
             insert_line: None,
@@ -344,7 +472,53 @@ impl TextEditorTool {
 
         Ok(ToolResult {
             success: true,
-            output: Some(format!("Successfully created {}", file_path)),
+            output: Some(if replaced {
+                format!("Successfully overwrote {} (original backed up for undo)", file_path)
+            } else {
+                format!("Successfully created {}", file_path)
+            }),
+            error: None,
+            data: None,
+        })
+    }
+
+    /// Revert the most recent file creation: restore the overwritten content,
+    /// or remove the file if it did not exist before.
+    pub async fn undo_edit(&mut self) -> Result<ToolResult, Box<dyn std::error::Error>> {
+        let Some(command) = self.edit_history.pop() else {
+            return Ok(ToolResult {
+                success: false,
+                output: None,
+                error: Some("No edits to undo".to_string()),
+                data: None,
+            });
+        };
+
+        if !matches!(command.command, EditorCommandType::Create) {
+            let error = format!("Undo is not supported for {:?} edits", command.command);
+            self.edit_history.push(command);
+            return Ok(ToolResult {
+                success: false,
+                output: None,
+                error: Some(error),
+                data: None,
+            });
+        }
+
+        let output = match &command.old_str {
+            Some(original) => {
+                fs::write(&command.path, original).await?;
+                format!("Restored original content of {}", command.path)
+            }
+            None => {
+                fs::remove_file(&command.path).await?;
+                format!("Removed {}", command.path)
+            }
+        };
+
+        Ok(ToolResult {
+            success: true,
+            output: Some(output),
             error: None,
             data: None,
         })
@@ -355,9 +529,117 @@ impl TextEditorTool {
     }
 }
 
+/// Remove the (empty) directories between `dir` and `created_root` that a
+/// failed `create` left behind. Directories that existed before are untouched.
+async fn remove_created_dirs(dir: &Path, created_root: Option<&Path>) {
+    let Some(created_root) = created_root else {
+        return;
+    };
+    for ancestor in dir.ancestors() {
+        // remove_dir only succeeds on empty directories
+        if fs::remove_dir(ancestor).await.is_err() || ancestor == created_root {
+            break;
+        }
+    }
+}
+
+/// Resolve a path to an absolute one (relative to the cwd), following symlinks
+/// for the longest existing prefix so not-yet-created files can be checked too.
+fn resolve_path(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")).join(path)
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+
+    let mut existing = normalized.clone();
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        match existing.file_name() {
+            Some(name) => rest.push(name.to_os_string()),
+            None => return normalized,
+        }
+        if !existing.pop() {
+            return normalized;
+        }
+    }
+
+    let mut resolved = existing.canonicalize().unwrap_or(existing);
+    for part in rest.iter().rev() {
+        resolved.push(part);
+    }
+    resolved
+}
+
+/// Default cap on the command output returned to the model (64 KiB)
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// How long a bash command may run when the caller doesn't pass a timeout
+pub const DEFAULT_COMMAND_TIMEOUT_SECS: u64 = 30;
+
+/// Truncate `text` to at most `max_bytes` (on a char boundary), appending a
+/// marker with the number of bytes dropped. Returns None if nothing was cut.
+fn truncate_output(text: &str, max_bytes: usize) -> Option<String> {
+    if text.len() <= max_bytes {
+        return None;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    Some(format!("{}\n...({} bytes truncated)", &text[..end], text.len() - end))
+}
+
+/// Resolve the argument of a `cd` command to a directory.
+///
+/// Strips matching quotes, expands `~` and `$HOME`, treats an empty argument
+/// as the home directory and `-` as the previous directory.
+fn resolve_cd_target(arg: &str, previous: Option<&str>, home: Option<&Path>) -> Result<PathBuf, String> {
+    let arg = arg.trim();
+    let arg = match (arg.chars().next(), arg.chars().last()) {
+        (Some(q @ ('"' | '\'')), Some(end)) if arg.len() >= 2 && q == end => &arg[1..arg.len() - 1],
+        _ => arg,
+    };
+
+    if arg == "-" {
+        return previous
+            .map(PathBuf::from)
+            .ok_or_else(|| "No previous directory".to_string());
+    }
+
+    let home_relative = if arg.is_empty() || arg == "~" || arg == "$HOME" || arg == "${HOME}" {
+        Some("")
+    } else {
+        ["~/", "$HOME/", "${HOME}/"]
+            .iter()
+            .find_map(|prefix| arg.strip_prefix(prefix))
+    };
+
+    match home_relative {
+        Some(rest) => {
+            let home = home.ok_or_else(|| "Cannot determine home directory".to_string())?;
+            Ok(if rest.is_empty() { home.to_path_buf() } else { home.join(rest) })
+        }
+        None => Ok(PathBuf::from(arg)),
+    }
+}
+
 #[derive(Clone)]
 pub struct BashTool {
     current_directory: String,
+    previous_directory: Option<String>,
+    max_output_bytes: usize,
 }
 
 impl BashTool {
@@ -367,15 +649,37 @@ impl BashTool {
                 .unwrap_or_else(|_| std::path::PathBuf::from("."))
                 .to_string_lossy()
                 .to_string(),
+            previous_directory: None,
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
         }
     }
 
-    pub async fn execute(&mut self, command: &str, _timeout: Option<u64>) -> Result<ToolResult, Box<dyn std::error::Error>> {
+    /// Limit how much command output is returned; the rest is kept in `data.full_output`
+    pub fn with_max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = max_output_bytes;
+        self
+    }
+
+    /// Run `command` in the shell, killing it after `timeout` seconds
+    /// (default [`DEFAULT_COMMAND_TIMEOUT_SECS`]). `cd` is handled in-process.
+    pub async fn execute(&mut self, command: &str, timeout: Option<u64>) -> Result<ToolResult, Box<dyn std::error::Error>> {
         // Handle cd commands specially
-        if command.starts_with("cd ") {
-            let new_dir = command[3..].trim();
-            match std::env::set_current_dir(new_dir) {
+        let trimmed = command.trim();
+        if trimmed == "cd" || trimmed.starts_with("cd ") {
+            let target = match resolve_cd_target(&trimmed[2..], self.previous_directory.as_deref(), dirs::home_dir().as_deref()) {
+                Ok(target) => target,
+                Err(e) => {
+                    return Ok(ToolResult {
+                        success: false,
+                        output: None,
+                        error: Some(format!("Cannot change directory: {}", e)),
+                        data: None,
+                    });
+                }
+            };
+            match std::env::set_current_dir(&target) {
                 Ok(()) => {
+                    self.previous_directory = Some(self.current_directory.clone());
                     self.current_directory = std::env::current_dir()
                         .unwrap_or_else(|_| std::path::PathBuf::from("."))
                         .to_string_lossy()
@@ -397,39 +701,65 @@ impl BashTool {
         } else {
             // Execute other commands using the system shell
             #[cfg(unix)]
-            let output = Command::new("sh")
-                .arg("-c")
-                .arg(command)
-                .output()?;
+            let (shell, flag) = ("sh", "-c");
 
             #[cfg(windows)]
-            let output = Command::new("cmd")
-                .arg("/C")
-                .arg(command)
-                .output()?;
+            let (shell, flag) = ("cmd", "/C");
+
+            // Dropping the output future on timeout kills the child
+            let mut child = tokio::process::Command::new(shell);
+            child.arg(flag).arg(command).kill_on_drop(true);
+            let timeout_secs = timeout.unwrap_or(DEFAULT_COMMAND_TIMEOUT_SECS);
+            let output = match tokio::time::timeout(std::time::Duration::from_secs(timeout_secs), child.output()).await {
+                Ok(output) => output?,
+                Err(_) => {
+                    return Ok(ToolResult {
+                        success: false,
+                        output: None,
+                        error: Some(format!("Command timed out after {} seconds", timeout_secs)),
+                        data: None,
+                    });
+                }
+            };
 
             let stdout = String::from_utf8_lossy(&output.stdout);
             let stderr = String::from_utf8_lossy(&output.stderr);
 
+            // Streams stay separate in `data`; `output`/`error` carry the readable text
+            let mut data = serde_json::json!({
+                "stdout": stdout,
+                "stderr": stderr,
+                "exit_code": output.status.code(),
+            });
+
             if output.status.success() {
                 let full_output = if !stderr.is_empty() {
                     format!("{}\nSTDERR: {}", stdout, stderr)
                 } else {
                     stdout.to_string()
                 };
+                let full_output = full_output.trim();
 
+                let truncated = truncate_output(full_output, self.max_output_bytes);
+                if truncated.is_some() {
+                    data["full_output"] = serde_json::json!(full_output);
+                }
                 Ok(ToolResult {
                     success: true,
-                    output: Some(full_output.trim().to_string()),
+                    output: Some(truncated.unwrap_or_else(|| full_output.to_string())),
                     error: None,
-                    data: None,
+                    data: Some(data),
                 })
             } else {
+                let truncated = truncate_output(&stderr, self.max_output_bytes);
+                if truncated.is_some() {
+                    data["full_output"] = serde_json::json!(stderr);
+                }
                 Ok(ToolResult {
                     success: false,
                     output: None,
-                    error: Some(format!("Command failed: {}", stderr)),
-                    data: None,
+                    error: Some(format!("Command failed: {}", truncated.as_deref().unwrap_or(&stderr))),
+                    data: Some(data),
                 })
             }
         }
@@ -589,6 +919,55 @@ impl SearchTool {
         file_types: Option<&Vec<String>>,
         exclude_files: Option<&Vec<String>>,
     ) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+        let args = self.ripgrep_args(
+            query,
+            include_pattern,
+            exclude_pattern,
+            case_sensitive,
+            whole_word,
+            regex,
+            max_results,
+            file_types,
+            exclude_files,
+        );
+
+        // Execute ripgrep command
+        #[cfg(unix)]
+        let output = std::process::Command::new("rg")
+            .args(&args)
+            .output()?;
+
+        #[cfg(windows)]
+        let output = std::process::Command::new("rg")
+            .args(&args)
+            .output()?;
+
+        if !output.status.success() && output.status.code() != Some(1) {
+            // Exit code 1 means no matches found, which is not an error
+            return Err(format!("Ripgrep failed: {}", String::from_utf8_lossy(&output.stderr)).into());
+        }
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        let results = self.parse_ripgrep_output(&output_str);
+
+        Ok(results)
+    }
+
+    /// Build the ripgrep command line. The query is matched literally
+    /// (`--fixed-strings`) unless `regex` is `Some(true)`, the same default
+    /// as the in-process `search_code` tool.
+    fn ripgrep_args(
+        &self,
+        query: &str,
+        include_pattern: Option<&str>,
+        exclude_pattern: Option<&str>,
+        case_sensitive: Option<bool>,
+        whole_word: Option<bool>,
+        regex: Option<bool>,
+        max_results: Option<u32>,
+        file_types: Option<&Vec<String>>,
+        exclude_files: Option<&Vec<String>>,
+    ) -> Vec<String> {
         let mut args = vec![
             "--json".to_string(),
             "--with-filename".to_string(),
@@ -608,7 +987,7 @@ impl SearchTool {
             args.push("--word-regexp".to_string());
         }
 
-        // Add regex mode
+        // Literal by default; regex matching is opt-in
         if !regex.unwrap_or(false) {
             args.push("--fixed-strings".to_string());
         }
@@ -663,26 +1042,7 @@ impl SearchTool {
         args.push(query.to_string());
         args.push(self.current_directory.clone());
 
-        // Execute ripgrep command
-        #[cfg(unix)]
-        let output = std::process::Command::new("rg")
-            .args(&args)
-            .output()?;
-
-        #[cfg(windows)]
-        let output = std::process::Command::new("rg")
-            .args(&args)
-            .output()?;
-
-        if !output.status.success() && output.status.code() != Some(1) {
-            // Exit code 1 means no matches found, which is not an error
-            return Err(format!("Ripgrep failed: {}", String::from_utf8_lossy(&output.stderr)).into());
-        }
-
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        let results = self.parse_ripgrep_output(&output_str);
-
-        Ok(results)
+        args
     }
 
     fn parse_ripgrep_output(&self, output: &str) -> Vec<SearchResult> {
@@ -1120,38 +1480,31 @@ impl MorphEditorTool {
         }
 
         // Call Morph Fast Apply API
-        // For now, we'll simulate this by using a local replacement method
-        // In a real implementation, we would make an HTTP request to the Morph API
         let merged_code = self.call_morph_apply(instructions, &initial_code, code_edit).await?;
 
         // Write the merged code back to file
         tokio::fs::write(&resolved_path, &merged_code).await?;
 
-        // Generate diff for display
+        // Diff once; the rendered text and the structured hunks come from the same result
         let old_lines: Vec<&str> = initial_code.lines().collect();
         let new_lines: Vec<&str> = merged_code.lines().collect();
-        let diff = self.generate_diff(&old_lines, &new_lines, target_file);
+        let hunks = diff_hunks(&old_lines, &new_lines, DIFF_CONTEXT_LINES);
+        let diff = self.generate_diff(&hunks, target_file);
 
         Ok(ToolResult {
             success: true,
             output: Some(diff),
             error: None,
-            data: None,
+            data: Some(serde_json::json!({ "hunks": hunks })),
         })
     }
 
     async fn call_morph_apply(
         &self,
-        _instructions: &str,
+        instructions: &str,
         initial_code: &str,
-        _edit_snippet: &str,
+        edit_snippet: &str,
     ) -> Result<String, Box<dyn std::error::Error>> {
-        // In a real implementation, this would make an HTTP request to the Morph API
-        // Since that requires HTTP client functionality and API access, we'll provide a placeholder
-        // that simply returns the initial code for now
-
-        // If we had a real HTTP client (like reqwest), it would look like this:
-        /*
         let client = reqwest::Client::new();
         let response = client.post(format!("{}/chat/completions", self.morph_base_url))
             .header("Authorization", format!("Bearer {}", self.morph_api_key))
@@ -1169,50 +1522,26 @@ impl MorphEditorTool {
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            return Err(format!("Morph API error: {}", response.status()).into());
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(format!("Morph API error ({}): {}", status, error_text).into());
         }
 
         let response_json: serde_json::Value = response.json().await?;
 
-        if let Some(content) = response_json.pointer("/choices/0/message/content")
-            .and_then(|v| v.as_str()) {
-            Ok(content.to_string())
-        } else {
-            Err("Invalid response format from Morph API".into())
+        // Never overwrite the file with an empty result
+        match response_json.pointer("/choices/0/message/content").and_then(|v| v.as_str()) {
+            Some(content) if !content.trim().is_empty() => Ok(content.to_string()),
+            Some(_) => Err("Morph API returned empty content".into()),
+            None => Err("Invalid response format from Morph API: missing choices[0].message.content".into()),
         }
-        */
-
-        // For now, as a placeholder, just return the original code
-        // A real implementation would call the morph API
-        Ok(initial_code.to_string())
     }
 
-    fn generate_diff(
-        &self,
-        old_lines: &[&str],
-        new_lines: &[&str],
-        file_path: &str,
-    ) -> String {
-        use std::collections::HashSet;
-
-        let old_set: HashSet<&str> = old_lines.iter().cloned().collect();
-        let new_set: HashSet<&str> = new_lines.iter().cloned().collect();
-
-        let mut added_lines = 0;
-        let mut removed_lines = 0;
-
-        for line in old_lines {
-            if !new_set.contains(line) {
-                removed_lines += 1;
-            }
-        }
-
-        for line in new_lines {
-            if !old_set.contains(line) {
-                added_lines += 1;
-            }
-        }
+    fn generate_diff(&self, hunks: &[DiffHunk], file_path: &str) -> String {
+        let lines = || hunks.iter().flat_map(|hunk| &hunk.lines);
+        let added_lines = lines().filter(|line| matches!(line, DiffLine::Added(_))).count();
+        let removed_lines = lines().filter(|line| matches!(line, DiffLine::Removed(_))).count();
 
         let mut summary = format!("Updated {} with Morph Fast Apply", file_path);
         if added_lines > 0 && removed_lines > 0 {
@@ -1230,10 +1559,9 @@ impl MorphEditorTool {
         let mut diff = summary + "\n";
         diff += &format!("--- a/{}\n", file_path);
         diff += &format!("+++ b/{}\n", file_path);
-
-        // Simple diff representation - in a full implementation this would use a more sophisticated diff algorithm
-        diff += "@@ -1,1 +1,1 @@\n";
-        diff += " ... (diff representation would be more detailed in full implementation) ... \n";
+        for hunk in hunks {
+            diff += &hunk.render();
+        }
 
         diff.trim_end().to_string()
     }
@@ -1242,10 +1570,149 @@ impl MorphEditorTool {
         self.morph_api_key = api_key.to_string();
     }
 
+    pub fn set_base_url(&mut self, base_url: &str) {
+        self.morph_base_url = base_url.trim_end_matches('/').to_string();
+    }
+
     pub fn get_api_key(&self) -> &str {
         &self.morph_api_key
     }
 }
 
+/// Lines of unchanged context kept around each hunk (same as `diff -u`)
+const DIFF_CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DiffOp<'a> {
+    Equal(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+/// Line-based diff (Myers, linear memory), so duplicate lines are matched by
+/// position rather than by content alone.
+fn diff_ops<'a>(old_lines: &[&'a str], new_lines: &[&'a str]) -> Vec<DiffOp<'a>> {
+    similar::capture_diff_slices(similar::Algorithm::Myers, old_lines, new_lines)
+        .iter()
+        .flat_map(|op| op.iter_changes(old_lines, new_lines))
+        .map(|change| match change.tag() {
+            similar::ChangeTag::Equal => DiffOp::Equal(change.value()),
+            similar::ChangeTag::Delete => DiffOp::Delete(change.value()),
+            similar::ChangeTag::Insert => DiffOp::Insert(change.value()),
+        })
+        .collect()
+}
+
+/// One line of a diff hunk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "text", rename_all = "lowercase")]
+pub enum DiffLine {
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+impl DiffLine {
+    /// The line in unified diff format, without the trailing newline
+    pub fn render(&self) -> String {
+        match self {
+            DiffLine::Context(line) => format!(" {}", line),
+            DiffLine::Removed(line) => format!("-{}", line),
+            DiffLine::Added(line) => format!("+{}", line),
+        }
+    }
+}
+
+/// A group of nearby changes with their context; line numbers start at 1
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+impl DiffHunk {
+    /// `@@ -1,3 +1,4 @@`
+    pub fn header(&self) -> String {
+        format!("@@ -{},{} +{},{} @@", self.old_start, self.old_len, self.new_start, self.new_len)
+    }
+
+    /// The hunk in unified diff format, header included
+    pub fn render(&self) -> String {
+        let mut output = self.header() + "\n";
+        for line in &self.lines {
+            output += &line.render();
+            output.push('\n');
+        }
+        output
+    }
+}
+
+/// Diff two files line by line and group the changes into hunks with
+/// `context` unchanged lines around them.
+pub fn diff_hunks(old_lines: &[&str], new_lines: &[&str], context: usize) -> Vec<DiffHunk> {
+    let ops = diff_ops(old_lines, new_lines);
+
+    // Group changes that are at most 2 * context lines apart into one hunk
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, DiffOp::Equal(_)))
+        .map(|(index, _)| index)
+        .collect();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &index in &changes {
+        match ranges.last_mut() {
+            Some(last) if index - last.1 <= 2 * context + 1 => last.1 = index,
+            _ => ranges.push((index.saturating_sub(context), index)),
+        }
+    }
+
+    // Number of old/new lines before each op, used for the hunk headers
+    let mut positions = Vec::with_capacity(ops.len());
+    let (mut old_line, mut new_line) = (0, 0);
+    for op in &ops {
+        positions.push((old_line, new_line));
+        match op {
+            DiffOp::Equal(_) => {
+                old_line += 1;
+                new_line += 1;
+            }
+            DiffOp::Delete(_) => old_line += 1,
+            DiffOp::Insert(_) => new_line += 1,
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, last_change)| {
+            let end = (last_change + context + 1).min(ops.len());
+            let hunk = &ops[start..end];
+            let old_len = hunk.iter().filter(|op| !matches!(op, DiffOp::Insert(_))).count();
+            let new_len = hunk.iter().filter(|op| !matches!(op, DiffOp::Delete(_))).count();
+            // An empty range starts at the line before it, as in `diff -u`
+            let (old_before, new_before) = positions[start];
+            DiffHunk {
+                old_start: old_before + usize::from(old_len > 0),
+                old_len,
+                new_start: new_before + usize::from(new_len > 0),
+                new_len,
+                lines: hunk
+                    .iter()
+                    .map(|op| match op {
+                        DiffOp::Equal(line) => DiffLine::Context(line.to_string()),
+                        DiffOp::Delete(line) => DiffLine::Removed(line.to_string()),
+                        DiffOp::Insert(line) => DiffLine::Added(line.to_string()),
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
 // Public exports - only re-export if not already defined in this module
-// The actual types are already available since they're defined in this file
\ No newline at end of file
+// The actual types are already available since they're defined in this file
+
+mod tests;
@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use crate::tools::*;
     use tokio;
 
     #[tokio::test]
@@ -14,7 +14,7 @@ mod tests {
             .as_nanos());
         
         let content = "Hello, World!";
-        let result = editor.create(&temp_path, content).await.unwrap();
+        let result = editor.create(&temp_path, content, false).await.unwrap();
         assert!(result.success);
         
         // View the file
@@ -26,6 +26,95 @@ mod tests {
         std::fs::remove_file(&temp_path).ok();
     }
 
+    fn temp_root(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("grok_tools_{}_{}", name, std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_text_editor_create_nested() {
+        let root = temp_root("nested");
+        let mut editor = TextEditorTool::new().with_project_root(&root);
+
+        let file = root.join("a/b/c.txt");
+        let result = editor.create(file.to_str().unwrap(), "nested", false).await.unwrap();
+        assert!(result.success);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "nested");
+        assert_eq!(editor.get_edit_history().len(), 1);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_text_editor_create_refuses_existing_file() {
+        let root = temp_root("exists");
+        let file = root.join("keep.txt");
+        std::fs::write(&file, "original").unwrap();
+        let mut editor = TextEditorTool::new().with_project_root(&root);
+
+        let result = editor.create(file.to_str().unwrap(), "clobbered", false).await.unwrap();
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("already exists"));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "original");
+        assert!(editor.get_edit_history().is_empty());
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_text_editor_overwrite_backs_up_for_undo() {
+        let root = temp_root("overwrite");
+        let file = root.join("keep.txt");
+        std::fs::write(&file, "original").unwrap();
+        let mut editor = TextEditorTool::new().with_project_root(&root);
+
+        let result = editor.create(file.to_str().unwrap(), "replaced", true).await.unwrap();
+        assert!(result.success);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "replaced");
+
+        let undo = editor.undo_edit().await.unwrap();
+        assert!(undo.success);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "original");
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_text_editor_create_permission_denied() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = temp_root("readonly");
+        let readonly = root.join("readonly");
+        std::fs::create_dir(&readonly).unwrap();
+        std::fs::set_permissions(&readonly, std::fs::Permissions::from_mode(0o555)).unwrap();
+
+        // Permissions are not enforced for root
+        if std::fs::write(readonly.join("probe"), "").is_ok() {
+            std::fs::remove_dir_all(&root).ok();
+            return;
+        }
+
+        let mut editor = TextEditorTool::new().with_project_root(&root);
+
+        let result = editor.create(readonly.join("file.txt").to_str().unwrap(), "x", false).await.unwrap();
+        assert!(!result.success);
+        assert!(result.error.unwrap().starts_with("Couldn't write file"));
+
+        let result = editor.create(readonly.join("sub/file.txt").to_str().unwrap(), "x", false).await.unwrap();
+        assert!(!result.success);
+        assert!(result.error.unwrap().starts_with("Couldn't create parent directory"));
+
+        assert!(editor.get_edit_history().is_empty());
+
+        std::fs::set_permissions(&readonly, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::remove_dir_all(&root).ok();
+    }
+
     #[tokio::test]
     async fn test_bash_tool_execution() {
         let mut bash = BashTool::new();
@@ -35,4 +124,306 @@ mod tests {
         assert!(result.success);
         assert!(result.output.unwrap().contains("hello"));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_bash_tool_separates_stdout_and_stderr() {
+        let mut bash = BashTool::new();
+
+        let result = bash.execute("echo out; echo err >&2", None).await.unwrap();
+        assert!(result.success);
+        assert!(result.output.unwrap().contains("STDERR: err"));
+
+        let data = result.data.unwrap();
+        assert_eq!(data["stdout"], "out\n");
+        assert_eq!(data["stderr"], "err\n");
+        assert_eq!(data["exit_code"], 0);
+
+        let result = bash.execute("echo partial; echo boom >&2; exit 3", None).await.unwrap();
+        assert!(!result.success);
+        let data = result.data.unwrap();
+        assert_eq!(data["stdout"], "partial\n");
+        assert_eq!(data["stderr"], "boom\n");
+        assert_eq!(data["exit_code"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_bash_tool_kills_command_after_timeout() {
+        let mut bash = BashTool::new();
+
+        let started = std::time::Instant::now();
+        let result = bash.execute("sleep 5", Some(1)).await.unwrap();
+        assert!(!result.success);
+        assert_eq!(result.error.as_deref(), Some("Command timed out after 1 seconds"));
+        assert!(started.elapsed() < std::time::Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn test_bash_tool_truncates_large_output() {
+        let mut bash = BashTool::new().with_max_output_bytes(100);
+
+        let result = bash.execute("for i in $(seq 1 100); do echo line-$i; done", None).await.unwrap();
+        assert!(result.success);
+
+        let output = result.output.unwrap();
+        assert!(output.len() < 200);
+        assert!(output.starts_with("line-1\n"));
+        assert!(output.contains("bytes truncated)"));
+
+        let full = result.data.unwrap()["full_output"].as_str().unwrap().to_string();
+        assert!(full.ends_with("line-100"));
+    }
+
+    #[test]
+    fn test_cd_target_strips_quotes() {
+        assert_eq!(resolve_cd_target(" \"my dir\"", None, None).unwrap(), PathBuf::from("my dir"));
+        assert_eq!(resolve_cd_target(" 'my dir'", None, None).unwrap(), PathBuf::from("my dir"));
+        assert_eq!(resolve_cd_target(" plain", None, None).unwrap(), PathBuf::from("plain"));
+    }
+
+    #[test]
+    fn test_cd_target_expands_home() {
+        let home = Path::new("/home/user");
+        assert_eq!(resolve_cd_target("", None, Some(home)).unwrap(), home);
+        assert_eq!(resolve_cd_target(" ~", None, Some(home)).unwrap(), home);
+        assert_eq!(resolve_cd_target(" ~/projects", None, Some(home)).unwrap(), home.join("projects"));
+        assert_eq!(resolve_cd_target(" \"$HOME/my dir\"", None, Some(home)).unwrap(), home.join("my dir"));
+        assert!(resolve_cd_target(" ~", None, None).is_err());
+    }
+
+    #[test]
+    fn test_cd_dash_returns_previous_directory() {
+        assert_eq!(resolve_cd_target(" -", Some("/tmp/previous"), None).unwrap(), PathBuf::from("/tmp/previous"));
+        assert!(resolve_cd_target(" -", None, None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_view_truncates_very_long_lines() {
+        let root = temp_root("long_line");
+        let path = root.join("bundle.min.js");
+        let long_line = "x".repeat(MAX_VIEW_LINE_CHARS + 500);
+        std::fs::write(&path, format!("short\n{}\n", long_line)).unwrap();
+
+        let editor = TextEditorTool::new().with_project_root(&root);
+        let output = editor.view(path.to_str().unwrap(), None).await.unwrap().output.unwrap();
+
+        let shown = output.lines().find(|line| line.starts_with("2: ")).unwrap();
+        assert!(shown.ends_with("…(500 chars)"));
+        assert_eq!(shown.len(), "2: ".len() + MAX_VIEW_LINE_CHARS + "…(500 chars)".len());
+        assert!(output.contains("1: short"));
+        assert!(output.contains("1 line(s) longer than"));
+
+        // The file itself is left intact
+        assert!(std::fs::read_to_string(&path).unwrap().contains(&long_line));
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    fn morph_diff(old: &str, new: &str) -> String {
+        let morph = MorphEditorTool::new(Some("test-key".to_string()));
+        let old_lines: Vec<&str> = old.lines().collect();
+        let new_lines: Vec<&str> = new.lines().collect();
+        morph.generate_diff(&diff_hunks(&old_lines, &new_lines, 3), "src/lib.rs")
+    }
+
+    #[test]
+    fn test_diff_hunks_match_rendered_diff() {
+        let old = ["fn a() {", "    1", "}", "fn b() {", "    2", "}"];
+        let new = ["fn a() {", "    10", "}", "fn b() {", "    2", "}"];
+        let hunks = diff_hunks(&old, &new, 1);
+        assert_eq!(
+            hunks,
+            vec![DiffHunk {
+                old_start: 1,
+                old_len: 3,
+                new_start: 1,
+                new_len: 3,
+                lines: vec![
+                    DiffLine::Context("fn a() {".to_string()),
+                    DiffLine::Removed("    1".to_string()),
+                    DiffLine::Added("    10".to_string()),
+                    DiffLine::Context("}".to_string()),
+                ],
+            }]
+        );
+
+        let morph = MorphEditorTool::new(Some("test-key".to_string()));
+        let diff = morph.generate_diff(&hunks, "src/lib.rs");
+        let rendered: Vec<String> = std::iter::once(hunks[0].header())
+            .chain(hunks[0].lines.iter().map(DiffLine::render))
+            .collect();
+        assert!(diff.ends_with(&rendered.join("\n")));
+        assert!(diff.starts_with("Updated src/lib.rs with Morph Fast Apply - 1 addition and 1 removal\n"));
+
+        let json = serde_json::to_value(&hunks[0].lines[1]).unwrap();
+        assert_eq!(json, serde_json::json!({ "kind": "removed", "text": "    1" }));
+    }
+
+    #[test]
+    fn test_morph_diff_pure_insertion() {
+        let diff = morph_diff("a\nb\nc\nd\ne\n", "a\nb\nc\nnew\nd\ne\n");
+        assert_eq!(
+            diff,
+            "Updated src/lib.rs with Morph Fast Apply - 1 addition\n\
+             --- a/src/lib.rs\n\
+             +++ b/src/lib.rs\n\
+             @@ -1,5 +1,6 @@\n a\n b\n c\n+new\n d\n e"
+        );
+    }
+
+    #[test]
+    fn test_morph_diff_pure_deletion() {
+        let old: String = (1..=12).map(|i| format!("line {}\n", i)).collect();
+        let new = old.replace("line 10\n", "");
+        let diff = morph_diff(&old, &new);
+        assert!(diff.starts_with("Updated src/lib.rs with Morph Fast Apply - 1 removal\n"));
+        assert!(diff.contains("@@ -7,6 +7,5 @@\n line 7\n line 8\n line 9\n-line 10\n line 11\n line 12"));
+        assert!(!diff.contains("line 6"));
+    }
+
+    #[test]
+    fn test_morph_diff_mixed_edit_counts_duplicate_lines() {
+        // The duplicated "}" lines used to be miscounted by set comparison
+        let old = "fn a() {\n    1\n}\nfn b() {\n    2\n}\n";
+        let new = "fn a() {\n    10\n}\n}\nfn b() {\n    2\n}\n";
+        let diff = morph_diff(old, new);
+        assert!(diff.starts_with("Updated src/lib.rs with Morph Fast Apply - 2 additions and 1 removal\n"));
+        assert!(diff.contains("@@ -1,5 +1,6 @@\n fn a() {\n-    1\n+    10\n+}\n }\n fn b() {"));
+
+        // Changes far apart produce separate hunks with their own line numbers
+        let old: String = (1..=20).map(|i| format!("{}\n", i)).collect();
+        let new: String = (1..=20)
+            .map(|i| match i {
+                2 => "two\n".to_string(),
+                19 => "nineteen\n".to_string(),
+                _ => format!("{}\n", i),
+            })
+            .collect();
+        let diff = morph_diff(&old, &new);
+        assert!(diff.contains("@@ -1,5 +1,5 @@"));
+        assert!(diff.contains("@@ -16,5 +16,5 @@"));
+    }
+
+    #[test]
+    fn test_morph_diff_handles_large_files() {
+        // A full LCS table for this input would need gigabytes
+        let old: String = (1..=50_000).map(|i| format!("line {}\n", i)).collect();
+        let new = old.replacen("line 25000\n", "line 25000 changed\n", 1);
+        let diff = morph_diff(&old, &new);
+        assert!(diff.starts_with("Updated src/lib.rs with Morph Fast Apply - 1 addition and 1 removal\n"));
+        assert!(diff.contains("@@ -24997,7 +24997,7 @@"));
+    }
+
+    #[test]
+    fn test_search_query_is_literal_unless_regex_requested() {
+        let search = SearchTool::new();
+        let args = |regex: Option<bool>| search.ripgrep_args("a.b", None, None, None, None, regex, None, None, None);
+
+        // "a.b" must not match "axb" unless the caller opts into regex mode
+        assert!(args(None).contains(&"--fixed-strings".to_string()));
+        assert!(args(Some(false)).contains(&"--fixed-strings".to_string()));
+        assert!(!args(Some(true)).contains(&"--fixed-strings".to_string()));
+        assert!(args(Some(true)).contains(&"a.b".to_string()));
+    }
+
+    #[cfg(feature = "morph-integration-tests")]
+    mod morph_integration {
+        use crate::tools::*;
+        use serde_json::json;
+        use wiremock::matchers::{body_partial_json, header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        async fn morph_against(server: &MockServer) -> MorphEditorTool {
+            let mut morph = MorphEditorTool::new(Some("test-key".to_string()));
+            morph.set_base_url(&server.uri());
+            morph
+        }
+
+        #[tokio::test]
+        async fn test_morph_apply_returns_merged_code() {
+            let server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/chat/completions"))
+                .and(header("authorization", "Bearer test-key"))
+                .and(body_partial_json(json!({ "model": "morph-v3-large" })))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "choices": [{ "message": { "role": "assistant", "content": "fn main() {\n    println!(\"hi\");\n}\n" } }]
+                })))
+                .expect(1)
+                .mount(&server)
+                .await;
+
+            let morph = morph_against(&server).await;
+            let merged = morph
+                .call_morph_apply("print hi", "fn main() {}\n", "println!(\"hi\");")
+                .await
+                .unwrap();
+            assert_eq!(merged, "fn main() {\n    println!(\"hi\");\n}\n");
+
+            let requests = server.received_requests().await.unwrap();
+            let body: serde_json::Value = requests[0].body_json().unwrap();
+            let content = body["messages"][0]["content"].as_str().unwrap();
+            assert!(content.starts_with("<instruction>print hi</instruction>\n<code>fn main() {}"));
+            assert!(content.ends_with("<update>println!(\"hi\");</update>"));
+        }
+
+        #[tokio::test]
+        async fn test_morph_apply_reports_http_errors() {
+            let server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/chat/completions"))
+                .respond_with(ResponseTemplate::new(401).set_body_string("invalid api key"))
+                .mount(&server)
+                .await;
+
+            let morph = morph_against(&server).await;
+            let err = morph.call_morph_apply("x", "a", "b").await.unwrap_err().to_string();
+            assert!(err.contains("401"));
+            assert!(err.contains("invalid api key"));
+        }
+
+        #[tokio::test]
+        async fn test_morph_apply_rejects_missing_or_empty_content() {
+            let server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/chat/completions"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "choices": [{ "message": { "content": "" } }]
+                })))
+                .mount(&server)
+                .await;
+
+            let morph = morph_against(&server).await;
+            let err = morph.call_morph_apply("x", "a", "b").await.unwrap_err().to_string();
+            assert!(err.contains("empty content"));
+
+            server.reset().await;
+            Mock::given(method("POST"))
+                .and(path("/chat/completions"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "choices": [] })))
+                .mount(&server)
+                .await;
+            let err = morph.call_morph_apply("x", "a", "b").await.unwrap_err().to_string();
+            assert!(err.contains("missing choices[0].message.content"));
+        }
+
+        #[tokio::test]
+        async fn test_morph_edit_file_leaves_file_untouched_on_error() {
+            let server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/chat/completions"))
+                .respond_with(ResponseTemplate::new(500).set_body_string("upstream down"))
+                .mount(&server)
+                .await;
+
+            let dir = std::env::temp_dir().join(format!("morph_edit_{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let file = dir.join("lib.rs");
+            std::fs::write(&file, "fn answer() -> u32 { 41 }\n").unwrap();
+
+            let morph = morph_against(&server).await;
+            let result = morph.edit_file(file.to_str().unwrap(), "bump", "42").await;
+            assert!(result.is_err());
+            assert_eq!(std::fs::read_to_string(&file).unwrap(), "fn answer() -> u32 { 41 }\n");
+            std::fs::remove_dir_all(&dir).ok();
+        }
+    }
+}
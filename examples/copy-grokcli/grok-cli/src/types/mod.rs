@@ -60,6 +60,30 @@ pub struct ChatEntry {
     pub is_streaming: Option<bool>,
 }
 
+impl ChatEntry {
+    /// Entry for a single tool call requested by an assistant message
+    pub fn tool_call_entry(call: &GrokToolCall) -> Self {
+        Self {
+            entry_type: ChatEntryType::ToolCall,
+            content: format!("{}({})", call.function.name, call.function.arguments),
+            timestamp: Utc::now(),
+            tool_calls: None,
+            tool_call: Some(call.clone()),
+            tool_result: None,
+            is_streaming: None,
+        }
+    }
+
+    /// Whether this is a tool call (or tool result) entry belonging to `parent`,
+    /// the assistant entry that requested it
+    pub fn belongs_to(&self, parent: &ChatEntry) -> bool {
+        match (&self.tool_call, &parent.tool_calls) {
+            (Some(call), Some(calls)) => calls.iter().any(|c| c.id == call.id),
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GrokMessage {
     pub role: String,
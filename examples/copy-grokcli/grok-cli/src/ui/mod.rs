@@ -25,16 +25,27 @@ pub struct ChatState {
     show_mention_hints: bool,
     mention_hints: Vec<String>,
     selected_mention_hint: usize,
+    /// Set after /commit-and-push has committed; the next input confirms the push
+    pending_push: bool,
+    /// Show tool call arguments instead of collapsed one-line entries (Ctrl+O)
+    show_tool_details: bool,
 }
 
-const AVAILABLE_COMMANDS: &[&str] = &[
-    "/help - Show help information",
+/// Every slash command the input loop handles. Command hints and /help are
+/// both built from this list.
+const COMMANDS: &[&str] = &[
+    "/help - Show this help message",
     "/clear - Clear chat history",
-    "/models - Switch Grok Model",
-    "/commit-and-push - AI commit & push to remote",
+    "/status - Show application status",
+    "/model - Show current model",
+    "/commit-and-push - Commit all changes with an AI message and push",
     "/exit - Exit the application",
 ];
 
+fn help_text() -> String {
+    format!("Available commands:\n{}", COMMANDS.join("\n"))
+}
+
 const AVAILABLE_MENTIONS: &[&str] = &[
     "@file - Mention a file",
     "@model - Mention current model",
@@ -42,6 +53,21 @@ const AVAILABLE_MENTIONS: &[&str] = &[
     "@history - Mention chat history",
 ];
 
+/// Text shown for a chat entry. Tool calls are rendered under the assistant
+/// message that requested them, collapsed to the tool name unless expanded.
+fn format_entry(entry: &ChatEntry, show_tool_details: bool) -> String {
+    match &entry.entry_type {
+        ChatEntryType::User => format!("👤 You: {}", entry.content),
+        ChatEntryType::Assistant => format!("🤖 Grok: {}", entry.content),
+        ChatEntryType::ToolResult => format!("🔧 Tool Result: {}", entry.content),
+        ChatEntryType::ToolCall => match (&entry.tool_call, show_tool_details) {
+            (Some(call), true) => format!("  ▾ 🔧 {}({})", call.function.name, call.function.arguments),
+            (Some(call), false) => format!("  ▸ 🔧 {}", call.function.name),
+            (None, _) => format!("🔧 Tool Call: {}", entry.content),
+        },
+    }
+}
+
 fn get_welcome_message() -> String {
     "🤖 Welcome to starfellcode CLI!\n\n\
     Tips for getting started:\n\
@@ -71,6 +97,8 @@ pub async fn run_app(mut agent: GrokAgent, initial_message: String) -> Result<()
         show_mention_hints: false,
         mention_hints: vec![],
         selected_mention_hint: 0,
+        pending_push: false,
+        show_tool_details: false,
     };
 
     // If there's an initial message, process it first
@@ -111,6 +139,11 @@ pub async fn run_app(mut agent: GrokAgent, initial_message: String) -> Result<()
                                     }
                                 }
                                 crate::types::StreamingChunkType::Done => {
+                                    if let Some(tool_calls) = chunk.tool_calls {
+                                        let entries: Vec<ChatEntry> = tool_calls.iter().map(ChatEntry::tool_call_entry).collect();
+                                        chat_state.chat_history[response_idx].tool_calls = Some(tool_calls);
+                                        chat_state.chat_history.extend(entries);
+                                    }
                                     chat_state.chat_history[response_idx].is_streaming = Some(false);
                                     break;
                                 }
@@ -159,6 +192,7 @@ async fn run_ui_loop(
     #[derive(Clone, Debug)]
     enum StreamMessage {
         Content(String),
+        ToolCalls(Vec<crate::types::GrokToolCall>),
         Done,
         Error(String),
     }
@@ -188,12 +222,7 @@ async fn run_ui_loop(
             // Chat history
             let chat_items: Vec<ListItem> = state.chat_history.iter()
                 .map(|entry| {
-                    let content = match &entry.entry_type {
-                        ChatEntryType::User => format!("👤 You: {}", entry.content),
-                        ChatEntryType::Assistant => format!("🤖 Grok: {}", entry.content),
-                        ChatEntryType::ToolResult => format!("🔧 Tool Result: {}", entry.content),
-                        ChatEntryType::ToolCall => format!("🔧 Tool Call: {}", entry.content),
-                    };
+                    let content = format_entry(entry, state.show_tool_details);
 
                     ListItem::new(content)
                         .style(match &entry.entry_type {
@@ -289,6 +318,9 @@ async fn run_ui_loop(
                             KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) && c == 'c' => {
                                 return Ok(());
                             },
+                            KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) && c == 'o' => {
+                                state.show_tool_details = !state.show_tool_details;
+                            },
                             KeyCode::Char(c) => {
                                 state.input.push(c);
                                 
@@ -322,7 +354,7 @@ async fn run_ui_loop(
                                 if state.input.starts_with('/') && !state.show_mention_hints {
                                     state.show_command_hints = true;
                                     let input_lower = state.input.to_lowercase();
-                                    state.command_hints = AVAILABLE_COMMANDS
+                                    state.command_hints = COMMANDS
                                         .iter()
                                         .filter(|cmd| {
                                             // Extract command name (before the dash)
@@ -344,7 +376,7 @@ async fn run_ui_loop(
                                 if state.input.starts_with('/') {
                                     state.show_command_hints = true;
                                     let input_lower = state.input.to_lowercase();
-                                    state.command_hints = AVAILABLE_COMMANDS
+                                    state.command_hints = COMMANDS
                                         .iter()
                                         .filter(|cmd| {
                                             // Extract command name (before the dash)
@@ -418,17 +450,36 @@ async fn run_ui_loop(
                                     state.show_mention_hints = false;
                                     state.mention_hints.clear();
                                     
+                                    // Answer to the push confirmation of /commit-and-push
+                                    if state.pending_push {
+                                        state.pending_push = false;
+                                        let answer = user_input.trim().to_lowercase();
+                                        let response = if answer == "y" || answer == "yes" {
+                                            match std::env::current_dir()
+                                                .map_err(|e| e.into())
+                                                .and_then(|dir| crate::commands::commit::push(&dir))
+                                            {
+                                                Ok(output) => output,
+                                                Err(e) => format!("✗ {}", e),
+                                            }
+                                        } else {
+                                            "Push cancelled. The commit was kept locally.".to_string()
+                                        };
+
+                                        state.chat_history.push(ChatEntry {
+                                            entry_type: ChatEntryType::Assistant,
+                                            content: response,
+                                            timestamp: chrono::Utc::now(),
+                                            tool_calls: None,
+                                            tool_call: None,
+                                            tool_result: None,
+                                            is_streaming: None,
+                                        });
+                                    }
                                     // Check if input is a command
-                                    if user_input.starts_with('/') {
+                                    else if user_input.starts_with('/') {
                                         let cmd_response = match user_input.trim() {
-                                            "/help" => {
-                                                "Available commands:\n\
-                                                /help - Show this help message\n\
-                                                /clear - Clear chat history\n\
-                                                /status - Show application status\n\
-                                                /model - Show current model\n\
-                                                /exit - Exit the application".to_string()
-                                            },
+                                            "/help" => help_text(),
                                             "/clear" => {
                                                 state.chat_history.clear();
                                                 "Chat history cleared.".to_string()
@@ -442,6 +493,19 @@ async fn run_ui_loop(
                                                 "Current model: grok-2\n\
                                                 Available models: grok-2, grok-vision".to_string()
                                             },
+                                            "/commit-and-push" => {
+                                                let result = match std::env::current_dir() {
+                                                    Ok(dir) => crate::commands::commit::commit_all(&dir, &*agent).await,
+                                                    Err(e) => Err(e.into()),
+                                                };
+                                                match result {
+                                                    Ok(report) => {
+                                                        state.pending_push = true;
+                                                        format!("{}\n\nPush to remote? (y/n)", report.summary())
+                                                    }
+                                                    Err(e) => format!("✗ {}", e),
+                                                }
+                                            },
                                             "/exit" => {
                                                 return Ok(());
                                             },
@@ -500,6 +564,9 @@ async fn run_ui_loop(
                                                                         }
                                                                     }
                                                                     crate::types::StreamingChunkType::Done => {
+                                                                        if let Some(tool_calls) = chunk.tool_calls {
+                                                                            let _ = tx_clone.send(StreamMessage::ToolCalls(tool_calls)).await;
+                                                                        }
                                                                         let _ = tx_clone.send(StreamMessage::Done).await;
                                                                         break;
                                                                     }
@@ -541,6 +608,12 @@ async fn run_ui_loop(
                                 state.chat_history[response_idx].content.push_str(&content);
                             }
                         }
+                        StreamMessage::ToolCalls(tool_calls) => {
+                            // Keep the prose and list the calls right after it
+                            let entries: Vec<ChatEntry> = tool_calls.iter().map(ChatEntry::tool_call_entry).collect();
+                            state.chat_history[response_idx].tool_calls = Some(tool_calls);
+                            state.chat_history.splice(response_idx + 1..response_idx + 1, entries);
+                        }
                         StreamMessage::Done => {
                             if response_idx < state.chat_history.len() {
                                 state.chat_history[response_idx].is_streaming = Some(false);
@@ -1 +1,2 @@
-pub mod settings_manager;
\ No newline at end of file
+pub mod output_sink;
+pub mod settings_manager;
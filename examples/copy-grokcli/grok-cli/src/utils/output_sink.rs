@@ -0,0 +1,191 @@
+use crate::types::{ChatEntry, ChatEntryType};
+use serde_json::json;
+use std::io::Write;
+
+/// Output format for headless (`--prompt`) mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text
+    Text,
+    /// One JSON record per line (JSONL)
+    Json,
+}
+
+/// Destination for the entries produced by a conversation turn.
+///
+/// The interactive TUI renders entries itself; headless mode picks a sink
+/// based on `--output`.
+pub trait OutputSink {
+    fn emit(&mut self, entry: &ChatEntry) -> std::io::Result<()>;
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Create the sink for the given format writing to stdout
+pub fn stdout_sink(format: OutputFormat) -> Box<dyn OutputSink> {
+    match format {
+        OutputFormat::Text => Box::new(TextSink::new(std::io::stdout())),
+        OutputFormat::Json => Box::new(JsonSink::new(std::io::stdout())),
+    }
+}
+
+/// Emits each entry as a JSON line (the default headless output). Records
+/// carry a `kind` of `message`, `tool_call` or `tool_result`.
+pub struct JsonSink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// The serialized entry (what headless mode has always printed) plus a
+    /// `kind` and flattened fields for the record type
+    fn record(entry: &ChatEntry) -> serde_json::Value {
+        let mut record = serde_json::to_value(entry).unwrap_or_else(|_| json!({}));
+        let call = entry.tool_call.as_ref();
+        match entry.entry_type {
+            ChatEntryType::User | ChatEntryType::Assistant => {
+                record["kind"] = json!("message");
+                record["role"] = json!(if entry.entry_type == ChatEntryType::User { "user" } else { "assistant" });
+            }
+            ChatEntryType::ToolCall => {
+                record["kind"] = json!("tool_call");
+                record["id"] = json!(call.map(|call| &call.id));
+                record["name"] = json!(call.map(|call| &call.function.name));
+                record["arguments"] = json!(call.map(|call| &call.function.arguments));
+            }
+            ChatEntryType::ToolResult => {
+                let result = entry.tool_result.as_ref();
+                record["kind"] = json!("tool_result");
+                record["id"] = json!(call.map(|call| &call.id));
+                record["name"] = json!(call.map(|call| &call.function.name));
+                record["success"] = json!(result.map(|result| result.success));
+                record["output"] = json!(result.and_then(|result| result.output.as_ref()));
+                record["error"] = json!(result.and_then(|result| result.error.as_ref()));
+            }
+        }
+        record
+    }
+}
+
+impl<W: Write> OutputSink for JsonSink<W> {
+    fn emit(&mut self, entry: &ChatEntry) -> std::io::Result<()> {
+        serde_json::to_writer(&mut self.writer, &Self::record(entry))?;
+        self.writer.write_all(b"\n")
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Plain text output, one block per entry
+pub struct TextSink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> TextSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: Write> OutputSink for TextSink<W> {
+    fn emit(&mut self, entry: &ChatEntry) -> std::io::Result<()> {
+        match entry.entry_type {
+            // The prompt was given on the command line, no need to echo it
+            ChatEntryType::User => Ok(()),
+            ChatEntryType::Assistant => writeln!(self.writer, "{}", entry.content),
+            ChatEntryType::ToolCall => writeln!(self.writer, "🔧 {}", entry.content),
+            ChatEntryType::ToolResult => writeln!(self.writer, "{}", entry.content),
+        }
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{GrokToolCall, GrokToolCallFunction, ToolResult};
+
+    fn entry(entry_type: ChatEntryType, content: &str) -> ChatEntry {
+        ChatEntry {
+            entry_type,
+            content: content.to_string(),
+            timestamp: chrono::Utc::now(),
+            tool_calls: None,
+            tool_call: None,
+            tool_result: None,
+            is_streaming: None,
+        }
+    }
+
+    #[test]
+    fn test_json_sink_emits_jsonl_for_completed_turn() {
+        let call = GrokToolCall {
+            id: "call_1".to_string(),
+            call_type: "function".to_string(),
+            function: GrokToolCallFunction {
+                name: "view_file".to_string(),
+                arguments: r#"{"path":"Cargo.toml"}"#.to_string(),
+            },
+        };
+
+        let mut tool_call = entry(ChatEntryType::ToolCall, "Calling view_file");
+        tool_call.tool_call = Some(call.clone());
+        let mut tool_result = entry(ChatEntryType::ToolResult, "[package]");
+        tool_result.tool_call = Some(call);
+        tool_result.tool_result = Some(ToolResult {
+            success: true,
+            output: Some("[package]".to_string()),
+            error: None,
+            data: None,
+        });
+
+        let turn = vec![
+            entry(ChatEntryType::User, "show the manifest"),
+            tool_call,
+            tool_result,
+            entry(ChatEntryType::Assistant, "Here it is."),
+        ];
+
+        let mut sink = JsonSink::new(Vec::new());
+        for e in &turn {
+            sink.emit(e).unwrap();
+        }
+        sink.finish().unwrap();
+
+        let output = String::from_utf8(sink.into_inner()).unwrap();
+        let records: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("each line is valid JSON"))
+            .collect();
+
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0]["kind"], "message");
+        assert_eq!(records[0]["role"], "user");
+        assert_eq!(records[1]["kind"], "tool_call");
+        assert_eq!(records[1]["name"], "view_file");
+        assert_eq!(records[2]["kind"], "tool_result");
+        assert_eq!(records[2]["success"], true);
+        assert_eq!(records[3]["role"], "assistant");
+        assert_eq!(records[3]["content"], "Here it is.");
+
+        // The fields of the serialized ChatEntry are still there for existing scripts
+        assert_eq!(records[0]["type"], "User");
+        assert_eq!(records[2]["type"], "ToolResult");
+        assert_eq!(records[2]["tool_result"]["output"], "[package]");
+        assert_eq!(records[1]["tool_call"]["function"]["name"], "view_file");
+    }
+}
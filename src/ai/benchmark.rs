@@ -0,0 +1,223 @@
+/// 模型基准对比
+/// 把同一个提示并发发给多个模型，记录耗时、token 数和回复，并排展示
+
+use crate::ai::client::{ChatCompletion, ChatMessage};
+use crate::core::TokenCalculator;
+use futures_util::stream::{self, StreamExt};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthChar;
+
+/// 默认同时进行的模型请求数
+pub const DEFAULT_BENCHMARK_CONCURRENCY: usize = 4;
+/// 并排视图中每列的显示宽度
+const COLUMN_WIDTH: usize = 36;
+/// 并排视图中每个回复最多显示的行数
+const MAX_RESPONSE_ROWS: usize = 20;
+
+/// 单个模型的基准结果
+#[derive(Debug, Clone)]
+pub struct BenchmarkResult {
+    pub model: String,
+    pub latency: Duration,
+    pub prompt_tokens: usize,
+    pub response_tokens: usize,
+    /// 回复内容，或失败原因
+    pub outcome: Result<String, String>,
+}
+
+/// 向每个模型发送同一提示，最多 concurrency 个请求同时进行。
+/// 结果按 models 的顺序返回；单个模型失败不会中断其他模型
+pub async fn run_benchmark(
+    prompt: &str,
+    models: &[(String, Arc<dyn ChatCompletion>)],
+    concurrency: usize,
+) -> Vec<BenchmarkResult> {
+    let mut indexed: Vec<(usize, BenchmarkResult)> = stream::iter(models.iter().enumerate())
+        .map(|(index, (model, client))| async move { (index, run_single(prompt, model, client.as_ref()).await) })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+    // 按完成顺序收集，按输入顺序展示
+    indexed.sort_by_key(|(index, _)| *index);
+    indexed.into_iter().map(|(_, result)| result).collect()
+}
+
+async fn run_single(prompt: &str, model: &str, client: &dyn ChatCompletion) -> BenchmarkResult {
+    let calculator = TokenCalculator::from_model_name(model);
+    let messages = vec![ChatMessage {
+        role: "user".to_string(),
+        content: prompt.to_string(),
+    }];
+
+    let started = Instant::now();
+    let outcome = client.complete(messages).await.map_err(|e| e.to_string());
+    let latency = started.elapsed();
+
+    BenchmarkResult {
+        model: model.to_string(),
+        latency,
+        prompt_tokens: calculator.count_tokens(prompt),
+        response_tokens: outcome.as_ref().map(|r| calculator.count_tokens(r)).unwrap_or(0),
+        outcome,
+    }
+}
+
+/// 对比视图：汇总表 + 各模型回复并排
+pub fn format_comparison(prompt: &str, results: &[BenchmarkResult]) -> String {
+    let mut report = format!("📊 模型对比: {}\n\n", prompt);
+
+    for result in results {
+        let status = match &result.outcome {
+            Ok(_) => "✓".to_string(),
+            Err(e) => format!("❌ {}", e),
+        };
+        report.push_str(&format!(
+            "  {:<24} {:>8} ms  输入 {:>5} / 输出 {:>5} tokens  {}\n",
+            result.model,
+            result.latency.as_millis(),
+            result.prompt_tokens,
+            result.response_tokens,
+            status
+        ));
+    }
+
+    // 并排显示回复：每个模型一列
+    let columns: Vec<Vec<String>> = results
+        .iter()
+        .map(|result| {
+            let text = match &result.outcome {
+                Ok(response) => response.clone(),
+                Err(e) => format!("(失败) {}", e),
+            };
+            let mut column = vec![pad(&result.model, COLUMN_WIDTH), "─".repeat(COLUMN_WIDTH)];
+            column.extend(wrap(&text, COLUMN_WIDTH).into_iter().take(MAX_RESPONSE_ROWS));
+            column
+        })
+        .collect();
+
+    let rows = columns.iter().map(|c| c.len()).max().unwrap_or(0);
+    report.push('\n');
+    for row in 0..rows {
+        let line: Vec<String> = columns
+            .iter()
+            .map(|column| column.get(row).cloned().unwrap_or_else(|| " ".repeat(COLUMN_WIDTH)))
+            .collect();
+        report.push_str(line.join(" │ ").trim_end());
+        report.push('\n');
+    }
+    report.trim_end().to_string()
+}
+
+/// 按显示宽度折行，每行补齐到 width
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for source_line in text.lines() {
+        let mut line = String::new();
+        let mut line_width = 0;
+        for c in source_line.chars() {
+            let w = c.width().unwrap_or(0);
+            if line_width + w > width {
+                lines.push(pad(&line, width));
+                line.clear();
+                line_width = 0;
+            }
+            line.push(c);
+            line_width += w;
+        }
+        lines.push(pad(&line, width));
+    }
+    lines
+}
+
+/// 截断或补齐到指定显示宽度
+fn pad(text: &str, width: usize) -> String {
+    let mut result = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > width {
+            break;
+        }
+        result.push(c);
+        used += w;
+    }
+    result.push_str(&" ".repeat(width - used));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::client::CompletionError;
+    use std::future::Future;
+    use std::pin::Pin;
+
+    /// 延迟固定时间后返回固定回复（或失败）的假模型
+    struct FakeModel {
+        reply: Result<&'static str, &'static str>,
+        delay: Duration,
+    }
+
+    impl ChatCompletion for FakeModel {
+        fn complete(&self, _messages: Vec<ChatMessage>) -> Pin<Box<dyn Future<Output = Result<String, CompletionError>> + Send + '_>> {
+            Box::pin(async move {
+                tokio::time::sleep(self.delay).await;
+                self.reply.map(str::to_string).map_err(|e| e.into())
+            })
+        }
+
+        fn complete_stream(
+            &self,
+            _messages: Vec<ChatMessage>,
+            _callback: Box<dyn FnMut(String) -> bool + Send>,
+        ) -> Pin<Box<dyn Future<Output = Result<(), CompletionError>> + Send + '_>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_collects_all_models() {
+        let models: Vec<(String, Arc<dyn ChatCompletion>)> = vec![
+            ("fast-model".to_string(), Arc::new(FakeModel { reply: Ok("4"), delay: Duration::from_millis(5) })),
+            ("broken-model".to_string(), Arc::new(FakeModel { reply: Err("rate limited"), delay: Duration::ZERO })),
+            ("slow-model".to_string(), Arc::new(FakeModel { reply: Ok("The answer is 4."), delay: Duration::from_millis(40) })),
+        ];
+
+        let results = run_benchmark("What is 2 + 2?", &models, 1).await;
+        assert_eq!(results.len(), 3);
+
+        assert_eq!(results[0].outcome.as_deref(), Ok("4"));
+        assert_eq!(results[1].outcome, Err("rate limited".to_string()));
+        assert_eq!(results[2].outcome.as_deref(), Ok("The answer is 4."));
+        assert!(results[0].latency >= Duration::from_millis(5));
+        assert!(results[2].latency >= Duration::from_millis(40));
+        assert!(results[2].response_tokens > results[0].response_tokens);
+        assert_eq!(results[1].response_tokens, 0);
+
+        let report = format_comparison("What is 2 + 2?", &results);
+        assert!(report.contains("fast-model"));
+        assert!(report.contains("The answer is 4."));
+        assert!(report.contains("rate limited"));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_benchmark_preserves_order() {
+        let models: Vec<(String, Arc<dyn ChatCompletion>)> = vec![
+            ("slow-model".to_string(), Arc::new(FakeModel { reply: Ok("slow"), delay: Duration::from_millis(200) })),
+            ("broken-model".to_string(), Arc::new(FakeModel { reply: Err("rate limited"), delay: Duration::from_millis(10) })),
+            ("fast-model".to_string(), Arc::new(FakeModel { reply: Ok("fast"), delay: Duration::from_millis(10) })),
+        ];
+
+        let started = Instant::now();
+        let results = run_benchmark("ping", &models, 3).await;
+        // 并发执行：总耗时接近最慢的模型，而不是三者之和
+        assert!(started.elapsed() < Duration::from_millis(400));
+
+        let names: Vec<&str> = results.iter().map(|r| r.model.as_str()).collect();
+        assert_eq!(names, vec!["slow-model", "broken-model", "fast-model"]);
+        assert_eq!(results[0].outcome.as_deref(), Ok("slow"));
+        assert_eq!(results[1].outcome, Err("rate limited".to_string()));
+        assert_eq!(results[2].outcome.as_deref(), Ok("fast"));
+    }
+}
@@ -1,10 +1,15 @@
-use crate::ai::config::LLMConfig;
+use crate::ai::config::{LLMConfig, LLMProvider};
 use crate::tools::ToolDefinition;
-use futures_util::StreamExt;
+use futures_util::{Stream, StreamExt};
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
 use std::time::Duration;
 
+/// 单次 LLM 请求（含流式读取）的超时时间
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
 #[derive(Clone)]
 pub struct LLMClient {
     client: reqwest::Client,
@@ -30,6 +35,46 @@ pub struct ChatMessage {
     pub content: String,
 }
 
+/// 系统提示的放置方式（LLMConfig::system_message_policy，按模型配置）
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum SystemMessagePolicy {
+    /// 作为 `system` 角色的消息发送
+    #[default]
+    SystemRole,
+    /// 合并到第一条用户消息的开头（提供商拒绝 `system` 角色时）
+    PrependToFirstUser,
+}
+
+impl SystemMessagePolicy {
+    /// 解析 LLM_SYSTEM_MESSAGE 的取值：`system` 或 `prepend`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "system" => Some(SystemMessagePolicy::SystemRole),
+            "prepend" | "user" => Some(SystemMessagePolicy::PrependToFirstUser),
+            _ => None,
+        }
+    }
+
+    /// 按策略整理消息；PrependToFirstUser 时所有系统消息按顺序合并到第一条用户消息前，
+    /// 没有用户消息时改为一条用户消息
+    pub fn apply(&self, messages: Vec<ChatMessage>) -> Vec<ChatMessage> {
+        if *self == SystemMessagePolicy::SystemRole {
+            return messages;
+        }
+        let (system, mut rest): (Vec<ChatMessage>, Vec<ChatMessage>) =
+            messages.into_iter().partition(|message| message.role == "system");
+        if system.is_empty() {
+            return rest;
+        }
+        let system = system.into_iter().map(|message| message.content).collect::<Vec<_>>().join("\n\n");
+        match rest.iter_mut().find(|message| message.role == "user") {
+            Some(first_user) => first_user.content = format!("{}\n\n{}", system, first_user.content),
+            None => rest.insert(0, ChatMessage { role: "user".to_string(), content: system }),
+        }
+        rest
+    }
+}
+
 #[derive(Debug, Serialize, Clone)]
 struct ToolDefinitionForLLM {
     #[serde(rename = "type")]
@@ -46,12 +91,157 @@ struct FunctionDefinition {
 
 #[derive(Debug, Deserialize)]
 struct StreamChunkData {
+    // 只带 usage 的最后一块可能没有 choices
+    #[serde(default)]
     choices: Vec<StreamChoice>,
+    #[serde(default)]
+    usage: Option<ResponseUsage>,
+}
+
+/// 流式回复结束时提供商报告的用量和结束原因（不是每个提供商都会发送）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamUsage {
+    /// (prompt, completion) tokens
+    pub tokens: Option<(usize, usize)>,
+    pub finish_reason: Option<String>,
+}
+
+impl StreamUsage {
+    /// 转换为 CompletionMetadata；提供商没有报告用量时按 messages 和回复估算
+    pub fn into_metadata(self, model: &str, messages: &[ChatMessage], reply: &str) -> CompletionMetadata {
+        match self.tokens {
+            Some((prompt_tokens, completion_tokens)) => CompletionMetadata {
+                prompt_tokens,
+                completion_tokens,
+                finish_reason: self.finish_reason,
+                estimated: false,
+            },
+            None => CompletionMetadata {
+                finish_reason: self.finish_reason,
+                ..CompletionMetadata::estimate(model, messages, reply)
+            },
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 struct StreamChoice {
     delta: Option<Delta>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+/// 流在收到 `[DONE]` / finish_reason 之前被提前终止（连接重置、EOF 等）
+///
+/// 这类错误可以重试：已经收到的部分内容通过回调保留了下来，调用方可以基于它继续生成。
+#[derive(Debug, Clone)]
+pub struct StreamInterrupted {
+    pub reason: String,
+}
+
+impl std::fmt::Display for StreamInterrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "stream interrupted before completion: {}", self.reason)
+    }
+}
+
+impl std::error::Error for StreamInterrupted {}
+
+/// 判断流式请求的错误是否可重试
+pub fn is_retryable_stream_error(err: &(dyn std::error::Error + 'static)) -> bool {
+    if err.is::<StreamInterrupted>() {
+        return true;
+    }
+    match err.downcast_ref::<reqwest::Error>() {
+        Some(e) => e.is_timeout() || e.is_connect() || e.is_body(),
+        None => false,
+    }
+}
+
+/// 提供商返回的非 2xx 响应，message 是从响应体中提取出的可读信息
+#[derive(Debug, Clone)]
+pub struct ProviderError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl std::fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "请求失败 ({}): {}", self.status, self.message)
+    }
+}
+
+impl std::error::Error for ProviderError {}
+
+/// 错误响应体中最多保留的字符数（纯文本错误可能是整段 HTML）
+const MAX_ERROR_BODY_CHARS: usize = 300;
+
+/// 从错误响应体中提取可读信息，按提供商优先尝试其格式，再回退到其他已知格式：
+/// - OpenAI 兼容：`{"error": {"message", "type", "code"}}`
+/// - Anthropic：`{"type": "error", "error": {"type", "message"}}`
+/// - Ollama：`{"error": "..."}` 或纯文本
+/// - Gemini：以上格式外再包一层数组
+pub fn parse_error_body(provider: &LLMProvider, status: u16, body: &str) -> String {
+    let message = match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(json) => {
+            // Gemini 的 OpenAI 兼容接口会把错误包在数组里
+            let json = match json {
+                serde_json::Value::Array(mut items) if !items.is_empty() => items.swap_remove(0),
+                other => other,
+            };
+            let parsers: [fn(&serde_json::Value) -> Option<String>; 3] = match provider {
+                LLMProvider::Claude => [anthropic_error, openai_error, ollama_error],
+                LLMProvider::Ollama => [ollama_error, openai_error, anthropic_error],
+                _ => [openai_error, anthropic_error, ollama_error],
+            };
+            parsers.iter().find_map(|parse| parse(&json))
+        }
+        Err(_) => {
+            let text = body.trim();
+            (!text.is_empty()).then(|| text.chars().take(MAX_ERROR_BODY_CHARS).collect())
+        }
+    };
+
+    message.unwrap_or_else(|| {
+        reqwest::StatusCode::from_u16(status)
+            .ok()
+            .and_then(|s| s.canonical_reason())
+            .unwrap_or("未知错误")
+            .to_string()
+    })
+}
+
+/// `{"error": {"message": ..., "code"/"type": ...}}`
+fn openai_error(json: &serde_json::Value) -> Option<String> {
+    let error = json.get("error")?;
+    let message = error.get("message")?.as_str()?;
+    let kind = error
+        .get("code")
+        .and_then(|c| c.as_str())
+        .or_else(|| error.get("type").and_then(|t| t.as_str()));
+    Some(with_kind(message, kind))
+}
+
+/// `{"type": "error", "error": {"type": ..., "message": ...}}`
+fn anthropic_error(json: &serde_json::Value) -> Option<String> {
+    if json.get("type").and_then(|t| t.as_str()) != Some("error") {
+        return None;
+    }
+    let error = json.get("error")?;
+    let message = error.get("message")?.as_str()?;
+    Some(with_kind(message, error.get("type").and_then(|t| t.as_str())))
+}
+
+/// `{"error": "..."}`
+fn ollama_error(json: &serde_json::Value) -> Option<String> {
+    json.get("error")?.as_str().map(str::to_string)
+}
+
+fn with_kind(message: &str, kind: Option<&str>) -> String {
+    match kind {
+        Some(kind) if !kind.is_empty() && !message.contains(kind) => format!("{} ({})", message, kind),
+        _ => message.to_string(),
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -62,17 +252,103 @@ struct Delta {
 #[derive(Debug, Deserialize)]
 struct NonStreamingResponse {
     choices: Vec<ResponseChoice>,
+    usage: Option<ResponseUsage>,
 }
 
 #[derive(Debug, Deserialize)]
 struct ResponseChoice {
     message: ResponseMessage,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseUsage {
+    prompt_tokens: usize,
+    completion_tokens: usize,
+}
+
+/// 一次回复的 Token 用量和结束原因
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletionMetadata {
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    /// 提供商返回的 finish_reason（stop / length / tool_calls ...）
+    pub finish_reason: Option<String>,
+    /// 提供商没有返回 usage，用量是估算的
+    pub estimated: bool,
+}
+
+impl CompletionMetadata {
+    /// 提供商没有返回 usage 时按模型估算
+    pub fn estimate(model: &str, messages: &[ChatMessage], reply: &str) -> Self {
+        let calculator = crate::core::token_calculator::TokenCalculator::from_model_name(model);
+        Self {
+            prompt_tokens: messages.iter().map(|m| calculator.count_tokens(&m.content)).sum(),
+            completion_tokens: calculator.count_tokens(reply),
+            finish_reason: None,
+            estimated: true,
+        }
+    }
+}
+
+/// 解析非流式响应体，返回回复内容和用量；不是 OpenAI 兼容格式时返回 None
+/// 响应没有 usage 时用 messages 估算
+pub fn parse_completion_response(model: &str, messages: &[ChatMessage], body: &str) -> Option<(String, CompletionMetadata)> {
+    let parsed = serde_json::from_str::<NonStreamingResponse>(body).ok()?;
+    let choice = parsed.choices.into_iter().next()?;
+    let content = choice.message.content.unwrap_or_default();
+    let metadata = match parsed.usage {
+        Some(usage) => CompletionMetadata {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            finish_reason: choice.finish_reason,
+            estimated: false,
+        },
+        None => CompletionMetadata {
+            finish_reason: choice.finish_reason,
+            ..CompletionMetadata::estimate(model, messages, &content)
+        },
+    };
+    Some((content, metadata))
 }
 
 #[derive(Debug, Deserialize)]
 struct ResponseMessage {
     role: String,
     content: Option<String>,
+    tool_calls: Option<Vec<ResponseToolCall>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseToolCall {
+    function: ResponseFunction,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseFunction {
+    name: String,
+    /// 提供商返回的是 JSON 字符串
+    arguments: String,
+}
+
+/// 解析非流式响应体中模型请求的工具调用；没有调用或格式不兼容时返回空列表
+/// 参数不是合法的 JSON 对象时按无参数处理，由工具自己报告缺少的参数
+pub fn parse_tool_calls(body: &str) -> Vec<crate::tools::ToolCall> {
+    let Ok(parsed) = serde_json::from_str::<NonStreamingResponse>(body) else {
+        return Vec::new();
+    };
+    parsed
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.message.tool_calls)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|call| crate::tools::ToolCall {
+            tool_name: call.function.name,
+            arguments: serde_json::from_str(&call.function.arguments).unwrap_or_default(),
+        })
+        .collect()
 }
 
 impl LLMClient {
@@ -88,13 +364,23 @@ impl LLMClient {
 
         let client = reqwest::Client::builder()
             .default_headers(headers)
-            .timeout(Duration::from_secs(300))
+            .timeout(REQUEST_TIMEOUT)
             .build()
             .unwrap();
 
         Self { client, config }
     }
 
+    /// 客户端使用的配置
+    pub fn config(&self) -> &LLMConfig {
+        &self.config
+    }
+
+    /// 按当前模型配置的系统提示策略整理要发送的消息
+    fn prepare_messages(&self, messages: Vec<ChatMessage>) -> Vec<ChatMessage> {
+        self.config.system_message_policy.apply(messages)
+    }
+
     /// 生成非流式响应（支持工具调用）
     pub async fn generate_completion(
         &self,
@@ -102,6 +388,53 @@ impl LLMClient {
         model_override: Option<String>,
         tools: Option<Vec<ToolDefinition>>,
     ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        self.generate_completion_with_metadata(messages, model_override, tools)
+            .await
+            .map(|(content, _)| content)
+    }
+
+    /// 生成非流式响应，同时返回提供商报告的 Token 用量和结束原因
+    pub async fn generate_completion_with_metadata(
+        &self,
+        messages: Vec<ChatMessage>,
+        model_override: Option<String>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<(String, CompletionMetadata), Box<dyn std::error::Error + Send + Sync>> {
+        let (model, messages, response_text) = self.send_completion(messages, model_override, tools).await?;
+
+        // 解析响应
+        if let Some(parsed) = parse_completion_response(&model, &messages, &response_text) {
+            return Ok(parsed);
+        }
+
+        let metadata = CompletionMetadata::estimate(&model, &messages, &response_text);
+        Ok((response_text, metadata))
+    }
+
+    /// 带工具定义生成一轮回复：返回回复内容和模型请求的工具调用
+    pub async fn generate_completion_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        model_override: Option<String>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<(String, Vec<crate::tools::ToolCall>), Box<dyn std::error::Error + Send + Sync>> {
+        let (model, messages, response_text) = self.send_completion(messages, model_override, tools).await?;
+
+        let tool_calls = parse_tool_calls(&response_text);
+        let content = match parse_completion_response(&model, &messages, &response_text) {
+            Some((content, _)) => content,
+            None => response_text,
+        };
+        Ok((content, tool_calls))
+    }
+
+    /// 发送非流式请求，返回实际使用的模型、发出的消息和原始响应体
+    async fn send_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        model_override: Option<String>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<(String, Vec<ChatMessage>, String), Box<dyn std::error::Error + Send + Sync>> {
         let has_tools = tools.is_some();
 
         // 转换工具定义格式
@@ -118,9 +451,11 @@ impl LLMClient {
                 .collect()
         });
 
+        let model = model_override.unwrap_or_else(|| self.config.model.clone());
+        let messages = self.prepare_messages(messages);
         let request_body = ChatCompletionRequest {
-            model: model_override.unwrap_or_else(|| self.config.model.clone()),
-            messages,
+            model: model.clone(),
+            messages: messages.clone(),
             temperature: self.config.temperature,
             max_tokens: self.config.max_tokens,
             stream: false,
@@ -139,17 +474,29 @@ impl LLMClient {
             .send()
             .await?;
 
+        let response = self.check_status(response).await?;
         let response_text = response.text().await?;
-        println!("LLM Response: {}", response_text);
+        Ok((model, messages, response_text))
+    }
 
-        // 解析响应
-        if let Ok(parsed) = serde_json::from_str::<NonStreamingResponse>(&response_text) {
-            if let Some(choice) = parsed.choices.get(0) {
-                return Ok(choice.message.content.clone().unwrap_or_default());
-            }
-        }
+    /// 为一批文本生成向量（OpenAI 兼容的 /embeddings，或 Ollama 的 /api/embed）
+    pub async fn generate_embeddings(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, CompletionError> {
+        let request_body = serde_json::json!({
+            "model": crate::ai::embeddings::embedding_model(&self.config.provider),
+            "input": inputs,
+        });
+
+        let response = self
+            .client
+            .post(crate::ai::embeddings::embeddings_url(&self.config.base_url))
+            .json(&request_body)
+            .send()
+            .await?;
+        let response = self.check_status(response).await?;
 
-        Ok(response_text)
+        let body: serde_json::Value = response.json().await?;
+        crate::ai::embeddings::parse_embeddings_response(&body)
+            .ok_or_else(|| "无法解析 embeddings 响应".into())
     }
 
     /// 生成流式响应
@@ -157,11 +504,25 @@ impl LLMClient {
         &self,
         messages: Vec<ChatMessage>,
         model_override: Option<String>,
-        mut callback: impl FnMut(String) -> bool + Send + 'static,
+        callback: impl FnMut(String) -> bool + Send + 'static,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.generate_completion_stream_with_metadata(messages, model_override, callback)
+            .await
+            .map(|_| ())
+    }
+
+    /// 生成流式响应，结束后返回提供商报告的用量和结束原因（没有报告时估算）
+    pub async fn generate_completion_stream_with_metadata(
+        &self,
+        messages: Vec<ChatMessage>,
+        model_override: Option<String>,
+        mut callback: impl FnMut(String) -> bool + Send,
+    ) -> Result<CompletionMetadata, Box<dyn std::error::Error + Send + Sync>> {
+        let model = model_override.unwrap_or_else(|| self.config.model.clone());
+        let messages = self.prepare_messages(messages);
         let request_body = ChatCompletionRequest {
-            model: model_override.unwrap_or_else(|| self.config.model.clone()),
-            messages,
+            model: model.clone(),
+            messages: messages.clone(),
             temperature: self.config.temperature,
             max_tokens: self.config.max_tokens,
             stream: true,
@@ -169,41 +530,38 @@ impl LLMClient {
             tool_choice: None,
         };
 
-        let mut stream = self
+        let response = self
             .client
             .post(&self.config.base_url)
             .json(&request_body)
             .send()
-            .await?
-            .bytes_stream();
-
-        while let Some(item) = stream.next().await {
-            let chunk = item?;
-            let chunk_str = String::from_utf8(chunk.to_vec())?;
-
-            for line in chunk_str.lines() {
-                if line.starts_with("data: ") {
-                    let data = &line[6..];
-                    if data == "[DONE]" {
-                        return Ok(());
-                    }
+            .await?;
+        let stream = self.check_status(response).await?.bytes_stream();
 
-                    if let Ok(stream_chunk) = serde_json::from_str::<StreamChunkData>(data) {
-                        if let Some(choice) = stream_chunk.choices.get(0) {
-                            if let Some(delta) = &choice.delta {
-                                if let Some(content) = &delta.content {
-                                    if !callback(content.clone()) {
-                                        return Ok(());
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        let mut reply = String::new();
+        let collect = |token: String| {
+            reply.push_str(&token);
+            callback(token)
+        };
+        // Ollama 的流是逐行 JSON，其他提供商是 OpenAI 风格的 SSE
+        let usage = match self.config.provider {
+            LLMProvider::Ollama => consume_ollama_stream(stream, collect).await?,
+            _ => consume_sse_stream(stream, collect).await?,
+        };
+        Ok(usage.into_metadata(&model, &messages, &reply))
+    }
 
-        Ok(())
+    /// 非 2xx 响应转换为带可读信息的 ProviderError
+    async fn check_status(&self, response: reqwest::Response) -> Result<reqwest::Response, ProviderError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        Err(ProviderError {
+            status: status.as_u16(),
+            message: parse_error_body(&self.config.provider, status.as_u16(), &body),
+        })
     }
 
     /// 转换工具参数到 JSON Schema 格式
@@ -240,3 +598,461 @@ impl LLMClient {
         })
     }
 }
+
+pub type CompletionError = Box<dyn std::error::Error + Send + Sync>;
+
+/// 聊天补全接口：App 通过它在流式与非流式请求之间切换（测试中可替换为假客户端）
+pub trait ChatCompletion: Send + Sync {
+    /// 一次性返回完整回复
+    fn complete(&self, messages: Vec<ChatMessage>) -> Pin<Box<dyn Future<Output = Result<String, CompletionError>> + Send + '_>>;
+
+    /// 逐块回调回复内容
+    fn complete_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        callback: Box<dyn FnMut(String) -> bool + Send>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), CompletionError>> + Send + '_>>;
+
+    /// 同 complete，并返回用量和结束原因；默认不提供
+    fn complete_with_metadata(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Pin<Box<dyn Future<Output = Result<(String, Option<CompletionMetadata>), CompletionError>> + Send + '_>> {
+        Box::pin(async move { self.complete(messages).await.map(|reply| (reply, None)) })
+    }
+
+    /// 同 complete_stream，结束后返回用量和结束原因；默认不提供
+    fn complete_stream_with_metadata(
+        &self,
+        messages: Vec<ChatMessage>,
+        callback: Box<dyn FnMut(String) -> bool + Send>,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<CompletionMetadata>, CompletionError>> + Send + '_>> {
+        Box::pin(async move { self.complete_stream(messages, callback).await.map(|_| None) })
+    }
+}
+
+impl ChatCompletion for LLMClient {
+    fn complete(&self, messages: Vec<ChatMessage>) -> Pin<Box<dyn Future<Output = Result<String, CompletionError>> + Send + '_>> {
+        Box::pin(self.generate_completion(messages, None, None))
+    }
+
+    fn complete_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        callback: Box<dyn FnMut(String) -> bool + Send>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), CompletionError>> + Send + '_>> {
+        Box::pin(self.generate_completion_stream(messages, None, callback))
+    }
+
+    fn complete_with_metadata(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Pin<Box<dyn Future<Output = Result<(String, Option<CompletionMetadata>), CompletionError>> + Send + '_>> {
+        Box::pin(async move {
+            self.generate_completion_with_metadata(messages, None, None)
+                .await
+                .map(|(reply, metadata)| (reply, Some(metadata)))
+        })
+    }
+
+    fn complete_stream_with_metadata(
+        &self,
+        messages: Vec<ChatMessage>,
+        callback: Box<dyn FnMut(String) -> bool + Send>,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<CompletionMetadata>, CompletionError>> + Send + '_>> {
+        Box::pin(async move {
+            self.generate_completion_stream_with_metadata(messages, None, callback)
+                .await
+                .map(Some)
+        })
+    }
+}
+
+/// 消费 SSE 数据流，将每个内容增量交给回调
+///
+/// 一行（或一个多字节字符）可能跨多个数据块，未换行的部分留到下一块再解析；
+/// 在收到 `[DONE]` 或 finish_reason 之前流结束（或连接出错）时返回 [`StreamInterrupted`]。
+async fn consume_sse_stream<S, B, E>(
+    mut stream: S,
+    mut callback: impl FnMut(String) -> bool,
+) -> Result<StreamUsage, Box<dyn std::error::Error + Send + Sync>>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
+    let mut usage = StreamUsage::default();
+    let mut pending: Vec<u8> = Vec::new();
+
+    while let Some(item) = stream.next().await {
+        let chunk = item.map_err(|e| StreamInterrupted { reason: e.to_string() })?;
+        pending.extend_from_slice(chunk.as_ref());
+
+        while let Some(newline) = pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=newline).collect();
+            let line = String::from_utf8(line)?;
+            let line = line.trim_end();
+            if line.starts_with("data: ") {
+                let data = &line[6..];
+                if data == "[DONE]" {
+                    return Ok(usage);
+                }
+
+                if let Ok(stream_chunk) = serde_json::from_str::<StreamChunkData>(data) {
+                    // 用量通常在 finish_reason 之后、[DONE] 之前单独发送
+                    if let Some(reported) = stream_chunk.usage {
+                        usage.tokens = Some((reported.prompt_tokens, reported.completion_tokens));
+                    }
+                    if let Some(choice) = stream_chunk.choices.get(0) {
+                        if let Some(delta) = &choice.delta {
+                            if let Some(content) = &delta.content {
+                                if !callback(content.clone()) {
+                                    return Ok(usage);
+                                }
+                            }
+                        }
+                        if choice.finish_reason.is_some() {
+                            usage.finish_reason = choice.finish_reason.clone();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if usage.finish_reason.is_some() {
+        Ok(usage)
+    } else {
+        Err(Box::new(StreamInterrupted {
+            reason: "connection closed without [DONE]".to_string(),
+        }))
+    }
+}
+
+/// 消费 Ollama 的 NDJSON 数据流：每行一个 JSON 对象，
+/// 内容在 `response`（/api/generate）或 `message.content`（/api/chat）中，`done: true` 表示结束。
+///
+/// 一行可能跨多个数据块，未换行的部分留到下一块再解析；
+/// 行内带 `error` 时返回该错误，在 `done: true` 之前流结束时返回 [`StreamInterrupted`]。
+async fn consume_ollama_stream<S, B, E>(
+    mut stream: S,
+    mut callback: impl FnMut(String) -> bool,
+) -> Result<StreamUsage, Box<dyn std::error::Error + Send + Sync>>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
+    let mut pending: Vec<u8> = Vec::new();
+
+    while let Some(item) = stream.next().await {
+        let chunk = item.map_err(|e| StreamInterrupted { reason: e.to_string() })?;
+        pending.extend_from_slice(chunk.as_ref());
+
+        while let Some(newline) = pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=newline).collect();
+            let line = String::from_utf8(line)?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let Ok(json) = serde_json::from_str::<serde_json::Value>(line) else {
+                continue;
+            };
+            if let Some(error) = json.get("error").and_then(|e| e.as_str()) {
+                return Err(error.to_string().into());
+            }
+            let token = json
+                .get("response")
+                .or_else(|| json.pointer("/message/content"))
+                .and_then(|t| t.as_str())
+                .unwrap_or_default();
+            if !token.is_empty() && !callback(token.to_string()) {
+                return Ok(StreamUsage::default());
+            }
+            if json.get("done").and_then(|d| d.as_bool()).unwrap_or(false) {
+                // 最后一行带 prompt_eval_count / eval_count 和 done_reason
+                let count = |key: &str| json.get(key).and_then(|c| c.as_u64()).map(|c| c as usize);
+                return Ok(StreamUsage {
+                    tokens: count("prompt_eval_count").zip(count("eval_count")),
+                    finish_reason: json.get("done_reason").and_then(|r| r.as_str()).map(str::to_string),
+                });
+            }
+        }
+    }
+
+    Err(Box::new(StreamInterrupted {
+        reason: "connection closed without done: true".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sse_chunk(content: &str) -> Result<Vec<u8>, String> {
+        Ok(format!(
+            "data: {{\"choices\":[{{\"delta\":{{\"content\":\"{}\"}}}}]}}\n\n",
+            content
+        )
+        .into_bytes())
+    }
+
+    #[test]
+    fn test_client_config_round_trips_every_field() {
+        let config = LLMConfig {
+            provider: crate::ai::config::LLMProvider::DeepSeek,
+            api_key: "sk-test".to_string(),
+            model: "deepseek-coder".to_string(),
+            base_url: "https://example.com/v1".to_string(),
+            temperature: 0.25,
+            max_tokens: 4096,
+            system_message_policy: SystemMessagePolicy::PrependToFirstUser,
+        };
+
+        let client = LLMClient::new(config.clone());
+        assert_eq!(client.config(), &config);
+    }
+
+    #[test]
+    fn test_usage_and_finish_reason_are_captured() {
+        let messages = vec![ChatMessage { role: "user".to_string(), content: "Write a long story".to_string() }];
+        let body = r#"{"choices":[{"message":{"role":"assistant","content":"Once upon"},"finish_reason":"length"}],
+            "usage":{"prompt_tokens":12,"completion_tokens":256,"total_tokens":268}}"#;
+
+        let (content, metadata) = parse_completion_response("gpt-4", &messages, body).unwrap();
+        assert_eq!(content, "Once upon");
+        assert_eq!(metadata.prompt_tokens, 12);
+        assert_eq!(metadata.completion_tokens, 256);
+        assert_eq!(metadata.finish_reason.as_deref(), Some("length"));
+        assert!(!metadata.estimated);
+
+        let ui = crate::ui::types::MessageMetadata::from(&metadata);
+        assert_eq!((ui.prompt_tokens, ui.tokens), (Some(12), Some(256)));
+        assert_eq!(ui.finish_reason.as_deref(), Some("length"));
+
+        // 没有 usage 时回退到估算
+        let body = r#"{"choices":[{"message":{"role":"assistant","content":"Once upon"},"finish_reason":"stop"}]}"#;
+        let (_, metadata) = parse_completion_response("gpt-4", &messages, body).unwrap();
+        assert!(metadata.estimated);
+        assert_eq!(metadata, CompletionMetadata {
+            finish_reason: Some("stop".to_string()),
+            ..CompletionMetadata::estimate("gpt-4", &messages, "Once upon")
+        });
+        assert!(metadata.completion_tokens > 0);
+    }
+
+    #[test]
+    fn test_tool_calls_are_parsed_from_response() {
+        let body = r#"{"choices":[{"message":{"role":"assistant","content":null,"tool_calls":[
+            {"id":"call_1","type":"function","function":{"name":"read_file","arguments":"{\"path\":\"src/main.rs\"}"}},
+            {"id":"call_2","type":"function","function":{"name":"list_dir","arguments":"not json"}}]},
+            "finish_reason":"tool_calls"}]}"#;
+
+        let calls = parse_tool_calls(body);
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].tool_name, "read_file");
+        assert_eq!(calls[0].arguments["path"], "src/main.rs");
+        assert_eq!(calls[1].tool_name, "list_dir");
+        assert!(calls[1].arguments.is_empty());
+
+        let body = r#"{"choices":[{"message":{"role":"assistant","content":"done"},"finish_reason":"stop"}]}"#;
+        assert!(parse_tool_calls(body).is_empty());
+        assert!(parse_tool_calls("plain text").is_empty());
+    }
+
+    #[test]
+    fn test_provider_error_bodies_are_readable() {
+        let openai = r#"{"error":{"message":"The model `gpt-5x` does not exist","type":"invalid_request_error","code":"model_not_found"}}"#;
+        assert_eq!(
+            parse_error_body(&LLMProvider::OpenAI, 404, openai),
+            "The model `gpt-5x` does not exist (model_not_found)"
+        );
+
+        let anthropic = r#"{"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#;
+        assert_eq!(
+            parse_error_body(&LLMProvider::Claude, 401, anthropic),
+            "invalid x-api-key (authentication_error)"
+        );
+
+        let ollama_json = r#"{"error":"model 'llama9' not found, try pulling it first"}"#;
+        assert_eq!(
+            parse_error_body(&LLMProvider::Ollama, 404, ollama_json),
+            "model 'llama9' not found, try pulling it first"
+        );
+        assert_eq!(parse_error_body(&LLMProvider::Ollama, 500, "  model is loading\n"), "model is loading");
+
+        let gemini = r#"[{"error":{"code":400,"message":"API key not valid. Please pass a valid API key.","status":"INVALID_ARGUMENT"}}]"#;
+        assert_eq!(
+            parse_error_body(&LLMProvider::Gemini, 400, gemini),
+            "API key not valid. Please pass a valid API key."
+        );
+
+        // 空响应体时回退到状态码说明
+        assert_eq!(parse_error_body(&LLMProvider::OpenAI, 429, ""), "Too Many Requests");
+
+        let error = ProviderError { status: 401, message: "invalid x-api-key".to_string() };
+        assert_eq!(error.to_string(), "请求失败 (401): invalid x-api-key");
+    }
+
+    #[tokio::test]
+    async fn test_stream_cut_short_is_retryable_and_keeps_partial() {
+        let stream = futures_util::stream::iter(vec![sse_chunk("Hello"), sse_chunk(", wor")]);
+        let mut received = String::new();
+
+        let result = consume_sse_stream(stream, |token| {
+            received.push_str(&token);
+            true
+        })
+        .await;
+
+        let err = result.unwrap_err();
+        assert!(is_retryable_stream_error(err.as_ref()));
+        assert_eq!(received, "Hello, wor");
+    }
+
+    #[tokio::test]
+    async fn test_connection_reset_mid_stream_is_retryable() {
+        let stream = futures_util::stream::iter(vec![
+            sse_chunk("partial"),
+            Err("connection reset by peer".to_string()),
+        ]);
+        let mut received = String::new();
+
+        let result = consume_sse_stream(stream, |token| {
+            received.push_str(&token);
+            true
+        })
+        .await;
+
+        assert!(is_retryable_stream_error(result.unwrap_err().as_ref()));
+        assert_eq!(received, "partial");
+    }
+
+    #[tokio::test]
+    async fn test_stream_with_done_marker_completes() {
+        let stream = futures_util::stream::iter(vec![
+            sse_chunk("done"),
+            Ok(b"data: [DONE]\n\n".to_vec()),
+        ]);
+
+        assert!(consume_sse_stream(stream, |_| true).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_sse_lines_split_across_chunks() {
+        let token = "data: {\"choices\":[{\"delta\":{\"content\":\"你好\"}}]}\n\n".as_bytes();
+        // 在“你”的 UTF-8 字节中间切开
+        let cut = token.iter().position(|&b| b >= 0x80).unwrap() + 1;
+        let stream = futures_util::stream::iter(vec![
+            Ok::<_, String>(token[..cut].to_vec()),
+            Ok(token[cut..].to_vec()),
+            Ok(b"data: [DO".to_vec()),
+            Ok(b"NE]\n\n".to_vec()),
+        ]);
+        let mut received = String::new();
+
+        let result = consume_sse_stream(stream, |token| {
+            received.push_str(&token);
+            true
+        })
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(received, "你好");
+    }
+
+    #[tokio::test]
+    async fn test_stream_usage_is_captured_from_final_chunks() {
+        let stream = futures_util::stream::iter(vec![
+            sse_chunk("Once upon"),
+            Ok(b"data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"length\"}]}\n\n".to_vec()),
+            Ok(b"data: {\"choices\":[],\"usage\":{\"prompt_tokens\":12,\"completion_tokens\":256}}\n\n".to_vec()),
+            Ok(b"data: [DONE]\n\n".to_vec()),
+        ]);
+        let usage = consume_sse_stream(stream, |_| true).await.unwrap();
+        assert_eq!(usage, StreamUsage { tokens: Some((12, 256)), finish_reason: Some("length".to_string()) });
+
+        let messages = vec![ChatMessage { role: "user".to_string(), content: "Write a long story".to_string() }];
+        let metadata = usage.into_metadata("gpt-4", &messages, "Once upon");
+        assert_eq!((metadata.prompt_tokens, metadata.completion_tokens), (12, 256));
+        assert_eq!(metadata.finish_reason.as_deref(), Some("length"));
+        assert!(!metadata.estimated);
+
+        // 提供商没有发送用量时估算
+        let stream = futures_util::stream::iter(vec![sse_chunk("Once upon"), Ok(b"data: [DONE]\n\n".to_vec())]);
+        let metadata = consume_sse_stream(stream, |_| true).await.unwrap().into_metadata("gpt-4", &messages, "Once upon");
+        assert_eq!(metadata, CompletionMetadata::estimate("gpt-4", &messages, "Once upon"));
+    }
+
+    #[tokio::test]
+    async fn test_ollama_stream_forwards_response_tokens_until_done() {
+        let body = concat!(
+            "{\"model\":\"mistral\",\"response\":\"Hel\",\"done\":false}\n",
+            "{\"model\":\"mistral\",\"response\":\"lo\",\"done\":false}\n",
+            "{\"model\":\"mistral\",\"message\":{\"role\":\"assistant\",\"content\":\", world\"},\"done\":false}\n",
+            "{\"model\":\"mistral\",\"response\":\"\",\"done\":true,\"done_reason\":\"stop\",\"prompt_eval_count\":9,\"eval_count\":3}\n",
+            "{\"model\":\"mistral\",\"response\":\"ignored\",\"done\":false}\n",
+        );
+        // 按固定大小切块，让 JSON 行跨越数据块边界
+        let chunks: Vec<Result<Vec<u8>, String>> = body.as_bytes().chunks(7).map(|c| Ok(c.to_vec())).collect();
+        let mut received = Vec::new();
+
+        let result = consume_ollama_stream(futures_util::stream::iter(chunks), |token| {
+            received.push(token);
+            true
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), StreamUsage { tokens: Some((9, 3)), finish_reason: Some("stop".to_string()) });
+        assert_eq!(received.concat(), "Hello, world");
+        assert!(received.iter().all(|token| !token.is_empty()));
+
+        // 没有 done: true 就结束的流可以重试
+        let cut = vec![Ok::<_, String>(b"{\"response\":\"partial\",\"done\":false}\n".to_vec())];
+        let err = consume_ollama_stream(futures_util::stream::iter(cut), |_| true).await.unwrap_err();
+        assert!(is_retryable_stream_error(err.as_ref()));
+
+        let failed = vec![Ok::<_, String>(b"{\"error\":\"model 'llama9' not found\"}\n".to_vec())];
+        let err = consume_ollama_stream(futures_util::stream::iter(failed), |_| true).await.unwrap_err();
+        assert_eq!(err.to_string(), "model 'llama9' not found");
+    }
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage { role: role.to_string(), content: content.to_string() }
+    }
+
+    #[test]
+    fn test_system_prompt_placed_by_model_policy() {
+        let messages = vec![
+            message("system", "You are a careful reviewer."),
+            message("system", "Project rules: no unwrap."),
+            message("user", "Review this diff"),
+            message("assistant", "Looks good"),
+            message("user", "And this one?"),
+        ];
+
+        // 默认对所有提供商都使用 system 角色
+        for config in [LLMConfig::default(), LLMConfig::default_gemini("key".to_string()), LLMConfig::default_ollama()] {
+            let sent = LLMClient::new(config).prepare_messages(messages.clone());
+            assert_eq!(sent.len(), 5);
+            assert_eq!(sent[0].role, "system");
+            assert_eq!(sent[0].content, "You are a careful reviewer.");
+        }
+
+        let prepend = SystemMessagePolicy::parse("prepend").unwrap();
+        let gemma = LLMClient::new(LLMConfig { model: "gemma2".to_string(), system_message_policy: prepend, ..LLMConfig::default_ollama() });
+        let sent = gemma.prepare_messages(messages);
+        let roles: Vec<&str> = sent.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["user", "assistant", "user"]);
+        assert_eq!(sent[0].content, "You are a careful reviewer.\n\nProject rules: no unwrap.\n\nReview this diff");
+        assert_eq!(sent[2].content, "And this one?");
+
+        // 只有系统提示时改为一条用户消息
+        let sent = gemma.prepare_messages(vec![message("system", "Summarize the project.")]);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].role, "user");
+        assert_eq!(sent[0].content, "Summarize the project.");
+    }
+}
@@ -1,6 +1,7 @@
 /// AI 代码修改检测和处理
 /// 基于 Aider 的 Search/Replace 块格式和模糊匹配策略
 
+use crate::core::diff::{compute_hunks, DiffHunk, DEFAULT_CONTEXT_LINES};
 use regex::Regex;
 use std::fs;
 
@@ -15,6 +16,51 @@ pub enum CodeModificationOp {
     Delete { path: String },
 }
 
+impl CodeModificationOp {
+    /// 去掉模型从带行号的上下文中照抄过来的 `N: ` 前缀
+    pub fn without_line_numbers(self) -> Self {
+        match self {
+            Self::Create { path, content } => Self::Create { path, content: strip_line_numbers(&content) },
+            Self::Modify { path, search, replace } => Self::Modify {
+                path,
+                search: strip_line_numbers(&search),
+                replace: strip_line_numbers(&replace),
+            },
+            delete @ Self::Delete { .. } => delete,
+        }
+    }
+}
+
+/// 去掉每行开头的 `N: ` 行号前缀
+///
+/// 只有当所有非空行都带前缀且行号连续递增时才去掉，避免误伤本身以数字开头的内容
+pub fn strip_line_numbers(text: &str) -> String {
+    let re = Regex::new(r"^\s*(\d+): ?(.*)$").unwrap();
+    let mut stripped = Vec::new();
+    let mut previous: Option<usize> = None;
+
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            stripped.push(line);
+            continue;
+        }
+        let Some(cap) = re.captures(line) else {
+            return text.to_string();
+        };
+        let number: usize = cap[1].parse().unwrap_or(0);
+        if previous.is_some_and(|p| number <= p) {
+            return text.to_string();
+        }
+        previous = Some(number);
+        stripped.push(cap.get(2).map_or("", |m| m.as_str()));
+    }
+
+    if previous.is_none() {
+        return text.to_string();
+    }
+    stripped.join("\n")
+}
+
 /// 代码修改结果
 #[derive(Debug, Clone)]
 pub struct CodeModificationResult {
@@ -33,6 +79,15 @@ pub struct CodeDiff {
     pub file_path: String,
     pub old_content: String,
     pub new_content: String,
+    /// 创建时计算一次的差异，显示时直接使用
+    pub hunks: Vec<DiffHunk>,
+}
+
+impl CodeDiff {
+    pub fn new(file_path: impl Into<String>, old_content: String, new_content: String) -> Self {
+        let hunks = compute_hunks(&old_content, &new_content, DEFAULT_CONTEXT_LINES);
+        Self { file_path: file_path.into(), old_content, new_content, hunks }
+    }
 }
 
 /// AI 响应中的代码块
@@ -40,6 +95,38 @@ pub struct CodeDiff {
 pub struct CodeBlock {
     pub language: String,
     pub content: String,
+    /// 开始围栏（```）在原文中的行号（从0开始）
+    pub start_line: usize,
+    /// 结束围栏在原文中的行号（从0开始）；content 已去掉首尾空白，不能用它的行数推算
+    pub end_line: usize,
+}
+
+/// 解析文本中所有的 ``` 围栏代码块（代码修改检测与 /blocks 命令共用）
+pub fn parse_code_blocks(text: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let re = Regex::new(r"```(\w*)\n([\s\S]*?)```").unwrap();
+
+    for cap in re.captures_iter(text) {
+        let (start, end) = cap.get(0).map(|m| (m.start(), m.end())).unwrap_or((0, 0));
+        let language = cap.get(1)
+            .map(|m| m.as_str())
+            .unwrap_or("")
+            .to_string();
+        let content = cap.get(2)
+            .map(|m| m.as_str())
+            .unwrap_or("")
+            .trim()
+            .to_string();
+
+        blocks.push(CodeBlock {
+            language: if language.is_empty() { "text".to_string() } else { language },
+            content,
+            start_line: text[..start].matches('\n').count(),
+            end_line: text[..end].matches('\n').count(),
+        });
+    }
+
+    blocks
 }
 
 /// AI 代码修改检测器
@@ -58,7 +145,7 @@ impl AICodeModificationDetector {
         let mut operations = Vec::new();
         
         // 提取所有代码块
-        let code_blocks = Self::extract_code_blocks(response);
+        let code_blocks = parse_code_blocks(response);
         
         // 优先检测 Search/Replace 块格式（Aider 风格）
         let search_replace_ops = Self::detect_search_replace_blocks(response);
@@ -197,34 +284,9 @@ impl AICodeModificationDetector {
         None
     }
 
-    /// 提取代码块
-    fn extract_code_blocks(response: &str) -> Vec<CodeBlock> {
-        let mut blocks = Vec::new();
-        let re = Regex::new(r"```(\w*)\n([\s\S]*?)```").unwrap();
-        
-        for cap in re.captures_iter(response) {
-            let language = cap.get(1)
-                .map(|m| m.as_str())
-                .unwrap_or("")
-                .to_string();
-            let content = cap.get(2)
-                .map(|m| m.as_str())
-                .unwrap_or("")
-                .trim()
-                .to_string();
-            
-            blocks.push(CodeBlock {
-                language: if language.is_empty() { "text".to_string() } else { language },
-                content,
-            });
-        }
-        
-        blocks
-    }
-
     /// 检测创建文件指令
     fn detect_create_instructions(response: &str) -> Option<Vec<(String, usize)>> {
-        let re = Regex::new(r"(?:create|new)\s+(?:file\s+)?`([^`]+)`").unwrap();
+        let re = Regex::new(r"(?i)(?:create|new)\s+(?:file\s+)?`([^`]+)`").unwrap();
         let mut results = Vec::new();
         let mut block_idx = 0;
         
@@ -240,7 +302,7 @@ impl AICodeModificationDetector {
 
     /// 检测修改文件指令
     fn detect_modify_instructions(response: &str) -> Option<Vec<(String, usize)>> {
-        let re = Regex::new(r"(?:modify|update|change|edit|replace)\s+(?:file\s+)?`([^`]+)`").unwrap();
+        let re = Regex::new(r"(?i)(?:modify|update|change|edit|replace)\s+(?:file\s+)?`([^`]+)`").unwrap();
         let mut results = Vec::new();
         let mut block_idx = 0;
         
@@ -256,7 +318,7 @@ impl AICodeModificationDetector {
     
     /// 检测隐含的代码修改意图（当有代码块但没有明确指令时）
     pub fn detect_implicit_modifications(response: &str) -> Vec<CodeModificationOp> {
-        let code_blocks = Self::extract_code_blocks(response);
+        let code_blocks = parse_code_blocks(response);
         let mut operations = Vec::new();
 
         // 如果有代码块，检查是否有隐含的修改意图
@@ -323,7 +385,7 @@ impl AICodeModificationDetector {
 
     /// 检测删除文件指令
     fn detect_delete_instructions(response: &str) -> Option<Vec<String>> {
-        let re = Regex::new(r"(?:delete|remove)\s+(?:file\s+)?`([^`]+)`").unwrap();
+        let re = Regex::new(r"(?i)(?:delete|remove)\s+(?:file\s+)?`([^`]+)`").unwrap();
         let mut results = Vec::new();
         
         for cap in re.captures_iter(response) {
@@ -357,11 +419,7 @@ impl CodeMatcher {
         // 尝试精确匹配
         if old_content.contains(search) {
             let new_content = old_content.replace(search, replace);
-            return Ok(CodeDiff {
-                file_path: file_path.to_string(),
-                old_content,
-                new_content,
-            });
+            return Ok(CodeDiff::new(file_path, old_content, new_content));
         }
 
         // 尝试空白不敏感匹配
@@ -375,11 +433,7 @@ impl CodeMatcher {
                 let mut new_content = old_content.clone();
                 new_content.replace_range(start..end, replace);
                 
-                return Ok(CodeDiff {
-                    file_path: file_path.to_string(),
-                    old_content,
-                    new_content,
-                });
+                return Ok(CodeDiff::new(file_path, old_content, new_content));
             }
         }
 
@@ -389,6 +443,16 @@ impl CodeMatcher {
         ))
     }
 
+    /// 搜索块在文件中精确出现的次数（用于判断修改能否安全地自动应用）
+    pub fn count_exact_matches(file_path: &str, search: &str) -> Result<usize, String> {
+        if search.is_empty() {
+            return Ok(0);
+        }
+        let content = fs::read_to_string(file_path)
+            .map_err(|e| format!("无法读取文件: {}", e))?;
+        Ok(content.matches(search).count())
+    }
+
     /// 规范化空白（用于比较）
     fn normalize_whitespace(s: &str) -> String {
         s.lines()
@@ -505,6 +569,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_line_numbers_are_stripped_from_edits() {
+        let op = CodeModificationOp::Modify {
+            path: "src/lib.rs".to_string(),
+            search: "12: fn old() {\n13:     1\n14: }".to_string(),
+            replace: "12: fn new() {\n13:     2\n14: }".to_string(),
+        };
+
+        match op.without_line_numbers() {
+            CodeModificationOp::Modify { search, replace, .. } => {
+                assert_eq!(search, "fn old() {\n    1\n}");
+                assert_eq!(replace, "fn new() {\n    2\n}");
+            }
+            _ => panic!("Expected Modify operation"),
+        }
+
+        // 本身以数字开头、行号不连续的内容保持不变
+        assert_eq!(strip_line_numbers("3: a\n1: b"), "3: a\n1: b");
+        assert_eq!(strip_line_numbers("let x = 1;"), "let x = 1;");
+    }
+
     #[test]
     fn test_string_similarity() {
         assert_eq!(CodeMatcher::string_similarity("hello", "hello"), 1.0);
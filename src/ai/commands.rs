@@ -1,3 +1,5 @@
+use unicode_width::UnicodeWidthChar;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum CommandType {
     Help,
@@ -21,6 +23,39 @@ pub enum CommandType {
     ListProviders,  // /list-providers
     SaveConfig,     // /save-config
     LoadConfig,     // /load-config
+    Config,         // /config
+    // 代码块导航
+    Blocks,         // /blocks
+    Block,          // /block <n> [copy]
+    ChangeDir,      // /cd <dir>
+    Estimate,       // /estimate <text>
+    Budget,         // /budget [tokens|off]
+    Bash,           // /bash <command> [args]
+    AttachLastOutput, // /attach-last-output
+    ExportContext,  // /export-context <path>
+    BuildErrors,    // /build-errors [auto|ask|attach]
+    Search,         // /search [-r] <query>
+    Explain,        // /explain [on|off]
+    Verbose,        // /verbose [on|off|show]
+    Safe,           // /safe [on|off]
+    Yolo,           // /yolo [on|off]
+    Undo,           // /undo
+    Why,            // /why
+    Goto,           // /goto <index>
+    Pin,            // /pin <index>
+    Unpin,          // /unpin <index>
+    System,         // /system [set <text> | clear]
+    Timestamps,     // /timestamps [on|off|relative]
+    Stream,         // /stream [on|off]
+    Density,        // /density [compact|comfortable]
+    LineNumbers,    // /linenumbers [on|off]
+    Mentions,       // /mentions [fuzzy|semantic] [N]
+    Benchmark,      // /benchmark [--models a,b] [--concurrency N] <prompt>
+    Snippet,        // /snippet <name> [file]
+    CommitMsg,      // /commit-msg [conventional|gitmoji|plain]
+    Compose,        // /compose
+    Retry,          // /retry
+    File,           // /read-file 等文件命令，由 FileCommandHandler 执行
     Unknown,
 }
 
@@ -30,12 +65,207 @@ pub struct Command {
     pub args: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
+/// 帮助文本中的分组
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HelpSection {
+    Basic,
+    Config,
+    QuickConfig,
+    ConfigFile,
+    File,
+}
+
+impl HelpSection {
+    const ALL: [HelpSection; 5] = [
+        HelpSection::Basic,
+        HelpSection::File,
+        HelpSection::Config,
+        HelpSection::QuickConfig,
+        HelpSection::ConfigFile,
+    ];
+
+    fn title(&self) -> &'static str {
+        match self {
+            HelpSection::Basic => "基础命令",
+            HelpSection::Config => "配置命令",
+            HelpSection::QuickConfig => "快速配置",
+            HelpSection::ConfigFile => "配置管理",
+            HelpSection::File => "文件命令",
+        }
+    }
+}
+
+/// 命令注册表中的一项：解析、/help 和命令提示都从这里读取
+pub struct CommandSpec {
+    pub command_type: CommandType,
+    /// 第一个为主名称，其余为别名（不含 `/`）
+    pub names: &'static [&'static str],
+    /// 参数说明，如 `[on|off]`
+    pub usage: &'static str,
+    pub description: &'static str,
+    pub section: HelpSection,
+}
+
+macro_rules! command {
+    ($section:ident, $command_type:ident, [$($name:literal),+], $usage:literal, $description:literal) => {
+        CommandSpec {
+            command_type: CommandType::$command_type,
+            names: &[$($name),+],
+            usage: $usage,
+            description: $description,
+            section: HelpSection::$section,
+        }
+    };
+}
+
+/// 所有斜杠命令，按 /help 中的显示顺序排列
+pub const COMMANDS: &[CommandSpec] = &[
+    command!(Basic, Help, ["help", "h"], "", "显示此帮助信息"),
+    command!(Basic, Clear, ["clear", "c"], "[--force]", "清除聊天历史（保留置顶，--force 全部）"),
+    command!(Basic, History, ["history", "hist"], "", "显示聊天历史"),
+    command!(Basic, Status, ["status", "s"], "", "显示应用状态"),
+    command!(Basic, ListProviders, ["list-providers", "lp"], "", "列出所有可用的 AI 提供商"),
+    command!(Basic, Blocks, ["blocks"], "", "列出对话中的所有代码块"),
+    command!(Basic, Block, ["block"], "N [copy]", "跳转到第 N 个代码块（或复制它）"),
+    command!(Basic, Search, ["search"], "[-r] <query>", "搜索当前对话（-r 使用正则）"),
+    command!(Basic, Goto, ["goto"], "N", "跳转到第 N 条消息"),
+    command!(Basic, Pin, ["pin"], "N", "置顶第 N 条消息"),
+    command!(Basic, Unpin, ["unpin"], "N", "取消置顶第 N 条消息"),
+    command!(Basic, Explain, ["explain"], "[on|off]", "工具执行前用通俗语言说明操作"),
+    command!(Basic, Verbose, ["verbose"], "[on|off|show]", "完整显示工具输出，或折叠为一行摘要"),
+    command!(Basic, Safe, ["safe"], "[on|off]", "安全模式：禁用写入和执行类工具"),
+    command!(Basic, Yolo, ["yolo"], "[on|off]", "唯一匹配的编辑自动应用"),
+    command!(Basic, Undo, ["undo"], "", "撤销最近一次应用的文件修改"),
+    command!(Basic, Why, ["why"], "", "说明上一轮的模型路由原因"),
+    command!(Basic, Benchmark, ["benchmark", "bench"], "<prompt>", "用同一提示对比多个模型的耗时和回复"),
+    command!(Basic, Estimate, ["estimate", "est"], "<text>", "预估发送前的 token 数"),
+    command!(Basic, Budget, ["budget"], "[tokens|off]", "设置附加文件 Token 预算并预览裁剪结果"),
+    command!(Basic, Bash, ["bash"], "<cmd> [args]", "执行终端命令（仅限安全命令）"),
+    command!(Basic, AttachLastOutput, ["attach-last-output", "alo"], "", "将上一条命令输出附加到下一轮对话"),
+    command!(Basic, ExportContext, ["export-context"], "<path>", "导出附加文件、系统提示和最后输入"),
+    command!(Basic, BuildErrors, ["build-errors", "be"], "[auto|ask|attach]", "构建失败后附加编译错误"),
+    command!(Basic, Snippet, ["snippet", "snip"], "<name> [file]", "插入代码片段，或用模板创建文件"),
+    command!(Basic, CommitMsg, ["commit-msg", "cm"], "[style]", "按风格为暂存区改动生成提交信息"),
+    command!(Basic, Retry, ["retry"], "", "重新发送上一条消息（丢弃失败的回复）"),
+    command!(Basic, Compose, ["compose"], "", "在 $EDITOR 中编写消息并载入输入框"),
+    command!(File, File, ["read-file"], "<path>", "读取文件内容"),
+    command!(File, File, ["list-dir"], "<path>", "列出目录内容"),
+    command!(File, File, ["search-files"], "<dir> <pattern>", "在目录中按名称搜索文件"),
+    command!(File, File, ["create-file"], "<path> [content]", "创建文件"),
+    command!(File, File, ["modify-file"], "<path> <content>", "用新内容替换文件（显示差异）"),
+    command!(File, File, ["modify-file-search"], "<path> <search> <replace>", "搜索替换修改文件"),
+    command!(File, File, ["delete-file"], "<path>", "删除文件"),
+    command!(Config, Provider, ["provider", "p"], "", "显示当前 LLM 提供商"),
+    command!(Config, Model, ["model", "m"], "[name]", "显示或设置模型"),
+    command!(Config, Temperature, ["temp", "temperature"], "N", "设置本次会话的温度 (0-2, reset 恢复)"),
+    command!(Config, MaxTokens, ["tokens", "max_tokens"], "N", "设置最大令牌数"),
+    command!(Config, SetProvider, ["set-provider", "sp"], "<provider>", "切换 AI 提供商"),
+    command!(Config, SetApiKey, ["set-api-key", "sak"], "<key>", "设置 API 密钥"),
+    command!(Config, SetModel, ["set-model", "sm"], "<model> [--project]", "设置模型，--project 保存为项目默认"),
+    command!(Config, SetBaseUrl, ["set-base-url", "sbu"], "<url>", "设置基础 URL"),
+    command!(Config, ChangeDir, ["cd"], "<dir>", "切换项目目录(加载项目模型)"),
+    command!(Config, System, ["system"], "[set <text> | clear]", "查看/设置会话系统提示"),
+    command!(Config, Timestamps, ["timestamps", "ts"], "<mode>", "时间戳: on/off/relative"),
+    command!(Config, Stream, ["stream"], "[on|off]", "切换流式/非流式响应"),
+    command!(Config, Density, ["density"], "[compact|comfortable]", "聊天显示密度：紧凑/舒适"),
+    command!(Config, LineNumbers, ["linenumbers", "ln"], "[on|off]", "@ 附加文件时是否带行号"),
+    command!(Config, Mentions, ["mentions"], "[fuzzy|semantic] [N]", "@ 提及的文件排序方式"),
+    command!(QuickConfig, ConfigOpenAI, ["config-openai", "openai"], "<api_key> [model]", "快速配置 OpenAI"),
+    command!(QuickConfig, ConfigClaude, ["config-claude", "claude"], "<api_key> [model]", "快速配置 Claude"),
+    command!(QuickConfig, ConfigGemini, ["config-gemini", "gemini"], "<api_key> [model]", "快速配置 Gemini"),
+    command!(QuickConfig, ConfigOllama, ["config-ollama", "ollama"], "[model] [url]", "快速配置 Ollama (本地)"),
+    command!(QuickConfig, ConfigLocal, ["config-local", "local"], "<url> [model]", "快速配置本地服务器"),
+    command!(ConfigFile, SaveConfig, ["save-config", "save"], "", "保存当前配置到 .env"),
+    command!(ConfigFile, LoadConfig, ["load-config", "load"], "", "从 .env 重新加载配置"),
+    command!(ConfigFile, Config, ["config"], "", "显示实际生效的配置（API Key 已掩码）"),
+];
+
+/// 帮助中列出的快捷键
+const KEY_BINDINGS: &[(&str, &str)] = &[
+    ("Ctrl+O", "打开模型切换浮层"),
+    ("Esc", "停止生成；空闲时清空输入框"),
+    ("Ctrl+C", "复制选中文本；生成中先停止，再按退出"),
+];
+
+/// 帮助中列出的提及
+const MENTIONS: &[(&str, &str)] = &[
+    ("@model", "提及当前模型"),
+    ("@provider", "提及当前提供商"),
+    ("@history", "附加最近的对话（按 Token 预算截取）"),
+    ("@file [filename]", "提及文件内容"),
+    ("@diff, @diff-staged", "附加当前 git diff"),
+];
+
+/// 帮助中的使用示例
+const EXAMPLES: &[(&str, &str)] = &[
+    ("/openai sk-xxx gpt-4", "配置 OpenAI GPT-4"),
+    ("/claude claude-key claude-3-opus", "配置 Claude Opus"),
+    ("/gemini gemini-key gemini-pro", "配置 Gemini Pro"),
+    ("/ollama llama2", "使用本地 Llama2"),
+    ("/sp openai", "切换到 OpenAI 提供商"),
+    ("/sm gpt-4-turbo", "切换到 GPT-4 Turbo"),
+];
+
+impl CommandSpec {
+    /// 按名称（不含 `/`）查找命令
+    pub fn find(name: &str) -> Option<&'static CommandSpec> {
+        COMMANDS.iter().find(|spec| spec.names.contains(&name))
+    }
+
+    /// 帮助中左侧的命令列，如 `/clear, /c [--force]`
+    pub fn synopsis(&self) -> String {
+        let names: Vec<String> = self.names.iter().map(|name| format!("/{}", name)).collect();
+        if self.usage.is_empty() {
+            names.join(", ")
+        } else {
+            format!("{} {}", names.join(", "), self.usage)
+        }
+    }
+}
+
+/// 帮助框内部宽度（显示列）
+const HELP_INNER_WIDTH: usize = 64;
+/// 左侧命令列的最小宽度
+const HELP_COLUMN_WIDTH: usize = 22;
+
+fn display_width(text: &str) -> usize {
+    text.chars().map(|c| c.width().unwrap_or(0)).sum()
+}
+
+/// 帮助框中的一行，补齐到框宽；放不下时说明换到下一行并缩进
+fn help_row(left: &str, description: &str, out: &mut String) {
+    let mut row = format!(" {}", left);
+    let left_width = display_width(&row);
+    if left_width < HELP_COLUMN_WIDTH + 1 {
+        row.push_str(&" ".repeat(HELP_COLUMN_WIDTH + 1 - left_width));
+    }
+    let entry = format!(" - {}", description);
+    if display_width(&row) + display_width(&entry) > HELP_INNER_WIDTH {
+        help_line(&row, out);
+        row = " ".repeat(HELP_COLUMN_WIDTH + 1);
+    }
+    row.push_str(&entry);
+    help_line(&row, out);
+}
+
+fn help_line(content: &str, out: &mut String) {
+    let padding = HELP_INNER_WIDTH.saturating_sub(display_width(content));
+    out.push_str(&format!("║{}{}║\n", content, " ".repeat(padding)));
+}
+
+fn help_header(title: &str, out: &mut String) {
+    out.push_str(&format!("╠{}╣\n", "═".repeat(HELP_INNER_WIDTH)));
+    help_line(&format!("{}{}", " ".repeat(20), title), out);
+    out.push_str(&format!("╠{}╣\n", "═".repeat(HELP_INNER_WIDTH)));
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum MentionType {
     Model,      // @model - 提及当前模型
     Provider,   // @provider - 提及当前提供商
     History,    // @history - 提及聊天历史
     File,       // @file - 提及文件
+    Diff,       // @diff / @diff-staged - 附加当前 git diff
     Unknown,
 }
 
@@ -45,56 +275,76 @@ pub struct Mention {
     pub target: String,
 }
 
+/// 按空白切分命令参数；以引号开头的参数可以包含空格（如 `"my dir/a b.txt"`），
+/// 引号内用 `\"` 转义引号。词中的引号（如 what's）按普通字符处理
+pub fn split_arguments(input: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            break;
+        };
+
+        let mut arg = String::new();
+        if first == '"' || first == '\'' {
+            chars.next();
+            let mut closed = false;
+            while let Some(c) = chars.next() {
+                if c == '\\' && chars.peek() == Some(&first) {
+                    arg.push(first);
+                    chars.next();
+                } else if c == first {
+                    closed = true;
+                    break;
+                } else {
+                    arg.push(c);
+                }
+            }
+            if !closed {
+                return Err(format!("引号 {} 未闭合: {}", first, input.trim()));
+            }
+        }
+        // 紧跟在闭合引号后的字符仍属于同一个参数
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+            arg.push(c);
+        }
+        args.push(arg);
+    }
+    Ok(args)
+}
+
 pub struct CommandParser;
 
 impl CommandParser {
-    /// 解析命令（以 / 开头）
+    /// 解析命令（以 / 开头）；参数解析失败（如引号未闭合）时返回 None
     pub fn parse(input: &str) -> Option<Command> {
+        Self::try_parse(input).ok().flatten()
+    }
+
+    /// 解析命令，参数中的引号未闭合时返回错误说明
+    pub fn try_parse(input: &str) -> Result<Option<Command>, String> {
         if !input.starts_with('/') {
-            return None;
+            return Ok(None);
         }
 
-        let trimmed = input[1..].trim();
-        let parts: Vec<&str> = trimmed.split_whitespace().collect();
+        let parts = split_arguments(&input[1..])?;
 
         if parts.is_empty() {
-            return None;
+            return Ok(None);
         }
 
-        let command_type = match parts[0] {
-            "help" | "h" => CommandType::Help,
-            "clear" | "c" => CommandType::Clear,
-            "history" | "hist" => CommandType::History,
-            "model" | "m" => CommandType::Model,
-            "temp" | "temperature" => CommandType::Temperature,
-            "tokens" | "max_tokens" => CommandType::MaxTokens,
-            "provider" | "p" => CommandType::Provider,
-            "status" | "s" => CommandType::Status,
-            // 新增的配置命令
-            "set-provider" | "sp" => CommandType::SetProvider,
-            "set-api-key" | "sak" => CommandType::SetApiKey,
-            "set-model" | "sm" => CommandType::SetModel,
-            "set-base-url" | "sbu" => CommandType::SetBaseUrl,
-            "config-openai" | "openai" => CommandType::ConfigOpenAI,
-            "config-claude" | "claude" => CommandType::ConfigClaude,
-            "config-gemini" | "gemini" => CommandType::ConfigGemini,
-            "config-ollama" | "ollama" => CommandType::ConfigOllama,
-            "config-local" | "local" => CommandType::ConfigLocal,
-            "list-providers" | "lp" => CommandType::ListProviders,
-            "save-config" | "save" => CommandType::SaveConfig,
-            "load-config" | "load" => CommandType::LoadConfig,
-            _ => CommandType::Unknown,
-        };
+        let command_type = CommandSpec::find(&parts[0])
+            .map(|spec| spec.command_type.clone())
+            .unwrap_or(CommandType::Unknown);
 
-        let args = parts[1..]
-            .iter()
-            .map(|s| s.to_string())
-            .collect();
+        let args = parts[1..].to_vec();
 
-        Some(Command {
+        Ok(Some(Command {
             command_type,
             args,
-        })
+        }))
     }
 
     /// 解析提及（以 @ 开头）
@@ -115,11 +365,15 @@ impl CommandParser {
             "provider" => MentionType::Provider,
             "history" => MentionType::History,
             "file" => MentionType::File,
+            "diff" | "diff-staged" => MentionType::Diff,
             _ => MentionType::Unknown,
         };
 
-        let target = parts[1..]
-            .join(" ");
+        let target = if parts[0] == "diff-staged" {
+            "staged".to_string()
+        } else {
+            parts[1..].join(" ")
+        };
 
         Some(Mention {
             mention_type,
@@ -168,60 +422,28 @@ impl CommandParser {
         mentions
     }
 
-    /// 获取命令帮助文本
+    /// 获取命令帮助文本（由命令注册表生成）
     pub fn get_help_text() -> String {
-        r#"
-╔════════════════════════════════════════════════════════════════╗
-║                    基础命令                                    ║
-╠════════════════════════════════════════════════════════════════╣
-║ /help, /h              - 显示此帮助信息                        ║
-║ /clear, /c             - 清除聊天历史                          ║
-║ /history, /hist        - 显示聊天历史                          ║
-║ /status, /s            - 显示应用状态                          ║
-║ /list-providers, /lp   - 列出所有可用的 AI 提供商              ║
-╠════════════════════════════════════════════════════════════════╣
-║                    配置命令                                    ║
-╠════════════════════════════════════════════════════════════════╣
-║ /provider, /p          - 显示当前 LLM 提供商                   ║
-║ /model, /m [name]      - 显示或设置模型                        ║
-║ /temp, /temperature N  - 设置温度参数 (0.0-1.0)               ║
-║ /tokens, /max_tokens N - 设置最大令牌数                        ║
-║                                                                ║
-║ /set-provider, /sp <provider>    - 切换 AI 提供商              ║
-║ /set-api-key, /sak <key>         - 设置 API 密钥               ║
-║ /set-model, /sm <model>          - 设置模型名称                ║
-║ /set-base-url, /sbu <url>        - 设置基础 URL                ║
-╠════════════════════════════════════════════════════════════════╣
-║                    快速配置                                    ║
-╠════════════════════════════════════════════════════════════════╣
-║ /openai <api_key> [model]        - 快速配置 OpenAI             ║
-║ /claude <api_key> [model]        - 快速配置 Claude             ║
-║ /gemini <api_key> [model]        - 快速配置 Gemini             ║
-║ /ollama [model] [url]            - 快速配置 Ollama (本地)      ║
-║ /local <url> [model]             - 快速配置本地服务器          ║
-╠════════════════════════════════════════════════════════════════╣
-║                    配置管理                                    ║
-╠════════════════════════════════════════════════════════════════╣
-║ /save-config, /save              - 保存当前配置到 .env         ║
-║ /load-config, /load              - 从 .env 重新加载配置        ║
-╠════════════════════════════════════════════════════════════════╣
-║                    可用提及                                    ║
-╠════════════════════════════════════════════════════════════════╣
-║ @model                 - 提及当前模型                          ║
-║ @provider              - 提及当前提供商                        ║
-║ @history               - 提及聊天历史                          ║
-║ @file [filename]       - 提及文件内容                          ║
-╠════════════════════════════════════════════════════════════════╣
-║                    使用示例                                    ║
-╠════════════════════════════════════════════════════════════════╣
-║ /openai sk-xxx gpt-4             - 配置 OpenAI GPT-4          ║
-║ /claude claude-key claude-3-opus - 配置 Claude Opus           ║
-║ /gemini gemini-key gemini-pro    - 配置 Gemini Pro            ║
-║ /ollama llama2                   - 使用本地 Llama2            ║
-║ /sp openai                       - 切换到 OpenAI 提供商        ║
-║ /sm gpt-4-turbo                  - 切换到 GPT-4 Turbo         ║
-╚════════════════════════════════════════════════════════════════╝
-"#.to_string()
+        let mut out = format!("\n╔{}╗\n", "═".repeat(HELP_INNER_WIDTH));
+        for (i, section) in HelpSection::ALL.iter().enumerate() {
+            if i == 0 {
+                help_line(&format!("{}{}", " ".repeat(20), section.title()), &mut out);
+                out.push_str(&format!("╠{}╣\n", "═".repeat(HELP_INNER_WIDTH)));
+            } else {
+                help_header(section.title(), &mut out);
+            }
+            for spec in COMMANDS.iter().filter(|spec| spec.section == *section) {
+                help_row(&spec.synopsis(), spec.description, &mut out);
+            }
+        }
+        for (title, rows) in [("快捷键", KEY_BINDINGS), ("可用提及", MENTIONS), ("使用示例", EXAMPLES)] {
+            help_header(title, &mut out);
+            for (left, description) in rows {
+                help_row(left, description, &mut out);
+            }
+        }
+        out.push_str(&format!("╚{}╝\n", "═".repeat(HELP_INNER_WIDTH)));
+        out
     }
 }
 
@@ -231,20 +453,62 @@ mod tests {
 
     #[test]
     fn test_parse_help_command() {
-        let cmd = CommandParser::parse_command("/help");
+        let cmd = CommandParser::parse("/help");
         assert!(cmd.is_some());
         assert_eq!(cmd.unwrap().command_type, CommandType::Help);
     }
 
     #[test]
     fn test_parse_model_command_with_args() {
-        let cmd = CommandParser::parse_command("/model gpt-4");
+        let cmd = CommandParser::parse("/model gpt-4");
         assert!(cmd.is_some());
         let cmd = cmd.unwrap();
         assert_eq!(cmd.command_type, CommandType::Model);
         assert_eq!(cmd.args, vec!["gpt-4"]);
     }
 
+    #[test]
+    fn test_quoted_arguments_keep_spaces() {
+        let cmd = CommandParser::parse("/bash cat \"my dir/a b.txt\" what's").unwrap();
+        assert_eq!(cmd.args, vec!["cat", "my dir/a b.txt", "what's"]);
+
+        assert_eq!(split_arguments(r#"say "a \"quoted\" word""#).unwrap(), vec!["say", "a \"quoted\" word"]);
+        assert!(CommandParser::try_parse("/cd \"my dir").unwrap_err().contains("未闭合"));
+        assert!(CommandParser::parse("/cd \"my dir").is_none());
+    }
+
+    #[test]
+    fn test_help_lists_every_parseable_command() {
+        let help = CommandParser::get_help_text();
+        for spec in COMMANDS {
+            for name in spec.names {
+                let parsed = CommandParser::parse(&format!("/{}", name)).unwrap();
+                assert_eq!(parsed.command_type, spec.command_type);
+                assert!(help.contains(&format!("/{}", name)), "/{} missing from help", name);
+            }
+        }
+        assert_eq!(CommandParser::parse("/no-such-command").unwrap().command_type, CommandType::Unknown);
+
+        // 每一行都对齐到帮助框宽度
+        for line in help.lines().filter(|line| !line.is_empty()) {
+            assert_eq!(display_width(line), HELP_INNER_WIDTH + 2, "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_file_commands_are_registered() {
+        use crate::commands::file_commands::FileCommandHandler;
+
+        let help = CommandParser::get_help_text();
+        assert!(help.contains("/read-file <path>"));
+        let file_specs: Vec<_> = COMMANDS.iter().filter(|spec| spec.command_type == CommandType::File).collect();
+        assert!(!file_specs.is_empty());
+        for spec in file_specs {
+            let input = format!("/{} a b c", spec.names[0]);
+            assert!(FileCommandHandler::parse_command(&input).is_some(), "{} is not a file command", input);
+        }
+    }
+
     #[test]
     fn test_parse_mention() {
         let mention = CommandParser::parse_mention("@model");
@@ -256,5 +520,10 @@ mod tests {
     fn test_extract_mentions() {
         let mentions = CommandParser::extract_mentions("Hey @model, what about @provider?");
         assert_eq!(mentions.len(), 2);
+
+        let mentions = CommandParser::extract_mentions("review @diff and @diff-staged");
+        assert_eq!(mentions[0].mention_type, MentionType::Diff);
+        assert_eq!(mentions[1].mention_type, MentionType::Diff);
+        assert_eq!(mentions[1].target, "staged");
     }
 }
@@ -0,0 +1,282 @@
+/// 提交信息生成
+/// 把暂存区 diff 和所选风格的提示模板交给模型，再把回复整理成符合风格、标题不超长、正文按列宽折行的提交信息
+
+use crate::ai::client::{ChatCompletion, ChatMessage, CompletionError};
+use std::path::Path;
+
+/// 项目级自定义提示模板（相对于项目根目录），存在时替代内置风格模板
+pub const PROJECT_COMMIT_TEMPLATE_FILE: &str = ".starfell/commit_template";
+/// 标题行最大字符数
+pub const SUBJECT_MAX_CHARS: usize = 72;
+/// 正文折行列宽
+pub const BODY_WRAP_WIDTH: usize = 72;
+/// 发给模型的 diff 最多保留的字符数
+const MAX_DIFF_CHARS: usize = 12_000;
+
+/// Conventional Commits 允许的类型
+const CONVENTIONAL_TYPES: &[&str] = &[
+    "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore", "revert",
+];
+
+/// 提交信息风格
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CommitStyle {
+    /// `feat(scope): summary`
+    #[default]
+    Conventional,
+    /// `✨ summary`
+    Gitmoji,
+    /// 普通的祈使句标题
+    Plain,
+}
+
+impl CommitStyle {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "conventional" | "cc" => Some(CommitStyle::Conventional),
+            "gitmoji" | "emoji" => Some(CommitStyle::Gitmoji),
+            "plain" => Some(CommitStyle::Plain),
+            _ => None,
+        }
+    }
+
+    /// 读取 COMMIT_MESSAGE_STYLE，未设置或无法解析时使用 Conventional
+    pub fn from_env() -> Self {
+        std::env::var("COMMIT_MESSAGE_STYLE")
+            .ok()
+            .and_then(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CommitStyle::Conventional => "conventional",
+            CommitStyle::Gitmoji => "gitmoji",
+            CommitStyle::Plain => "plain",
+        }
+    }
+
+    /// 内置提示模板；`{max_subject}` 和 `{diff}` 会被替换
+    pub fn template(&self) -> &'static str {
+        match self {
+            CommitStyle::Conventional => "Write a git commit message for the staged diff below using Conventional Commits.\n\
+                The subject must be `type(scope): summary` or `type: summary`, where type is one of \
+                feat, fix, docs, style, refactor, perf, test, build, ci, chore, revert.\n\
+                Keep the subject under {max_subject} characters, then a blank line and a short body explaining why.\n\
+                Reply with the commit message only.\n\n{diff}",
+            CommitStyle::Gitmoji => "Write a git commit message for the staged diff below using gitmoji.\n\
+                The subject must start with one fitting emoji (e.g. ✨ feature, 🐛 fix, 📝 docs, ♻️ refactor, ✅ tests) \
+                followed by a space and a summary.\n\
+                Keep the subject under {max_subject} characters, then a blank line and a short body explaining why.\n\
+                Reply with the commit message only.\n\n{diff}",
+            CommitStyle::Plain => "Write a git commit message for the staged diff below.\n\
+                The subject is a short imperative sentence without a type prefix or emoji.\n\
+                Keep the subject under {max_subject} characters, then a blank line and a short body explaining why.\n\
+                Reply with the commit message only.\n\n{diff}",
+        }
+    }
+
+    /// 标题是否符合该风格
+    pub fn matches(&self, subject: &str) -> bool {
+        match self {
+            CommitStyle::Conventional => conventional_prefix_len(subject).is_some(),
+            CommitStyle::Gitmoji => subject.chars().next().is_some_and(|c| !c.is_ascii()),
+            CommitStyle::Plain => conventional_prefix_len(subject).is_none(),
+        }
+    }
+}
+
+/// 读取项目的自定义模板（`.starfell/commit_template`）
+pub fn load_project_template(project_dir: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(project_dir.join(PROJECT_COMMIT_TEMPLATE_FILE)).ok()?;
+    if content.trim().is_empty() {
+        None
+    } else {
+        Some(content)
+    }
+}
+
+/// 用模板和 diff 生成提示；模板中没有 `{diff}` 时把 diff 附在末尾
+pub fn build_prompt(template: &str, diff: &str) -> String {
+    let diff = if diff.chars().count() > MAX_DIFF_CHARS {
+        let kept: String = diff.chars().take(MAX_DIFF_CHARS).collect();
+        format!("{}\n… (diff 已截断)", kept)
+    } else {
+        diff.to_string()
+    };
+    let diff_block = format!("```diff\n{}\n```", diff.trim_end());
+    let prompt = template.replace("{max_subject}", &SUBJECT_MAX_CHARS.to_string());
+    if prompt.contains("{diff}") {
+        prompt.replace("{diff}", &diff_block)
+    } else {
+        format!("{}\n\n{}", prompt.trim_end(), diff_block)
+    }
+}
+
+/// 请求模型生成提交信息并整理格式
+pub async fn generate_commit_message(
+    client: &dyn ChatCompletion,
+    diff: &str,
+    style: CommitStyle,
+    template: Option<&str>,
+) -> Result<String, CompletionError> {
+    if diff.trim().is_empty() {
+        return Err("暂存区没有改动".into());
+    }
+    let messages = vec![ChatMessage {
+        role: "user".to_string(),
+        content: build_prompt(template.unwrap_or(style.template()), diff),
+    }];
+    let reply = client.complete(messages).await?;
+    format_commit_message(&reply, style).ok_or_else(|| "模型没有返回提交信息".into())
+}
+
+/// 整理模型回复：去掉代码围栏，标题补齐风格前缀并截断到 SUBJECT_MAX_CHARS，正文按 BODY_WRAP_WIDTH 折行
+pub fn format_commit_message(reply: &str, style: CommitStyle) -> Option<String> {
+    let mut lines = reply
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .skip_while(|line| line.trim().is_empty());
+    let subject = lines.next()?.trim().trim_matches('`').trim();
+    if subject.is_empty() {
+        return None;
+    }
+
+    let subject = match style {
+        _ if style.matches(subject) => subject.to_string(),
+        CommitStyle::Conventional => format!("chore: {}", subject),
+        CommitStyle::Gitmoji => format!("🔧 {}", subject),
+        CommitStyle::Plain => subject[conventional_prefix_len(subject).unwrap_or(0)..].trim_start().to_string(),
+    };
+    let mut message = truncate_subject(&subject, SUBJECT_MAX_CHARS);
+
+    let body: Vec<&str> = lines.collect();
+    let body = wrap_body(&body, BODY_WRAP_WIDTH);
+    if !body.is_empty() {
+        message.push_str("\n\n");
+        message.push_str(&body);
+    }
+    Some(message)
+}
+
+/// `type(scope)!: ` 前缀的字节长度
+fn conventional_prefix_len(subject: &str) -> Option<usize> {
+    let colon = subject.find(": ")?;
+    let head = subject[..colon].trim_end_matches('!');
+    let kind = head.split_once('(').map_or(head, |(kind, scope)| {
+        if scope.ends_with(')') { kind } else { "" }
+    });
+    CONVENTIONAL_TYPES.contains(&kind).then_some(colon + 2)
+}
+
+/// 超长时在单词边界截断，没有合适的边界时按字符截断
+fn truncate_subject(subject: &str, max_chars: usize) -> String {
+    let subject = subject.trim_end_matches('.');
+    if subject.chars().count() <= max_chars {
+        return subject.to_string();
+    }
+    let cut: String = subject.chars().take(max_chars).collect();
+    match cut.rfind(' ') {
+        Some(space) if space > max_chars / 2 => cut[..space].trim_end_matches([',', ';', ':']).to_string(),
+        _ => cut,
+    }
+}
+
+/// 按段落折行；列表项（`-`、`*`）的续行缩进对齐
+fn wrap_body(lines: &[&str], width: usize) -> String {
+    let mut paragraphs: Vec<String> = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let flush = |current: &mut Vec<String>, paragraphs: &mut Vec<String>| {
+        if !current.is_empty() {
+            paragraphs.push(current.join("\n"));
+            current.clear();
+        }
+    };
+
+    for line in lines {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            flush(&mut current, &mut paragraphs);
+            continue;
+        }
+        let trimmed = line.trim_start();
+        let is_item = trimmed.starts_with("- ") || trimmed.starts_with("* ");
+        let indent = if is_item { "  " } else { "" };
+        let mut row = String::new();
+        for word in trimmed.split_whitespace() {
+            if !row.is_empty() && row.chars().count() + 1 + word.chars().count() > width {
+                current.push(std::mem::take(&mut row));
+                row.push_str(indent);
+            } else if !row.is_empty() && row != indent {
+                row.push(' ');
+            }
+            row.push_str(word);
+        }
+        current.push(row);
+    }
+    flush(&mut current, &mut paragraphs);
+    paragraphs.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Mutex;
+
+    /// 返回固定回复并记录收到的提示
+    struct FakeClient {
+        reply: &'static str,
+        prompt: Mutex<String>,
+    }
+
+    impl ChatCompletion for FakeClient {
+        fn complete(&self, messages: Vec<ChatMessage>) -> Pin<Box<dyn Future<Output = Result<String, CompletionError>> + Send + '_>> {
+            *self.prompt.lock().unwrap() = messages[0].content.clone();
+            Box::pin(async move { Ok(self.reply.to_string()) })
+        }
+
+        fn complete_stream(
+            &self,
+            _messages: Vec<ChatMessage>,
+            _callback: Box<dyn FnMut(String) -> bool + Send>,
+        ) -> Pin<Box<dyn Future<Output = Result<(), CompletionError>> + Send + '_>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    const DIFF: &str = "--- a/src/parser.rs\n+++ b/src/parser.rs\n@@ -1 +1 @@\n-fn parse() {}\n+fn parse() -> Result<()> { Ok(()) }\n";
+
+    #[tokio::test]
+    async fn test_generated_message_follows_style_and_subject_cap() {
+        let client = FakeClient {
+            reply: "```\nfeat(parser): return a Result from parse so callers can report malformed input instead of panicking\n\nThe parser used to panic on malformed input, which took down the whole editor whenever a half-typed file was opened.\n```",
+            prompt: Mutex::new(String::new()),
+        };
+        let message = generate_commit_message(&client, DIFF, CommitStyle::Conventional, None).await.unwrap();
+        let prompt = client.prompt.lock().unwrap().clone();
+        assert!(prompt.contains("Conventional Commits") && prompt.contains("+fn parse() -> Result<()>"));
+
+        let mut lines = message.lines();
+        let subject = lines.next().unwrap();
+        assert!(subject.starts_with("feat(parser): "));
+        assert!(subject.chars().count() <= SUBJECT_MAX_CHARS);
+        assert_eq!(lines.next(), Some(""));
+        assert!(lines.all(|line| line.chars().count() <= BODY_WRAP_WIDTH));
+
+        // 回复不符合所选风格时补上前缀
+        let client = FakeClient { reply: "Return a Result from parse", prompt: Mutex::new(String::new()) };
+        let gitmoji = generate_commit_message(&client, DIFF, CommitStyle::Gitmoji, None).await.unwrap();
+        assert!(CommitStyle::Gitmoji.matches(&gitmoji));
+        let conventional = generate_commit_message(&client, DIFF, CommitStyle::Conventional, None).await.unwrap();
+        assert_eq!(conventional, "chore: Return a Result from parse");
+
+        let client = FakeClient { reply: "fix: Return a Result from parse.", prompt: Mutex::new(String::new()) };
+        let plain = generate_commit_message(&client, DIFF, CommitStyle::Plain, Some("Custom rules.")).await.unwrap();
+        assert_eq!(plain, "Return a Result from parse");
+        assert!(client.prompt.lock().unwrap().starts_with("Custom rules.\n\n```diff"));
+
+        assert!(generate_commit_message(&client, "  \n", CommitStyle::Plain, None).await.is_err());
+    }
+}
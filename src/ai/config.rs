@@ -1,5 +1,10 @@
+use crate::ai::client::SystemMessagePolicy;
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::path::Path;
+
+/// 项目级模型偏好文件（相对于项目根目录）
+pub const PROJECT_MODEL_FILE: &str = ".starfell/model";
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum LLMProvider {
@@ -33,9 +38,35 @@ impl LLMProvider {
             LLMProvider::LocalServer => "local_server".to_string(),
         }
     }
+
+    /// 读取 API Key 的环境变量；本地提供商不需要 Key，返回 None
+    pub fn api_key_env_var(&self) -> Option<&'static str> {
+        match self {
+            LLMProvider::OpenAI => Some("OPENAI_API_KEY"),
+            LLMProvider::Gemini => Some("GEMINI_API_KEY"),
+            LLMProvider::Claude => Some("ANTHROPIC_API_KEY"),
+            LLMProvider::DeepSeek => Some("DEEPSEEK_API_KEY"),
+            LLMProvider::Ollama | LLMProvider::LocalServer => None,
+        }
+    }
+
+    /// 没有 API Key 时的提示：运行哪个快速配置命令或设置哪个环境变量
+    pub fn missing_api_key_guidance(&self) -> String {
+        let command = match self {
+            LLMProvider::Gemini => "/gemini <key>",
+            LLMProvider::Claude => "/claude <key>",
+            _ => "/openai <key>",
+        };
+        format!(
+            "⚠ 未配置 API Key — 运行 {} 或设置 {}（本地模型可用 /ollama）",
+            command,
+            self.api_key_env_var().unwrap_or("OPENAI_API_KEY")
+        )
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// LLM 配置（全项目唯一的配置类型，LLMClient 直接持有它，不做字段拷贝）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct LLMConfig {
     pub provider: LLMProvider,
     pub api_key: String,
@@ -43,6 +74,16 @@ pub struct LLMConfig {
     pub base_url: String,
     pub temperature: f32,
     pub max_tokens: u32,
+    /// 系统提示的放置方式：默认使用 `system` 角色，不接受该角色的模型（如部分 Ollama 模型）
+    /// 通过 LLM_SYSTEM_MESSAGE=prepend 改为合并到第一条用户消息
+    #[serde(default)]
+    pub system_message_policy: SystemMessagePolicy,
+}
+
+impl Default for LLMConfig {
+    fn default() -> Self {
+        Self::default_openai(String::new())
+    }
 }
 
 impl LLMConfig {
@@ -104,6 +145,11 @@ impl LLMConfig {
             .parse()
             .unwrap_or(200);
 
+        let system_message_policy = env::var("LLM_SYSTEM_MESSAGE")
+            .ok()
+            .and_then(|value| SystemMessagePolicy::parse(&value))
+            .unwrap_or_default();
+
         Ok(LLMConfig {
             provider,
             api_key,
@@ -111,9 +157,15 @@ impl LLMConfig {
             base_url,
             temperature,
             max_tokens,
+            system_message_policy,
         })
     }
 
+    /// 提供商需要 API Key 但没有配置
+    pub fn is_missing_api_key(&self) -> bool {
+        self.provider.api_key_env_var().is_some() && self.api_key.trim().is_empty()
+    }
+
     /// Create a default OpenAI configuration
     pub fn default_openai(api_key: String) -> Self {
         Self {
@@ -123,6 +175,7 @@ impl LLMConfig {
             base_url: "https://api.openai.com/v1/chat/completions".to_string(),
             temperature: 0.7,
             max_tokens: 200,
+            system_message_policy: SystemMessagePolicy::SystemRole,
         }
     }
 
@@ -135,6 +188,7 @@ impl LLMConfig {
             base_url: "https://generativelanguage.googleapis.com/v1beta/openai/".to_string(),
             temperature: 0.7,
             max_tokens: 200,
+            system_message_policy: SystemMessagePolicy::SystemRole,
         }
     }
 
@@ -147,6 +201,7 @@ impl LLMConfig {
             base_url: "https://api.deepseek.com/v1".to_string(),
             temperature: 0.7,
             max_tokens: 2048,
+            system_message_policy: SystemMessagePolicy::SystemRole,
         }
     }
 
@@ -159,6 +214,7 @@ impl LLMConfig {
             base_url: "http://localhost:11434/api/chat".to_string(),
             temperature: 0.7,
             max_tokens: 200,
+            system_message_policy: SystemMessagePolicy::SystemRole,
         }
     }
 
@@ -171,6 +227,7 @@ impl LLMConfig {
             base_url,
             temperature: 0.7,
             max_tokens: 200,
+            system_message_policy: SystemMessagePolicy::SystemRole,
         }
     }
 
@@ -393,4 +450,59 @@ impl LLMConfig {
             self.max_tokens
         )
     }
+
+    /// 用于显示的 API Key：只保留最后 4 个字符
+    pub fn masked_api_key(&self) -> String {
+        let chars: Vec<char> = self.api_key.chars().collect();
+        match chars.len() {
+            0 => "(未设置)".to_string(),
+            // 太短时后 4 位就是整个 Key，全部隐藏
+            1..=8 => "****".to_string(),
+            len => format!("****{}", chars[len - 4..].iter().collect::<String>()),
+        }
+    }
+
+    /// 读取项目目录下的模型偏好（`.starfell/model`）
+    pub fn load_project_model(project_dir: &Path) -> Option<String> {
+        let content = std::fs::read_to_string(project_dir.join(PROJECT_MODEL_FILE)).ok()?;
+        let model = content.trim();
+        if model.is_empty() {
+            None
+        } else {
+            Some(model.to_string())
+        }
+    }
+
+    /// 将模型偏好写入项目目录的 `.starfell/model`
+    pub fn save_project_model(project_dir: &Path, model: &str) -> std::io::Result<()> {
+        let path = project_dir.join(PROJECT_MODEL_FILE);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, format!("{}\n", model))
+    }
+
+    /// 用项目偏好覆盖全局默认模型，返回被选中的模型
+    pub fn apply_project_model(&mut self, project_dir: &Path) -> Option<String> {
+        let model = Self::load_project_model(project_dir)?;
+        self.model = model.clone();
+        Some(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_project_model_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        assert_eq!(LLMConfig::load_project_model(temp_dir.path()), None);
+
+        LLMConfig::save_project_model(temp_dir.path(), "gpt-4o").unwrap();
+        let mut config = LLMConfig::default();
+        assert_eq!(config.apply_project_model(temp_dir.path()), Some("gpt-4o".to_string()));
+        assert_eq!(config.model, "gpt-4o");
+    }
 }
@@ -0,0 +1,172 @@
+/// 上下文 Token 预算（/budget）
+/// @ 提及的文件总 Token 数超过预算时，优先保留小文件：放不下的文件先截断到剩余预算，剩余预算太少时整个丢弃
+
+use crate::core::conversation_engine::FileContent;
+use crate::core::TokenCalculator;
+
+/// 剩余预算少于该值时不再截断，直接丢弃文件（太短的片段没有参考价值）
+const MIN_TRUNCATED_TOKENS: usize = 64;
+
+/// 单个文件在预算下的处理方式
+#[derive(Debug, Clone, PartialEq)]
+pub enum BudgetAction {
+    Keep,
+    /// 截断到指定 Token 数
+    Truncate(usize),
+    Drop,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetDecision {
+    pub path: String,
+    pub tokens: usize,
+    pub action: BudgetAction,
+}
+
+/// 计算每个文件在预算下的处理方式，结果与 files 顺序一致。
+/// 按文件大小从小到大分配预算，同样大小时先提及的优先
+pub fn plan_budget(files: &[FileContent], budget: usize, calculator: &TokenCalculator) -> Vec<BudgetDecision> {
+    let mut decisions: Vec<BudgetDecision> = files
+        .iter()
+        .map(|file| BudgetDecision {
+            path: file.path.clone(),
+            tokens: calculator.count_tokens(&file.content),
+            action: BudgetAction::Drop,
+        })
+        .collect();
+
+    let mut order: Vec<usize> = (0..decisions.len()).collect();
+    order.sort_by_key(|&i| decisions[i].tokens);
+
+    let mut remaining = budget;
+    for i in order {
+        let decision = &mut decisions[i];
+        if decision.tokens <= remaining {
+            decision.action = BudgetAction::Keep;
+            remaining -= decision.tokens;
+        } else if remaining >= MIN_TRUNCATED_TOKENS {
+            decision.action = BudgetAction::Truncate(remaining);
+            remaining = 0;
+        }
+    }
+    decisions
+}
+
+/// 按计划丢弃或截断文件（截断保留开头的完整行）
+pub fn apply_budget(files: Vec<FileContent>, plan: &[BudgetDecision], calculator: &TokenCalculator) -> Vec<FileContent> {
+    files
+        .into_iter()
+        .zip(plan)
+        .filter_map(|(file, decision)| match decision.action {
+            BudgetAction::Keep => Some(file),
+            BudgetAction::Drop => None,
+            BudgetAction::Truncate(limit) => {
+                let mut kept = truncate_lines(&file.content, limit, calculator).to_string();
+                if !kept.is_empty() && !kept.ends_with('\n') {
+                    kept.push('\n');
+                }
+                kept.push_str("… (已截断以符合上下文预算)\n");
+                Some(FileContent::new(file.path, kept))
+            }
+        })
+        .collect()
+}
+
+/// content 开头的完整行，逐行累加的 Token 数不超过 limit。
+/// 只扫描一遍，每行只计算一次 Token 数
+fn truncate_lines<'a>(content: &'a str, limit: usize, calculator: &TokenCalculator) -> &'a str {
+    let line_ends = content
+        .char_indices()
+        .filter(|&(_, c)| c == '\n')
+        .map(|(i, _)| i)
+        .chain(std::iter::once(content.len()));
+
+    let mut used = 0;
+    let mut start = 0;
+    let mut end = 0;
+    for line_end in line_ends {
+        if start >= content.len() {
+            break;
+        }
+        let line_end = (line_end + 1).min(content.len());
+        let tokens = calculator.count_tokens(&content[start..line_end]);
+        if used + tokens > limit {
+            break;
+        }
+        used += tokens;
+        end = line_end;
+        start = line_end;
+    }
+    &content[..end]
+}
+
+/// /budget 的预览：每个文件的 Token 数和处理方式
+pub fn format_budget_preview(budget: usize, plan: &[BudgetDecision]) -> String {
+    let total: usize = plan.iter().map(|d| d.tokens).sum();
+    let mut preview = format!("📐 上下文预算 {} tokens（附加文件共 {} tokens）", budget, total);
+    if plan.is_empty() {
+        preview.push_str("\n  最后一条输入中没有 @ 提及的文件");
+    }
+    for decision in plan {
+        let action = match decision.action {
+            BudgetAction::Keep => "保留".to_string(),
+            BudgetAction::Truncate(limit) => format!("截断到 {} tokens", limit),
+            BudgetAction::Drop => "丢弃".to_string(),
+        };
+        preview.push_str(&format!("\n  {:>6} tokens  {}  {}", decision.tokens, action, decision.path));
+    }
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> Vec<FileContent> {
+        vec![
+            FileContent::new("src/big.rs", "let value = 1;\n".repeat(200)),
+            FileContent::new("src/small.rs", "fn small() {}\n"),
+            FileContent::new("src/medium.rs", "let value = 1;\n".repeat(40)),
+        ]
+    }
+
+    #[test]
+    fn test_lower_budget_drops_largest_files_first() {
+        let calculator = TokenCalculator::from_model_name("gpt-4");
+        let actions = |budget| -> Vec<BudgetAction> {
+            plan_budget(&files(), budget, &calculator).into_iter().map(|d| d.action).collect()
+        };
+
+        assert_eq!(actions(100_000), vec![BudgetAction::Keep, BudgetAction::Keep, BudgetAction::Keep]);
+
+        let medium = actions(400);
+        assert!(matches!(medium[0], BudgetAction::Truncate(_)));
+        assert_eq!(&medium[1..], &[BudgetAction::Keep, BudgetAction::Keep]);
+
+        assert_eq!(actions(20), vec![BudgetAction::Drop, BudgetAction::Keep, BudgetAction::Drop]);
+
+        let plan = plan_budget(&files(), 400, &calculator);
+        let kept = apply_budget(files(), &plan, &calculator);
+        assert_eq!(kept.len(), 3);
+        assert!(kept[0].content.ends_with("已截断以符合上下文预算)\n"));
+        assert!(calculator.count_tokens(&kept[0].content) < plan[0].tokens);
+        assert!(format_budget_preview(400, &plan).contains("截断到"));
+    }
+
+    #[test]
+    fn test_truncate_keeps_whole_lines_within_limit() {
+        let calculator = TokenCalculator::from_model_name("gpt-4");
+        let content = "let value = 1;\n".repeat(50_000);
+
+        let kept = truncate_lines(&content, 500, &calculator);
+        assert!(content.starts_with(kept));
+        assert!(kept.ends_with('\n'));
+        let line_tokens = calculator.count_tokens("let value = 1;\n");
+        let used: usize = kept.split_inclusive('\n').map(|line| calculator.count_tokens(line)).sum();
+        assert!(used <= 500 && used + line_tokens > 500);
+
+        // 最后一行没有换行符时也能完整保留
+        assert_eq!(truncate_lines("a\nb", 100, &calculator), "a\nb");
+        assert_eq!(truncate_lines("", 100, &calculator), "");
+    }
+}
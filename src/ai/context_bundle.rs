@@ -0,0 +1,125 @@
+/// 上下文导出（/export-context）
+/// 把当前附加的文件、会话系统提示和最后一条用户输入写成一个 Markdown 文件，便于提交 issue 或分享给同事复现。
+/// 写出前会掩码密钥类字段的值
+
+use crate::tools::config_tool::mask_secrets;
+
+/// 一次请求的可复现上下文
+#[derive(Debug, Default)]
+pub struct ContextBundle {
+    pub system_prompt: Option<String>,
+    /// 用户输入中 @ 提及的文件：(路径, 内容)
+    pub files: Vec<(String, String)>,
+    /// /attach-last-output 等附加的命令输出
+    pub attached_output: Vec<String>,
+    pub last_input: Option<String>,
+}
+
+impl ContextBundle {
+    /// 从用户输入收集 @ 提及的文件；不存在或无法读取的路径会被忽略
+    pub fn collect(system_prompt: Option<String>, attached_output: Vec<String>, last_input: Option<String>) -> Self {
+        let files = last_input
+            .as_deref()
+            .map(mentioned_files)
+            .unwrap_or_default();
+        Self { system_prompt, files, attached_output, last_input }
+    }
+
+    /// 渲染为 Markdown（已掩码密钥）
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("# Context bundle\n");
+
+        out.push_str("\n## System prompt\n\n");
+        match &self.system_prompt {
+            Some(prompt) => out.push_str(&fenced("", &redact(prompt))),
+            None => out.push_str("(default)\n"),
+        }
+
+        out.push_str("\n## Attached files\n");
+        if self.files.is_empty() {
+            out.push_str("\n(none)\n");
+        }
+        for (path, content) in &self.files {
+            let language = std::path::Path::new(path)
+                .extension()
+                .map(|e| e.to_string_lossy().to_string())
+                .unwrap_or_default();
+            out.push_str(&format!("\n### {}\n\n", path));
+            out.push_str(&fenced(&language, &redact(content)));
+        }
+
+        if !self.attached_output.is_empty() {
+            out.push_str("\n## Attached command output\n");
+            for output in &self.attached_output {
+                out.push('\n');
+                out.push_str(&fenced("", &redact(output)));
+            }
+        }
+
+        out.push_str("\n## Last input\n\n");
+        match &self.last_input {
+            Some(input) => out.push_str(&fenced("", &redact(input))),
+            None => out.push_str("(none)\n"),
+        }
+        out
+    }
+}
+
+/// 输入中所有 `@path` 对应的文件（按出现顺序，去重）
+fn mentioned_files(input: &str) -> Vec<(String, String)> {
+    let mut files: Vec<(String, String)> = Vec::new();
+    for path in input.split_whitespace().filter_map(|word| word.strip_prefix('@')) {
+        if path.is_empty() || files.iter().any(|(p, _)| p == path) {
+            continue;
+        }
+        if let Ok(content) = std::fs::read_to_string(path) {
+            files.push((path.to_string(), content));
+        }
+    }
+    files
+}
+
+fn redact(text: &str) -> String {
+    mask_secrets(text).0
+}
+
+/// 用比内容中最长反引号序列更长的围栏包裹，避免内容里的 ``` 提前结束代码块
+fn fenced(language: &str, content: &str) -> String {
+    let longest = content
+        .lines()
+        .map(|line| line.trim_start().chars().take_while(|&c| c == '`').count())
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}{}\n{}\n{}\n", fence, language, content.trim_end_matches('\n'), fence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_contains_attached_files_and_redacts_secrets() {
+        let dir = tempfile::tempdir().unwrap();
+        let main_rs = dir.path().join("main.rs").to_string_lossy().to_string();
+        let env = dir.path().join(".env").to_string_lossy().to_string();
+        std::fs::write(&main_rs, "fn main() {\n    println!(\"hi\");\n}\n").unwrap();
+        std::fs::write(&env, "OPENAI_API_KEY=sk-live-123\nMODEL=gpt-4o\n").unwrap();
+
+        let bundle = ContextBundle::collect(
+            Some("Answer briefly".to_string()),
+            Vec::new(),
+            Some(format!("why does @{} not read @{} ?", main_rs, env)),
+        );
+        assert_eq!(bundle.files.len(), 2);
+
+        let markdown = bundle.to_markdown();
+        assert!(markdown.contains(&format!("### {}", main_rs)));
+        assert!(markdown.contains("```rs\nfn main() {\n    println!(\"hi\");\n}\n```"));
+        assert!(markdown.contains(&format!("### {}", env)));
+        assert!(markdown.contains("MODEL=gpt-4o"));
+        assert!(!markdown.contains("sk-live-123"));
+        assert!(markdown.contains("Answer briefly"));
+        assert!(markdown.contains("why does @"));
+    }
+}
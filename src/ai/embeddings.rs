@@ -0,0 +1,82 @@
+/// 文本向量（embeddings）接口
+/// 用于 @ 提及的语义排序；请求走当前配置的提供商
+
+use crate::ai::client::{CompletionError, LLMClient};
+use crate::ai::config::LLMProvider;
+use std::future::Future;
+use std::pin::Pin;
+
+/// 生成文本向量的客户端（测试中可替换为假客户端）
+pub trait EmbeddingClient: Send + Sync {
+    fn embed(&self, inputs: Vec<String>) -> Pin<Box<dyn Future<Output = Result<Vec<Vec<f32>>, CompletionError>> + Send + '_>>;
+}
+
+impl EmbeddingClient for LLMClient {
+    fn embed(&self, inputs: Vec<String>) -> Pin<Box<dyn Future<Output = Result<Vec<Vec<f32>>, CompletionError>> + Send + '_>> {
+        Box::pin(self.generate_embeddings(inputs))
+    }
+}
+
+/// 各提供商默认的向量模型
+pub fn embedding_model(provider: &LLMProvider) -> &'static str {
+    match provider {
+        LLMProvider::Gemini => "text-embedding-004",
+        LLMProvider::Ollama => "nomic-embed-text",
+        _ => "text-embedding-3-small",
+    }
+}
+
+/// 由聊天接口地址推导 embeddings 接口地址
+pub fn embeddings_url(base_url: &str) -> String {
+    if let Some(prefix) = base_url.strip_suffix("/chat/completions") {
+        format!("{}/embeddings", prefix)
+    } else if let Some(prefix) = base_url.strip_suffix("/api/chat") {
+        format!("{}/api/embed", prefix)
+    } else {
+        format!("{}/embeddings", base_url.trim_end_matches('/'))
+    }
+}
+
+/// 解析响应：OpenAI 格式 `data[].embedding`，或 Ollama 格式 `embeddings`
+pub fn parse_embeddings_response(body: &serde_json::Value) -> Option<Vec<Vec<f32>>> {
+    let to_vector = |value: &serde_json::Value| -> Option<Vec<f32>> {
+        value.as_array()?.iter().map(|x| x.as_f64().map(|x| x as f32)).collect()
+    };
+
+    if let Some(data) = body.get("data").and_then(|d| d.as_array()) {
+        return data.iter().map(|item| to_vector(item.get("embedding")?)).collect();
+    }
+    body.get("embeddings")?.as_array()?.iter().map(to_vector).collect()
+}
+
+/// 余弦相似度（任一向量为零时返回 0）
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embeddings_url_and_response_formats() {
+        assert_eq!(embeddings_url("https://api.openai.com/v1/chat/completions"), "https://api.openai.com/v1/embeddings");
+        assert_eq!(embeddings_url("http://localhost:11434/api/chat"), "http://localhost:11434/api/embed");
+        assert_eq!(embeddings_url("https://api.deepseek.com/v1"), "https://api.deepseek.com/v1/embeddings");
+
+        let openai = serde_json::json!({ "data": [{ "embedding": [1.0, 0.0] }, { "embedding": [0.0, 1.0] }] });
+        assert_eq!(parse_embeddings_response(&openai), Some(vec![vec![1.0, 0.0], vec![0.0, 1.0]]));
+        let ollama = serde_json::json!({ "embeddings": [[0.5, 0.5]] });
+        assert_eq!(parse_embeddings_response(&ollama), Some(vec![vec![0.5, 0.5]]));
+
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+}
@@ -0,0 +1,316 @@
+/// @ 提及的嵌套展开
+/// 被提及的文件（如项目说明文件）中也可以用 `@path` 引用其他文件，展开时按深度上限递归，
+/// 并检测循环引用：a 引用 b、b 又引用 a 时，第二次引用被跳过并记录下来。
+/// `@diff` / `@diff-staged` 附加当前 git diff，`@history` 附加最近的对话
+
+use crate::core::conversation_engine::FileContent;
+use crate::core::message::{Message, Role};
+use crate::core::TokenCalculator;
+use std::path::{Path, PathBuf};
+
+/// 嵌套展开的最大深度（用户输入中直接提及的文件深度为 0）
+pub const MAX_MENTION_DEPTH: usize = 3;
+/// @diff 附加的 diff 最多保留的字符数
+pub const DIFF_MENTION_MAX_CHARS: usize = 20_000;
+
+/// 展开结果
+#[derive(Debug, Default)]
+pub struct MentionExpansion {
+    /// 按展开顺序排列的文件（每个文件只出现一次）
+    pub files: Vec<FileContent>,
+    /// 被跳过的循环引用，形如 `a.md → b.md → a.md`
+    pub cycles: Vec<String>,
+    /// 因超过深度上限而没有展开的引用
+    pub depth_limited: Vec<String>,
+    /// 无法附加的行范围（`@path:10-20` 超出文件长度等）
+    pub invalid_ranges: Vec<String>,
+}
+
+impl MentionExpansion {
+    /// 给用户的提示；没有跳过任何引用时返回 None
+    pub fn report(&self) -> Option<String> {
+        if self.cycles.is_empty() && self.depth_limited.is_empty() && self.invalid_ranges.is_empty() {
+            return None;
+        }
+        let mut report = String::from("⚠ 展开 @ 引用时跳过了部分文件：");
+        for cycle in &self.cycles {
+            report.push_str(&format!("\n  循环引用: {}", cycle));
+        }
+        for path in &self.depth_limited {
+            report.push_str(&format!("\n  超过嵌套深度 {}: {}", MAX_MENTION_DEPTH, path));
+        }
+        for reason in &self.invalid_ranges {
+            report.push_str(&format!("\n  行范围无效: {}", reason));
+        }
+        Some(report)
+    }
+}
+
+/// 展开用户直接提及的文件及其中的嵌套引用
+pub fn expand_mentions(paths: &[String], max_depth: usize) -> MentionExpansion {
+    let mut expansion = MentionExpansion::default();
+    let mut seen: Vec<PathBuf> = Vec::new();
+    let mut chain: Vec<(PathBuf, String)> = Vec::new();
+    for path in paths {
+        expand(path, 0, max_depth, &mut chain, &mut seen, &mut expansion);
+    }
+    expansion
+}
+
+fn expand(
+    path: &str,
+    depth: usize,
+    max_depth: usize,
+    chain: &mut Vec<(PathBuf, String)>,
+    seen: &mut Vec<PathBuf>,
+    expansion: &mut MentionExpansion,
+) {
+    let Ok(canonical) = std::fs::canonicalize(path) else {
+        return;
+    };
+    if let Some(start) = chain.iter().position(|(p, _)| *p == canonical) {
+        let mut cycle: Vec<&str> = chain[start..].iter().map(|(_, name)| name.as_str()).collect();
+        cycle.push(path);
+        expansion.cycles.push(cycle.join(" → "));
+        return;
+    }
+    // 已经通过其他路径展开过的文件不重复附加
+    if seen.contains(&canonical) {
+        return;
+    }
+    let Ok(content) = std::fs::read_to_string(&canonical) else {
+        return;
+    };
+    seen.push(canonical.clone());
+
+    let nested = nested_mentions(&content, &canonical);
+    expansion.files.push(FileContent::new(path, content));
+
+    if nested.is_empty() {
+        return;
+    }
+    if depth >= max_depth {
+        expansion.depth_limited.extend(nested);
+        return;
+    }
+    chain.push((canonical, path.to_string()));
+    for nested_path in nested {
+        expand(&nested_path, depth + 1, max_depth, chain, seen, expansion);
+    }
+    chain.pop();
+}
+
+/// 文件内容中的 `@path` 引用：只认行首或空白之后、且指向存在文件的引用（避免把装饰器、邮箱当成引用）。
+/// 相对路径先相对于引用它的文件所在目录解析，再相对于当前目录
+fn nested_mentions(content: &str, file: &Path) -> Vec<String> {
+    let base = file.parent().unwrap_or(Path::new("."));
+    content
+        .split_whitespace()
+        .filter_map(|word| word.strip_prefix('@'))
+        .filter(|path| !path.is_empty())
+        .filter_map(|path| {
+            let relative = base.join(path);
+            if relative.is_file() {
+                Some(relative.to_string_lossy().to_string())
+            } else if Path::new(path).is_file() {
+                Some(path.to_string())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// `@diff` 返回 Some(false)，`@diff-staged` 返回 Some(true)，其他提及返回 None
+pub fn diff_mention(mention: &str) -> Option<bool> {
+    match mention {
+        "diff" => Some(false),
+        "diff-staged" => Some(true),
+        _ => None,
+    }
+}
+
+/// `@history` 附加的最近对话最多占用的 Token 数
+pub const HISTORY_MENTION_TOKEN_BUDGET: usize = 2_000;
+/// `@history` 中单条消息最多保留的字符数，避免一条长回复占满预算
+const HISTORY_MESSAGE_MAX_CHARS: usize = 1_500;
+
+/// 输入中是否有 `@history`
+pub fn has_history_mention(input: &str) -> bool {
+    input.split_whitespace().any(|word| word == "@history")
+}
+
+/// 去掉输入中独立的 `@history` 词，`@history.md` 等文件引用保持不变
+pub fn strip_history_mention(input: &str) -> String {
+    input
+        .split_inclusive(char::is_whitespace)
+        .map(|piece| {
+            let word = piece.trim_end_matches(char::is_whitespace);
+            if word == "@history" { &piece[word.len()..] } else { piece }
+        })
+        .collect()
+}
+
+/// 把最近的用户/AI 消息渲染为上下文：从最新的往前取，直到超出 budget，再按时间顺序输出。
+/// 系统消息和空消息不计入；过长的消息只保留开头
+pub fn history_context(messages: &[Message], calculator: &TokenCalculator, budget: usize) -> FileContent {
+    let mut entries = Vec::new();
+    let mut used = 0;
+    for message in messages.iter().rev() {
+        let speaker = match message.role {
+            Role::User => "用户",
+            Role::Assistant => "AI",
+            Role::System => continue,
+        };
+        let text = message.content.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let mut text: String = text.chars().take(HISTORY_MESSAGE_MAX_CHARS).collect();
+        if text.len() < message.content.trim().len() {
+            text.push_str(" …");
+        }
+        let entry = format!("{}: {}", speaker, text);
+        let tokens = calculator.count_tokens(&entry);
+        if used + tokens > budget {
+            break;
+        }
+        used += tokens;
+        entries.push(entry);
+    }
+
+    let content = if entries.is_empty() {
+        "(没有可附加的对话)".to_string()
+    } else {
+        entries.reverse();
+        entries.join("\n\n")
+    };
+    FileContent::new("@history", content)
+}
+
+/// 在 dir 中运行 git diff（staged 时为 --staged），超过 max_chars 时截断并注明；
+/// 不在 git 仓库中或 git 不可用时返回说明而不是 diff
+pub fn git_diff_context(dir: &Path, staged: bool, max_chars: usize) -> FileContent {
+    let label = if staged { "git diff --staged" } else { "git diff" };
+    let mut command = std::process::Command::new("git");
+    command.arg("diff").current_dir(dir);
+    if staged {
+        command.arg("--staged");
+    }
+
+    let content = match command.output() {
+        Ok(output) if output.status.success() => {
+            let diff = String::from_utf8_lossy(&output.stdout).to_string();
+            let total = diff.chars().count();
+            if diff.trim().is_empty() {
+                "(没有未提交的改动)".to_string()
+            } else if total > max_chars {
+                let kept: String = diff.chars().take(max_chars).collect();
+                format!("{}\n… (diff 已截断：共 {} 个字符，只保留前 {} 个)", kept.trim_end(), total, max_chars)
+            } else {
+                diff
+            }
+        }
+        Ok(output) => format!("(无法获取 diff，当前目录可能不是 git 仓库: {})", String::from_utf8_lossy(&output.stderr).trim()),
+        Err(e) => format!("(无法运行 git: {})", e),
+    };
+    FileContent::new(label, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_context_keeps_newest_messages_within_budget() {
+        let calculator = TokenCalculator::from_model_name("gpt-4");
+        let message = |role, content: &str| Message { role, content: content.to_string() };
+        let messages = vec![
+            message(Role::User, "oldest question about lifetimes"),
+            message(Role::Assistant, "oldest answer"),
+            message(Role::System, "✓ 已切换模型"),
+            message(Role::User, "how do I borrow mutably"),
+            message(Role::Assistant, "use &mut"),
+        ];
+
+        let all = history_context(&messages, &calculator, HISTORY_MENTION_TOKEN_BUDGET);
+        assert_eq!(all.path, "@history");
+        assert!(all.content.starts_with("用户: oldest question"));
+        assert!(all.content.ends_with("AI: use &mut"));
+        assert!(!all.content.contains("已切换模型"));
+
+        // 预算只够最近两条时，丢弃更早的消息
+        let recent = "用户: how do I borrow mutably\n\nAI: use &mut";
+        let budget = calculator.count_tokens("用户: how do I borrow mutably") + calculator.count_tokens("AI: use &mut");
+        assert_eq!(history_context(&messages, &calculator, budget).content, recent);
+
+        assert!(history_context(&[], &calculator, budget).content.contains("没有可附加的对话"));
+        assert!(has_history_mention("recap @history please"));
+        assert!(!has_history_mention("email me@history.com"));
+        assert_eq!(strip_history_mention("recap @history please"), "recap  please");
+        assert_eq!(strip_history_mention("see @history.md and @history"), "see @history.md and ");
+    }
+
+    #[test]
+    fn test_mutual_references_terminate_and_report_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.md");
+        let b = dir.path().join("b.md");
+        std::fs::write(&a, "Project rules. See @b.md\n").unwrap();
+        std::fs::write(&b, "Style guide, back to @a.md and @missing.md\n").unwrap();
+
+        let expansion = expand_mentions(&[a.to_string_lossy().to_string()], MAX_MENTION_DEPTH);
+
+        assert_eq!(expansion.files.len(), 2);
+        assert!(expansion.files[1].content.starts_with("Style guide"));
+        assert_eq!(expansion.cycles.len(), 1);
+        assert!(expansion.cycles[0].ends_with("a.md"));
+        assert_eq!(expansion.cycles[0].matches("→").count(), 2);
+        assert!(expansion.report().unwrap().contains("循环引用"));
+    }
+
+    #[test]
+    fn test_depth_cap_stops_long_chains() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..5 {
+            std::fs::write(dir.path().join(format!("{}.md", i)), format!("next: @{}.md\n", i + 1)).unwrap();
+        }
+        std::fs::write(dir.path().join("5.md"), "end\n").unwrap();
+
+        let root = dir.path().join("0.md").to_string_lossy().to_string();
+        let expansion = expand_mentions(&[root], 2);
+        assert_eq!(expansion.files.len(), 3);
+        assert_eq!(expansion.depth_limited.len(), 1);
+        assert!(expansion.depth_limited[0].ends_with("3.md"));
+        assert!(expansion.cycles.is_empty());
+    }
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git").args(args).current_dir(dir).output().unwrap().status;
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn test_diff_mention_attaches_working_tree_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        git(dir.path(), &["init", "-q"]);
+        git(dir.path(), &["config", "user.email", "dev@example.com"]);
+        git(dir.path(), &["config", "user.name", "Dev"]);
+        std::fs::write(dir.path().join("lib.rs"), "fn answer() -> u32 { 41 }\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "init"]);
+        std::fs::write(dir.path().join("lib.rs"), "fn answer() -> u32 { 42 }\n").unwrap();
+
+        let diff = git_diff_context(dir.path(), false, DIFF_MENTION_MAX_CHARS);
+        assert_eq!(diff.path, "git diff");
+        assert!(diff.content.contains("-fn answer() -> u32 { 41 }"));
+        assert!(diff.content.contains("+fn answer() -> u32 { 42 }"));
+        assert!(git_diff_context(dir.path(), true, DIFF_MENTION_MAX_CHARS).content.contains("没有未提交的改动"));
+
+        let truncated = git_diff_context(dir.path(), false, 20);
+        assert!(truncated.content.contains("diff 已截断"));
+
+        let outside = tempfile::tempdir().unwrap();
+        assert!(git_diff_context(outside.path(), false, DIFF_MENTION_MAX_CHARS).content.starts_with("(无法"));
+    }
+}
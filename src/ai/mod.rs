@@ -1,10 +1,17 @@
 pub mod client;
 pub mod commands;
+pub mod commit_message;
 pub mod config;
+pub mod embeddings;
+pub mod benchmark;
 pub mod context;
+pub mod context_budget;
+pub mod context_bundle;
+pub mod mentions;
 pub mod fim;
 pub mod streaming;
 pub mod advanced_client;
 pub mod tools;
 pub mod code_modification;
+pub mod modification_history;
 pub mod prompt_builder;
\ No newline at end of file
@@ -0,0 +1,109 @@
+/// 已应用修改的撤销栈（/undo）
+/// 每次写入前记录文件的原内容，撤销时恢复：新建的文件被删除，修改的恢复原内容，删除的重新创建
+
+use crate::ai::code_modification::CodeModificationOp;
+
+/// 撤销栈最多保留的修改数
+const MAX_UNDO_ENTRIES: usize = 50;
+
+/// 一次已应用的修改及其修改前的文件内容
+#[derive(Debug, Clone)]
+pub struct AppliedModification {
+    pub op: CodeModificationOp,
+    /// 修改前的内容；文件原本不存在时为 None
+    pub before: Option<String>,
+}
+
+impl AppliedModification {
+    fn path(&self) -> &str {
+        match &self.op {
+            CodeModificationOp::Create { path, .. }
+            | CodeModificationOp::Modify { path, .. }
+            | CodeModificationOp::Delete { path } => path,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ModificationHistory {
+    applied: Vec<AppliedModification>,
+}
+
+impl ModificationHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 读取修改前的内容，应在写入文件之前调用
+    pub fn snapshot(path: &str) -> Option<String> {
+        std::fs::read_to_string(path).ok()
+    }
+
+    /// 记录一次成功应用的修改
+    pub fn record(&mut self, op: CodeModificationOp, before: Option<String>) {
+        self.applied.push(AppliedModification { op, before });
+        if self.applied.len() > MAX_UNDO_ENTRIES {
+            self.applied.remove(0);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.applied.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty()
+    }
+
+    /// 撤销最近一次修改；没有可撤销的修改时返回 None。
+    /// 恢复失败时修改留在栈中，可以再次尝试
+    pub fn undo_last(&mut self) -> Option<Result<String, String>> {
+        let entry = self.applied.pop()?;
+        let path = entry.path().to_string();
+        let result = match &entry.before {
+            None => std::fs::remove_file(&path).map(|_| format!("↩ 已撤销创建，删除文件: {}", path)),
+            Some(content) => {
+                let message = match entry.op {
+                    CodeModificationOp::Delete { .. } => format!("↩ 已撤销删除，重新创建文件: {}", path),
+                    _ => format!("↩ 已撤销修改，恢复文件: {}", path),
+                };
+                std::fs::write(&path, content).map(|_| message)
+            }
+        };
+        Some(result.map_err(|e| {
+            self.applied.push(entry);
+            format!("撤销 {} 失败: {}", path, e)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_restores_modified_and_deleted_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lib.rs").to_string_lossy().to_string();
+        std::fs::write(&path, "fn old() {}\n").unwrap();
+        let mut history = ModificationHistory::new();
+
+        let before = ModificationHistory::snapshot(&path);
+        std::fs::write(&path, "fn new() {}\n").unwrap();
+        history.record(
+            CodeModificationOp::Modify { path: path.clone(), search: "old".to_string(), replace: "new".to_string() },
+            before,
+        );
+
+        let before = ModificationHistory::snapshot(&path);
+        std::fs::remove_file(&path).unwrap();
+        history.record(CodeModificationOp::Delete { path: path.clone() }, before);
+
+        assert!(history.undo_last().unwrap().unwrap().contains("重新创建"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn new() {}\n");
+
+        history.undo_last().unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn old() {}\n");
+        assert!(history.undo_last().is_none());
+    }
+}
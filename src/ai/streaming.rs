@@ -1,6 +1,54 @@
 use tokio::sync::mpsc;
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use crate::ai::client::CompletionMetadata;
+use crate::tools::{ToolCall, ToolResult};
+
+/// 取出接收器中所有立即可用的 Token（以 first 开头），合并为一批
+///
+/// 遇到非 Token 事件（Done/Error/Interrupted/工具事件）时停止，并将其一并返回，
+/// 由调用方在处理完这批 Token 之后再处理。
+pub fn drain_tokens(
+    rx: &mut mpsc::UnboundedReceiver<StreamEvent>,
+    first: String,
+) -> (Vec<String>, Option<StreamEvent>) {
+    let mut tokens = vec![first];
+    while let Ok(event) = rx.try_recv() {
+        match event {
+            StreamEvent::Token(token) => tokens.push(token),
+            other => return (tokens, Some(other)),
+        }
+    }
+    (tokens, None)
+}
+
+/// 可以加锁追加内容的流式响应容器
+pub trait ResponseSink {
+    fn with_response(&self, f: &mut dyn FnMut(&mut StreamingChatResponse));
+}
+
+impl ResponseSink for std::sync::Mutex<StreamingChatResponse> {
+    fn with_response(&self, f: &mut dyn FnMut(&mut StreamingChatResponse)) {
+        let mut response = self.lock().unwrap();
+        f(&mut response);
+    }
+}
+
+/// 一次加锁追加一批 Token
+pub fn append_token_batch(sink: &impl ResponseSink, tokens: &[String]) {
+    sink.with_response(&mut |response| {
+        for token in tokens {
+            response.append(token);
+        }
+    });
+}
+
+/// 在同一次加锁中取出已累积的内容并重置，取出后追加的 Token 属于下一段内容，既不会丢失也不会重复
+pub fn take_response(sink: &impl ResponseSink) -> String {
+    let mut content = String::new();
+    sink.with_response(&mut |response| content = response.take());
+    content
+}
 
 /// 流式响应事件
 #[derive(Debug, Clone)]
@@ -11,6 +59,20 @@ pub enum StreamEvent {
     Done,
     /// 发生错误
     Error(String),
+    /// 流在完成前中断（连接重置等），可重试；已收到的内容保留
+    Interrupted(String),
+    /// explain 模式下，工具执行前的通俗说明
+    Explanation(String),
+    /// 解析到工具调用，即将执行
+    ToolCall(ToolCall),
+    /// 工具执行完成（按调用顺序依次到达）
+    ToolResult(ToolResult),
+    /// 回复的 Token 用量和结束原因，在 Done 之前到达
+    Usage(CompletionMetadata),
+    /// 不影响本次回复的警告（如工具执行后钩子失败），显示为系统消息
+    Warning(String),
+    /// 工具目录扫描的进度（已扫描的文件数）
+    ScanProgress(usize),
 }
 
 /// 流式响应处理器
@@ -51,6 +113,55 @@ impl StreamHandler {
             .map_err(|e| e.to_string())
     }
 
+    /// 发送流中断（可重试）
+    pub fn send_interrupted(&self, reason: String) -> Result<(), String> {
+        self.tx
+            .send(StreamEvent::Interrupted(reason))
+            .map_err(|e| e.to_string())
+    }
+
+    /// 发送工具执行前的说明（explain 模式）
+    pub fn send_explanation(&self, text: String) -> Result<(), String> {
+        self.tx
+            .send(StreamEvent::Explanation(text))
+            .map_err(|e| e.to_string())
+    }
+
+    /// 发送不影响本次回复的警告
+    pub fn send_warning(&self, text: String) -> Result<(), String> {
+        self.tx
+            .send(StreamEvent::Warning(text))
+            .map_err(|e| e.to_string())
+    }
+
+    /// 通知即将执行的工具调用
+    pub fn send_tool_call(&self, call: ToolCall) -> Result<(), String> {
+        self.tx
+            .send(StreamEvent::ToolCall(call))
+            .map_err(|e| e.to_string())
+    }
+
+    /// 通知工具执行结果
+    pub fn send_tool_result(&self, result: ToolResult) -> Result<(), String> {
+        self.tx
+            .send(StreamEvent::ToolResult(result))
+            .map_err(|e| e.to_string())
+    }
+
+    /// 发送回复的用量
+    pub fn send_usage(&self, usage: CompletionMetadata) -> Result<(), String> {
+        self.tx
+            .send(StreamEvent::Usage(usage))
+            .map_err(|e| e.to_string())
+    }
+
+    /// 发送工具目录扫描的进度
+    pub fn send_scan_progress(&self, files_scanned: usize) -> Result<(), String> {
+        self.tx
+            .send(StreamEvent::ScanProgress(files_scanned))
+            .map_err(|e| e.to_string())
+    }
+
     /// 非阻塞地尝试接收一个事件
     pub fn try_recv(&mut self) -> Result<StreamEvent, mpsc::error::TryRecvError> {
         // 我们需要一个可变引用来调用 try_recv，但由于 Arc<Mutex<...>> 的结构，
@@ -103,6 +214,12 @@ impl StreamingChatResponse {
         &self.content
     }
 
+    /// 取出内容并重置
+    pub fn take(&mut self) -> String {
+        self.is_complete = false;
+        std::mem::take(&mut self.content)
+    }
+
     /// 重置响应
     pub fn reset(&mut self) {
         self.content.clear();
@@ -153,4 +270,78 @@ mod tests {
             // 成功
         }
     }
+
+    /// 统计加锁次数的 ResponseSink
+    struct CountingSink {
+        inner: std::sync::Mutex<StreamingChatResponse>,
+        locks: std::sync::atomic::AtomicUsize,
+    }
+
+    impl ResponseSink for CountingSink {
+        fn with_response(&self, f: &mut dyn FnMut(&mut StreamingChatResponse)) {
+            self.locks.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            self.inner.with_response(f);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_coalesced_tokens_single_lock() {
+        let handler = StreamHandler::new();
+        for i in 0..50 {
+            handler.send_token(format!("t{} ", i)).unwrap();
+        }
+        handler.send_done().unwrap();
+
+        let rx = handler.get_receiver();
+        let mut receiver = rx.lock().await;
+        let first = match receiver.recv().await {
+            Some(StreamEvent::Token(token)) => token,
+            other => panic!("unexpected event: {:?}", other),
+        };
+        let (tokens, next) = drain_tokens(&mut receiver, first);
+        assert_eq!(tokens.len(), 50);
+        assert!(matches!(next, Some(StreamEvent::Done)));
+
+        let sink = CountingSink {
+            inner: std::sync::Mutex::new(StreamingChatResponse::new()),
+            locks: std::sync::atomic::AtomicUsize::new(0),
+        };
+        append_token_batch(&sink, &tokens);
+
+        assert_eq!(sink.locks.load(std::sync::atomic::Ordering::SeqCst), 1);
+        let expected: String = (0..50).map(|i| format!("t{} ", i)).collect();
+        assert_eq!(sink.inner.lock().unwrap().get_content(), expected);
+    }
+
+    #[test]
+    fn test_concurrent_appends_and_take_lose_nothing() {
+        let sink = Arc::new(std::sync::Mutex::new(StreamingChatResponse::new()));
+        let writers: Vec<_> = (0..4)
+            .map(|writer| {
+                let sink = sink.clone();
+                std::thread::spawn(move || {
+                    for i in 0..200 {
+                        append_token_batch(&*sink, &[format!("<{}:{}>", writer, i)]);
+                    }
+                })
+            })
+            .collect();
+
+        let mut taken = String::new();
+        for _ in 0..50 {
+            taken.push_str(&take_response(&*sink));
+            std::thread::yield_now();
+        }
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        taken.push_str(&take_response(&*sink));
+
+        for writer in 0..4 {
+            for i in 0..200 {
+                assert_eq!(taken.matches(&format!("<{}:{}>", writer, i)).count(), 1);
+            }
+        }
+        assert!(sink.lock().unwrap().get_content().is_empty());
+    }
 }
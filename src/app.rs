@@ -1,21 +1,178 @@
-use crate::ai::client::{LLMClient, ChatMessage};
-use crate::ai::commands::{CommandParser, CommandType};
-use crate::ai::config::LLMConfig;
+use crate::ai::client::{is_retryable_stream_error, ChatCompletion, LLMClient, ChatMessage};
+use crate::ai::commands::{CommandParser, CommandSpec, CommandType};
+use crate::ai::config::{LLMConfig, LLMProvider};
+use crate::ai::commit_message::CommitStyle;
+use crate::core::conversation_engine::{FileContent, LineRange};
+use crate::ai::mentions::{diff_mention, expand_mentions, git_diff_context, has_history_mention, history_context, strip_history_mention, MentionExpansion, DIFF_MENTION_MAX_CHARS, HISTORY_MENTION_TOKEN_BUDGET, MAX_MENTION_DEPTH};
 use crate::ai::streaming::{StreamHandler, StreamingChatResponse};
 use crate::core::message::{Message, Role};
 use crate::core::history::ChatHistory;
-use crate::core::{GeminiArchitecture, ConversationEngine, ChatOrchestrator};
+use crate::core::{GeminiArchitecture, ConversationEngine, ChatOrchestrator, TokenCalculator};
+use crate::core::shutdown::{BackgroundTasks, ShutdownReport, ShutdownToken};
+use crate::events::interrupt_key::InterruptKey;
 use crate::ui::command_hints::CommandHints;
 use crate::commands::file_commands::FileCommandHandler;
 use crate::prompts;
-use crate::ai::code_modification::{AICodeModificationDetector, CodeModificationOp, CodeDiff, CodeMatcher};
+use crate::ai::code_modification::{parse_code_blocks, AICodeModificationDetector, CodeBlock, CodeModificationOp, CodeDiff, CodeMatcher};
+use crate::ai::modification_history::ModificationHistory;
 use crate::core::vibe_coding::{VibeWorkflowManager, VibeStage};
 use crate::commands::VibeCommandHandler;
 use crate::ui::filename_suggestion::FilenameSuggestion;
 use ratatui::{Frame, widgets::ScrollbarState};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use crate::ui;
 
+/// 在后台发起一次聊天请求，结果统一通过 StreamHandler 回传：
+/// 流式模式逐块发送 Token，非流式模式把完整回复作为单个 Token 发送；
+/// 退出时 cancel 被触发、用户按下中断键时 interrupt 被触发，请求直接放弃
+fn spawn_chat_request(
+    client: Arc<dyn ChatCompletion>,
+    messages: Vec<ChatMessage>,
+    streaming: bool,
+    handler: StreamHandler,
+    cancel: ShutdownToken,
+    interrupt: ShutdownToken,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let request = async {
+            let usage = if streaming {
+                let handler_clone = handler.clone();
+                let callback = move |token: String| {
+                    let _ = handler_clone.send_token(token);
+                    true
+                };
+                client.complete_stream_with_metadata(messages, Box::new(callback)).await?
+            } else {
+                let (response, usage) = client.complete_with_metadata(messages).await?;
+                let _ = handler.send_token(response);
+                usage
+            };
+            if let Some(usage) = usage {
+                let _ = handler.send_usage(usage);
+            }
+            Ok::<(), crate::ai::client::CompletionError>(())
+        };
+        let result = tokio::select! {
+            result = request => result,
+            _ = cancel.cancelled() => return,
+            _ = interrupt.cancelled() => return,
+        };
+
+        match result {
+            Ok(_) => {
+                let _ = handler.send_done();
+            }
+            Err(e) if is_retryable_stream_error(e.as_ref()) => {
+                let _ = handler.send_interrupted(e.to_string());
+            }
+            Err(e) => {
+                let _ = handler.send_error(e.to_string());
+            }
+        }
+    })
+}
+
+/// 每扫描多少个文件向 UI 报告一次进度
+const SCAN_PROGRESS_INTERVAL: usize = 100;
+
+/// 工具目录扫描的控制：每扫描 SCAN_PROGRESS_INTERVAL 个文件通过 handler 报告一次进度
+fn scan_control_reporting_to(handler: &StreamHandler) -> crate::tools::scan_control::ScanControl {
+    let progress = handler.clone();
+    crate::tools::scan_control::ScanControl::new().with_progress(move |files_scanned| {
+        if files_scanned % SCAN_PROGRESS_INTERVAL == 0 {
+            let _ = progress.send_scan_progress(files_scanned);
+        }
+    })
+}
+
+/// 通过 AIAgent 发起一次可以调用工具的请求（非流式模式）：
+/// 工具调用和结果由 Agent 发送到 handler，最终回复作为单个 Token 发送；
+/// 请求被放弃时取消工具的目录扫描
+fn spawn_agent_request(
+    agent: crate::core::AIAgent,
+    messages: Vec<ChatMessage>,
+    handler: StreamHandler,
+    cancel: ShutdownToken,
+    interrupt: ShutdownToken,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        // 放弃请求时同时停止工具在阻塞线程池中进行的目录扫描
+        let control = scan_control_reporting_to(&handler);
+        let agent = agent.with_scan_control(control.clone());
+        let result = tokio::select! {
+            result = agent.process_message(messages) => result,
+            _ = cancel.cancelled() => {
+                control.cancel();
+                return;
+            }
+            _ = interrupt.cancelled() => {
+                control.cancel();
+                return;
+            }
+        };
+
+        match result {
+            Ok(response) if response.status == crate::core::AgentStatus::MaxRoundsReached => {
+                let _ = handler.send_error("工具调用轮数达到上限，已停止".to_string());
+            }
+            Ok(response) => {
+                if let Some(reply) = response.messages.last() {
+                    let _ = handler.send_token(reply.content.clone());
+                }
+                let _ = handler.send_done();
+            }
+            Err(e) => {
+                let _ = handler.send_error(e.to_string());
+            }
+        }
+    })
+}
+
+/// 让 Agent 把工具调用、结果和 explain 模式的说明发送到当前请求的 handler，由主循环显示到聊天中
+fn with_request_events(agent: crate::core::AIAgent, handler: &StreamHandler) -> crate::core::AIAgent {
+    let explanations = handler.clone();
+    agent
+        .with_tool_events(handler.clone())
+        .with_explain_callback(move |text| {
+            let _ = explanations.send_explanation(text);
+        })
+}
+
+/// 用户中断生成后追加在部分回复末尾的标记
+pub const INTERRUPTED_MARKER: &str = "[interrupted]";
+
+/// 工具进度行的前缀
+const TOOL_LINE_PREFIX: &str = "🔧 ";
+
+fn tool_running_line(tool_name: &str) -> String {
+    format!("{}running {}…", TOOL_LINE_PREFIX, tool_name)
+}
+
+/// 安全模式下拒绝会写文件或执行命令的斜杠命令
+fn safe_mode_blocked(command: &str) -> String {
+    format!("🔒 安全模式已开启，{} 已禁用（/safe off 可关闭）", command)
+}
+
+/// Ctrl+C 停止生成后，再按一次 Ctrl+C 退出的有效时间
+pub const QUIT_CONFIRM_WINDOW: Duration = Duration::from_secs(2);
+
+/// @ 查询停止变化多久后才发起语义排序，避免每次按键都请求 embeddings
+pub const SEMANTIC_RANKING_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// /attach-last-output 附加的命令输出上限（字符数）
+const ATTACHED_OUTPUT_BUDGET: usize = 4000;
+
+/// 保留末尾 max_chars 个字符（错误信息通常在输出末尾）
+fn truncate_to_tail(text: &str, max_chars: usize) -> String {
+    let count = text.chars().count();
+    if count <= max_chars {
+        text.to_string()
+    } else {
+        text.chars().skip(count - max_chars).collect()
+    }
+}
+
 // ============ Action 系统 ============
 
 /// Action - 事件驱动的应用状态管理
@@ -92,24 +249,6 @@ impl Default for ActionQueue {
     }
 }
 
-/// 格式化 Diff 对比
-fn format_diff(old: &str, new: &str) -> String {
-    let old_lines: Vec<&str> = old.lines().collect();
-    let new_lines: Vec<&str> = new.lines().collect();
-    let max_lines = old_lines.len().max(new_lines.len());
-    
-    let mut result = String::new();
-    for i in 0..max_lines {
-        if i < old_lines.len() {
-            result.push_str(&format!("- {}\n", old_lines[i]));
-        }
-        if i < new_lines.len() {
-            result.push_str(&format!("+ {}\n", new_lines[i]));
-        }
-    }
-    result
-}
-
 #[derive(Debug, PartialEq)]
 pub enum AppAction {
     None,
@@ -131,8 +270,20 @@ pub struct App {
     pub input_text: String,
     pub input_cursor: usize, // Track cursor position in input text
     pub llm_config: Option<LLMConfig>,
+    /// 全局（环境变量）默认模型，切换项目时先恢复它再应用项目偏好
+    pub default_model: Option<String>,
     pub llm_client: Option<Arc<LLMClient>>,
     pub is_streaming: bool,
+    /// 本次生成开始的时间，状态栏的输入指示器按它计算动画帧
+    pub streaming_started_at: Option<Instant>,
+    /// 正在执行的工具已扫描的文件数，状态栏显示
+    pub scan_progress: Option<usize>,
+    /// 输入指示器动画（TYPING_ANIMATION_MS 可配置）
+    pub typing_animation: crate::ui::typing_indicator::TypingAnimation,
+    /// 是否使用流式响应（/stream on|off）
+    pub stream_responses: bool,
+    /// @ 附加的文件内容是否带 `N: ` 行号（/linenumbers on|off）
+    pub line_numbered_context: bool,
     pub stream_handler: Option<StreamHandler>,
     pub streaming_response: Arc<Mutex<StreamingChatResponse>>,
     pub command_hints: CommandHints,
@@ -143,11 +294,80 @@ pub struct App {
     pub modification_confirmation_pending: bool,
     pub modification_selected_index: usize,
     pub modification_choice: ModificationChoice,
+    /// 已应用修改的撤销栈（/undo）
+    pub modification_history: ModificationHistory,
 
     // 聊天历史滚动
     pub chat_scroll_offset: usize,
+    /// 当前终端尺寸下 chat_scroll_offset 的上限，终端尺寸变化时重新计算
+    pub chat_max_scroll: usize,
     pub scrollbar_state: ScrollbarState,
 
+    // explain 模式：工具执行前显示通俗说明
+    pub explain_mode: bool,
+    /// 工具输出完整显示（默认），关闭时长输出折叠为一行摘要（/verbose）
+    pub verbose_tool_output: bool,
+    /// 最近一次工具调用的完整输出（/verbose show）
+    pub last_tool_output: Option<String>,
+    /// 安全模式：禁用所有写入/执行类工具（/safe 或 --safe）
+    pub safe_mode: bool,
+    /// YOLO 模式：AI 提出的编辑在能安全定位时直接应用，不弹出确认；创建和删除文件仍需确认（/yolo）
+    pub yolo_mode: bool,
+
+    // /system 设置的会话级系统提示，放在每次请求消息列表的最前面
+    pub custom_system_prompt: Option<String>,
+    /// /budget 设置的附加文件 Token 预算，None 时不裁剪
+    pub context_token_budget: Option<usize>,
+    /// /temp 设置的会话级温度，覆盖配置中的温度但不写入配置
+    pub temperature_override: Option<f32>,
+    /// /commit-msg 使用的提交信息风格（COMMIT_MESSAGE_STYLE 可配置）
+    pub commit_style: CommitStyle,
+    /// 退出时写入历史文件的最大消息条数（HISTORY_MAX_MESSAGES 可配置）
+    pub persisted_history_limit: usize,
+    /// 当前会话的历史文件，设置后 shutdown 在任何退出路径上都会写入
+    pub history_path: Option<std::path::PathBuf>,
+    /// 进行中请求的中断标记（中断键触发）
+    pub request_interrupt: Option<ShutdownToken>,
+    /// 停止生成的按键（默认 Esc，INTERRUPT_KEY 可配置）
+    pub interrupt_key: InterruptKey,
+    /// /compose 请求打开外部编辑器，由主循环挂起 TUI 后处理
+    pub compose_requested: bool,
+    /// Ctrl+C 停止生成的时间；QUIT_CONFIRM_WINDOW 内再按一次才退出
+    pub quit_armed_at: Option<Instant>,
+    /// 当前请求和已收到的回复；流中途出错时保留，/retry 从中断处继续
+    pub stream_buffer: crate::core::streaming::StreamBuffer,
+    /// 本次回复提供商报告的用量，回复完成时附加到 AI 消息上
+    pending_usage: Option<crate::ai::client::CompletionMetadata>,
+    /// 本轮请求发出的时间，回复完成时记入 /why 的响应耗时
+    routing_started_at: Option<Instant>,
+    /// 已显示 "running" 行、还在等待结果的工具（按调用顺序）
+    running_tools: std::collections::VecDeque<crate::tools::ToolCall>,
+    /// 工具审计日志（TOOL_AUDIT_LOG），设置后 Agent 每次执行工具都追加一行 JSON
+    pub tool_audit_log: Option<std::path::PathBuf>,
+    /// 创建 AI Agent 时调用的自定义工具初始化函数（main.rs 在初始化 LLM 客户端前设置）
+    pub tool_plugins: Vec<crate::tools::ToolInitFn>,
+
+    // 消息时间戳显示方式（/timestamps）
+    pub timestamp_mode: crate::ui::timestamps::TimestampMode,
+    /// 聊天显示密度（/density）
+    pub chat_density: crate::ui::density::ChatDensity,
+
+    // 最近一次 /bash 命令的输出，以及等待附加到下一轮对话的上下文
+    pub last_command_output: Option<String>,
+    pub attached_context: Vec<String>,
+    /// 最近一次发送的用户输入（已附加命令输出，未展开 @ 引用）及其消息位置，/retry 据此重建请求
+    last_chat_input: Option<(usize, String)>,
+    /// 构建失败后是否自动附加解析出的编译错误（/build-errors auto|ask）
+    pub auto_attach_build_errors: bool,
+    /// 等待用户确认附加的编译错误（/build-errors attach）
+    pub pending_build_errors: Vec<crate::tools::CompilerError>,
+
+    // 后台任务（聊天请求、工具注册等），退出时统一取消并等待
+    pub background_tasks: BackgroundTasks,
+
+    // /block 跳转后高亮的代码块：(消息索引, 消息内容中的行范围)
+    pub highlighted_block: Option<(usize, std::ops::Range<usize>)>,
+
     // Action 系统
     pub action_queue: ActionQueue,
 
@@ -162,8 +382,17 @@ pub struct App {
     // @ 提及建议
     pub mention_suggestions: crate::ui::mention_suggestions::MentionSuggestions,
 
+    // Ctrl+O 模型切换浮层
+    pub model_switcher: crate::ui::model_switcher::ModelSwitcher,
+
     // 文件搜索引擎
     pub file_search: crate::ui::file_search::FileSearchEngine,
+    /// @ 提及的语义排序（/mentions semantic 开启，None 表示仅模糊匹配）
+    pub semantic_ranker: Option<crate::ui::semantic_search::SemanticFileRanker>,
+    /// 后台语义排序结果：(查询, 排序后的建议)
+    semantic_results: Arc<Mutex<Option<(String, Vec<String>)>>>,
+    /// @ 查询最后一次变化的时间；防抖结束后由 poll_semantic_ranking 发起排序
+    semantic_query_changed_at: Option<Instant>,
 
     // 高效渲染引擎
     pub render_engine: crate::ui::render_engine::RenderEngine,
@@ -201,8 +430,14 @@ impl App {
             input_text: String::new(),
             input_cursor: 0,
             llm_config: None,
+            default_model: None,
             llm_client: None,
             is_streaming: false,
+            streaming_started_at: None,
+            scan_progress: None,
+            typing_animation: crate::ui::typing_indicator::TypingAnimation::default(),
+            stream_responses: true,
+            line_numbered_context: false,
             stream_handler: None,
             streaming_response: Arc::new(Mutex::new(StreamingChatResponse::new())),
             command_hints: CommandHints::new(),
@@ -211,7 +446,39 @@ impl App {
             modification_confirmation_pending: false,
             modification_selected_index: 0,
             modification_choice: ModificationChoice::Confirm,
+            modification_history: ModificationHistory::new(),
             chat_scroll_offset: 0,
+            chat_max_scroll: 0,
+            explain_mode: false,
+            verbose_tool_output: true,
+            last_tool_output: None,
+            safe_mode: false,
+            yolo_mode: false,
+            custom_system_prompt: None,
+            context_token_budget: None,
+            temperature_override: None,
+            commit_style: CommitStyle::default(),
+            persisted_history_limit: crate::core::history::DEFAULT_PERSISTED_MESSAGES,
+            history_path: None,
+            request_interrupt: None,
+            interrupt_key: InterruptKey::default(),
+            compose_requested: false,
+            quit_armed_at: None,
+            running_tools: std::collections::VecDeque::new(),
+            stream_buffer: Default::default(),
+            pending_usage: None,
+            routing_started_at: None,
+            tool_audit_log: None,
+            tool_plugins: Vec::new(),
+            timestamp_mode: Default::default(),
+            chat_density: Default::default(),
+            last_command_output: None,
+            attached_context: Vec::new(),
+            last_chat_input: None,
+            auto_attach_build_errors: false,
+            pending_build_errors: Vec::new(),
+            background_tasks: BackgroundTasks::new(),
+            highlighted_block: None,
             scrollbar_state: ScrollbarState::default(),
             action_queue: ActionQueue::new(),
             input_scroll_offset: 0,
@@ -219,7 +486,11 @@ impl App {
             selection_start: None,
             selection_end: None,
             mention_suggestions: crate::ui::mention_suggestions::MentionSuggestions::new(),
+            model_switcher: crate::ui::model_switcher::ModelSwitcher::new(),
             file_search: crate::ui::file_search::FileSearchEngine::new(),
+            semantic_ranker: None,
+            semantic_results: Arc::new(Mutex::new(None)),
+            semantic_query_changed_at: None,
             render_engine: crate::ui::render_engine::RenderEngine::new(),
             frame_count: 0,
             gemini: GeminiArchitecture::new(),
@@ -233,13 +504,18 @@ impl App {
     }
 
     pub fn init_ai_client_with_config(&mut self, config: LLMConfig) {
+        self.default_model = Some(config.model.clone());
         self.llm_config = Some(config);
         self.update_llm_client();
     }
 
     fn update_llm_client(&mut self) {
         if let Some(config) = &self.llm_config {
-            let client = Arc::new(LLMClient::new(config.clone()));
+            let mut config = config.clone();
+            if let Some(temperature) = self.temperature_override {
+                config.temperature = temperature;
+            }
+            let client = Arc::new(LLMClient::new(config));
             self.llm_client = Some(client.clone());
             // 重新创建 conversation_engine 并设置 llm_client
             self.conversation_engine = ConversationEngine::new()
@@ -249,22 +525,50 @@ impl App {
             // 初始化 ChatOrchestrator
             self.chat_orchestrator = Some(ChatOrchestrator::new(client.clone()));
 
-            // 初始化 AI Agent（类似 grok-cli 的 GrokAgent）
-            let agent_config = crate::core::AIAgentConfig {
-                max_tool_rounds: 50,
-                model: config.model.clone(),
-                enable_search: false,
-            };
-            let ai_agent = crate::core::AIAgent::new(client, agent_config);
+            self.rebuild_ai_agent();
+        }
+    }
 
-            // 注册标准工具
-            let agent_clone = ai_agent.clone();
-            tokio::spawn(async move {
-                agent_clone.register_standard_tools().await;
-            });
+    /// 用当前客户端重新创建 AI Agent（类似 grok-cli 的 GrokAgent）并在后台注册标准工具
+    fn rebuild_ai_agent(&mut self) {
+        let Some(client) = self.llm_client.clone() else {
+            return;
+        };
+        let agent_config = crate::core::AIAgentConfig {
+            max_tool_rounds: 50,
+            model: client.config().model.clone(),
+            enable_search: false,
+            explain: self.explain_mode,
+            safe_mode: self.safe_mode,
+            tool_plugins: self.tool_plugins.clone(),
+            path_guard: self.file_command_handler.path_guard().clone(),
+        };
+        let mut hooks = crate::core::HookManager::new();
+        if let Some(path) = &self.tool_audit_log {
+            hooks.register_after_tool_execution_hook(Arc::new(crate::core::hooks::FileAuditHook::new(path)));
+        }
+        let ai_agent = crate::core::AIAgent::new(client, agent_config).with_hooks(Arc::new(hooks));
+
+        // 注册标准工具
+        let agent_clone = ai_agent.clone();
+        self.background_tasks.spawn(async move {
+            agent_clone.register_standard_tools().await;
+        });
+
+        self.ai_agent = Some(ai_agent);
+    }
 
-            self.ai_agent = Some(ai_agent);
+    /// 退出前的清理：取消后台任务并在超时内等待它们结束，超时的任务被中止，然后保存会话历史
+    ///
+    /// 保存失败时返回错误，由调用方在恢复终端后报告
+    pub async fn shutdown(&mut self, timeout: std::time::Duration) -> std::io::Result<ShutdownReport> {
+        let report = self.background_tasks.shutdown(timeout).await;
+        self.is_streaming = false;
+        self.stream_handler = None;
+        if let Some(path) = &self.history_path {
+            self.save_history(path)?;
         }
+        Ok(report)
     }
 
     pub fn add_user_message(&mut self, text: &str) {
@@ -276,6 +580,34 @@ impl App {
         self.scroll_to_bottom();
     }
 
+    /// 未配置提供商，或提供商需要的 API Key 为空时，返回配置提示
+    fn missing_api_key_guidance(&self) -> Option<String> {
+        match &self.llm_config {
+            None => Some(LLMProvider::OpenAI.missing_api_key_guidance()),
+            Some(config) if config.is_missing_api_key() => Some(config.provider.missing_api_key_guidance()),
+            Some(_) => None,
+        }
+    }
+
+    /// 放弃正在输入的消息：清空输入框并关闭 @ 提及和命令提示，不影响对话历史
+    pub fn clear_input(&mut self) {
+        self.input_text.clear();
+        self.input_cursor = 0;
+        self.input_scroll_offset = 0;
+        self.command_hints.clear();
+        self.mention_suggestions.close();
+        self.file_search.clear();
+    }
+
+    /// 用外部编辑器编辑输入框内容，成功后替换输入框并把光标移到末尾
+    pub fn compose_with_editor(&mut self, editor: &str) -> std::io::Result<()> {
+        let content = crate::ui::compose::edit_in_editor(editor, &self.input_text)?;
+        self.clear_input();
+        self.input_cursor = content.chars().count();
+        self.input_text = content;
+        Ok(())
+    }
+
     pub async fn handle_chat_submit(&mut self) {
         let input = self.input_text.clone();
         if input.is_empty() {
@@ -283,6 +615,8 @@ impl App {
         }
 
         self.add_user_message(&input);
+        // 本轮用户消息的位置，@history 只附加它之前的对话
+        let turn = self.chat_history.get_messages().len() - 1;
         self.input_text.clear();
         self.input_cursor = 0; // Reset cursor position
         self.command_hints.clear();
@@ -290,46 +624,26 @@ impl App {
 
         if input.starts_with('/') {
             self.handle_command(&input).await;
-        } else if self.llm_client.is_some() {
-            // 使用 StreamHandler 进行流式输出
-            let handler = StreamHandler::new();
-            self.stream_handler = Some(handler.clone());
-            self.is_streaming = true;
-
-            // 在聊天历史中预先插入一条空的 AI 消息，用于流式更新
+        } else if let Some(guidance) = self.missing_api_key_guidance() {
+            // 发送前检查：没有 API Key 时提示如何配置，而不是等 HTTP 请求失败
             self.chat_history.add_message(Message {
-                role: Role::Assistant,
-                content: String::new(),
+                role: Role::System,
+                content: guidance,
             });
             self.scroll_to_bottom();
-
-            let client = self.llm_client.as_ref().unwrap().clone();
-            let input_clone = input.clone();
-
-            tokio::spawn(async move {
-                let handler_clone = handler.clone();
-                let callback = move |token: String| {
-                    let _ = handler_clone.send_token(token);
-                    true
-                };
-
-                let messages = vec![ChatMessage {
-                    role: "user".to_string(),
-                    content: input_clone,
-                }];
-
-                match client.generate_completion_stream(messages, None, callback).await {
-                    Ok(_) => {
-                        let _ = handler.send_done();
-                    }
-                    Err(e) => {
-                        let _ = handler.send_error(e.to_string());
-                    }
-                }
-            });
+        } else if self.llm_client.is_some() {
+            // 先展开 @ 引用：它可能追加提示消息，占位的 AI 消息必须在这之后插入
+            let chat_input = self.build_chat_input(&input);
+            self.last_chat_input = Some((turn, chat_input.clone()));
+            let chat_input = self.process_mentions(&chat_input, turn);
+
+            self.record_chat_routing(&chat_input);
+            self.stream_buffer.begin(chat_input.clone());
+            self.send_chat_request(chat_input, false);
         } else {
             // 如果 LLM client 未初始化，使用备用方案（Gemini）
-            let processed_input = self.process_mentions(&input);
+            let chat_input = self.build_chat_input(&input);
+            let processed_input = self.process_mentions(&chat_input, turn);
             match self.gemini.chat(processed_input.clone()).await {
                 Ok(response) => {
                     self.chat_history.add_message(Message {
@@ -351,9 +665,56 @@ impl App {
     }
 
     /// 处理消息中的 @ 提及，读取文件内容并注入
-    fn process_mentions(&self, input: &str) -> String {
-        let mut result = input.to_string();
-        let mut file_contents = String::new();
+    /// 设置了 /budget 时，超出预算的文件会被截断或丢弃
+    /// 嵌套引用中的循环或超过深度的引用会被跳过，并以系统消息提示
+    /// turn 是这条输入对应的用户消息在历史中的位置
+    fn process_mentions(&mut self, input: &str, turn: usize) -> String {
+        use crate::ai::context_budget::{apply_budget, plan_budget};
+
+        let (mut cleaned, expansion) = self.collect_mentions(input);
+        if let Some(report) = expansion.report() {
+            self.chat_history.add_message(Message {
+                role: Role::System,
+                content: report,
+            });
+        }
+        let mut files = expansion.files;
+        if has_history_mention(input) {
+            cleaned = strip_history_mention(&cleaned);
+            files.insert(0, self.history_mention_context(turn));
+        }
+        if let Some(budget) = self.context_token_budget {
+            let calculator = self.token_calculator();
+            let plan = plan_budget(&files, budget, &calculator);
+            files = apply_budget(files, &plan, &calculator);
+        }
+        let file_contents: Vec<String> = files.iter().map(|file| file.render(self.line_numbered_context)).collect();
+        if file_contents.is_empty() {
+            cleaned
+        } else {
+            format!("{}{}", cleaned, file_contents.concat())
+        }
+    }
+
+    /// @history：按 Token 预算附加当前这条消息之前的最近对话
+    fn history_mention_context(&self, turn: usize) -> FileContent {
+        let messages: Vec<Message> = self.chat_history.get_messages().iter().take(turn).cloned().collect();
+        history_context(&messages, &self.token_calculator(), HISTORY_MENTION_TOKEN_BUDGET)
+    }
+
+    /// 拆分输入：返回去掉 @path 后的文本，以及将要附加的文件内容块
+    fn split_mentions(&self, input: &str, line_numbers: bool) -> (String, Vec<String>) {
+        let (cleaned, expansion) = self.collect_mentions(input);
+        (cleaned, expansion.files.iter().map(|file| file.render(line_numbers)).collect())
+    }
+
+    /// 拆分输入：返回去掉 @path 后的文本，以及提及的文件（含文件中嵌套 @ 引用的文件）
+    /// 按完整的 @ 词处理，`@diff` 不会影响 `@diff-staged`；@diff 在项目根目录运行 git
+    fn collect_mentions(&self, input: &str) -> (String, MentionExpansion) {
+        let mut result = String::with_capacity(input.len());
+        let mut mentioned = Vec::new();
+        let mut extra = Vec::new();
+        let mut invalid_ranges = Vec::new();
 
         // 查找所有 @path 模式
         let mut i = 0;
@@ -371,34 +732,140 @@ impl App {
                     i += 1;
                 }
                 
-                // 尝试读取文件
-                if !path.is_empty() {
-                    match std::fs::read_to_string(&path) {
-                        Ok(content) => {
-                            file_contents.push_str(&format!(
-                                "\n\n<file_content path=\"{}\">\n{}\n</file_content>\n",
-                                path, content
-                            ));
-                            // 从结果中移除 @path
-                            result = result.replace(&format!("@{}", path), "");
-                        }
-                        Err(_) => {
-                            // 文件不存在，保留 @path 在消息中
-                        }
+                let (file_path, range) = LineRange::split_mention(&path);
+                if let Some(staged) = diff_mention(&path) {
+                    extra.push(git_diff_context(&self.file_search.root_path, staged, DIFF_MENTION_MAX_CHARS));
+                } else if let (Some(range), true) = (range, std::path::Path::new(file_path).is_file()) {
+                    // @path:10-20 只附加指定行，不展开其中的嵌套引用
+                    match std::fs::read_to_string(file_path)
+                        .map_err(|e| format!("{}: {}", file_path, e))
+                        .and_then(|content| FileContent::with_range(file_path, &content, range))
+                    {
+                        Ok(file) => extra.push(file),
+                        Err(reason) => invalid_ranges.push(reason),
                     }
+                } else if !path.is_empty() && std::path::Path::new(&path).is_file() {
+                    // 从结果中移除 @path
+                    mentioned.push(path);
+                } else {
+                    // 文件不存在时保留 @path 在消息中
+                    result.push('@');
+                    result.push_str(&path);
                 }
             } else {
+                result.push(chars[i]);
                 i += 1;
             }
         }
 
-        // 清理消息（移除多余空格）并添加文件内容
-        let cleaned = result.trim().to_string();
-        if file_contents.is_empty() {
-            cleaned
-        } else {
-            format!("{}{}", cleaned, file_contents)
+        // 清理消息（移除多余空格）
+        let mut expansion = expand_mentions(&mentioned, MAX_MENTION_DEPTH);
+        expansion.files.extend(extra);
+        expansion.invalid_ranges = invalid_ranges;
+        (result.trim().to_string(), expansion)
+    }
+
+    /// 当前模型的 Token 计算器（未配置模型时按 gpt-4 估算）
+    fn token_calculator(&self) -> TokenCalculator {
+        let model = self
+            .llm_config
+            .as_ref()
+            .map(|c| c.model.clone())
+            .unwrap_or_else(|| "gpt-4".to_string());
+        TokenCalculator::from_model_name(&model)
+    }
+
+    /// /temp [value|reset] - 设置本次会话的温度（0-2，超出范围时截断），不修改已保存的配置
+    fn set_temperature(&mut self, args: &[String]) -> String {
+        let Some(config) = &self.llm_config else {
+            return "❌ LLM 尚未配置".to_string();
+        };
+        let configured = config.temperature;
+        let previous = self.temperature_override.unwrap_or(configured);
+
+        match args.first().map(|a| a.as_str()) {
+            None => match self.temperature_override {
+                Some(temperature) => format!("🌡 当前温度 {:.2}（会话覆盖，配置为 {:.2}，/temp reset 恢复）", temperature, configured),
+                None => format!("🌡 当前温度 {:.2}（来自配置）\n用法: /temp [0-2|reset]", configured),
+            },
+            Some("reset") | Some("off") => {
+                self.temperature_override = None;
+                self.update_llm_client();
+                format!("🌡 温度 {:.2} → {:.2}（已恢复为配置值）", previous, configured)
+            }
+            Some(value) => {
+                let Ok(requested) = value.parse::<f32>() else {
+                    return "用法: /temp [0-2|reset]".to_string();
+                };
+                if !requested.is_finite() {
+                    return "用法: /temp [0-2|reset]".to_string();
+                }
+                let temperature = requested.clamp(0.0, 2.0);
+                self.temperature_override = Some(temperature);
+                self.update_llm_client();
+                let mut response = format!("🌡 温度 {:.2} → {:.2}（仅本次会话，/temp reset 恢复）", previous, temperature);
+                if temperature != requested {
+                    response.push_str(&format!("\n  {} 超出范围 0-2，已截断", value));
+                }
+                response
+            }
+        }
+    }
+
+    /// /budget [tokens|off] - 设置附加文件的 Token 预算，并预览最后一条输入中提及的文件会被如何裁剪
+    fn set_context_budget(&mut self, args: &[String]) -> String {
+        use crate::ai::context_budget::{format_budget_preview, plan_budget};
+
+        match args.first().map(|a| a.as_str()) {
+            None => {}
+            Some("off") => {
+                self.context_token_budget = None;
+                return "上下文预算已关闭：附加文件不再裁剪".to_string();
+            }
+            Some(value) => match value.parse::<usize>() {
+                Ok(tokens) if tokens > 0 => self.context_token_budget = Some(tokens),
+                _ => return "用法: /budget [tokens|off]".to_string(),
+            },
         }
+        let Some(budget) = self.context_token_budget else {
+            return "未设置上下文预算，附加文件不裁剪\n用法: /budget [tokens|off]".to_string();
+        };
+
+        let last_input = self
+            .chat_history
+            .get_messages()
+            .iter()
+            .rev()
+            .find(|msg| msg.role == Role::User && !msg.content.starts_with('/'))
+            .map(|msg| msg.content.clone())
+            .unwrap_or_default();
+        let (_, expansion) = self.collect_mentions(&last_input);
+        format_budget_preview(budget, &plan_budget(&expansion.files, budget, &self.token_calculator()))
+    }
+
+    /// /estimate <text> - 预估发送该输入（含 @file 附加内容）所需的 tokens
+    fn estimate_prompt_tokens(&self, args: &[String]) -> String {
+        if args.is_empty() {
+            return "用法: /estimate <要发送的内容，可包含 @file>".to_string();
+        }
+        let (input, context) = self.split_mentions(&args.join(" "), self.line_numbered_context);
+        let calculator = self.token_calculator();
+        let estimate = calculator.estimate_prompt(&input, &context);
+
+        let mut report = format!(
+            "📏 Token 预估 ({}, {}):\n  输入: {}\n  附加上下文: {} ({} 个文件)\n  合计: {} / {}",
+            calculator.get_model_info().name,
+            calculator.backend(),
+            estimate.input_tokens,
+            estimate.context_tokens,
+            context.len(),
+            estimate.total_tokens,
+            estimate.context_window
+        );
+        if let Some(warning) = estimate.warning() {
+            report.push_str(&format!("\n{}", warning));
+        }
+        report
     }
 
     async fn handle_command(&mut self, input: &str) {
@@ -426,7 +893,26 @@ impl App {
         }
 
         // 其次尝试解析为文件命令
-        if let Some(file_cmd) = FileCommandHandler::parse_command(input) {
+        let file_cmd = match FileCommandHandler::try_parse_command(input) {
+            Ok(file_cmd) => file_cmd,
+            Err(e) => {
+                self.chat_history.add_message(Message {
+                    role: Role::System,
+                    content: format!("❌ 命令解析失败: {}", e),
+                });
+                self.scroll_to_bottom();
+                return;
+            }
+        };
+        if let Some(file_cmd) = file_cmd {
+            if self.safe_mode && !file_cmd.is_read_only() {
+                self.chat_history.add_message(Message {
+                    role: Role::System,
+                    content: safe_mode_blocked(input.split_whitespace().next().unwrap_or(input)),
+                });
+                self.scroll_to_bottom();
+                return;
+            }
             let result = self.file_command_handler.execute(file_cmd);
 
             // 显示命令结果
@@ -439,10 +925,10 @@ impl App {
             // 如果有 Diff 对比，显示它
             if let Some(diff) = result.diff {
                 let diff_content = format!(
-                    "--- {} (原始)\n+++{} (新版本)\n{}",
+                    "--- {} (原始)\n+++ {} (新版本)\n{}",
                     diff.file_path,
                     diff.file_path,
-                    format_diff(&diff.old_content, &diff.new_content)
+                    crate::core::diff::render_unified(&diff.hunks)
                 );
                 self.chat_history.add_message(Message {
                     role: Role::System,
@@ -455,12 +941,86 @@ impl App {
         }
 
         // 再次尝试解析为普通命令
-        if let Some(cmd) = CommandParser::parse(input) {
+        let cmd = match CommandParser::try_parse(input) {
+            Ok(cmd) => cmd,
+            Err(e) => {
+                self.chat_history.add_message(Message {
+                    role: Role::System,
+                    content: format!("❌ 命令解析失败: {}", e),
+                });
+                self.scroll_to_bottom();
+                return;
+            }
+        };
+        if let Some(cmd) = cmd {
             let response = match cmd.command_type {
                 CommandType::Help => CommandParser::get_help_text(),
-                CommandType::Clear => {
-                    self.chat_history.clear();
-                    "✓ Chat history cleared".to_string()
+                CommandType::Clear => self.clear_history(&cmd.args),
+                CommandType::SetModel => self.set_model(&cmd.args),
+                CommandType::Config => self.format_effective_config(),
+                CommandType::Estimate => self.estimate_prompt_tokens(&cmd.args),
+                CommandType::Budget => self.set_context_budget(&cmd.args),
+                CommandType::Temperature => self.set_temperature(&cmd.args),
+                CommandType::Bash => self.run_bash_command(&cmd.args).await,
+                CommandType::AttachLastOutput => self.attach_last_output(),
+                CommandType::ExportContext => self.export_context(&cmd.args),
+                CommandType::BuildErrors => self.build_errors_command(&cmd.args),
+                CommandType::ChangeDir => self.change_project_dir(&cmd.args),
+                CommandType::Explain => self.toggle_explain_mode(&cmd.args),
+                CommandType::Verbose => self.set_verbose_tool_output(&cmd.args),
+                CommandType::Safe => self.toggle_safe_mode(&cmd.args),
+                CommandType::Yolo => self.toggle_yolo_mode(&cmd.args),
+                CommandType::Undo => self.undo_last_modification(),
+                CommandType::Why => self.explain_last_routing(),
+                CommandType::Benchmark => self.run_model_benchmark(&cmd.args).await,
+                CommandType::Snippet => self.insert_snippet(&cmd.args),
+                CommandType::CommitMsg => self.generate_commit_message(&cmd.args).await,
+                CommandType::Retry => {
+                    // 成功时回复直接流入新的 AI 消息，不再追加命令结果
+                    if let Err(message) = self.retry_last().await {
+                        self.chat_history.add_message(Message {
+                            role: Role::System,
+                            content: message,
+                        });
+                        self.scroll_to_bottom();
+                    }
+                    return;
+                }
+                CommandType::Compose => {
+                    self.compose_requested = true;
+                    format!("✎ 正在打开编辑器 {}，保存退出后内容会载入输入框", crate::ui::compose::editor_command())
+                }
+                CommandType::System => self.system_prompt_command(&cmd.args),
+                CommandType::Timestamps => self.set_timestamp_mode(&cmd.args),
+                CommandType::Density => self.set_chat_density(&cmd.args),
+                CommandType::Stream => self.set_stream_mode(&cmd.args),
+                CommandType::LineNumbers => self.set_line_numbered_context(&cmd.args),
+                CommandType::Mentions => self.set_mention_ranking(&cmd.args),
+                CommandType::Search => self.format_history_search(&cmd.args),
+                CommandType::Pin => self.set_message_pinned(&cmd.args, true),
+                CommandType::Unpin => self.set_message_pinned(&cmd.args, false),
+                CommandType::Goto => {
+                    self.goto_message(&cmd.args);
+                    return;
+                }
+                CommandType::Blocks => self.format_code_block_list(),
+                CommandType::Block => {
+                    // 跳转需要在添加回复消息之后计算滚动位置
+                    let response = self.jump_to_code_block(&cmd.args);
+                    self.chat_history.add_message(Message {
+                        role: Role::System,
+                        content: response,
+                    });
+                    self.scroll_to_highlighted_block();
+                    return;
+                }
+                // 参数齐全的文件命令已由 FileCommandHandler 执行，到这里说明缺少参数
+                CommandType::File => {
+                    let name = input.split_whitespace().next().unwrap_or(input).trim_start_matches('/');
+                    match CommandSpec::find(name) {
+                        Some(spec) => format!("❌ 参数不足，用法: {}", spec.synopsis()),
+                        None => format!("Unknown command: {}", input),
+                    }
                 }
                 // NOTE: Other command handlers would go here
                 _ => format!("Unknown command: {}", input),
@@ -474,200 +1034,3005 @@ impl App {
         }
     }
 
-    /// 处理 AI 响应中的代码修改指令
-    pub fn process_ai_response_for_modifications(&mut self, response: &str) {
-        // 首先检测明确的修改指令
-        let mut ops = AICodeModificationDetector::detect_modifications(response);
-
-        // 如果没有明确指令，检测隐含的修改意图
-        if ops.is_empty() {
-            ops = AICodeModificationDetector::detect_implicit_modifications(response);
+    /// 把最近 persisted_history_limit 条消息写入 path（JSON）
+    pub fn save_history(&self, path: &std::path::Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
         }
+        let entries = self.chat_history.to_persisted(self.persisted_history_limit);
+        let json = serde_json::to_string_pretty(&entries).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
 
-        if ops.is_empty() {
-            return;
-        }
+    /// 从 path 恢复聊天历史，返回恢复的消息数；文件不存在或内容损坏时保持空历史
+    pub fn load_history(&mut self, path: &std::path::Path) -> usize {
+        let entries = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str::<Vec<crate::core::history::PersistedMessage>>(&json).ok())
+            .unwrap_or_default();
+        self.chat_history.restore(entries);
+        self.scroll_to_bottom();
+        self.chat_history.get_messages().len()
+    }
 
-        // 检查是否有未指定文件名的操作（需要用户确认）
-        let mut needs_filename_confirmation = false;
-        for op in &ops {
-            if let CodeModificationOp::Modify { path, search: _, replace: _ } = op {
-                if path.starts_with("UNSPECIFIED_") {
-                    needs_filename_confirmation = true;
-                    break;
-                }
+    /// 应用启动时会话选择器的结果，返回恢复的消息数。
+    /// 恢复归档会话或开始新会话时，原来的当前会话先被归档，退出时新的当前会话写回自动保存文件
+    pub fn apply_session_choice(
+        &mut self,
+        store: &crate::core::sessions::SessionStore,
+        choice: &crate::core::sessions::SessionChoice,
+    ) -> std::io::Result<usize> {
+        use crate::core::sessions::{SessionChoice, CURRENT_SESSION_ID};
+
+        match choice {
+            SessionChoice::Resume(id) if id == CURRENT_SESSION_ID => Ok(self.load_history(&store.current_path())),
+            SessionChoice::Resume(id) => {
+                store.archive_current()?;
+                self.chat_history.restore(store.take(id)?.unwrap_or_default());
+                self.scroll_to_bottom();
+                Ok(self.chat_history.get_messages().len())
+            }
+            SessionChoice::Fresh => {
+                store.archive_current()?;
+                self.chat_history.clear_all();
+                Ok(0)
             }
         }
+    }
 
-        if needs_filename_confirmation {
-            // ✅ 显示文件名建议对话框，让用户选择
-            // 提取代码内容和语言
-            let mut unspecified_content = String::new();
-            let mut detected_language = String::new();
+    /// /config - 显示请求实际使用的配置，以及项目和会话级的覆盖
+    fn format_effective_config(&self) -> String {
+        use crate::ai::config::PROJECT_MODEL_FILE;
 
-            for op in &ops {
-                if let CodeModificationOp::Modify { path, search: _, replace } = op {
-                    if path.starts_with("UNSPECIFIED_") {
-                        unspecified_content = replace.clone();
-                        // 从路径中提取语言（如 UNSPECIFIED_main.rs -> rs）
-                        if let Some(ext_start) = path.rfind('.') {
-                            detected_language = path[ext_start + 1..].to_string();
-                        }
-                        break;
-                    }
-                }
-            }
+        let Some(client) = &self.llm_client else {
+            return "❌ LLM 尚未配置".to_string();
+        };
+        let config = client.config();
+        let mut lines = vec![
+            "⚙ 当前生效的配置:".to_string(),
+            format!("  提供商: {}", config.provider.to_string()),
+            format!("  模型: {}", config.model),
+            format!("  Base URL: {}", config.base_url),
+            format!("  API Key: {}", config.masked_api_key()),
+            format!("  温度: {:.2}", config.temperature),
+            format!("  最大 Token: {}", config.max_tokens),
+            format!("  请求超时: {}s", crate::ai::client::REQUEST_TIMEOUT.as_secs()),
+        ];
+
+        let mut overrides = Vec::new();
+        let project_root = &self.file_search.root_path;
+        if let Some(model) = LLMConfig::load_project_model(project_root) {
+            overrides.push(format!("  项目模型 ({}): {}", project_root.join(PROJECT_MODEL_FILE).display(), model));
+        }
+        if let Some(temperature) = self.temperature_override {
+            overrides.push(format!("  会话温度 (/temp): {:.2}", temperature));
+        }
+        if self.custom_system_prompt.is_some() {
+            overrides.push("  自定义系统提示 (/system)".to_string());
+        }
+        if let Some(budget) = self.context_token_budget {
+            overrides.push(format!("  附加文件预算 (/budget): {} tokens", budget));
+        }
+        if !overrides.is_empty() {
+            lines.push("覆盖:".to_string());
+            lines.extend(overrides);
+        }
+        lines.join("\n")
+    }
 
-            self.filename_suggestion.show(unspecified_content, detected_language);
-            return; // 不进入修改确认流程，让用户通过对话框选择
+    /// 先恢复全局默认模型，再用项目目录下的 `.starfell/model` 覆盖，避免上一个项目的偏好残留
+    pub fn apply_project_model(&mut self, project_dir: &std::path::Path) -> Option<String> {
+        let config = self.llm_config.as_mut()?;
+        let previous = config.model.clone();
+        if let Some(default_model) = &self.default_model {
+            config.model = default_model.clone();
+        }
+        let model = config.apply_project_model(project_dir);
+        if config.model != previous {
+            self.update_llm_client();
         }
+        model
+    }
 
-        // 为每个修改操作生成 Diff
-        for op in ops {
-            let diff = match &op {
-                CodeModificationOp::Create { path, content } => {
-                    // 创建操作：显示新内容
-                    Some(CodeDiff {
-                        file_path: path.clone(),
-                        old_content: String::new(),
-                        new_content: content.clone(),
-                    })
-                }
-                CodeModificationOp::Modify { path, search, replace } => {
-                    // 修改操作：尝试匹配并生成 Diff
-                    match CodeMatcher::find_and_replace(path, search, replace) {
-                        Ok(diff) => Some(diff),
-                        Err(e) => {
-                            // 匹配失败，显示错误信息
-                            self.chat_history.add_message(Message {
-                                role: Role::System,
-                                content: format!("❌ 代码匹配失败: {}", e),
-                            });
-                            None
-                        }
-                    }
-                }
-                CodeModificationOp::Delete { path } => {
-                    // 删除操作：显示文件路径
-                    Some(CodeDiff {
-                        file_path: path.clone(),
-                        old_content: format!("(删除文件: {})", path),
-                        new_content: String::new(),
-                    })
-                }
-            };
+    /// /set-model <model> [--project] - 设置模型，--project 时保存为项目默认
+    fn set_model(&mut self, args: &[String]) -> String {
+        let Some(model) = args.first() else {
+            return "用法: /set-model <model> [--project]".to_string();
+        };
+        let Some(config) = self.llm_config.as_mut() else {
+            return "❌ LLM 尚未配置".to_string();
+        };
+        config.model = model.clone();
+        self.update_llm_client();
 
-            if let Some(diff) = diff {
-                self.pending_modifications.push((op, Some(diff)));
+        if args.iter().any(|a| a == "--project") {
+            let root = self.file_search.root_path.clone();
+            match LLMConfig::save_project_model(&root, model) {
+                Ok(_) => format!("✓ 模型已设置为 {}，并保存为项目默认 ({})", model, crate::ai::config::PROJECT_MODEL_FILE),
+                Err(e) => format!("✓ 模型已设置为 {}，但保存项目偏好失败: {}", model, e),
             }
+        } else {
+            format!("✓ 模型已设置为 {} (使用 /set-model {} --project 保存为项目默认)", model, model)
         }
+    }
 
-        // 如果有待确认的修改，激活确认对话
-        if !self.pending_modifications.is_empty() {
-            self.modification_confirmation_pending = true;
-            self.modification_selected_index = 0;
-            self.modification_choice = ModificationChoice::Confirm;
+    /// 打开模型切换浮层（Ctrl+O）
+    pub fn open_model_switcher(&mut self) {
+        let Some(config) = &self.llm_config else {
+            self.chat_history.add_message(Message {
+                role: Role::System,
+                content: "❌ LLM 尚未配置".to_string(),
+            });
+            self.scroll_to_bottom();
+            return;
+        };
+        let models = crate::ui::model_switcher::known_models(&config.provider);
+        let current = config.model.clone();
+        self.model_switcher.open(models, &current);
+    }
 
-            // 确认对话现在作为独立的 UI 层显示，不添加到聊天历史
+    /// 应用模型切换浮层中选中的模型
+    pub fn apply_model_switcher_selection(&mut self) {
+        let selected = self.model_switcher.selected();
+        self.model_switcher.close();
+        let Some(model) = selected else {
+            return;
+        };
+        if let Some(config) = self.llm_config.as_mut() {
+            config.model = model.clone();
+            self.update_llm_client();
+            self.chat_history.add_message(Message {
+                role: Role::System,
+                content: format!("✓ 模型已切换为 {}", model),
+            });
+            self.scroll_to_bottom();
         }
     }
 
-    /// 生成系统提示，用于改进 AI 配对编程的回复质量
-    /// 
-    /// 使用 prompts 模块中的提示词生成器，根据对话历史长度生成适应性提示
-    fn generate_system_prompt(&self) -> String {
-        let message_count = self.chat_history.get_messages().len();
-        prompts::get_pair_programming_prompt(message_count)
+    /// /cd <dir> - 切换项目目录，并自动选择该项目偏好的模型
+    fn change_project_dir(&mut self, args: &[String]) -> String {
+        let Some(dir) = args.first() else {
+            return format!("当前项目目录: {}", self.file_search.root_path.display());
+        };
+        let path = match std::path::Path::new(dir).canonicalize() {
+            Ok(p) if p.is_dir() => p,
+            _ => return format!("❌ 目录不存在: {}", dir),
+        };
+        if let Err(e) = std::env::set_current_dir(&path) {
+            return format!("❌ 无法切换目录: {}", e);
+        }
+
+        self.file_search.set_root(path.clone());
+        self.file_search.build_cache();
+        self.file_command_handler.set_project_root(&path);
+        // Agent 的写文件工具跟随新的项目根目录
+        self.rebuild_ai_agent();
+
+        let previous_model = self.llm_config.as_ref().map(|c| c.model.clone());
+        match self.apply_project_model(&path) {
+            Some(model) => format!("📁 项目目录: {}\n✓ 已选择项目模型: {}", path.display(), model),
+            None => match self.llm_config.as_ref().map(|c| c.model.clone()) {
+                Some(model) if Some(&model) != previous_model.as_ref() => {
+                    format!("📁 项目目录: {}\n✓ 已恢复默认模型: {}", path.display(), model)
+                }
+                _ => format!("📁 项目目录: {}", path.display()),
+            },
+        }
     }
 
-    pub async fn start_streaming_chat(&mut self, prompt: &str) {
-        if self.llm_client.is_none() {
-            return;
+    /// /bash <command> [args] - 通过 CommandExecuteTool 执行命令并记录输出
+    async fn run_bash_command(&mut self, args: &[String]) -> String {
+        use crate::tools::{tool::Tool, CommandExecuteTool, ToolCall};
+
+        if self.safe_mode {
+            return safe_mode_blocked("/bash");
         }
-        self.is_streaming = true;
-        
-        // 在聊天历史中预先插入一条空的 AI 消息，用于流式更新
-        self.chat_history.add_message(Message {
-            role: Role::Assistant,
-            content: String::new(),
-        });
-        self.scroll_to_bottom();
-        
-        let handler = StreamHandler::new();
-        self.stream_handler = Some(handler.clone());
+        let Some(command) = args.first() else {
+            return "用法: /bash <command> [args]".to_string();
+        };
+        let call = ToolCall {
+            tool_name: "execute_command".to_string(),
+            arguments: [
+                ("command".to_string(), serde_json::json!(command)),
+                ("args".to_string(), serde_json::json!(&args[1..])),
+            ].into(),
+        };
+        let result = CommandExecuteTool.execute(call.clone()).await;
 
-        let client = self.llm_client.as_ref().unwrap().clone();
-        let prompt = prompt.to_string();
-        let system_prompt = self.generate_system_prompt();
+        if result.data.is_null() {
+            return format!("❌ {}", result.error.unwrap_or_default());
+        }
 
-        tokio::spawn(async move {
-            let handler_clone = handler.clone();
-            let callback = move |token: String| {
-                let _ = handler_clone.send_token(token);
-                true
-            };
+        let output = crate::tools::tool_display::full_output(&call, &result);
+        let shown = crate::tools::tool_display::render_tool_result(&call, &result, self.verbose_tool_output);
+        self.last_command_output = Some(output.clone());
+        self.last_tool_output = Some(output.clone());
+        match self.offer_build_errors(&output) {
+            Some(offer) => format!("{}\n{}", shown, offer),
+            None => shown,
+        }
+    }
 
-            // 构建消息数组，包含系统提示和用户消息
-            let messages = vec![
-                ChatMessage {
-                    role: "system".to_string(),
-                    content: system_prompt,
-                },
-                ChatMessage {
-                    role: "user".to_string(),
-                    content: prompt,
+    /// /commit-msg [conventional|gitmoji|plain] - 为暂存区改动生成提交信息；指定风格时同时设为默认。
+    /// 项目根目录存在 .starfell/commit_template 时用它替代内置模板
+    async fn generate_commit_message(&mut self, args: &[String]) -> String {
+        use crate::ai::commit_message::{generate_commit_message, load_project_template};
+
+        if let Some(value) = args.first() {
+            match CommitStyle::parse(value) {
+                Some(style) => self.commit_style = style,
+                None => return "用法: /commit-msg [conventional|gitmoji|plain]".to_string(),
+            }
+        }
+        let Some(client) = self.llm_client.clone() else {
+            return "❌ 未配置 LLM 客户端，无法生成提交信息".to_string();
+        };
+
+        let root = self.file_search.root_path.clone();
+        let output = match tokio::process::Command::new("git")
+            .args(["diff", "--staged"])
+            .current_dir(&root)
+            .output()
+            .await
+        {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => return format!("❌ git diff 失败: {}", String::from_utf8_lossy(&output.stderr).trim()),
+            Err(e) => return format!("❌ 无法运行 git: {}", e),
+        };
+        let diff = String::from_utf8_lossy(&output.stdout).to_string();
+        if diff.trim().is_empty() {
+            return "暂存区没有改动（先使用 git add）".to_string();
+        }
+
+        let template = load_project_template(&root);
+        match generate_commit_message(client.as_ref(), &diff, self.commit_style, template.as_deref()).await {
+            Ok(message) => format!("📝 提交信息（{}）:\n\n{}", self.commit_style.name(), message),
+            Err(e) => format!("❌ 生成提交信息失败: {}", e),
+        }
+    }
+
+    /// 从命令输出中解析编译错误：auto 模式直接附加到下一轮对话，否则等待 /build-errors attach
+    fn offer_build_errors(&mut self, output: &str) -> Option<String> {
+        let errors = crate::tools::parse_cargo_errors(output);
+        self.pending_build_errors.clear();
+        if errors.is_empty() {
+            return None;
+        }
+        let count = errors.len();
+        if self.auto_attach_build_errors {
+            self.attached_context.push(crate::tools::build_errors::format_errors_context(&errors));
+            Some(format!("📎 已自动附加 {} 个编译错误。要我修复这些错误吗？直接发送消息即可", count))
+        } else {
+            self.pending_build_errors = errors;
+            Some(format!("⚠ 发现 {} 个编译错误。要我修复这些错误吗？使用 /build-errors attach 附加到下一轮对话", count))
+        }
+    }
+
+    /// /build-errors [auto|ask|attach] - 设置编译错误的附加方式，或附加待确认的错误
+    fn build_errors_command(&mut self, args: &[String]) -> String {
+        match args.first().map(|a| a.as_str()) {
+            Some("auto") => self.auto_attach_build_errors = true,
+            Some("ask") => self.auto_attach_build_errors = false,
+            Some("attach") => {
+                if self.pending_build_errors.is_empty() {
+                    return "没有待附加的编译错误（先使用 /bash cargo build 构建）".to_string();
+                }
+                let errors = std::mem::take(&mut self.pending_build_errors);
+                self.attached_context.push(crate::tools::build_errors::format_errors_context(&errors));
+                return format!("📎 已附加 {} 个编译错误，将随下一条消息发送", errors.len());
+            }
+            None => {}
+            Some(_) => return "用法: /build-errors auto|ask|attach".to_string(),
+        }
+        if self.auto_attach_build_errors {
+            "✓ 构建失败后自动附加编译错误".to_string()
+        } else {
+            "✓ 构建失败后先询问，再用 /build-errors attach 附加编译错误".to_string()
+        }
+    }
+
+    /// /explain [on|off] - 切换 explain 模式
+    fn toggle_explain_mode(&mut self, args: &[String]) -> String {
+        self.explain_mode = match args.first().map(|a| a.as_str()) {
+            Some("on") => true,
+            Some("off") => false,
+            _ => !self.explain_mode,
+        };
+        // 重新创建 AI Agent 以应用新设置
+        self.update_llm_client();
+        if self.explain_mode {
+            "💡 explain 模式已开启：工具执行前会说明将要做什么".to_string()
+        } else {
+            "explain 模式已关闭".to_string()
+        }
+    }
+
+    /// /verbose [on|off|show] - 切换工具输出的显示方式，show 显示最近一次工具调用的完整输出
+    fn set_verbose_tool_output(&mut self, args: &[String]) -> String {
+        self.verbose_tool_output = match args.first().map(|a| a.as_str()) {
+            Some("show") => {
+                return self.last_tool_output.clone().unwrap_or_else(|| "还没有工具输出".to_string());
+            }
+            Some("on") => true,
+            Some("off") => false,
+            None => !self.verbose_tool_output,
+            Some(_) => return "用法: /verbose [on|off|show]".to_string(),
+        };
+        if self.verbose_tool_output {
+            "verbose 已开启：工具输出完整显示".to_string()
+        } else {
+            format!("verbose 已关闭：超过 {} 行的工具输出折叠为一行摘要", crate::tools::tool_display::SUMMARY_MIN_LINES)
+        }
+    }
+
+    /// 记录本轮聊天使用的模型和原因（/why 显示）。
+    /// 聊天请求不经过 Gemini 路由器，直接使用当前配置的模型
+    fn record_chat_routing(&mut self, chat_input: &str) {
+        use crate::core::gemini_architecture::{RoutingDecision, RoutingMetadata, RoutingReport};
+
+        let Some(model) = self.llm_config.as_ref().map(|c| c.model.clone()) else {
+            return;
+        };
+        let origin = if LLMConfig::load_project_model(&self.file_search.root_path).as_deref() == Some(model.as_str()) {
+            format!("项目模型 ({})", crate::ai::config::PROJECT_MODEL_FILE)
+        } else {
+            "当前配置的模型".to_string()
+        };
+        let mode = if self.ai_agent.is_some() && !self.stream_responses {
+            "Agent 模式，可调用工具"
+        } else {
+            "流式回复"
+        };
+        let history: Vec<String> = self.chat_history.get_messages().iter().map(|m| m.content.clone()).collect();
+        let estimated_tokens = TokenCalculator::from_model_name(&model).estimate_prompt(chat_input, &history).total_tokens;
+
+        self.gemini.record_routing(RoutingReport {
+            decision: RoutingDecision {
+                model,
+                metadata: RoutingMetadata {
+                    source: "configured".to_string(),
+                    latency_ms: 0,
+                    reasoning: format!("未启用路由，使用{}（{}）", origin, mode),
                 },
-            ];
+            },
+            estimated_tokens,
+            attempts: 1,
+            latency_ms: 0,
+        });
+        self.routing_started_at = Some(Instant::now());
+    }
+
+    /// /why - 说明上一轮为什么选择了该模型
+    fn explain_last_routing(&self) -> String {
+        match self.gemini.last_routing() {
+            Some(report) => report.describe(),
+            None => {
+                let model = self
+                    .llm_config
+                    .as_ref()
+                    .map(|c| c.model.clone())
+                    .unwrap_or_else(|| "未配置".to_string());
+                format!("上一轮对话没有经过模型路由，直接使用当前配置的模型: {}", model)
+            }
+        }
+    }
+
+    /// /benchmark [--models a,b] <prompt> - 把同一提示依次发给多个模型并对比结果
+    /// 模型列表默认来自环境变量 BENCHMARK_MODELS（逗号分隔），均使用当前提供商配置
+    async fn run_model_benchmark(&mut self, args: &[String]) -> String {
+        use crate::ai::benchmark::DEFAULT_BENCHMARK_CONCURRENCY;
+
+        let usage = "用法: /benchmark [--models a,b] [--concurrency N] <prompt>\n未指定 --models 时使用环境变量 BENCHMARK_MODELS，\
+            未指定 --concurrency 时使用 BENCHMARK_CONCURRENCY（默认 4）";
+        let mut models = std::env::var("BENCHMARK_MODELS").unwrap_or_default();
+        let mut concurrency = std::env::var("BENCHMARK_CONCURRENCY")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_BENCHMARK_CONCURRENCY);
+        let mut prompt_args = args;
+        loop {
+            match prompt_args {
+                [flag, list, rest @ ..] if flag == "--models" => {
+                    models = list.clone();
+                    prompt_args = rest;
+                }
+                [flag, value, rest @ ..] if flag == "--concurrency" => {
+                    match value.parse::<usize>() {
+                        Ok(n) if n > 0 => concurrency = n,
+                        _ => return format!("❌ 无效的并发数: {}\n{}", value, usage),
+                    }
+                    prompt_args = rest;
+                }
+                _ => break,
+            }
+        }
+        let models: Vec<String> = models.split(',').map(str::trim).filter(|m| !m.is_empty()).map(String::from).collect();
+        let prompt = prompt_args.join(" ");
+
+        if prompt.is_empty() {
+            return usage.to_string();
+        }
+        if models.is_empty() {
+            return "❌ 未配置对比模型：设置 BENCHMARK_MODELS=model-a,model-b 或使用 --models".to_string();
+        }
+        let Some(config) = self.llm_config.clone() else {
+            return "❌ 未配置 LLM 客户端，无法运行对比".to_string();
+        };
+
+        let clients: Vec<(String, Arc<dyn ChatCompletion>)> = models
+            .into_iter()
+            .map(|model| {
+                let config = LLMConfig { model: model.clone(), ..config.clone() };
+                (model, Arc::new(LLMClient::new(config)) as Arc<dyn ChatCompletion>)
+            })
+            .collect();
+
+        let results = crate::ai::benchmark::run_benchmark(&prompt, &clients, concurrency).await;
+        crate::ai::benchmark::format_comparison(&prompt, &results)
+    }
 
-            match client.generate_completion_stream(messages, None, callback).await {
-                Ok(_) => {
-                    let _ = handler.send_done();
+    /// /yolo [on|off] - 切换 YOLO 模式
+    fn toggle_yolo_mode(&mut self, args: &[String]) -> String {
+        self.yolo_mode = match args.first().map(|a| a.as_str()) {
+            Some("on") => true,
+            Some("off") => false,
+            _ => !self.yolo_mode,
+        };
+        // 文件命令（/modify、/delete 等）共用同一开关
+        if self.yolo_mode {
+            self.file_command_handler.enable_yolo_mode();
+        } else {
+            self.file_command_handler.disable_yolo_mode();
+        }
+        if self.yolo_mode {
+            "⚡ YOLO 模式已开启：能唯一定位的编辑将直接应用，创建/删除文件和无法确定位置的修改仍需确认".to_string()
+        } else {
+            "YOLO 模式已关闭：所有修改都需要确认".to_string()
+        }
+    }
+
+    /// /snippet <name> [file] - 把片段插入输入框（光标放在 {cursor} 处），或用模板创建文件
+    fn insert_snippet(&mut self, args: &[String]) -> String {
+        use crate::commands::snippets::SnippetLibrary;
+
+        let library = SnippetLibrary::from_env();
+        let Some(name) = args.first() else {
+            return format!("用法: /snippet <name> [file]\n可用片段: {}", library.names().join(", "));
+        };
+        let Some(snippet) = library.load(name) else {
+            return format!("❌ 未找到片段: {}\n可用片段: {}", name, library.names().join(", "));
+        };
+
+        match args.get(1) {
+            Some(path) => {
+                if self.safe_mode {
+                    return safe_mode_blocked("/snippet <name> <file>");
+                }
+                if std::path::Path::new(path).exists() {
+                    return format!("❌ 文件已存在: {}（不会覆盖）", path);
                 }
-                Err(e) => {
-                    let _ = handler.send_error(e.to_string());
+                let command = crate::commands::FileCommand::CreateFile {
+                    path: path.clone(),
+                    content: Some(snippet.body),
+                };
+                self.file_command_handler.execute(command).message
+            }
+            None => {
+                // 插入到当前光标处
+                let mut chars: Vec<char> = self.input_text.chars().collect();
+                let at = self.input_cursor.min(chars.len());
+                chars.splice(at..at, snippet.body.chars());
+                self.input_text = chars.into_iter().collect();
+                self.input_cursor = at + snippet.cursor;
+                format!("✓ 已插入片段 {}", snippet.name)
+            }
+        }
+    }
+
+    /// /safe [on|off] - 切换安全模式
+    fn toggle_safe_mode(&mut self, args: &[String]) -> String {
+        self.safe_mode = match args.first().map(|a| a.as_str()) {
+            Some("on") => true,
+            Some("off") => false,
+            _ => !self.safe_mode,
+        };
+        // 重新创建 AI Agent 以应用新设置
+        self.update_llm_client();
+        if self.safe_mode {
+            "🔒 安全模式已开启：写文件、执行命令、构建等工具已禁用，只保留读取/搜索/分析工具".to_string()
+        } else {
+            "安全模式已关闭".to_string()
+        }
+    }
+
+    /// /system [set <text> | clear] - 查看或编辑会话级系统提示
+    fn system_prompt_command(&mut self, args: &[String]) -> String {
+        match args.first().map(|a| a.as_str()) {
+            None => match &self.custom_system_prompt {
+                Some(prompt) => format!("📝 当前系统提示:\n{}", prompt),
+                None => "当前未设置系统提示（使用 /system set <text> 设置）".to_string(),
+            },
+            Some("set") if args.len() > 1 => {
+                let prompt = args[1..].join(" ");
+                self.custom_system_prompt = Some(prompt.clone());
+                format!("✓ 系统提示已设置，将用于本次会话的每个请求:\n{}", prompt)
+            }
+            Some("clear") => {
+                self.custom_system_prompt = None;
+                "✓ 系统提示已清除".to_string()
+            }
+            _ => "用法: /system | /system set <text> | /system clear".to_string(),
+        }
+    }
+
+    /// /timestamps [on|off|relative] - 设置消息时间戳的显示方式
+    fn set_timestamp_mode(&mut self, args: &[String]) -> String {
+        use crate::ui::timestamps::TimestampMode;
+        let Some(mode) = args.first().and_then(|a| TimestampMode::parse(a)) else {
+            return "用法: /timestamps on|off|relative".to_string();
+        };
+        self.timestamp_mode = mode;
+        match mode {
+            TimestampMode::Off => "时间戳已隐藏".to_string(),
+            TimestampMode::Absolute => "✓ 显示绝对时间戳 [HH:MM:SS]".to_string(),
+            TimestampMode::Relative => "✓ 显示相对时间戳（如 2m ago）".to_string(),
+        }
+    }
+
+    /// /density [compact|comfortable] - 设置聊天显示密度，不带参数时切换
+    fn set_chat_density(&mut self, args: &[String]) -> String {
+        use crate::ui::density::ChatDensity;
+        self.chat_density = match args.first() {
+            Some(value) => match ChatDensity::parse(value) {
+                Some(density) => density,
+                None => return "用法: /density compact|comfortable".to_string(),
+            },
+            None if self.chat_density.is_compact() => ChatDensity::Comfortable,
+            None => ChatDensity::Compact,
+        };
+        match self.chat_density {
+            ChatDensity::Compact => "✓ 紧凑显示：小头像、无消息间空行、隐藏时间戳".to_string(),
+            ChatDensity::Comfortable => "✓ 舒适显示：完整头像和消息头".to_string(),
+        }
+    }
+
+    /// /stream on|off - 切换流式 / 非流式响应
+    fn set_stream_mode(&mut self, args: &[String]) -> String {
+        match args.first().map(|a| a.as_str()) {
+            Some("on") => self.stream_responses = true,
+            Some("off") => self.stream_responses = false,
+            None => {}
+            Some(_) => return "用法: /stream on|off".to_string(),
+        }
+        if self.stream_responses {
+            "✓ 流式响应已开启".to_string()
+        } else {
+            "✓ 流式响应已关闭：回复将在生成完成后一次性显示".to_string()
+        }
+    }
+
+    /// /linenumbers on|off - @ 附加的文件是否带行号；开启时模型提出的修改会去掉行号前缀
+    fn set_line_numbered_context(&mut self, args: &[String]) -> String {
+        match args.first().map(|a| a.as_str()) {
+            Some("on") => self.line_numbered_context = true,
+            Some("off") => self.line_numbered_context = false,
+            None => self.line_numbered_context = !self.line_numbered_context,
+            Some(_) => return "用法: /linenumbers on|off".to_string(),
+        }
+        if self.line_numbered_context {
+            "✓ @ 附加的文件将带行号（N: ）发送".to_string()
+        } else {
+            "✓ @ 附加的文件不再带行号".to_string()
+        }
+    }
+
+    /// /mentions fuzzy|semantic [N] - 切换 @ 提及的文件排序方式。
+    /// N 为参与语义索引的文件数上限，默认取 MENTION_INDEX_MAX_FILES
+    fn set_mention_ranking(&mut self, args: &[String]) -> String {
+        use crate::ui::semantic_search::DEFAULT_MAX_INDEXED_FILES;
+
+        match args.first().map(|a| a.as_str()) {
+            Some("fuzzy") => {
+                self.semantic_ranker = None;
+                "✓ @ 提及使用文件名模糊匹配".to_string()
+            }
+            Some("semantic") => {
+                let Some(client) = self.llm_client.clone() else {
+                    return "❌ 未配置 LLM 客户端，无法使用语义排序".to_string();
+                };
+                let max_files = match args.get(1) {
+                    Some(arg) => match arg.parse::<usize>() {
+                        Ok(n) if n > 0 => n,
+                        _ => return format!("❌ 文件数上限必须是正整数: {}", arg),
+                    },
+                    None => std::env::var("MENTION_INDEX_MAX_FILES")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .filter(|n| *n > 0)
+                        .unwrap_or(DEFAULT_MAX_INDEXED_FILES),
+                };
+                let ranker = crate::ui::semantic_search::SemanticFileRanker::new(client).with_max_files(max_files);
+
+                // 预先建立文件向量索引
+                self.file_search.build_cache();
+                let files = self.file_search.cache.clone();
+                let file_count = files.iter().filter(|path| path.is_file()).count();
+                let warmup = ranker.clone();
+                self.background_tasks.spawn(async move {
+                    let _ = warmup.index(&files).await;
+                });
+
+                self.semantic_ranker = Some(ranker);
+                let mut message = format!(
+                    "✓ @ 提及使用语义排序，最多索引 {} 个文件（向量不可用时自动退回模糊匹配）",
+                    max_files
+                );
+                if file_count > max_files {
+                    message.push_str(&format!(
+                        "\n⚠ 项目共有 {} 个文件，超出上限的 {} 个不参与语义排序（/mentions semantic N 可调整）",
+                        file_count,
+                        file_count - max_files
+                    ));
                 }
+                message
+            }
+            _ => {
+                let mode = match &self.semantic_ranker {
+                    Some(ranker) => format!("semantic（最多索引 {} 个文件）", ranker.max_files()),
+                    None => "fuzzy".to_string(),
+                };
+                format!("当前 @ 提及排序: {}\n用法: /mentions fuzzy|semantic [N]", mode)
+            }
+        }
+    }
+
+    /// @ 查询变化时调用：记录变化时间，停止输入 SEMANTIC_RANKING_DEBOUNCE 后才排序
+    pub fn request_semantic_ranking(&mut self) {
+        if self.semantic_ranker.is_some() {
+            self.semantic_query_changed_at = Some(Instant::now());
+        }
+    }
+
+    /// 防抖结束后在后台按语义对当前 @ 查询排序；结果由 apply_semantic_ranking 应用
+    pub fn poll_semantic_ranking(&mut self, now: Instant) {
+        let Some(changed_at) = self.semantic_query_changed_at else {
+            return;
+        };
+        if now.duration_since(changed_at) < SEMANTIC_RANKING_DEBOUNCE {
+            return;
+        }
+        self.semantic_query_changed_at = None;
+        let Some(ranker) = self.semantic_ranker.clone() else {
+            return;
+        };
+        if ranker.is_unavailable() {
+            return;
+        }
+        let query = self.file_search.query.clone();
+        let Some(term) = query.rsplit('@').next().map(str::trim).filter(|t| !t.is_empty()) else {
+            return;
+        };
+
+        let term = term.to_string();
+        let files = self.file_search.cache.clone();
+        let slot = self.semantic_results.clone();
+        tokio::spawn(async move {
+            if let Ok(ranked) = ranker.rank(&term, &files).await {
+                let suggestions = ranked.iter().take(20).map(|p| format!("@{}", p.display())).collect();
+                *slot.lock().unwrap() = Some((query, suggestions));
             }
         });
     }
 
-    pub fn render(&mut self, f: &mut Frame) {
-        // 使用像素艺术风格布局 (v2 - 4x4 头像)
-        self.frame_count = self.frame_count.wrapping_add(1);
-        ui::pixel_layout_v2::render_pixel_layout(f, self);
+    /// 应用后台语义排序的结果（查询已变化或建议已关闭时丢弃）
+    pub fn apply_semantic_ranking(&mut self) {
+        let Some((query, suggestions)) = self.semantic_results.lock().unwrap().take() else {
+            return;
+        };
+        if query != self.file_search.query || !self.mention_suggestions.visible {
+            return;
+        }
+        self.file_search.results = suggestions;
+        self.file_search.selected_index = 0;
+        self.mention_suggestions.suggestions = self.file_search.results.clone();
+        self.mention_suggestions.selected_index = 0;
     }
 
-    pub async fn finalize_streaming_response(&mut self) {
-        let ai_response_opt = {
-            let mut response = self.streaming_response.lock().unwrap();
-            if !response.content.is_empty() {
-                let content = response.content.clone();
-                response.reset();
-                Some(content)
+    /// /attach-last-output - 将最近的命令输出（截断到预算内）附加到下一轮对话
+    fn attach_last_output(&mut self) -> String {
+        let Some(output) = self.last_command_output.clone() else {
+            return "没有可附加的命令输出（先使用 /bash 执行命令）".to_string();
+        };
+        let truncated = truncate_to_tail(&output, ATTACHED_OUTPUT_BUDGET);
+        let message = if truncated.len() < output.len() {
+            format!("📎 已附加上一条命令输出（截断为最后 {} 字符），将随下一条消息发送", ATTACHED_OUTPUT_BUDGET)
+        } else {
+            "📎 已附加上一条命令输出，将随下一条消息发送".to_string()
+        };
+        self.attached_context.push(truncated);
+        message
+    }
+
+    /// /export-context <path> - 把附加的文件、系统提示和最后一条输入导出为 Markdown（密钥已掩码）
+    fn export_context(&self, args: &[String]) -> String {
+        use crate::ai::context_bundle::ContextBundle;
+
+        let Some(path) = args.first() else {
+            return "用法: /export-context <path>".to_string();
+        };
+        let last_input = self
+            .chat_history
+            .get_messages()
+            .iter()
+            .rev()
+            .find(|msg| msg.role == Role::User && !msg.content.starts_with('/'))
+            .map(|msg| msg.content.clone());
+        let bundle = ContextBundle::collect(self.custom_system_prompt.clone(), self.attached_context.clone(), last_input);
+
+        match std::fs::write(path, bundle.to_markdown()) {
+            Ok(_) => format!("📦 已导出上下文到 {}（{} 个文件，密钥已掩码）", path, bundle.files.len()),
+            Err(e) => format!("❌ 导出上下文失败: {}", e),
+        }
+    }
+
+    /// 构建发送给 LLM 的用户输入：附加的上下文（只使用一次）+ 用户输入
+    fn build_chat_input(&mut self, input: &str) -> String {
+        if self.attached_context.is_empty() {
+            return input.to_string();
+        }
+        let context: String = self
+            .attached_context
+            .drain(..)
+            .map(|c| format!("<command_output>\n{}\n</command_output>\n\n", c))
+            .collect();
+        format!("{}{}", context, input)
+    }
+
+    /// 收集聊天历史中所有的代码块：(消息索引, 代码块)
+    pub fn collect_code_blocks(&self) -> Vec<(usize, CodeBlock)> {
+        self.chat_history
+            .get_messages()
+            .iter()
+            .enumerate()
+            .flat_map(|(msg_idx, msg)| {
+                parse_code_blocks(&msg.content)
+                    .into_iter()
+                    .map(move |block| (msg_idx, block))
+            })
+            .collect()
+    }
+
+    /// /blocks - 列出所有代码块（序号、语言、来源消息、首行）
+    fn format_code_block_list(&self) -> String {
+        let blocks = self.collect_code_blocks();
+        if blocks.is_empty() {
+            return "对话中没有代码块".to_string();
+        }
+
+        let messages = self.chat_history.get_messages();
+        let mut output = format!("📦 共 {} 个代码块 (使用 /block N 跳转，/block N copy 复制):\n", blocks.len());
+        for (i, (msg_idx, block)) in blocks.iter().enumerate() {
+            let role = match messages[*msg_idx].role {
+                Role::User => "USER",
+                Role::Assistant => "AI",
+                Role::System => "SYSTEM",
+            };
+            let first_line = block.content.lines().next().unwrap_or("");
+            output.push_str(&format!(
+                "  #{} [{}] 消息 #{} ({}): {}\n",
+                i + 1,
+                block.language,
+                msg_idx + 1,
+                role,
+                first_line
+            ));
+        }
+        output
+    }
+
+    /// /block <n> [copy] - 高亮第 n 个代码块，或复制到剪贴板
+    fn jump_to_code_block(&mut self, args: &[String]) -> String {
+        let blocks = self.collect_code_blocks();
+        let index = match args.first().and_then(|a| a.parse::<usize>().ok()) {
+            Some(n) if n >= 1 && n <= blocks.len() => n - 1,
+            _ => return format!("用法: /block <1-{}> [copy]", blocks.len()),
+        };
+        let (msg_idx, block) = &blocks[index];
+
+        if args.get(1).map(|a| a == "copy").unwrap_or(false) {
+            return match arboard::Clipboard::new().and_then(|mut c| c.set_text(block.content.clone())) {
+                Ok(_) => format!("✓ 已复制代码块 #{} 到剪贴板", index + 1),
+                Err(e) => format!("❌ 复制失败: {}", e),
+            };
+        }
+
+        // 从开始围栏到结束围栏（含）
+        self.highlighted_block = Some((*msg_idx, block.start_line..block.end_line + 1));
+        format!("→ 代码块 #{} [{}] (消息 #{})", index + 1, block.language, msg_idx + 1)
+    }
+
+    /// 调整滚动偏移，使高亮代码块位于可见区域底部（与历史区域的行布局一致）
+    fn scroll_to_highlighted_block(&mut self) {
+        self.chat_scroll_offset = match self.highlighted_block.clone() {
+            Some((msg_idx, range)) => self.lines_after(msg_idx, range.end),
+            None => 0,
+        };
+    }
+
+    /// 历史区域中位于第 msg_idx 条消息第 line_end 行之后的渲染行数
+    /// （与历史区域的行布局一致：头像行 + 内容行 + 消息间空行，最后一条除外）
+    fn lines_after(&self, msg_idx: usize, line_end: usize) -> usize {
+        let messages = self.chat_history.get_messages();
+        let mut lines_after = 0;
+        for (i, msg) in messages.iter().enumerate().skip(msg_idx) {
+            let content_lines = msg.content.lines().count();
+            if i == msg_idx {
+                lines_after += content_lines.saturating_sub(line_end);
             } else {
-                response.reset();
-                None
+                lines_after += 1 + content_lines;
+            }
+            if i + 1 < messages.len() {
+                lines_after += 1;
             }
-        };
-        
-        // 在释放 response 借用后，处理 AI 响应中的代码修改指令
-        if let Some(ai_response) = ai_response_opt {
-            // 此时聊天历史中的最后一条 AI 消息已经在流式过程中被更新完毕
-            // 这里不再重复添加，只用于解析代码修改指令
-            
-            // 自动滚动到底部
-            // self.scroll_to_bottom();
-            
-            // 检测修改指令并立即显示确认对话
-            // 不等待用户继续输入
-            self.process_ai_response_for_modifications(&ai_response);
         }
-        
-        self.is_streaming = false;
-        self.stream_handler = None;
+        lines_after
     }
-    
-    /// 滚动到聊天历史底部
-    pub fn scroll_to_bottom(&mut self) {
-        self.chat_scroll_offset = 0;
+
+    /// 在聊天历史中搜索（大小写不敏感，可选正则），返回匹配消息的索引
+    /// 用户输入的命令（以 / 开头）不参与搜索
+    pub fn search_history(&self, query: &str, use_regex: bool) -> Result<Vec<usize>, regex::Error> {
+        let pattern = if use_regex { query.to_string() } else { regex::escape(query) };
+        let re = regex::RegexBuilder::new(&pattern).case_insensitive(true).build()?;
+
+        Ok(self
+            .chat_history
+            .get_messages()
+            .iter()
+            .enumerate()
+            .filter(|(_, msg)| !(msg.role == Role::User && msg.content.starts_with('/')))
+            .filter(|(_, msg)| re.is_match(&msg.content))
+            .map(|(i, _)| i)
+            .collect())
     }
-}
\ No newline at end of file
+
+    /// /search [-r] <query> - 列出匹配的消息（索引 + 片段）
+    fn format_history_search(&self, args: &[String]) -> String {
+        let use_regex = args.first().map(|a| a == "-r" || a == "--regex").unwrap_or(false);
+        let query = args[usize::from(use_regex)..].join(" ");
+        if query.is_empty() {
+            return "用法: /search [-r] <query>".to_string();
+        }
+
+        let matches = match self.search_history(&query, use_regex) {
+            Ok(m) => m,
+            Err(e) => return format!("❌ 无效的正则表达式: {}", e),
+        };
+        if matches.is_empty() {
+            return format!("🔍 没有找到匹配 \"{}\" 的消息", query);
+        }
+
+        let messages = self.chat_history.get_messages();
+        let query_lower = query.to_lowercase();
+        let mut output = format!("🔍 {} 条消息匹配 \"{}\" (使用 /goto N 跳转):\n", matches.len(), query);
+        for idx in matches {
+            let content = &messages[idx].content;
+            let line = content
+                .lines()
+                .find(|l| use_regex || l.to_lowercase().contains(&query_lower))
+                .unwrap_or("")
+                .trim();
+            let snippet: String = line.chars().take(60).collect();
+            output.push_str(&format!("  #{}: {}\n", idx + 1, snippet));
+        }
+        output
+    }
+
+    /// /goto <index> - 滚动到指定消息（索引从 1 开始）
+    /// /clear [--force] - 清除聊天历史，置顶消息只有加 --force 才会清除
+    fn clear_history(&mut self, args: &[String]) -> String {
+        if args.iter().any(|a| a == "--force" || a == "-f") {
+            self.chat_history.clear_all();
+            return "✓ Chat history cleared".to_string();
+        }
+        self.chat_history.clear();
+        match self.chat_history.pinned_count() {
+            0 => "✓ Chat history cleared".to_string(),
+            n => format!("✓ Chat history cleared，保留 {} 条置顶消息（/clear --force 全部清除）", n),
+        }
+    }
+
+    /// /pin <index> | /unpin <index> - 置顶的消息不会被 /clear 和历史容量上限淘汰
+    fn set_message_pinned(&mut self, args: &[String], pinned: bool) -> String {
+        let total = self.chat_history.get_messages().len();
+        let command = if pinned { "pin" } else { "unpin" };
+        let Some(n) = args.first().and_then(|a| a.parse::<usize>().ok()).filter(|n| (1..=total).contains(n)) else {
+            return format!("用法: /{} <1-{}>", command, total);
+        };
+        self.chat_history.set_pinned(n - 1, pinned);
+        if pinned {
+            format!("📌 已置顶消息 #{}", n)
+        } else {
+            format!("已取消置顶消息 #{}", n)
+        }
+    }
+
+    fn goto_message(&mut self, args: &[String]) {
+        let total = self.chat_history.get_messages().len();
+        let target = args
+            .first()
+            .and_then(|a| a.parse::<usize>().ok())
+            .filter(|n| (1..=total).contains(n));
+
+        self.chat_history.add_message(Message {
+            role: Role::System,
+            content: match target {
+                Some(n) => format!("→ 消息 #{}", n),
+                None => format!("用法: /goto <1-{}>", total),
+            },
+        });
+        // 在添加回复消息之后计算滚动位置
+        self.chat_scroll_offset = target.map(|n| self.lines_after(n - 1, usize::MAX)).unwrap_or(0);
+    }
+
+    /// 处理 AI 响应中的代码修改指令
+    pub fn process_ai_response_for_modifications(&mut self, response: &str) {
+        // 首先检测明确的修改指令
+        let mut ops = AICodeModificationDetector::detect_modifications(response);
+
+        // 如果没有明确指令，检测隐含的修改意图
+        if ops.is_empty() {
+            ops = AICodeModificationDetector::detect_implicit_modifications(response);
+        }
+
+        if ops.is_empty() {
+            return;
+        }
+
+        // 上下文带行号时，模型可能把行号照抄进修改内容
+        if self.line_numbered_context {
+            ops = ops.into_iter().map(CodeModificationOp::without_line_numbers).collect();
+        }
+
+        // 检查是否有未指定文件名的操作（需要用户确认）
+        let mut needs_filename_confirmation = false;
+        for op in &ops {
+            if let CodeModificationOp::Modify { path, search: _, replace: _ } = op {
+                if path.starts_with("UNSPECIFIED_") {
+                    needs_filename_confirmation = true;
+                    break;
+                }
+            }
+        }
+
+        if needs_filename_confirmation {
+            // ✅ 显示文件名建议对话框，让用户选择
+            // 提取代码内容和语言
+            let mut unspecified_content = String::new();
+            let mut detected_language = String::new();
+
+            for op in &ops {
+                if let CodeModificationOp::Modify { path, search: _, replace } = op {
+                    if path.starts_with("UNSPECIFIED_") {
+                        unspecified_content = replace.clone();
+                        // 从路径中提取语言（如 UNSPECIFIED_main.rs -> rs）
+                        if let Some(ext_start) = path.rfind('.') {
+                            detected_language = path[ext_start + 1..].to_string();
+                        }
+                        break;
+                    }
+                }
+            }
+
+            self.filename_suggestion.show(unspecified_content, detected_language);
+            return; // 不进入修改确认流程，让用户通过对话框选择
+        }
+
+        // 为每个修改操作生成 Diff
+        for op in ops {
+            let diff = match &op {
+                CodeModificationOp::Create { path, content } => {
+                    // 创建操作：显示新内容
+                    Some(CodeDiff::new(path.clone(), String::new(), content.clone()))
+                }
+                CodeModificationOp::Modify { path, search, replace } => {
+                    // 修改操作：尝试匹配并生成 Diff
+                    match CodeMatcher::find_and_replace(path, search, replace) {
+                        Ok(diff) => Some(diff),
+                        Err(e) => {
+                            // 匹配失败，显示错误信息
+                            self.chat_history.add_message(Message {
+                                role: Role::System,
+                                content: format!("❌ 代码匹配失败: {}", e),
+                            });
+                            None
+                        }
+                    }
+                }
+                CodeModificationOp::Delete { path } => {
+                    // 删除操作：显示文件路径
+                    Some(CodeDiff::new(path.clone(), format!("(删除文件: {})", path), String::new()))
+                }
+            };
+
+            if let Some(diff) = diff {
+                self.pending_modifications.push((op, Some(diff)));
+            }
+        }
+
+        // YOLO 模式下，只有全部是编辑且每个搜索块都在文件中唯一匹配时才自动应用
+        if self.yolo_mode && !self.pending_modifications.is_empty() {
+            match self.first_unsafe_modification() {
+                None => {
+                    self.apply_pending_modifications();
+                    return;
+                }
+                Some(reason) => {
+                    self.chat_history.add_message(Message {
+                        role: Role::System,
+                        content: format!("⚠ YOLO 模式未自动应用修改：{}，请确认后再应用", reason),
+                    });
+                }
+            }
+        }
+
+        // 如果有待确认的修改，激活确认对话
+        if !self.pending_modifications.is_empty() {
+            self.modification_confirmation_pending = true;
+            self.modification_selected_index = 0;
+            self.modification_choice = ModificationChoice::Confirm;
+
+            // 确认对话现在作为独立的 UI 层显示，不添加到聊天历史
+        }
+    }
+
+    /// /undo - 撤销最近一次应用的文件修改
+    fn undo_last_modification(&mut self) -> String {
+        match self.modification_history.undo_last() {
+            Some(Ok(message)) if self.modification_history.is_empty() => format!("{}（没有更多可撤销的修改）", message),
+            Some(Ok(message)) => format!("{}（剩余 {} 次可撤销）", message, self.modification_history.len()),
+            Some(Err(e)) => format!("❌ {}", e),
+            None => "没有可撤销的修改".to_string(),
+        }
+    }
+
+    /// 返回第一个不能安全自动应用的修改的原因。YOLO 只自动应用编辑，
+    /// 创建和删除文件、搜索块未精确匹配或匹配多处的修改都需要确认
+    fn first_unsafe_modification(&self) -> Option<String> {
+        self.pending_modifications.iter().find_map(|(op, _)| {
+            let (path, search) = match op {
+                CodeModificationOp::Modify { path, search, .. } => (path, search),
+                CodeModificationOp::Create { path, .. } => {
+                    return Some(format!("会创建新文件 {}", path));
+                }
+                CodeModificationOp::Delete { path } => {
+                    return Some(format!("会删除文件 {}", path));
+                }
+            };
+            match CodeMatcher::count_exact_matches(path, search) {
+                Ok(1) => None,
+                Ok(0) => Some(format!("{} 中没有与搜索块完全一致的代码（仅模糊匹配）", path)),
+                Ok(n) => Some(format!("搜索块在 {} 中匹配了 {} 处，无法确定要修改哪一处", path, n)),
+                Err(e) => Some(e),
+            }
+        })
+    }
+
+    /// 应用一个已确认的修改并记录到撤销栈，返回给用户的结果说明。
+    /// 目标路径须在项目根目录内（--allow-outside 除外）；
+    /// Modify 在当前文件内容中查找 search 并替换为 replace，找不到时不写入文件
+    pub fn apply_modification(&mut self, op: &CodeModificationOp) -> Result<String, String> {
+        let path = match op {
+            CodeModificationOp::Create { path, .. }
+            | CodeModificationOp::Modify { path, .. }
+            | CodeModificationOp::Delete { path } => path,
+        };
+        self.file_command_handler
+            .path_guard()
+            .check(path)
+            .map_err(|e| format!("❌ 操作被拒绝: {}", e))?;
+        let before = ModificationHistory::snapshot(path);
+        let result = match op {
+            CodeModificationOp::Create { path, content } => std::fs::write(path, content)
+                .map(|_| format!("✅ 文件已创建: {}", path))
+                .map_err(|e| format!("❌ 创建文件失败: {}", e)),
+            CodeModificationOp::Modify { path, search, replace } => {
+                let diff = CodeMatcher::find_and_replace(path, search, replace)
+                    .map_err(|e| format!("❌ 代码匹配失败: {}", e))?;
+                std::fs::write(path, diff.new_content)
+                    .map(|_| format!("✅ 文件已修改: {}", path))
+                    .map_err(|e| format!("❌ 修改文件失败: {}", e))
+            }
+            CodeModificationOp::Delete { path } => std::fs::remove_file(path)
+                .map(|_| format!("✅ 文件已删除: {}", path))
+                .map_err(|e| format!("❌ 删除文件失败: {}", e)),
+        };
+        if result.is_ok() {
+            self.modification_history.record(op.clone(), before);
+        }
+        result
+    }
+
+    /// 应用所有待确认的修改（确认对话中选择确认，或 YOLO 模式自动应用），
+    /// 每个结果作为系统消息显示，然后关闭确认对话
+    pub fn apply_pending_modifications(&mut self) -> Vec<Result<String, String>> {
+        let results: Vec<Result<String, String>> = std::mem::take(&mut self.pending_modifications)
+            .into_iter()
+            .map(|(op, _diff)| self.apply_modification(&op))
+            .collect();
+        for result in &results {
+            self.chat_history.add_message(Message {
+                role: Role::System,
+                content: result.clone().unwrap_or_else(|e| e),
+            });
+        }
+        self.modification_confirmation_pending = false;
+        self.scroll_to_bottom();
+        results
+    }
+
+    /// 生成系统提示，用于改进 AI 配对编程的回复质量
+    /// 
+    /// 使用 prompts 模块中的提示词生成器，根据对话历史长度生成适应性提示
+    fn generate_system_prompt(&self) -> String {
+        let message_count = self.chat_history.get_messages().len();
+        prompts::get_pair_programming_prompt(message_count)
+    }
+
+    /// 构建请求消息列表：会话系统提示（/system）始终在最前，其后是默认系统提示和用户消息
+    fn build_request_messages(&self, default_system_prompt: Option<String>, user_content: String) -> Vec<ChatMessage> {
+        let mut messages = Vec::new();
+        for content in self.custom_system_prompt.clone().into_iter().chain(default_system_prompt) {
+            messages.push(ChatMessage {
+                role: "system".to_string(),
+                content,
+            });
+        }
+        messages.push(ChatMessage {
+            role: "user".to_string(),
+            content: user_content,
+        });
+        messages
+    }
+
+    pub async fn start_streaming_chat(&mut self, prompt: &str) {
+        if self.llm_client.is_none() {
+            return;
+        }
+        self.is_streaming = true;
+        self.streaming_started_at = Some(Instant::now());
+        self.scan_progress = None;
+
+        // 在聊天历史中预先插入一条空的 AI 消息，用于流式更新
+        self.chat_history.add_message(Message {
+            role: Role::Assistant,
+            content: String::new(),
+        });
+        self.scroll_to_bottom();
+
+        let handler = StreamHandler::new();
+        self.stream_handler = Some(handler.clone());
+
+        let client = self.llm_client.as_ref().unwrap().clone();
+        // 构建消息数组，包含系统提示和用户消息
+        let messages = self.build_request_messages(Some(self.generate_system_prompt()), prompt.to_string());
+        let task = spawn_chat_request(client, messages, self.stream_responses, handler, self.background_tasks.token(), self.new_request_interrupt());
+        self.background_tasks.track(task);
+    }
+
+    /// 发出聊天请求（提交和 /retry 共用），通过 StreamHandler 接收回复（流式或一次性，由 /stream 控制）。
+    /// continue_last 为 true 且最后一条是 AI 消息时，新的内容直接接在它后面（从中断处继续）
+    fn send_chat_request(&mut self, chat_input: String, continue_last: bool) {
+        let Some(client) = self.llm_client.clone() else {
+            return;
+        };
+        let handler = StreamHandler::new();
+        self.stream_handler = Some(handler.clone());
+        self.is_streaming = true;
+        self.streaming_started_at = Some(Instant::now());
+        self.scan_progress = None;
+
+        let last_is_assistant = self.chat_history.get_messages().back().is_some_and(|msg| msg.role == Role::Assistant);
+        if !(continue_last && last_is_assistant) {
+            // 在聊天历史中预先插入一条空的 AI 消息，用于流式更新
+            self.chat_history.add_message(Message {
+                role: Role::Assistant,
+                content: String::new(),
+            });
+        }
+        self.scroll_to_bottom();
+
+        let messages = self.build_request_messages(None, chat_input);
+        let cancel = self.background_tasks.token();
+        let interrupt = self.new_request_interrupt();
+        // 非流式模式下由 Agent 处理，模型可以调用工具，执行过程实时显示
+        let task = match self.ai_agent.clone().filter(|_| !self.stream_responses) {
+            Some(agent) => spawn_agent_request(with_request_events(agent, &handler), messages, handler, cancel, interrupt),
+            None => spawn_chat_request(client, messages, self.stream_responses, handler, cancel, interrupt),
+        };
+        self.background_tasks.track(task);
+    }
+
+    /// /retry：重新发送最近一条用户消息，先移除它之后失败的回复。
+    /// 上一次回复在中途出错时保留了部分内容，则带上部分内容从中断处继续
+    pub async fn retry_last(&mut self) -> Result<(), String> {
+        if self.is_streaming {
+            return Err("正在生成回复，先停止后再 /retry".to_string());
+        }
+        let index = self.last_retryable_message().ok_or_else(|| "还没有可以重试的消息，先发送一条吧".to_string())?;
+        if let Some(guidance) = self.missing_api_key_guidance() {
+            return Err(guidance);
+        }
+        match self.stream_buffer.resume_prompt() {
+            Some(prompt) => {
+                self.prepare_resume(index);
+                self.stream_buffer.on_resume();
+                self.send_chat_request(prompt, true);
+            }
+            None => {
+                let prompt = self.prepare_retry(index);
+                self.stream_buffer.begin(prompt.clone());
+                self.send_chat_request(prompt, false);
+            }
+        }
+        Ok(())
+    }
+
+    /// 保留第 index 条用户消息和紧随其后的部分回复，删除之后的错误提示等消息
+    fn prepare_resume(&mut self, index: usize) {
+        let partial_reply = self
+            .chat_history
+            .get_messages()
+            .get(index + 1)
+            .is_some_and(|msg| msg.role == Role::Assistant);
+        self.chat_history.remove_after(if partial_reply { index + 1 } else { index });
+        self.scroll_to_bottom();
+    }
+
+    /// 最近一条非命令的用户消息的位置
+    fn last_retryable_message(&self) -> Option<usize> {
+        self.chat_history
+            .get_messages()
+            .iter()
+            .rposition(|msg| matches!(msg.role, Role::User) && !msg.content.starts_with('/'))
+    }
+
+    /// 删除第 index 条用户消息之后的消息，返回与提交时相同的请求内容：
+    /// 附加过的命令输出保持不变，@ 提及的文件重新读取
+    fn prepare_retry(&mut self, index: usize) -> String {
+        let message = self.chat_history.get_messages()[index].content.clone();
+        let input = match &self.last_chat_input {
+            Some((turn, input)) if *turn == index && input.ends_with(&message) => input.clone(),
+            _ => message,
+        };
+        self.chat_history.remove_after(index);
+        let prompt = self.process_mentions(&input, index);
+        self.scroll_to_bottom();
+        prompt
+    }
+
+    /// 为新的请求创建中断标记
+    fn new_request_interrupt(&mut self) -> ShutdownToken {
+        let token = ShutdownToken::new();
+        self.request_interrupt = Some(token.clone());
+        token
+    }
+
+    /// 用户按下中断键：停止进行中的生成，保留已收到的内容并标记 [interrupted]。
+    /// 不解析部分回复中的修改指令。没有进行中的生成时返回 false
+    pub fn interrupt_generation(&mut self) -> bool {
+        if !self.is_streaming {
+            return false;
+        }
+        if let Some(token) = self.request_interrupt.take() {
+            token.cancel();
+        }
+        self.streaming_response.lock().unwrap().reset();
+        // 用户主动停止，不保留给 /retry 继续
+        self.stream_buffer.complete();
+        self.is_streaming = false;
+        self.stream_handler = None;
+
+        if let Some(last_msg) = self.chat_history.get_messages_mut().back_mut() {
+            if let Role::Assistant = last_msg.role {
+                if !last_msg.content.is_empty() {
+                    last_msg.content.push('\n');
+                }
+                last_msg.content.push_str(INTERRUPTED_MARKER);
+            }
+        }
+        self.scroll_to_bottom();
+        true
+    }
+
+    /// Ctrl+C（没有选中文本时）：生成中先停止生成，QUIT_CONFIRM_WINDOW 内再按一次才退出；空闲时直接退出
+    pub fn handle_ctrl_c(&mut self) -> AppAction {
+        self.handle_ctrl_c_at(Instant::now())
+    }
+
+    fn handle_ctrl_c_at(&mut self, now: Instant) -> AppAction {
+        if self.is_streaming {
+            self.interrupt_generation();
+            self.quit_armed_at = Some(now);
+            return AppAction::None;
+        }
+        match self.quit_armed_at.take() {
+            Some(armed_at) if now.duration_since(armed_at) <= QUIT_CONFIRM_WINDOW => AppAction::Quit,
+            // 超过时间窗口：重新提示，避免停止生成后隔了很久的一次 Ctrl+C 直接退出
+            Some(_) => {
+                self.quit_armed_at = Some(now);
+                AppAction::None
+            }
+            None => AppAction::Quit,
+        }
+    }
+
+    /// 是否在等待第二次 Ctrl+C（状态栏显示提示）
+    pub fn quit_pending(&self) -> bool {
+        self.quit_armed_at.is_some_and(|armed_at| armed_at.elapsed() <= QUIT_CONFIRM_WINDOW)
+    }
+
+    pub fn render(&mut self, f: &mut Frame) {
+        // 使用像素艺术风格布局 (v2 - 4x4 头像)
+        self.frame_count = self.frame_count.wrapping_add(1);
+        ui::pixel_layout_v2::render_pixel_layout(f, self);
+    }
+
+    pub async fn finalize_streaming_response(&mut self) {
+        // 一次加锁取出内容并重置，之后的解析不再持有锁
+        let content = crate::ai::streaming::take_response(&*self.streaming_response);
+        self.attach_reply_usage(&content);
+        let ai_response_opt = (!content.is_empty()).then_some(content);
+        
+        // 在释放 response 借用后，处理 AI 响应中的代码修改指令
+        if let Some(ai_response) = ai_response_opt {
+            // 此时聊天历史中的最后一条 AI 消息已经在流式过程中被更新完毕
+            // 这里不再重复添加，只用于解析代码修改指令
+            
+            // 自动滚动到底部
+            // self.scroll_to_bottom();
+            
+            // 检测修改指令并立即显示确认对话
+            // 不等待用户继续输入
+            self.process_ai_response_for_modifications(&ai_response);
+        }
+        
+        if let Some(started) = self.routing_started_at.take() {
+            self.gemini.finish_routing(started.elapsed().as_millis() as u64);
+        }
+        self.is_streaming = false;
+        self.stream_handler = None;
+        self.request_interrupt = None;
+        self.stream_buffer.complete();
+    }
+
+    /// 记录提供商报告的回复用量（StreamEvent::Usage）
+    pub fn record_usage(&mut self, usage: crate::ai::client::CompletionMetadata) {
+        self.pending_usage = Some(usage);
+    }
+
+    /// 把本次回复的用量附加到最后一条 AI 消息上；提供商没有报告时按请求和回复估算
+    fn attach_reply_usage(&mut self, reply: &str) {
+        let usage = self.pending_usage.take();
+        if reply.is_empty() {
+            return;
+        }
+        let Some(index) = self.chat_history.get_messages().iter().rposition(|msg| msg.role == Role::Assistant) else {
+            return;
+        };
+        let model = self.llm_client.as_ref().map(|client| client.config().model.clone()).unwrap_or_default();
+        let usage = usage.unwrap_or_else(|| {
+            let request = ChatMessage {
+                role: "user".to_string(),
+                content: self.stream_buffer.get_request().unwrap_or_default().to_string(),
+            };
+            crate::ai::client::CompletionMetadata::estimate(&model, &[request], reply)
+        });
+        let metadata = crate::ui::types::MessageMetadata {
+            model: Some(model),
+            ..crate::ui::types::MessageMetadata::from(&usage)
+        };
+        self.chat_history.set_metadata(index, metadata);
+    }
+
+    /// 流出错：结束本次生成，但保留 stream_buffer 中的请求和部分回复，/retry 时从中断处继续
+    pub async fn handle_stream_error(&mut self, error: &str) {
+        let buffer = std::mem::take(&mut self.stream_buffer);
+        self.finalize_streaming_response().await;
+        self.stream_buffer = buffer;
+
+        let hint = if self.stream_buffer.has_partial() { "，/retry 从中断处继续" } else { "，/retry 重新发送" };
+        self.chat_history.add_message(Message {
+            role: Role::System,
+            content: format!("❌ 响应出错（{}）{}", error, hint),
+        });
+        self.scroll_to_bottom();
+    }
+    
+    /// 追加一批流式 Token：更新最后一条 AI 消息，并一次加锁同步到 streaming_response
+    pub fn append_stream_tokens(&mut self, tokens: &[String]) {
+        for token in tokens {
+            self.stream_buffer.append(token);
+        }
+        let after_tool_line = self
+            .chat_history
+            .get_messages()
+            .back()
+            .is_some_and(|msg| msg.role == Role::System && msg.content.starts_with(TOOL_LINE_PREFIX));
+        if after_tool_line {
+            // 工具行之后的回复另起一条 AI 消息
+            self.chat_history.add_message(Message {
+                role: Role::Assistant,
+                content: tokens.concat(),
+            });
+        } else if let Some(last_msg) = self.chat_history.get_messages_mut().back_mut() {
+            if let Role::Assistant = last_msg.role {
+                for token in tokens {
+                    last_msg.content.push_str(token);
+                }
+            }
+        }
+        crate::ai::streaming::append_token_batch(&*self.streaming_response, tokens);
+    }
+
+    /// explain 模式：在工具进度行之前显示通俗说明
+    pub fn show_tool_explanation(&mut self, text: &str) {
+        self.chat_history.add_message(Message {
+            role: Role::System,
+            content: format!("💡 {}", text),
+        });
+        self.scroll_to_bottom();
+    }
+
+    /// 显示不影响本次回复的警告（StreamEvent::Warning）
+    pub fn show_warning(&mut self, text: &str) {
+        self.chat_history.add_message(Message {
+            role: Role::System,
+            content: format!("⚠ {}", text),
+        });
+        self.scroll_to_bottom();
+    }
+
+    /// 流中解析到工具调用：立即显示 "🔧 running <tool>…"，执行完成后由 show_tool_result 更新
+    pub fn show_tool_call(&mut self, call: &crate::tools::ToolCall) {
+        self.chat_history.add_message(Message {
+            role: Role::System,
+            content: tool_running_line(&call.tool_name),
+        });
+        self.running_tools.push_back(call.clone());
+        self.scroll_to_bottom();
+    }
+
+    /// 工具执行完成：把最早一个仍在运行的工具行更新为结果（按 /verbose 完整显示或折叠为摘要），
+    /// 完整输出保存到 last_tool_output 供 /verbose show 查看
+    pub fn show_tool_result(&mut self, result: &crate::tools::ToolResult) {
+        use crate::tools::tool_display::{full_output, render_tool_result};
+
+        self.scan_progress = None;
+        let Some(call) = self.running_tools.pop_front() else {
+            return;
+        };
+        let tool_name = &call.tool_name;
+        let running = tool_running_line(tool_name);
+        self.last_tool_output = Some(full_output(&call, result));
+        let summary = if result.success {
+            let shown = render_tool_result(&call, result, self.verbose_tool_output);
+            let separator = if shown.contains('\n') { "\n" } else { " " };
+            format!("✓{}{}", separator, shown)
+        } else {
+            format!("✗ {}", result.error.clone().unwrap_or_default())
+        };
+
+        if let Some(line) = self
+            .chat_history
+            .get_messages_mut()
+            .iter_mut()
+            .rev()
+            .find(|msg| msg.role == Role::System && msg.content == running)
+        {
+            line.content = format!("{}{} {}", TOOL_LINE_PREFIX, tool_name, summary);
+        }
+        self.scroll_to_bottom();
+    }
+
+    /// 流中途中断：保留已收到的部分内容，不解析修改指令，提示用户可以继续
+    pub fn handle_stream_interrupted(&mut self, reason: &str) {
+        let partial_len = crate::ai::streaming::take_response(&*self.streaming_response).len();
+
+        self.is_streaming = false;
+        self.stream_handler = None;
+        self.request_interrupt = None;
+
+        self.chat_history.add_message(Message {
+            role: Role::System,
+            content: format!(
+                "⚠ 响应在完成前中断（{}），已保留 {} 字节的部分内容，/retry 从中断处继续。",
+                reason, partial_len
+            ),
+        });
+        self.scroll_to_bottom();
+    }
+
+    /// 滚动到聊天历史底部
+    pub fn scroll_to_bottom(&mut self) {
+        self.chat_scroll_offset = 0;
+        self.highlighted_block = None;
+    }
+
+    /// 按新的终端尺寸重新计算最大滚动量（内容会按新宽度重新折行），并把滚动位置限制在范围内
+    pub fn update_max_scroll(&mut self, width: u16, height: u16) {
+        let size = ratatui::layout::Rect::new(0, 0, width, height);
+        self.chat_max_scroll = crate::ui::pixel_layout_v2::max_chat_scroll(self, size);
+        self.chat_scroll_offset = self.chat_scroll_offset.min(self.chat_max_scroll);
+
+        let input_lines = self.input_text.lines().count();
+        self.input_scroll_offset = self.input_scroll_offset.min(input_lines.saturating_sub(3));
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_blocks_command_lists_code_blocks() {
+        let mut app = App::new();
+        app.chat_history.add_message(Message {
+            role: Role::User,
+            content: "看看这个:\n```rust\nfn main() {}\n```".to_string(),
+        });
+        app.chat_history.add_message(Message {
+            role: Role::Assistant,
+            content: "两种写法:\n```python\nprint('hi')\n```\n以及\n```bash\necho hi\n```".to_string(),
+        });
+
+        app.handle_command("/blocks").await;
+
+        let listing = &app.chat_history.get_messages().back().unwrap().content;
+        assert!(listing.contains("共 3 个代码块"));
+        assert!(listing.contains("#1 [rust] 消息 #1 (USER): fn main() {}"));
+        assert!(listing.contains("#2 [python] 消息 #2 (AI): print('hi')"));
+        assert!(listing.contains("#3 [bash] 消息 #2 (AI): echo hi"));
+
+        app.handle_command("/block 2").await;
+        assert_eq!(app.highlighted_block, Some((1, 1..4)));
+        assert!(app.chat_scroll_offset > 0);
+
+        // 内容首尾的空行被 trim 掉，高亮范围仍要覆盖到结束围栏（前面两条命令的输出占了消息 #3、#4）
+        app.chat_history.add_message(Message {
+            role: Role::User,
+            content: "```rust\n\nfn padded() {}\n\n\n```\n之后".to_string(),
+        });
+        app.handle_command("/block 4").await;
+        assert_eq!(app.highlighted_block, Some((4, 0..6)));
+    }
+
+    #[tokio::test]
+    async fn test_line_numbered_context_and_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lib.rs");
+        std::fs::write(&path, "fn old() {\n    1\n}\n").unwrap();
+        let path = path.to_str().unwrap().to_string();
+
+        let mut app = App::new();
+        assert!(!app.process_mentions(&format!("看看 @{}", path), 0).contains("1: fn old()"));
+
+        app.handle_command("/linenumbers on").await;
+        let prompt = app.process_mentions(&format!("看看 @{}", path), 0);
+        assert!(prompt.contains("1: fn old() {\n2:     1\n3: }"));
+
+        // 模型照抄了行号：应用修改时去掉，写入内容不带行号
+        let response = format!(
+            "In `{}`:\n<<<<<<< SEARCH\n1: fn old() {{\n2:     1\n3: }}\n=======\n1: fn new() {{\n2:     2\n3: }}\n>>>>>>> REPLACE",
+            path
+        );
+        app.process_ai_response_for_modifications(&response);
+        let (_, diff) = &app.pending_modifications[0];
+        assert_eq!(diff.as_ref().unwrap().new_content, "fn new() {\n    2\n}\n");
+    }
+
+    #[test]
+    fn test_circular_mentions_reported_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("GROK.md");
+        std::fs::write(&a, "rules, see @notes.md\n").unwrap();
+        std::fs::write(dir.path().join("notes.md"), "notes, see @GROK.md\n").unwrap();
+
+        let mut app = App::new();
+        let before = app.chat_history.get_messages().len();
+        let prompt = app.process_mentions(&format!("看看 @{}", a.display()), 0);
+        assert_eq!(prompt.matches("rules, see").count(), 1);
+        assert_eq!(prompt.matches("notes, see").count(), 1);
+        assert_eq!(app.chat_history.get_messages().len(), before + 1);
+        assert!(app.chat_history.get_messages().back().unwrap().content.contains("循环引用"));
+    }
+
+    #[test]
+    fn test_line_range_mention_attaches_slice_or_reports() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lib.rs");
+        std::fs::write(&path, "one\ntwo\nthree\nfour\nfive\n").unwrap();
+
+        let mut app = App::new();
+        let prompt = app.process_mentions(&format!("看看 @{}:2-3", path.display()), 0);
+        assert!(prompt.contains("lines=\"2-3\">\ntwo\nthree\n"));
+        assert!(!prompt.contains("one") && !prompt.contains("four"));
+
+        let before = app.chat_history.get_messages().len();
+        let prompt = app.process_mentions(&format!("看看 @{}:4-9", path.display()), 0);
+        assert!(!prompt.contains("<file_content"));
+        assert_eq!(app.chat_history.get_messages().len(), before + 1);
+        assert!(app.chat_history.get_messages().back().unwrap().content.contains("只有 5 行"));
+    }
+
+    #[tokio::test]
+    async fn test_yolo_mode_prompts_for_ambiguous_modify() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.rs");
+        let original = "let retries = 3;\nlet timeout = 10;\nlet retries = 3;\n";
+        std::fs::write(&path, original).unwrap();
+        let path_str = path.to_str().unwrap().to_string();
+
+        let mut app = App::new();
+        app.file_command_handler.set_path_guard(crate::fs::path_guard::PathGuard::new(dir.path()));
+        app.handle_command("/yolo on").await;
+
+        // 搜索块匹配两处：不自动应用，仍然弹出确认
+        let ambiguous = format!(
+            "In `{}`:\n<<<<<<< SEARCH\nlet retries = 3;\n=======\nlet retries = 5;\n>>>>>>> REPLACE",
+            path_str
+        );
+        app.process_ai_response_for_modifications(&ambiguous);
+        assert!(app.modification_confirmation_pending);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+        assert!(app.chat_history.get_messages().back().unwrap().content.contains("匹配了 2 处"));
+
+        app.pending_modifications.clear();
+        app.modification_confirmation_pending = false;
+
+        // 唯一匹配：直接应用
+        let unique = format!(
+            "In `{}`:\n<<<<<<< SEARCH\nlet timeout = 10;\n=======\nlet timeout = 30;\n>>>>>>> REPLACE",
+            path_str
+        );
+        app.process_ai_response_for_modifications(&unique);
+        assert!(!app.modification_confirmation_pending);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "let retries = 3;\nlet timeout = 30;\nlet retries = 3;\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_yolo_modify_outside_project_is_rejected() {
+        use crate::fs::path_guard::PathGuard;
+
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        let outside = dir.path().join("outside.rs");
+        std::fs::write(&outside, "let secret = 1;\n").unwrap();
+
+        let mut app = App::new();
+        app.file_command_handler.set_path_guard(PathGuard::new(&project));
+        app.handle_command("/yolo on").await;
+
+        let response = format!(
+            "In `{}`:\n<<<<<<< SEARCH\nlet secret = 1;\n=======\nlet secret = 2;\n>>>>>>> REPLACE",
+            project.join("../outside.rs").display()
+        );
+        app.process_ai_response_for_modifications(&response);
+        assert_eq!(std::fs::read_to_string(&outside).unwrap(), "let secret = 1;\n");
+        assert!(app.chat_history.get_messages().back().unwrap().content.contains("outside the project root"));
+        assert!(app.modification_history.is_empty());
+
+        // --allow-outside 时允许
+        app.file_command_handler.set_path_guard(PathGuard::new(&project).with_allow_outside(true));
+        app.process_ai_response_for_modifications(&response);
+        assert_eq!(std::fs::read_to_string(&outside).unwrap(), "let secret = 2;\n");
+    }
+
+    #[tokio::test]
+    async fn test_yolo_mode_still_confirms_create_and_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("old.rs");
+        std::fs::write(&existing, "fn old() {}\n").unwrap();
+        let created = dir.path().join("new.rs");
+
+        let mut app = App::new();
+        app.handle_command("/yolo on").await;
+
+        // 删除文件：不自动应用，文件保留
+        app.process_ai_response_for_modifications(&format!("delete `{}`", existing.display()));
+        assert!(app.modification_confirmation_pending);
+        assert!(existing.exists());
+        assert!(app.chat_history.get_messages().back().unwrap().content.contains("会删除文件"));
+
+        app.pending_modifications.clear();
+        app.modification_confirmation_pending = false;
+
+        // 创建文件：不自动应用，文件不会被写入
+        app.process_ai_response_for_modifications(&format!(
+            "create file `{}`\n```rust\nfn new() {{}}\n```",
+            created.display()
+        ));
+        assert!(app.modification_confirmation_pending);
+        assert!(!created.exists());
+        assert!(app.chat_history.get_messages().back().unwrap().content.contains("会创建新文件"));
+    }
+
+    #[tokio::test]
+    async fn test_undo_removes_created_file() {
+        use crate::events::handler::EventHandler;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        let path_str = path.to_str().unwrap().to_string();
+
+        let mut app = App::new();
+        app.file_command_handler.set_path_guard(crate::fs::path_guard::PathGuard::new(dir.path()));
+        app.pending_modifications.push((
+            CodeModificationOp::Create { path: path_str.clone(), content: "# Notes\n".to_string() },
+            None,
+        ));
+        app.modification_confirmation_pending = true;
+        EventHandler::handle_chat_event(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(path.exists());
+        assert_eq!(app.modification_history.len(), 1);
+
+        app.handle_command("/undo").await;
+        assert!(!path.exists());
+        let undone = &app.chat_history.get_messages().back().unwrap().content;
+        assert!(undone.contains("已撤销创建") && undone.contains("没有更多可撤销的修改"), "{}", undone);
+
+        app.handle_command("/undo").await;
+        assert!(app.chat_history.get_messages().back().unwrap().content.contains("没有可撤销的修改"));
+    }
+
+    #[tokio::test]
+    async fn test_pinned_message_survives_clear_command() {
+        let mut app = App::new();
+        app.chat_history.add_message(Message { role: Role::User, content: "必须兼容 Rust 1.70".to_string() });
+        app.chat_history.add_message(Message { role: Role::Assistant, content: "好的".to_string() });
+
+        app.handle_command("/pin 1").await;
+        app.handle_command("/clear").await;
+        let messages = app.chat_history.get_messages();
+        assert_eq!(messages[0].content, "必须兼容 Rust 1.70");
+        assert!(app.chat_history.is_pinned(0));
+        assert!(messages.iter().all(|m| m.content != "好的"));
+
+        app.handle_command("/clear --force").await;
+        assert!(app.chat_history.get_messages().iter().all(|m| m.content != "必须兼容 Rust 1.70"));
+    }
+
+    #[test]
+    fn test_resize_recomputes_and_clamps_scroll() {
+        use crate::events::handler::EventHandler;
+
+        let mut app = App::new();
+        for i in 0..20 {
+            app.chat_history.add_message(Message { role: Role::User, content: format!("message {} {}", i, "word ".repeat(20)) });
+        }
+
+        EventHandler::handle_resize(&mut app, 40, 30);
+        let narrow_max = app.chat_max_scroll;
+        assert!(narrow_max > 0);
+        app.chat_scroll_offset = narrow_max;
+
+        // 变宽后折行减少、可见行增多，滚动上限变小，偏移被限制到新上限
+        EventHandler::handle_resize(&mut app, 200, 60);
+        assert!(app.chat_max_scroll < narrow_max);
+        assert_eq!(app.chat_scroll_offset, app.chat_max_scroll);
+
+        EventHandler::handle_resize(&mut app, 200, 500);
+        assert_eq!(app.chat_max_scroll, 0);
+        assert_eq!(app.chat_scroll_offset, 0);
+    }
+
+    #[tokio::test]
+    async fn test_export_context_writes_mentioned_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("lib.rs").to_string_lossy().to_string();
+        let bundle = dir.path().join("bundle.md").to_string_lossy().to_string();
+        std::fs::write(&source, "pub fn answer() -> u32 { 42 }\n").unwrap();
+
+        let mut app = App::new();
+        app.add_user_message(&format!("explain @{}", source));
+        app.handle_command(&format!("/export-context {}", bundle)).await;
+
+        let exported = std::fs::read_to_string(&bundle).unwrap();
+        assert!(exported.contains(&source));
+        assert!(exported.contains("pub fn answer() -> u32 { 42 }"));
+    }
+
+    #[tokio::test]
+    async fn test_snippet_inserted_with_cursor_at_placeholder() {
+        let mut app = App::new();
+        app.handle_command("/snippet rust-test").await;
+
+        assert!(app.input_text.starts_with("#[cfg(test)]"));
+        assert!(!app.input_text.contains("{cursor}"));
+        let before_cursor: String = app.input_text.chars().take(app.input_cursor).collect();
+        assert!(before_cursor.ends_with("    fn "));
+
+        app.handle_command("/snippet no-such-snippet").await;
+        assert!(app.chat_history.get_messages().back().unwrap().content.contains("未找到片段"));
+    }
+
+    #[tokio::test]
+    async fn test_model_switcher_enter_sets_config_model() {
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+        use crate::events::handler::EventHandler;
+
+        let mut app = App::new();
+        app.init_ai_client_with_config(LLMConfig::default_openai("test".to_string()));
+
+        EventHandler::handle_chat_event(&mut app, KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL));
+        assert!(app.model_switcher.visible);
+
+        for c in "4om".chars() {
+            EventHandler::handle_chat_event(&mut app, KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        EventHandler::handle_chat_event(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+
+        assert!(!app.model_switcher.visible);
+        assert_eq!(app.llm_config.as_ref().unwrap().model, "gpt-4o-mini");
+        assert_eq!(app.llm_client.as_ref().unwrap().config().model, "gpt-4o-mini");
+        assert!(app.input_text.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_system_prompt_is_first_request_message() {
+        let mut app = App::new();
+        app.handle_command("/system set Always answer in English").await;
+
+        let messages = app.build_request_messages(Some("default".to_string()), "hi".to_string());
+        assert_eq!(messages[0].role, "system");
+        assert_eq!(messages[0].content, "Always answer in English");
+        assert_eq!(messages[1].content, "default");
+        assert_eq!(messages.last().unwrap().content, "hi");
+
+        app.handle_command("/system clear").await;
+        let messages = app.build_request_messages(None, "hi".to_string());
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].role, "user");
+    }
+
+    #[tokio::test]
+    async fn test_attach_last_output_included_in_next_turn() {
+        let mut app = App::new();
+
+        app.handle_command("/bash echo build-failed-marker").await;
+        assert!(app.last_command_output.as_ref().unwrap().contains("build-failed-marker"));
+
+        app.handle_command("/attach-last-output").await;
+        let input = app.build_chat_input("why did this fail?");
+        assert!(input.contains("<command_output>"));
+        assert!(input.contains("build-failed-marker"));
+        assert!(input.ends_with("why did this fail?"));
+
+        // 附加的上下文只用于下一轮
+        assert_eq!(app.build_chat_input("next"), "next");
+    }
+
+    #[tokio::test]
+    async fn test_safe_mode_blocks_bash_and_file_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("blocked.txt");
+        let mut app = App::new();
+        app.safe_mode = true;
+
+        app.handle_command("/bash echo should-not-run").await;
+        let last = &app.chat_history.get_messages().back().unwrap().content;
+        assert!(last.contains("安全模式"));
+        assert!(app.last_command_output.is_none());
+
+        app.handle_command(&format!("/create-file {} hello", target.display())).await;
+        let last = &app.chat_history.get_messages().back().unwrap().content;
+        assert!(last.contains("安全模式"));
+        assert!(!target.exists());
+
+        // 只读文件命令不受影响
+        app.handle_command(&format!("/list-dir {}", dir.path().display())).await;
+        let last = &app.chat_history.get_messages().back().unwrap().content;
+        assert!(!last.contains("安全模式"));
+    }
+
+    #[test]
+    fn test_failed_build_errors_queued_as_context() {
+        let output = "\
+error[E0425]: cannot find value `count` in this scope
+  --> src/lib.rs:12:9
+   |
+12 |         count += 1;
+   |         ^^^^^ not found in this scope
+
+error: could not compile `demo` (lib) due to 1 previous error
+";
+        let mut app = App::new();
+
+        // 默认先询问，确认后才附加
+        let offer = app.offer_build_errors(output).unwrap();
+        assert!(offer.contains("要我修复这些错误吗"));
+        assert!(app.attached_context.is_empty());
+        assert_eq!(app.pending_build_errors.len(), 1);
+        assert_eq!(app.pending_build_errors[0].file, "src/lib.rs");
+        assert_eq!(app.pending_build_errors[0].line, 12);
+        assert_eq!(app.pending_build_errors[0].message, "cannot find value `count` in this scope");
+
+        app.build_errors_command(&["attach".to_string()]);
+        assert!(app.pending_build_errors.is_empty());
+        let input = app.build_chat_input("fix it");
+        assert!(input.contains("src/lib.rs:12:9: error[E0425]"));
+
+        // auto 模式直接附加
+        app.build_errors_command(&["auto".to_string()]);
+        app.offer_build_errors(output).unwrap();
+        assert_eq!(app.attached_context.len(), 1);
+        assert!(app.offer_build_errors("   Finished dev profile").is_none());
+    }
+
+    #[test]
+    fn test_search_history_returns_matching_indices() {
+        let mut app = App::new();
+        for (role, content) in [
+            (Role::User, "How do I parse JSON in Rust?"),
+            (Role::Assistant, "Use serde_json::from_str to parse JSON."),
+            (Role::User, "/search json"),
+            (Role::User, "And what about TOML?"),
+            (Role::Assistant, "The toml crate works like serde_json."),
+        ] {
+            app.chat_history.add_message(Message { role, content: content.to_string() });
+        }
+
+        assert_eq!(app.search_history("json", false).unwrap(), vec![0, 1, 4]);
+        assert_eq!(app.search_history("TOML", false).unwrap(), vec![3, 4]);
+        assert_eq!(app.search_history(r"serde_\w+::", true).unwrap(), vec![1]);
+        assert!(app.search_history("(", true).is_err());
+    }
+
+    /// 记录调用了哪个方法的假客户端
+    #[derive(Default)]
+    struct RecordingClient {
+        complete_calls: std::sync::atomic::AtomicUsize,
+        stream_calls: std::sync::atomic::AtomicUsize,
+    }
+
+    impl ChatCompletion for RecordingClient {
+        fn complete(&self, _messages: Vec<ChatMessage>) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<String, crate::ai::client::CompletionError>> + Send + '_>> {
+            self.complete_calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Box::pin(async { Ok("whole reply".to_string()) })
+        }
+
+        fn complete_stream(
+            &self,
+            _messages: Vec<ChatMessage>,
+            mut callback: Box<dyn FnMut(String) -> bool + Send>,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), crate::ai::client::CompletionError>> + Send + '_>> {
+            self.stream_calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Box::pin(async move {
+                callback("whole ".to_string());
+                callback("reply".to_string());
+                Ok(())
+            })
+        }
+    }
+
+    fn drain_tokens(handler: &StreamHandler) -> Vec<String> {
+        let receiver = handler.get_receiver();
+        let mut rx = receiver.try_lock().unwrap();
+        let mut tokens = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let crate::ai::streaming::StreamEvent::Token(token) = event {
+                tokens.push(token);
+            }
+        }
+        tokens
+    }
+
+    #[tokio::test]
+    async fn test_stream_mode_routes_to_client_method() {
+        use std::sync::atomic::Ordering;
+
+        let mut app = App::new();
+        app.handle_command("/stream off").await;
+        assert!(!app.stream_responses);
+
+        let client = Arc::new(RecordingClient::default());
+        let handler = StreamHandler::new();
+        spawn_chat_request(client.clone(), Vec::new(), app.stream_responses, handler.clone(), ShutdownToken::new(), ShutdownToken::new()).await.unwrap();
+        assert_eq!(client.complete_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(client.stream_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(drain_tokens(&handler), vec!["whole reply"]);
+
+        app.handle_command("/stream on").await;
+        let handler = StreamHandler::new();
+        spawn_chat_request(client.clone(), Vec::new(), app.stream_responses, handler.clone(), ShutdownToken::new(), ShutdownToken::new()).await.unwrap();
+        assert_eq!(client.complete_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(client.stream_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(drain_tokens(&handler), vec!["whole ", "reply"]);
+    }
+
+    #[tokio::test]
+    async fn test_interrupt_keeps_partial_response() {
+        use crate::events::handler::EventHandler;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let mut app = App::new();
+        app.add_user_message("explain lifetimes");
+        app.chat_history.add_message(Message { role: Role::Assistant, content: String::new() });
+        app.is_streaming = true;
+        app.stream_handler = Some(StreamHandler::new());
+        let interrupt = app.new_request_interrupt();
+        app.append_stream_tokens(&["Lifetimes describe ".to_string(), "how long".to_string()]);
+
+        EventHandler::handle_chat_event(&mut app, KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+
+        assert!(interrupt.is_cancelled());
+        assert!(!app.is_streaming);
+        assert!(app.stream_handler.is_none());
+        let last = app.chat_history.get_messages().back().unwrap();
+        assert_eq!(last.role, Role::Assistant);
+        assert_eq!(last.content, format!("Lifetimes describe how long\n{}", INTERRUPTED_MARKER));
+
+        // 没有进行中的生成时，中断键按原来的方式处理
+        assert!(!app.interrupt_generation());
+    }
+
+    #[tokio::test]
+    async fn test_ctrl_c_during_streaming_cancels_before_quitting() {
+        use crate::events::handler::EventHandler;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        let mut app = App::new();
+        app.chat_history.add_message(Message { role: Role::Assistant, content: String::new() });
+        app.is_streaming = true;
+        app.stream_handler = Some(StreamHandler::new());
+        let interrupt = app.new_request_interrupt();
+
+        // 第一次：只停止生成
+        assert_eq!(EventHandler::handle_chat_event(&mut app, ctrl_c), AppAction::None);
+        assert!(interrupt.is_cancelled());
+        assert!(!app.is_streaming);
+        assert!(app.quit_pending());
+
+        // 时间窗口内第二次：退出
+        assert_eq!(EventHandler::handle_chat_event(&mut app, ctrl_c), AppAction::Quit);
+
+        // 超过时间窗口后的 Ctrl+C 只重新提示
+        let armed_at = Instant::now();
+        app.quit_armed_at = Some(armed_at);
+        let late = armed_at + QUIT_CONFIRM_WINDOW + Duration::from_millis(1);
+        assert_eq!(app.handle_ctrl_c_at(late), AppAction::None);
+        assert_eq!(app.handle_ctrl_c_at(late + Duration::from_millis(500)), AppAction::Quit);
+
+        // 空闲时直接退出
+        assert_eq!(App::new().handle_ctrl_c(), AppAction::Quit);
+    }
+
+    #[tokio::test]
+    async fn test_missing_api_key_guides_instead_of_requesting() {
+        let mut app = App::new();
+        app.init_ai_client_with_config(LLMConfig::default_openai(String::new()));
+        app.input_text = "explain lifetimes".to_string();
+
+        app.handle_chat_submit().await;
+
+        assert!(!app.is_streaming);
+        assert!(app.stream_handler.is_none());
+        let last = app.chat_history.get_messages().back().unwrap();
+        assert_eq!(last.role, Role::System);
+        assert_eq!(last.content, "⚠ 未配置 API Key — 运行 /openai <key> 或设置 OPENAI_API_KEY（本地模型可用 /ollama）");
+
+        // 本地提供商不需要 Key
+        assert!(!LLMConfig::default_ollama().is_missing_api_key());
+    }
+
+    #[test]
+    fn test_esc_clears_partial_input_and_closes_mentions() {
+        use crate::events::handler::EventHandler;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("mentions.md"), "# notes").unwrap();
+        let mut app = App::new();
+        app.file_search.set_root(dir.path().to_path_buf());
+        app.file_search.build_cache();
+        app.add_user_message("earlier question");
+        for c in "@men".chars() {
+            EventHandler::handle_chat_event(&mut app, KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        assert_eq!(app.input_text, "@men");
+        assert!(app.mention_suggestions.visible);
+
+        EventHandler::handle_chat_event(&mut app, KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+
+        assert!(app.input_text.is_empty());
+        assert_eq!(app.input_cursor, 0);
+        assert!(!app.mention_suggestions.visible);
+        assert!(app.file_search.results.is_empty());
+        assert_eq!(app.chat_history.get_messages().back().unwrap().content, "earlier question");
+    }
+
+    #[tokio::test]
+    async fn test_compose_loads_editor_content_into_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let editor = crate::ui::compose::tests::fake_editor(dir.path(), "多行消息\nline two\n");
+        let mut app = App::new();
+
+        app.input_text = "/compose".to_string();
+        app.handle_chat_submit().await;
+        assert!(app.compose_requested);
+
+        app.compose_requested = false;
+        app.compose_with_editor(&editor).unwrap();
+        assert_eq!(app.input_text, "多行消息\nline two");
+        assert_eq!(app.input_cursor, app.input_text.chars().count());
+    }
+
+    #[tokio::test]
+    async fn test_retry_resends_last_user_message_with_mentions() {
+        let mut app = App::new();
+        app.handle_command("/retry").await;
+        assert!(app.chat_history.get_messages().back().unwrap().content.contains("还没有可以重试的消息"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.rs");
+        std::fs::write(&path, "fn retried() {}\n").unwrap();
+        let mut config = LLMConfig::default_openai("test".to_string());
+        config.base_url = "http://127.0.0.1:9".to_string();
+        app.init_ai_client_with_config(config);
+
+        let question = format!("解释 @{}", path.display());
+        app.add_user_message(&question);
+        app.chat_history.add_message(Message { role: Role::Assistant, content: "partial".to_string() });
+        app.chat_history.add_message(Message { role: Role::System, content: "❌ 请求失败".to_string() });
+        app.add_user_message("/retry");
+
+        let index = app.last_retryable_message().unwrap();
+        let prompt = app.prepare_retry(index);
+        assert!(prompt.contains("fn retried() {}"));
+        let messages = app.chat_history.get_messages();
+        assert_eq!(messages.back().unwrap().content, question);
+
+        app.retry_last().await.unwrap();
+        let messages = app.chat_history.get_messages();
+        assert!(matches!(messages.back().unwrap().role, Role::Assistant));
+        assert!(messages.back().unwrap().content.is_empty());
+        assert_eq!(messages[messages.len() - 2].content, question);
+        assert!(app.is_streaming);
+        assert!(app.retry_last().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retry_rebuilds_the_submitted_request() {
+        let mut app = app_with_unreachable_client();
+        app.attached_context.push("error[E0308]: mismatched types".to_string());
+        app.input_text = "fix this".to_string();
+        app.handle_chat_submit().await;
+        let submitted = app.stream_buffer.get_request().unwrap().to_string();
+        assert!(submitted.starts_with("<command_output>\nerror[E0308]"));
+        assert!(app.attached_context.is_empty());
+
+        assert!(app.interrupt_generation());
+        app.retry_last().await.unwrap();
+        assert_eq!(app.stream_buffer.get_request(), Some(submitted.as_str()));
+        let messages = app.chat_history.get_messages();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].content, "fix this");
+        assert!(messages[1].content.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_config_shows_effective_values_with_masked_key() {
+        let key = "sk-test-1234567890abcd";
+        let dir = tempfile::tempdir().unwrap();
+        LLMConfig::save_project_model(dir.path(), "project-model").unwrap();
+
+        let mut app = App::new();
+        app.file_search.set_root(dir.path().to_path_buf());
+        app.init_ai_client_with_config(LLMConfig::default_openai(key.to_string()));
+        app.apply_project_model(dir.path());
+        app.handle_command("/temp 1.5").await;
+
+        app.handle_command("/config").await;
+        let output = &app.chat_history.get_messages().back().unwrap().content;
+        assert!(output.contains("模型: project-model"));
+        assert!(output.contains("API Key: ****abcd"));
+        assert!(!output.contains(key));
+        assert!(!output.contains("1234567890"));
+        assert!(output.contains("温度: 1.50"));
+        assert!(output.contains("会话温度 (/temp): 1.50"));
+        assert!(output.contains(".starfell/model"));
+    }
+
+    /// 指向不可达地址的客户端：请求会在后台失败，这里只检查发出的请求内容
+    fn app_with_unreachable_client() -> App {
+        let mut app = App::new();
+        let mut config = LLMConfig::default_openai("test".to_string());
+        config.base_url = "http://127.0.0.1:9".to_string();
+        app.init_ai_client_with_config(config);
+        app
+    }
+
+    #[test]
+    fn test_diff_mentions_are_whole_tokens_and_use_project_root() {
+        let git = |dir: &std::path::Path, args: &[&str]| {
+            let status = std::process::Command::new("git").args(args).current_dir(dir).output().unwrap().status;
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        let dir = tempfile::tempdir().unwrap();
+        git(dir.path(), &["init", "-q"]);
+        git(dir.path(), &["config", "user.email", "dev@example.com"]);
+        git(dir.path(), &["config", "user.name", "Dev"]);
+        std::fs::write(dir.path().join("a.rs"), "const A: u32 = 1;\n").unwrap();
+        std::fs::write(dir.path().join("b.rs"), "const B: u32 = 1;\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "init"]);
+        std::fs::write(dir.path().join("a.rs"), "const A: u32 = 2;\n").unwrap();
+        git(dir.path(), &["add", "a.rs"]);
+        std::fs::write(dir.path().join("b.rs"), "const B: u32 = 2;\n").unwrap();
+
+        // 项目根目录不是当前工作目录
+        let mut app = App::new();
+        app.file_search.set_root(dir.path().to_path_buf());
+        let (cleaned, expansion) = app.collect_mentions("review @diff and @diff-staged please");
+        assert_eq!(cleaned, "review  and  please");
+
+        let labels: Vec<&str> = expansion.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(labels, vec!["git diff", "git diff --staged"]);
+        assert!(expansion.files[0].content.contains("+const B: u32 = 2;"));
+        assert!(expansion.files[1].content.contains("+const A: u32 = 2;"));
+        assert!(!expansion.files[1].content.contains("const B"));
+    }
+
+    #[tokio::test]
+    async fn test_history_mention_attaches_recent_conversation() {
+        let mut app = app_with_unreachable_client();
+        app.add_user_message("what is a trait object");
+        app.chat_history.add_message(Message { role: Role::Assistant, content: "a dyn Trait behind a pointer".to_string() });
+
+        app.input_text = "summarize @history in one line".to_string();
+        app.handle_chat_submit().await;
+        let prompt = app.stream_buffer.get_request().unwrap();
+        assert!(prompt.starts_with("summarize  in one line"));
+        assert!(prompt.contains("用户: what is a trait object"));
+        assert!(prompt.contains("AI: a dyn Trait behind a pointer"));
+        // 当前消息和占位的 AI 消息都不会被附加
+        assert!(!prompt.contains("用户: summarize"));
+        let last = app.chat_history.get_messages().back().unwrap();
+        assert_eq!((last.role.clone(), last.content.as_str()), (Role::Assistant, ""));
+
+        // 超出预算的更早消息被丢弃
+        let mut app = app_with_unreachable_client();
+        for i in 0..20 {
+            app.add_user_message(&format!("old context {} ", i).repeat(100));
+        }
+        app.add_user_message("recent question");
+        app.input_text = "@history".to_string();
+        app.handle_chat_submit().await;
+        let prompt = app.stream_buffer.get_request().unwrap();
+        assert!(prompt.contains("用户: recent question"));
+        assert!(prompt.contains("old context 19"));
+        assert!(!prompt.contains("old context 0 "));
+    }
+
+    #[tokio::test]
+    async fn test_mention_report_does_not_swallow_streamed_reply() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("short.txt");
+        std::fs::write(&path, "one\ntwo\n").unwrap();
+        let mut app = app_with_unreachable_client();
+
+        app.input_text = format!("look at @{}:4-9 and @history.md", path.display());
+        app.handle_chat_submit().await;
+        assert!(app.stream_buffer.get_request().unwrap().contains("@history.md"));
+
+        // 行范围无效的提示排在占位消息之前，回复仍写入 AI 消息
+        app.append_stream_tokens(&["Only two lines.".to_string()]);
+        let messages = app.chat_history.get_messages();
+        let reply = messages.back().unwrap();
+        assert_eq!(reply.role, Role::Assistant);
+        assert_eq!(reply.content, "Only two lines.");
+        assert!(messages[messages.len() - 2].content.contains("行范围无效"));
+    }
+
+    #[test]
+    fn test_tool_events_show_running_line_then_result() {
+        use crate::tools::{ToolCall, ToolResult};
+
+        let mut app = App::new();
+        app.add_user_message("run the tests");
+        app.chat_history.add_message(Message { role: Role::Assistant, content: String::new() });
+        app.append_stream_tokens(&["Running tests.".to_string()]);
+
+        let call = ToolCall { tool_name: "bash".to_string(), arguments: Default::default() };
+        app.show_tool_call(&call);
+        assert_eq!(app.chat_history.get_messages().back().unwrap().content, "🔧 running bash…");
+
+        // 工具执行期间到达的 Token 另起一条 AI 消息，不覆盖工具行
+        app.append_stream_tokens(&["Still waiting".to_string()]);
+        let result = ToolResult { success: true, data: serde_json::json!("test result: ok. 3 passed\nmore"), error: None };
+        app.show_tool_result(&result);
+
+        let contents: Vec<&str> = app.chat_history.get_messages().iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            vec!["run the tests", "Running tests.", "🔧 bash ✓\ntest result: ok. 3 passed\nmore", "Still waiting"]
+        );
+        assert_eq!(app.last_tool_output.as_deref(), Some("test result: ok. 3 passed\nmore"));
+
+        // verbose 关闭时长输出折叠为摘要，/verbose show 查看完整输出；开启时完整显示
+        let long_output = (1..=30).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n");
+        let long = ToolResult { success: true, data: serde_json::json!(long_output), error: None };
+        for verbose in [false, true] {
+            app.verbose_tool_output = verbose;
+            app.show_tool_call(&call);
+            app.show_tool_result(&long);
+            let line = app.chat_history.get_messages().iter().rev().find(|m| m.content.starts_with("🔧 bash")).unwrap();
+            assert_eq!(line.content.contains("line 30"), verbose, "{}", line.content);
+            assert_eq!(app.set_verbose_tool_output(&["show".to_string()]), long_output);
+        }
+
+        // 失败的工具显示错误
+        app.show_tool_call(&ToolCall { tool_name: "search_code".to_string(), arguments: Default::default() });
+        app.show_tool_result(&ToolResult { success: false, data: serde_json::Value::Null, error: Some("bad pattern".to_string()) });
+        assert_eq!(app.chat_history.get_messages().back().unwrap().content, "🔧 search_code ✗ bad pattern");
+    }
+
+    #[tokio::test]
+    async fn test_agent_tool_events_render_in_chat() {
+        use crate::ai::streaming::StreamEvent;
+        use crate::tools::ToolCall;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "remember the milk\n").unwrap();
+
+        for explain in [false, true] {
+            let mut app = app_with_unreachable_client();
+            app.toggle_explain_mode(&[if explain { "on" } else { "off" }.to_string()]);
+            app.add_user_message("what is in my notes?");
+            app.chat_history.add_message(Message { role: Role::Assistant, content: String::new() });
+
+            // 与 send_chat_request 相同：Agent 把工具事件发到当前请求的 StreamHandler
+            let handler = StreamHandler::new();
+            let agent = with_request_events(app.ai_agent.clone().unwrap(), &handler);
+            agent.register_standard_tools().await;
+            let call = ToolCall {
+                tool_name: "read_file".to_string(),
+                arguments: [("path".to_string(), serde_json::json!(path.to_str().unwrap()))].into_iter().collect(),
+            };
+            let executed = agent.execute_tool_calls(vec![call]).await;
+            assert!(executed[0].result.success);
+            assert_eq!(executed[0].explanation.is_some(), explain);
+
+            let receiver = handler.get_receiver();
+            let mut rx = receiver.try_lock().unwrap();
+            let mut rendered = Vec::new();
+            while let Ok(event) = rx.try_recv() {
+                match event {
+                    StreamEvent::Explanation(text) => app.show_tool_explanation(&text),
+                    StreamEvent::ToolCall(call) => app.show_tool_call(&call),
+                    StreamEvent::ToolResult(result) => app.show_tool_result(&result),
+                    _ => continue,
+                }
+                rendered.push(app.chat_history.get_messages().back().unwrap().content.clone());
+            }
+            if explain {
+                assert_eq!(rendered.remove(0), format!("💡 读取 {} 的内容，了解其中的代码", path.display()));
+            }
+            assert_eq!(rendered.len(), 2);
+            assert_eq!(rendered[0], "🔧 running read_file…");
+            assert!(rendered[1].starts_with("🔧 read_file ✓"), "{}", rendered[1]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_agent_write_tools_use_project_guard() {
+        use crate::fs::path_guard::PathGuard;
+        use crate::tools::ToolCall;
+
+        let project = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let target = outside.path().join("escape.txt");
+
+        let mut app = App::new();
+        app.file_command_handler.set_path_guard(PathGuard::new(project.path()));
+        app.init_ai_client_with_config(LLMConfig::default_openai("test".to_string()));
+
+        let agent = app.ai_agent.clone().unwrap();
+        agent.register_standard_tools().await;
+        let calls = ["write_file", "str_replace_editor"].map(|tool| ToolCall {
+            tool_name: tool.to_string(),
+            arguments: [
+                ("path".to_string(), serde_json::json!(target.to_str().unwrap())),
+                ("content".to_string(), serde_json::json!("pwned")),
+                ("old_str".to_string(), serde_json::json!("a")),
+                ("new_str".to_string(), serde_json::json!("b")),
+            ].into_iter().collect(),
+        });
+        for executed in agent.execute_tool_calls(calls.to_vec()).await {
+            assert!(!executed.result.success, "{}", executed.tool_name);
+            assert!(executed.result.error.unwrap().contains("outside the project root"));
+        }
+        assert!(!target.exists());
+    }
+
+    #[tokio::test]
+    async fn test_agent_tool_calls_are_written_to_audit_log() {
+        use crate::tools::ToolCall;
+
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("audit.jsonl");
+        std::fs::write(dir.path().join("notes.txt"), "hello audit").unwrap();
+
+        let mut app = App::new();
+        app.tool_audit_log = Some(log_path.clone());
+        app.init_ai_client_with_config(LLMConfig::default_openai("test".to_string()));
+
+        let agent = app.ai_agent.clone().unwrap();
+        agent.register_standard_tools().await;
+        let call = ToolCall {
+            tool_name: "read_file".to_string(),
+            arguments: [("path".to_string(), serde_json::json!(dir.path().join("notes.txt").to_str().unwrap()))]
+                .into_iter()
+                .collect(),
+        };
+        let executed = agent.execute_tool_calls(vec![call]).await;
+        assert!(executed[0].result.success);
+
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        let record: serde_json::Value = serde_json::from_str(log.lines().next().unwrap()).unwrap();
+        assert_eq!(record["tool"], "read_file");
+        assert_eq!(record["success"], true);
+        assert!(record["output"].as_str().unwrap().contains("hello audit"));
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_failure_is_shown_as_warning() {
+        use crate::tools::ToolCall;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "hello audit").unwrap();
+
+        let mut app = App::new();
+        // 日志路径是目录，追加写入必然失败
+        app.tool_audit_log = Some(dir.path().to_path_buf());
+        app.init_ai_client_with_config(LLMConfig::default_openai("test".to_string()));
+
+        let handler = StreamHandler::new();
+        let agent = with_request_events(app.ai_agent.clone().unwrap(), &handler);
+        agent.register_standard_tools().await;
+        let call = ToolCall {
+            tool_name: "read_file".to_string(),
+            arguments: [("path".to_string(), serde_json::json!(dir.path().join("notes.txt").to_str().unwrap()))]
+                .into_iter()
+                .collect(),
+        };
+        let executed = agent.execute_tool_calls(vec![call]).await;
+        assert!(executed[0].result.success);
+
+        let receiver = handler.get_receiver();
+        let mut rx = receiver.try_lock().unwrap();
+        let mut warnings = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let crate::ai::streaming::StreamEvent::Warning(text) = event {
+                warnings.push(text);
+            }
+        }
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("写入工具审计日志失败"), "{}", warnings[0]);
+
+        app.show_warning(&warnings[0]);
+        assert!(app.chat_history.get_messages().back().unwrap().content.starts_with("⚠ 工具执行后钩子失败 (read_file)"));
+    }
+
+    #[tokio::test]
+    async fn test_status_bar_typing_indicator_follows_animation_setting() {
+        use crate::ui::typing_indicator::TypingAnimation;
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let screen = |app: &mut App| {
+            let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+            terminal.draw(|f| app.render(f)).unwrap();
+            terminal.backend().buffer().content.iter().map(|cell| cell.symbol()).collect::<String>()
+        };
+
+        let mut app = app_with_unreachable_client();
+        app.input_text = "hello".to_string();
+        app.handle_chat_submit().await;
+        assert!(app.is_streaming);
+
+        app.typing_animation = TypingAnimation { frame_interval_ms: 100, enabled: true };
+        app.streaming_started_at = Some(Instant::now() - Duration::from_millis(250));
+        assert!(screen(&mut app).contains("⠹ STATUS: GENERATING"));
+
+        app.typing_animation = TypingAnimation::disabled();
+        assert!(screen(&mut app).contains("… STATUS: GENERATING"));
+
+        // 生成结束后指示器消失
+        app.append_stream_tokens(&["hi".to_string()]);
+        app.finalize_streaming_response().await;
+        let text = screen(&mut app);
+        assert!(text.contains("STATUS: CONNECTED"));
+        assert!(!text.contains("GENERATING"));
+    }
+
+    #[tokio::test]
+    async fn test_scan_progress_is_reported_and_shown() {
+        let handler = StreamHandler::new();
+        let control = scan_control_reporting_to(&handler);
+        for _ in 0..250 {
+            control.file_scanned();
+        }
+
+        let receiver = handler.get_receiver();
+        let mut rx = receiver.try_lock().unwrap();
+        let mut reported = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let crate::ai::streaming::StreamEvent::ScanProgress(files_scanned) = event {
+                reported.push(files_scanned);
+            }
+        }
+        assert_eq!(reported, vec![100, 200]);
+
+        let mut app = app_with_unreachable_client();
+        app.input_text = "scan the project".to_string();
+        app.handle_chat_submit().await;
+        app.scan_progress = reported.last().copied();
+        assert!(find_cell(&render_buffer(&mut app), "200 files scanned").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_timestamps_setting_applies_to_chat_layout() {
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let screen = |app: &mut App| {
+            let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+            terminal.draw(|f| app.render(f)).unwrap();
+            terminal.backend().buffer().content.iter().map(|cell| cell.symbol()).collect::<String>()
+        };
+
+        let mut app = App::new();
+        app.add_user_message("what time is it");
+        assert!(!screen(&mut app).contains("[just now]"));
+
+        app.handle_command("/timestamps relative").await;
+        assert!(screen(&mut app).contains("[just now]"));
+
+        app.handle_command("/timestamps off").await;
+        assert!(!screen(&mut app).contains("[just now]"));
+    }
+
+    /// 绘制一帧，返回屏幕缓冲区
+    fn render_buffer(app: &mut App) -> ratatui::buffer::Buffer {
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    /// 屏幕上 text 第一次出现处的首个单元格
+    fn find_cell<'a>(buffer: &'a ratatui::buffer::Buffer, text: &str) -> Option<&'a ratatui::buffer::Cell> {
+        let symbols: Vec<&str> = buffer.content.iter().map(|cell| cell.symbol()).collect();
+        let needle: Vec<String> = text.chars().map(|c| c.to_string()).collect();
+        symbols
+            .windows(needle.len())
+            .position(|window| window.iter().zip(&needle).all(|(a, b)| a == b))
+            .map(|i| &buffer.content[i])
+    }
+
+    #[tokio::test]
+    async fn test_chat_layout_renders_markdown() {
+        let mut app = App::new();
+        app.add_user_message("# Plan\n- step one\n  - nested\nuse `cargo test`");
+        let buffer = render_buffer(&mut app);
+
+        assert!(find_cell(&buffer, "Plan").unwrap().modifier.contains(ratatui::style::Modifier::BOLD));
+        assert!(find_cell(&buffer, "• step one").is_some());
+        assert!(find_cell(&buffer, "  ◦ nested").is_some());
+        // 行内代码去掉反引号
+        assert!(find_cell(&buffer, "`cargo").is_none());
+        assert!(find_cell(&buffer, "cargo test").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_chat_layout_highlights_code_blocks_by_fence_language() {
+        use ratatui::style::Color;
+
+        let mut app = App::new();
+        app.add_user_message("```rust\nlet greeting = \"hi\"; // say it\n```\n```haskell\nlet other = 1\n```");
+        let buffer = render_buffer(&mut app);
+
+        let theme = crate::ui::markdown::MarkdownTheme::new(Color::Reset, Color::Reset);
+        assert_eq!(find_cell(&buffer, "let greeting").unwrap().fg, theme.keyword);
+        assert_eq!(find_cell(&buffer, "\"hi\"").unwrap().fg, theme.string);
+        assert_eq!(find_cell(&buffer, "// say it").unwrap().fg, theme.comment);
+        // 未知语言不高亮
+        assert_eq!(find_cell(&buffer, "let other").unwrap().fg, theme.code);
+    }
+
+    #[tokio::test]
+    async fn test_reply_usage_is_attached_and_shown() {
+        use crate::ai::client::CompletionMetadata;
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let mut app = app_with_unreachable_client();
+        app.input_text = "write a long story".to_string();
+        app.handle_chat_submit().await;
+        app.append_stream_tokens(&["Once upon".to_string()]);
+
+        // 与主循环相同：Usage 事件先到，Done 时附加到回复上
+        let handler = StreamHandler::new();
+        handler.send_usage(CompletionMetadata {
+            prompt_tokens: 12,
+            completion_tokens: 256,
+            finish_reason: Some("length".to_string()),
+            estimated: false,
+        }).unwrap();
+        let event = handler.get_receiver().try_lock().unwrap().try_recv();
+        if let Ok(crate::ai::streaming::StreamEvent::Usage(usage)) = event {
+            app.record_usage(usage);
+        }
+        app.finalize_streaming_response().await;
+
+        let reply = app.chat_history.get_messages().len() - 1;
+        let metadata = app.chat_history.metadata(reply).unwrap();
+        assert_eq!(metadata.model.as_deref(), Some(app.llm_client.as_ref().unwrap().config().model.as_str()));
+        assert_eq!(metadata.usage_label().unwrap(), "12 → 256 tokens · ⚠ 回复已截断");
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+        let text: String = terminal.backend().buffer().content.iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains("12 → 256 tokens"));
+
+        // 提供商没有报告用量时显示估算值
+        app.input_text = "shorter please".to_string();
+        app.handle_chat_submit().await;
+        app.append_stream_tokens(&["Fine.".to_string()]);
+        app.finalize_streaming_response().await;
+        let reply = app.chat_history.get_messages().len() - 1;
+        assert!(app.chat_history.metadata(reply).unwrap().usage_label().unwrap().starts_with('~'));
+        assert!(app.chat_history.metadata(reply - 1).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_error_keeps_partial_until_successful_retry() {
+        let mut app = app_with_unreachable_client();
+
+        app.input_text = "explain ownership".to_string();
+        app.handle_chat_submit().await;
+        assert_eq!(app.stream_buffer.get_request(), Some("explain ownership"));
+        app.append_stream_tokens(&["Ownership ".to_string(), "means".to_string()]);
+
+        // 流中途出错：保留请求和部分回复
+        app.handle_stream_error("connection reset").await;
+        assert!(!app.is_streaming);
+        assert_eq!(app.stream_buffer.get_content(), "Ownership means");
+        assert!(app.chat_history.get_messages().back().unwrap().content.contains("/retry 从中断处继续"));
+
+        // /retry 接着部分回复继续，新的内容追加到同一条 AI 消息
+        app.handle_command("/retry").await;
+        assert!(app.is_streaming);
+        assert_eq!(app.stream_buffer.get_retry_count(), 1);
+        app.append_stream_tokens(&[" each value has one owner.".to_string()]);
+        let messages = app.chat_history.get_messages();
+        assert_eq!(messages.back().unwrap().content, "Ownership means each value has one owner.");
+        assert_eq!(messages[messages.len() - 2].content, "explain ownership");
+
+        // 成功完成后清空
+        app.finalize_streaming_response().await;
+        assert!(!app.stream_buffer.has_partial());
+        assert_eq!(app.stream_buffer.get_request(), None);
+    }
+
+    #[tokio::test]
+    async fn test_why_reports_routed_model_and_reason() {
+        let mut app = App::new();
+        app.handle_command("/why").await;
+        assert!(app.chat_history.get_messages().back().unwrap().content.contains("没有经过模型路由"));
+
+        app.gemini.route_turn("how do I read a file?").await.unwrap();
+        app.handle_command("/why").await;
+
+        let report = &app.chat_history.get_messages().back().unwrap().content;
+        assert!(report.contains("模型: gemini-2.0-flash"));
+        assert!(report.contains("策略: intent_based"));
+        assert!(report.contains("no review/debug intent"));
+    }
+
+    #[tokio::test]
+    async fn test_semantic_mentions_show_cap_and_debounce_queries() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.rs", "b.rs", "c.rs"] {
+            std::fs::write(dir.path().join(name), "fn main() {}\n").unwrap();
+        }
+        let mut app = app_with_unreachable_client();
+        app.file_search.set_root(dir.path().to_path_buf());
+
+        let message = app.set_mention_ranking(&["semantic".to_string(), "2".to_string()]);
+        assert!(message.contains("最多索引 2 个文件"), "{}", message);
+        assert!(message.contains("项目共有 3 个文件"), "{}", message);
+        assert!(app.set_mention_ranking(&[]).contains("最多索引 2 个文件"));
+        assert!(app.set_mention_ranking(&["semantic".to_string(), "0".to_string()]).starts_with("❌"));
+
+        // 连续输入只在停止输入 SEMANTIC_RANKING_DEBOUNCE 后发起一次排序
+        app.request_semantic_ranking();
+        let changed_at = app.semantic_query_changed_at.unwrap();
+        app.poll_semantic_ranking(changed_at + SEMANTIC_RANKING_DEBOUNCE / 2);
+        assert_eq!(app.semantic_query_changed_at, Some(changed_at));
+        app.poll_semantic_ranking(changed_at + SEMANTIC_RANKING_DEBOUNCE);
+        assert_eq!(app.semantic_query_changed_at, None);
+    }
+
+    #[tokio::test]
+    async fn test_why_reports_model_used_by_chat_submit() {
+        let mut app = app_with_unreachable_client();
+        let model = app.llm_config.as_ref().unwrap().model.clone();
+        app.input_text = "explain lifetimes".to_string();
+        app.handle_chat_submit().await;
+        app.finalize_streaming_response().await;
+
+        app.handle_command("/why").await;
+        let report = &app.chat_history.get_messages().back().unwrap().content;
+        assert!(report.contains(&format!("模型: {}", model)));
+        assert!(report.contains("策略: configured"));
+        assert!(report.contains("未启用路由"));
+        assert!(!report.contains("没有经过模型路由"));
+    }
+
+    #[test]
+    fn test_truncate_to_tail() {
+        assert_eq!(truncate_to_tail("abcdef", 3), "def");
+        assert_eq!(truncate_to_tail("abc", 10), "abc");
+    }
+
+    #[tokio::test]
+    async fn test_project_model_overrides_global_default() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        LLMConfig::save_project_model(temp_dir.path(), "project-model").unwrap();
+
+        let mut app = App::new();
+        app.init_ai_client_with_config(LLMConfig::default());
+        assert_eq!(app.llm_config.as_ref().unwrap().model, "gpt-3.5-turbo");
+
+        // 启动时对项目根目录执行的选择逻辑
+        assert_eq!(app.apply_project_model(temp_dir.path()), Some("project-model".to_string()));
+        assert_eq!(app.llm_config.as_ref().unwrap().model, "project-model");
+    }
+
+    #[tokio::test]
+    async fn test_switching_to_project_without_preference_restores_default_model() {
+        // /cd 切换目录后对新项目执行的选择逻辑：A 有偏好，B 没有
+        let project_a = tempfile::tempdir().unwrap();
+        let project_b = tempfile::tempdir().unwrap();
+        LLMConfig::save_project_model(project_a.path(), "model-a").unwrap();
+
+        let mut app = App::new();
+        app.init_ai_client_with_config(LLMConfig::default());
+        let client_model = |app: &App| app.llm_client.as_ref().unwrap().config().model.clone();
+
+        assert_eq!(app.apply_project_model(project_a.path()), Some("model-a".to_string()));
+        assert_eq!(client_model(&app), "model-a");
+
+        assert_eq!(app.apply_project_model(project_b.path()), None);
+        assert_eq!(app.llm_config.as_ref().unwrap().model, "gpt-3.5-turbo");
+        assert_eq!(client_model(&app), "gpt-3.5-turbo");
+    }
+
+    #[tokio::test]
+    async fn test_temperature_override_applies_to_next_request() {
+        let mut app = App::new();
+        app.init_ai_client_with_config(LLMConfig::default());
+        let client_temperature = |app: &App| app.llm_client.as_ref().unwrap().config().temperature;
+
+        app.handle_command("/temp 1.2").await;
+        assert_eq!(client_temperature(&app), 1.2);
+        assert!(app.chat_history.get_messages().back().unwrap().content.contains("0.70 → 1.20"));
+
+        // 超出范围时截断；重建客户端（如切换模型）后覆盖仍然生效，配置本身不变
+        app.handle_command("/temp 5").await;
+        app.handle_command("/set-model gpt-4o").await;
+        assert_eq!(client_temperature(&app), 2.0);
+        app.handle_command("/temp -1").await;
+        assert_eq!(client_temperature(&app), 0.0);
+        assert_eq!(app.llm_config.as_ref().unwrap().temperature, 0.7);
+
+        app.handle_command("/temp reset").await;
+        assert_eq!(client_temperature(&app), 0.7);
+        assert_eq!(app.temperature_override, None);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_saves_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+
+        let mut app = App::new();
+        app.history_path = Some(path.clone());
+        app.add_user_message("before exit");
+        // 不经过 Quit 动作（如主循环出错返回）同样保存
+        app.shutdown(std::time::Duration::from_millis(100)).await.unwrap();
+
+        let mut restored = App::new();
+        assert_eq!(restored.load_history(&path), 1);
+        assert_eq!(restored.chat_history.get_messages()[0].content, "before exit");
+
+        // 保存失败时返回错误而不是打印到终端
+        app.history_path = Some(dir.path().to_path_buf());
+        assert!(app.shutdown(std::time::Duration::from_millis(100)).await.is_err());
+    }
+
+    #[test]
+    fn test_history_round_trips_and_degrades_gracefully() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("history.json");
+
+        let mut app = App::new();
+        app.persisted_history_limit = 3;
+        for i in 0..5 {
+            app.add_user_message(&format!("question {}", i));
+            app.chat_history.add_message(Message { role: Role::Assistant, content: format!("answer {}", i) });
+        }
+        app.chat_history.set_pinned(9, true);
+        app.save_history(&path).unwrap();
+
+        // 只保存最近 3 条，时间戳和置顶标记随消息恢复
+        let mut restored = App::new();
+        assert_eq!(restored.load_history(&path), 3);
+        let contents: Vec<&str> = restored.chat_history.get_messages().iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["answer 3", "question 4", "answer 4"]);
+        assert_eq!(restored.chat_history.timestamp(2), app.chat_history.timestamp(9));
+        assert!(restored.chat_history.is_pinned(2));
+
+        let mut missing = App::new();
+        assert_eq!(missing.load_history(&dir.path().join("absent.json")), 0);
+
+        std::fs::write(&path, "{ not json").unwrap();
+        let mut corrupt = App::new();
+        corrupt.add_user_message("fresh session");
+        assert_eq!(corrupt.load_history(&path), 0);
+        assert!(corrupt.chat_history.is_empty());
+    }
+
+    #[test]
+    fn test_session_picker_selection_loads_history() {
+        use crate::core::sessions::{SessionChoice, SessionStore, CURRENT_SESSION_ID};
+        use crate::ui::session_picker::SessionPicker;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path());
+
+        // 两个会话：先保存的被归档，后保存的是当前会话
+        let mut earlier = App::new();
+        earlier.add_user_message("refactor the parser");
+        earlier.chat_history.add_message(Message { role: Role::Assistant, content: "done".to_string() });
+        earlier.save_history(&store.current_path()).unwrap();
+        store.archive_current().unwrap();
+        let mut latest = App::new();
+        latest.add_user_message("write release notes");
+        latest.save_history(&store.current_path()).unwrap();
+
+        let sessions = store.list();
+        let titles: Vec<&str> = sessions.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, vec!["write release notes", "refactor the parser"]);
+        assert_eq!(sessions[1].message_count, 2);
+        let mut picker = SessionPicker::new(sessions);
+
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        assert_eq!(picker.handle_key(key(KeyCode::Down)), None);
+        let choice = picker.handle_key(key(KeyCode::Enter)).unwrap();
+        assert!(matches!(&choice, SessionChoice::Resume(id) if id != CURRENT_SESSION_ID));
+
+        let mut app = App::new();
+        assert_eq!(app.apply_session_choice(&store, &choice).unwrap(), 2);
+        let contents: Vec<&str> = app.chat_history.get_messages().iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["refactor the parser", "done"]);
+
+        // 原来的当前会话被归档，仍可在下次启动时恢复；恢复的会话立即成为当前会话，
+        // 即使这次没有正常退出也不会丢失
+        let sessions = store.list();
+        let titles: Vec<&str> = sessions.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, vec!["write release notes", "refactor the parser"]);
+        assert_eq!(sessions[1].id, CURRENT_SESSION_ID);
+
+        // 开始新会话时历史为空
+        latest.save_history(&store.current_path()).unwrap();
+        let mut picker = SessionPicker::new(store.list());
+        let choice = picker.handle_key(key(KeyCode::Esc)).unwrap();
+        let mut fresh = App::new();
+        assert_eq!(fresh.apply_session_choice(&store, &choice).unwrap(), 0);
+        assert!(fresh.chat_history.is_empty());
+        assert!(!store.current_path().exists());
+    }
+
+    #[test]
+    fn test_confirmed_modify_replaces_only_search_block() {
+        use crate::events::handler::EventHandler;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.rs");
+        let path_str = path.to_str().unwrap().to_string();
+        std::fs::write(&path, "const RETRIES: u32 = 3;\nconst TIMEOUT: u32 = 30;\n").unwrap();
+
+        let mut app = App::new();
+        app.file_command_handler.set_path_guard(crate::fs::path_guard::PathGuard::new(dir.path()));
+        let op = CodeModificationOp::Modify {
+            path: path_str.clone(),
+            search: "const TIMEOUT: u32 = 30;".to_string(),
+            replace: "const TIMEOUT: u32 = 60;".to_string(),
+        };
+        assert_eq!(app.apply_modification(&op), Ok(format!("✅ 文件已修改: {}", path_str)));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "const RETRIES: u32 = 3;\nconst TIMEOUT: u32 = 60;\n");
+
+        // 搜索块不存在时报告错误，文件不变，也不进入撤销栈
+        let missing = CodeModificationOp::Modify {
+            path: path_str.clone(),
+            search: "const VERBOSE: bool = true;".to_string(),
+            replace: "const VERBOSE: bool = false;".to_string(),
+        };
+        assert!(app.apply_modification(&missing).unwrap_err().contains("代码匹配失败"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "const RETRIES: u32 = 3;\nconst TIMEOUT: u32 = 60;\n");
+        assert_eq!(app.modification_history.len(), 1);
+
+        // 确认对话中按 Enter 走同一条路径，不会用 replace 覆盖整个文件
+        app.pending_modifications.push((
+            CodeModificationOp::Modify {
+                path: path_str,
+                search: "const RETRIES: u32 = 3;".to_string(),
+                replace: "const RETRIES: u32 = 5;".to_string(),
+            },
+            None,
+        ));
+        app.modification_confirmation_pending = true;
+        EventHandler::handle_chat_event(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "const RETRIES: u32 = 5;\nconst TIMEOUT: u32 = 60;\n");
+        assert!(app.pending_modifications.is_empty());
+    }
+
+    #[test]
+    fn test_apply_pending_modifications_reports_each_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let created = dir.path().join("new.rs");
+        let existing = dir.path().join("lib.rs");
+        std::fs::write(&existing, "fn a() {}\n").unwrap();
+
+        let mut app = App::new();
+        app.file_command_handler.set_path_guard(crate::fs::path_guard::PathGuard::new(dir.path()));
+        app.pending_modifications = vec![
+            (CodeModificationOp::Create { path: created.to_str().unwrap().to_string(), content: "fn new() {}\n".to_string() }, None),
+            (
+                CodeModificationOp::Modify {
+                    path: existing.to_str().unwrap().to_string(),
+                    search: "fn missing() {}".to_string(),
+                    replace: "fn b() {}".to_string(),
+                },
+                None,
+            ),
+            (
+                CodeModificationOp::Modify {
+                    path: existing.to_str().unwrap().to_string(),
+                    search: "fn a() {}".to_string(),
+                    replace: "fn a() -> u8 { 1 }".to_string(),
+                },
+                None,
+            ),
+        ];
+        app.modification_confirmation_pending = true;
+
+        let results = app.apply_pending_modifications();
+        assert_eq!(results.iter().map(Result::is_ok).collect::<Vec<_>>(), vec![true, false, true]);
+        assert_eq!(std::fs::read_to_string(&created).unwrap(), "fn new() {}\n");
+        assert_eq!(std::fs::read_to_string(&existing).unwrap(), "fn a() -> u8 { 1 }\n");
+
+        let messages = app.chat_history.get_messages();
+        assert_eq!(messages.len(), 3);
+        assert!(messages[1].content.starts_with("❌ 代码匹配失败"));
+        assert!(app.pending_modifications.is_empty());
+        assert!(!app.modification_confirmation_pending);
+        assert_eq!(app.modification_history.len(), 2);
+    }
+}
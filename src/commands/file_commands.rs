@@ -1,5 +1,9 @@
 /// 文件操作命令处理
+use crate::ai::commands::split_arguments;
+use crate::core::diff::{compute_hunks, DiffHunk, DEFAULT_CONTEXT_LINES};
+use crate::fs::path_guard::PathGuard;
 use crate::utils::code_file_handler::CodeFileHandler;
+use std::path::Path;
 
 #[derive(Debug, Clone)]
 pub enum FileCommand {
@@ -23,6 +27,19 @@ pub enum FileCommand {
     SearchFiles { directory: String, pattern: String },
 }
 
+impl FileCommand {
+    /// 只读命令在安全模式下仍可使用
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            FileCommand::ReadFile { .. }
+                | FileCommand::ListDir { .. }
+                | FileCommand::SearchFiles { .. }
+                | FileCommand::CancelModify
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FileCommandResult {
     pub success: bool,
@@ -37,6 +54,15 @@ pub struct FileDiff {
     pub file_path: String,
     pub old_content: String,
     pub new_content: String,
+    /// 创建时计算一次的差异，显示时直接使用
+    pub hunks: Vec<DiffHunk>,
+}
+
+impl FileDiff {
+    pub fn new(file_path: impl Into<String>, old_content: String, new_content: String) -> Self {
+        let hunks = compute_hunks(&old_content, &new_content, DEFAULT_CONTEXT_LINES);
+        Self { file_path: file_path.into(), old_content, new_content, hunks }
+    }
 }
 
 pub struct FileCommandHandler {
@@ -45,6 +71,7 @@ pub struct FileCommandHandler {
     pending_modification: Option<(String, String)>, // (path, new_content)
     confirmation_pending: bool,
     confirmation_selected: ConfirmationChoice, // 当前选择
+    path_guard: PathGuard, // 写入/删除/创建的项目根目录约束
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -61,9 +88,25 @@ impl FileCommandHandler {
             pending_modification: None,
             confirmation_pending: false,
             confirmation_selected: ConfirmationChoice::Confirm,
+            path_guard: PathGuard::default(),
         }
     }
 
+    /// 设置项目根目录约束
+    pub fn set_path_guard(&mut self, guard: PathGuard) {
+        self.path_guard = guard;
+    }
+
+    /// 当前的项目根目录约束（AI Agent 的写文件工具共用）
+    pub fn path_guard(&self) -> &PathGuard {
+        &self.path_guard
+    }
+
+    /// 切换项目根目录（/cd）
+    pub fn set_project_root(&mut self, root: &Path) {
+        self.path_guard = self.path_guard.rebase(root);
+    }
+
     /// 上下箭头导航确认选择
     pub fn move_confirmation_up(&mut self) {
         if self.confirmation_pending {
@@ -109,78 +152,108 @@ impl FileCommandHandler {
         &self.file_handler
     }
 
-    /// 解析命令字符串
+    /// 解析命令字符串；引号未闭合时返回 None
     pub fn parse_command(input: &str) -> Option<FileCommand> {
-        let parts: Vec<&str> = input.trim().split_whitespace().collect();
+        Self::try_parse_command(input).ok().flatten()
+    }
+
+    /// 解析命令字符串，路径可以用引号包含空格（`/read-file "my file.txt"`）；
+    /// 引号未闭合时返回错误说明
+    pub fn try_parse_command(input: &str) -> Result<Option<FileCommand>, String> {
+        if !input.trim_start().starts_with("/") {
+            return Ok(None);
+        }
+        let parts = split_arguments(input)?;
         if parts.is_empty() {
-            return None;
+            return Ok(None);
         }
 
-        match parts[0] {
+        let command = match parts[0].as_str() {
             "/create-file" => {
                 if parts.len() < 2 {
-                    return None;
+                    return Ok(None);
                 }
-                let path = parts[1].to_string();
+                let path = parts[1].clone();
                 let content = if parts.len() > 2 {
                     Some(parts[2..].join(" "))
                 } else {
                     None
                 };
-                Some(FileCommand::CreateFile { path, content })
+                FileCommand::CreateFile { path, content }
             }
             "/modify-file" => {
                 if parts.len() < 3 {
-                    return None;
+                    return Ok(None);
                 }
-                let path = parts[1].to_string();
+                let path = parts[1].clone();
                 let content = parts[2..].join(" ");
-                Some(FileCommand::ModifyFile { path, content })
+                FileCommand::ModifyFile { path, content }
             }
             "/modify-file-search" => {
                 if parts.len() < 4 {
-                    return None;
+                    return Ok(None);
                 }
-                let path = parts[1].to_string();
-                let search = parts[2].to_string();
+                let path = parts[1].clone();
+                let search = parts[2].clone();
                 let replace = parts[3..].join(" ");
-                Some(FileCommand::ModifyFileSearch { path, search, replace })
+                FileCommand::ModifyFileSearch { path, search, replace }
             }
             "/delete-file" => {
                 if parts.len() < 2 {
-                    return None;
+                    return Ok(None);
                 }
-                let path = parts[1].to_string();
-                Some(FileCommand::DeleteFile { path })
+                FileCommand::DeleteFile { path: parts[1].clone() }
             }
             "/read-file" => {
                 if parts.len() < 2 {
-                    return None;
+                    return Ok(None);
                 }
-                let path = parts[1].to_string();
-                Some(FileCommand::ReadFile { path })
+                FileCommand::ReadFile { path: parts[1].clone() }
             }
             "/list-dir" => {
                 if parts.len() < 2 {
-                    return None;
+                    return Ok(None);
                 }
-                let path = parts[1].to_string();
-                Some(FileCommand::ListDir { path })
+                FileCommand::ListDir { path: parts[1].clone() }
             }
             "/search-files" => {
                 if parts.len() < 3 {
-                    return None;
+                    return Ok(None);
                 }
-                let directory = parts[1].to_string();
-                let pattern = parts[2].to_string();
-                Some(FileCommand::SearchFiles { directory, pattern })
+                let directory = parts[1].clone();
+                let pattern = parts[2].clone();
+                FileCommand::SearchFiles { directory, pattern }
             }
+            _ => return Ok(None),
+        };
+        Ok(Some(command))
+    }
+
+    /// 会写入磁盘的命令的目标路径
+    fn write_target(command: &FileCommand) -> Option<&str> {
+        match command {
+            FileCommand::CreateFile { path, .. }
+            | FileCommand::ModifyFile { path, .. }
+            | FileCommand::ModifyFileSearch { path, .. }
+            | FileCommand::DeleteFile { path } => Some(path),
             _ => None,
         }
     }
 
     /// 执行文件命令
     pub fn execute(&mut self, command: FileCommand) -> FileCommandResult {
+        if let Some(path) = Self::write_target(&command) {
+            if let Err(e) = self.path_guard.check(path) {
+                return FileCommandResult {
+                    success: false,
+                    message: format!("❌ 操作被拒绝: {}", e),
+                    content: None,
+                    requires_confirmation: false,
+                    diff: None,
+                };
+            }
+        }
+
         match command {
             FileCommand::CreateFile { path, content } => {
                 let content = content.unwrap_or_default();
@@ -239,11 +312,7 @@ impl FileCommandHandler {
                             message: format!("📝 显示修改对比 (使用 ↑↓ 选择，Enter 确认)"),
                             content: None,
                             requires_confirmation: true,
-                            diff: Some(FileDiff {
-                                file_path: path,
-                                old_content,
-                                new_content: content,
-                            }),
+                            diff: Some(FileDiff::new(path, old_content, content)),
                         }
                     }
                 } else {
@@ -434,6 +503,25 @@ mod tests {
         assert!(cmd.is_some());
     }
 
+    #[test]
+    fn test_parse_quoted_path_with_spaces() {
+        match FileCommandHandler::parse_command("/create-file \"my dir/a b.txt\" some content") {
+            Some(FileCommand::CreateFile { path, content }) => {
+                assert_eq!(path, "my dir/a b.txt");
+                assert_eq!(content.as_deref(), Some("some content"));
+            }
+            other => panic!("unexpected parse result: {:?}", other),
+        }
+
+        match FileCommandHandler::parse_command("/read-file 'notes/meeting notes.md'") {
+            Some(FileCommand::ReadFile { path }) => assert_eq!(path, "notes/meeting notes.md"),
+            other => panic!("unexpected parse result: {:?}", other),
+        }
+
+        let err = FileCommandHandler::try_parse_command("/read-file \"my file.txt").unwrap_err();
+        assert!(err.contains("未闭合"));
+    }
+
     #[test]
     fn test_parse_read_file() {
         let cmd = FileCommandHandler::parse_command("/read-file test.txt");
@@ -445,4 +533,30 @@ mod tests {
         let cmd = FileCommandHandler::parse_command("/delete-file test.txt");
         assert!(cmd.is_some());
     }
+
+    #[test]
+    fn test_create_file_confined_to_project_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let project = temp_dir.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+
+        let mut handler = FileCommandHandler::new();
+        handler.set_path_guard(PathGuard::new(&project));
+
+        let inside = project.join("a.txt");
+        let result = handler.execute(FileCommand::CreateFile {
+            path: inside.to_string_lossy().to_string(),
+            content: Some("hello".to_string()),
+        });
+        assert!(result.success);
+        assert!(inside.exists());
+
+        let outside = project.join("../b.txt");
+        let result = handler.execute(FileCommand::CreateFile {
+            path: outside.to_string_lossy().to_string(),
+            content: Some("hello".to_string()),
+        });
+        assert!(!result.success);
+        assert!(!temp_dir.path().join("b.txt").exists());
+    }
 }
@@ -1,6 +1,7 @@
 /// 命令处理系统
 pub mod file_commands;
 pub mod vibe_commands;
+pub mod snippets;
 
 pub use file_commands::FileCommand;
 pub use vibe_commands::{VibeCommand, VibeCommandHandler, VibeCommandResult};
@@ -0,0 +1,138 @@
+/// 代码片段 / 模板库（/snippet）
+/// 用户模板放在 ~/.starfell/snippets/ 下（文件名去掉扩展名即片段名），同名时覆盖内置模板
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// 模板中光标位置的占位符
+pub const CURSOR_MARKER: &str = "{cursor}";
+
+/// 用户模板目录（相对于 HOME）
+const USER_SNIPPET_DIR: &str = ".starfell/snippets";
+
+/// 内置模板：(名称, 内容)
+const BUILTIN_SNIPPETS: &[(&str, &str)] = &[
+    (
+        "rust-test",
+        "#[cfg(test)]\nmod tests {\n    use super::*;\n\n    #[test]\n    fn {cursor}() {\n    }\n}\n",
+    ),
+    (
+        "gitignore",
+        "/target\n/node_modules\n/dist\n.env\n.DS_Store\n*.log\n{cursor}",
+    ),
+    (
+        "readme",
+        "# {cursor}\n\n## 安装\n\n## 使用\n\n## 许可证\n",
+    ),
+];
+
+/// 展开后的片段：占位符已移除，cursor 为光标所在的字符位置
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snippet {
+    pub name: String,
+    pub body: String,
+    pub cursor: usize,
+}
+
+impl Snippet {
+    /// 解析模板：第一个 {cursor} 决定光标位置（没有时光标在末尾），所有占位符都会被移除
+    pub fn parse(name: &str, template: &str) -> Self {
+        let cursor = match template.find(CURSOR_MARKER) {
+            Some(index) => template[..index].chars().count(),
+            None => template.chars().count(),
+        };
+        Self {
+            name: name.to_string(),
+            body: template.replace(CURSOR_MARKER, ""),
+            cursor,
+        }
+    }
+}
+
+/// 模板库：用户目录 + 内置默认
+pub struct SnippetLibrary {
+    user_dir: Option<PathBuf>,
+}
+
+impl SnippetLibrary {
+    pub fn new(user_dir: Option<PathBuf>) -> Self {
+        Self { user_dir }
+    }
+
+    /// 使用 ~/.starfell/snippets 作为用户模板目录
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("HOME").ok().map(|home| Path::new(&home).join(USER_SNIPPET_DIR)))
+    }
+
+    /// 按名称加载片段，用户模板优先
+    pub fn load(&self, name: &str) -> Option<Snippet> {
+        if let Some(template) = self.load_user_template(name) {
+            return Some(Snippet::parse(name, &template));
+        }
+        BUILTIN_SNIPPETS
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, template)| Snippet::parse(name, template))
+    }
+
+    /// 所有可用的片段名称（排序、去重）
+    pub fn names(&self) -> Vec<String> {
+        let mut names: BTreeSet<String> = BUILTIN_SNIPPETS.iter().map(|(name, _)| name.to_string()).collect();
+        names.extend(self.user_templates().into_iter().map(|(name, _)| name));
+        names.into_iter().collect()
+    }
+
+    fn load_user_template(&self, name: &str) -> Option<String> {
+        self.user_templates()
+            .into_iter()
+            .find(|(template_name, _)| template_name == name)
+            .and_then(|(_, path)| std::fs::read_to_string(path).ok())
+    }
+
+    /// 用户目录中的模板：(片段名, 路径)
+    fn user_templates(&self) -> Vec<(String, PathBuf)> {
+        let Some(dir) = &self.user_dir else {
+            return Vec::new();
+        };
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .filter_map(|path| {
+                let name = path.file_stem()?.to_str()?.to_string();
+                Some((name, path))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_snippet_overrides_builtin_and_sets_cursor() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("readme.md"), "# Project\n\n{cursor}\n").unwrap();
+        std::fs::write(dir.path().join("handler.rs"), "fn handle() {}\n").unwrap();
+        let library = SnippetLibrary::new(Some(dir.path().to_path_buf()));
+
+        let readme = library.load("readme").unwrap();
+        assert_eq!(readme.body, "# Project\n\n\n");
+        assert_eq!(readme.cursor, "# Project\n\n".chars().count());
+
+        // 没有占位符时光标在末尾
+        let handler = library.load("handler").unwrap();
+        assert_eq!(handler.cursor, handler.body.chars().count());
+
+        let test = library.load("rust-test").unwrap();
+        assert!(!test.body.contains(CURSOR_MARKER));
+        assert!(test.body[..test.cursor].ends_with("fn "));
+
+        assert!(library.load("missing").is_none());
+        assert_eq!(library.names(), vec!["gitignore", "handler", "readme", "rust-test"]);
+    }
+}
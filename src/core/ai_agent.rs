@@ -14,6 +14,14 @@ pub struct AIAgentConfig {
     pub max_tool_rounds: usize,
     pub model: String,
     pub enable_search: bool,
+    /// explain 模式：工具执行前用通俗语言说明将要做什么
+    pub explain: bool,
+    /// 安全模式：只注册给 LLM 只读工具，写入/执行类工具被禁用
+    pub safe_mode: bool,
+    /// 内置工具注册完成后调用的自定义工具初始化函数（见 tools::plugins）
+    pub tool_plugins: Vec<crate::tools::ToolInitFn>,
+    /// 写文件类工具的项目根目录约束（与 FileCommandHandler 共用，遵循 --allow-outside）
+    pub path_guard: crate::fs::path_guard::PathGuard,
 }
 
 impl Default for AIAgentConfig {
@@ -22,6 +30,10 @@ impl Default for AIAgentConfig {
             max_tool_rounds: 50, // 默认最多 50 轮工具调用
             model: "grok-code-fast-1".to_string(),
             enable_search: false,
+            explain: false,
+            safe_mode: false,
+            tool_plugins: Vec::new(),
+            path_guard: crate::fs::path_guard::PathGuard::default(),
         }
     }
 }
@@ -32,6 +44,13 @@ pub struct AIAgent {
     tool_registry: Arc<Mutex<ToolRegistry>>,
     config: AIAgentConfig,
     todo_manager: Arc<tokio::sync::Mutex<crate::tools::todo_tool::TodoManager>>,
+    explain_callback: Option<Arc<dyn Fn(String) + Send + Sync>>,
+    /// 工具调用和结果实时发送到这里，UI 在执行期间就能显示进度
+    tool_events: Option<crate::ai::streaming::StreamHandler>,
+    /// 当前请求的扫描控制，请求取消时停止工具中正在进行的目录扫描
+    scan_control: Option<crate::tools::scan_control::ScanControl>,
+    /// 每次工具执行后触发的钩子（如 FileAuditHook 审计日志）
+    hooks: Arc<crate::core::HookManager>,
 }
 
 impl AIAgent {
@@ -48,42 +67,53 @@ impl AIAgent {
             tool_registry,
             config,
             todo_manager,
+            explain_callback: None,
+            tool_events: None,
+            scan_control: None,
+            hooks: Arc::new(crate::core::HookManager::new()),
         }
     }
 
+    /// 设置 explain 模式下接收工具说明的回调（例如显示到 UI）
+    pub fn with_explain_callback(mut self, callback: impl Fn(String) + Send + Sync + 'static) -> Self {
+        self.explain_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// 设置接收工具调用/结果事件的流处理器（通常是当前请求的 StreamHandler）
+    pub fn with_tool_events(mut self, handler: crate::ai::streaming::StreamHandler) -> Self {
+        self.tool_events = Some(handler);
+        self
+    }
+
+    /// 设置当前请求的扫描控制（每个请求一个，取消后不可复用）
+    pub fn with_scan_control(mut self, control: crate::tools::scan_control::ScanControl) -> Self {
+        self.scan_control = Some(control);
+        self
+    }
+
+    /// 设置工具执行后触发的钩子
+    pub fn with_hooks(mut self, hooks: Arc<crate::core::HookManager>) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
     /// 获取工具注册表（用于注册工具）
     pub fn tool_registry(&self) -> Arc<Mutex<ToolRegistry>> {
         self.tool_registry.clone()
     }
 
-    /// 注册所有标准工具
+    /// 注册所有标准工具，然后依次调用配置中的自定义工具初始化函数
     pub async fn register_standard_tools(&self) {
-        use crate::tools::*;
         let mut registry = self.tool_registry.lock().await;
+        registry.set_safe_mode(self.config.safe_mode);
 
-        // 文件工具
-        registry.register(Arc::new(FileReadTool));
-        registry.register(Arc::new(FileWriteTool));
-        registry.register(Arc::new(FileListTool));
-        registry.register(Arc::new(StrReplaceTool));
-
-        // 终端工具
-        registry.register(Arc::new(CommandExecuteTool));
-        registry.register(Arc::new(EnvironmentInfoTool));
-
-        // 代码工具
-        registry.register(Arc::new(CodeSearchTool));
-        registry.register(Arc::new(FunctionFinderTool));
-        registry.register(Arc::new(CodeStructureTool));
-
-        // 项目工具
-        registry.register(Arc::new(ProjectStructureTool));
-        registry.register(Arc::new(DependencyAnalyzerTool));
-        registry.register(Arc::new(BuildTool));
-
-        // Todo 工具（需要共享 manager）
-        registry.register(Arc::new(CreateTodoListTool::new(self.todo_manager.clone())));
-        registry.register(Arc::new(UpdateTodoListTool::new(self.todo_manager.clone())));
+        crate::tools::register_builtin_tools(&mut registry, self.todo_manager.clone(), &self.config.path_guard);
+        // 文件摘要需要模型客户端，随 Agent 注册
+        registry.register(Arc::new(crate::tools::SummarizeFileTool::new(self.llm_client.clone())));
+        for init in &self.config.tool_plugins {
+            init(&mut registry);
+        }
     }
 
     /// 处理用户消息（完整对话流程）
@@ -110,7 +140,7 @@ impl AIAgent {
             drop(registry);
 
             // 调用 LLM
-            let response = self.llm_client.generate_completion(
+            let (content, tool_calls) = self.llm_client.generate_completion_with_tools(
                 all_messages.clone(),
                 Some(self.config.model.clone()),
                 if !tool_definitions.is_empty() {
@@ -120,46 +150,82 @@ impl AIAgent {
                 },
             ).await?;
 
-            // 解析响应（简化版本）
-            match self.parse_llm_response(&response).await? {
-                LLMResponse::Content(content) => {
-                    all_messages.push(ChatMessage {
-                        role: "assistant".to_string(),
-                        content,
-                    });
-
-                    return Ok(AgentResponse {
-                        messages: all_messages,
-                        tool_calls: tool_calls_history,
-                        status: AgentStatus::Completed,
-                    });
+            if tool_calls.is_empty() {
+                all_messages.push(ChatMessage {
+                    role: "assistant".to_string(),
+                    content,
+                });
+
+                return Ok(AgentResponse {
+                    messages: all_messages,
+                    tool_calls: tool_calls_history,
+                    status: AgentStatus::Completed,
+                });
+            }
+
+            total_rounds += 1;
+            if !content.is_empty() {
+                all_messages.push(ChatMessage {
+                    role: "assistant".to_string(),
+                    content,
+                });
+            }
+
+            // 执行工具调用，把结果交给 LLM 继续处理
+            for (call, executed) in tool_calls.iter().zip(self.execute_tool_calls(tool_calls.clone()).await) {
+                all_messages.push(ChatMessage {
+                    role: "user".to_string(),
+                    content: format!(
+                        "<tool_result name=\"{}\">\n{}\n</tool_result>",
+                        executed.tool_name,
+                        crate::tools::tool_display::full_output(call, &executed.result)
+                    ),
+                });
+                tool_calls_history.push(executed);
+            }
+        }
+    }
+
+    /// 依次执行工具调用；设置了 tool_events 时，每次执行前后把调用和结果发送给 UI
+    pub async fn execute_tool_calls(&self, tool_calls: Vec<ToolCall>) -> Vec<ToolCallResult> {
+        let registry = self.tool_registry.lock().await;
+        let mut executed = Vec::with_capacity(tool_calls.len());
+        for tool_call in tool_calls {
+            let tool_name = tool_call.tool_name.clone();
+
+            // explain 模式：执行前先说明
+            let explanation = if self.config.explain {
+                let text = crate::tools::tool_explain::explain_tool_call(&tool_call);
+                if let Some(callback) = &self.explain_callback {
+                    callback(text.clone());
                 }
-                LLMResponse::ToolCalls(tool_calls) => {
-                    total_rounds += 1;
-
-                    // 执行工具调用
-                    let registry = self.tool_registry.lock().await;
-                    for tool_call in tool_calls {
-                        let tool_name = tool_call.tool_name.clone();
-                        let result = registry.execute(tool_call).await;
-
-                        tool_calls_history.push(ToolCallResult {
-                            tool_name,
-                            result: result.clone(),
-                        });
-
-                        // 将工具结果添加到消息中
-                        all_messages.push(ChatMessage {
-                            role: "assistant".to_string(),
-                            content: format!("Calling tool: {:?}", result),
-                        });
-                    }
-                    drop(registry);
-
-                    // 继续循环，让 LLM 处理工具结果
+                Some(text)
+            } else {
+                None
+            };
+
+            if let Some(events) = &self.tool_events {
+                let _ = events.send_tool_call(tool_call.clone());
+            }
+            let control = self.scan_control.clone().unwrap_or_default();
+            let result = registry.execute_with_control(tool_call.clone(), control).await;
+            if let Some(events) = &self.tool_events {
+                let _ = events.send_tool_result(result.clone());
+            }
+            // 钩子失败不影响工具结果；TUI 处于 raw 模式，失败通过 tool_events 交给 UI 显示
+            if let Err(e) = self.hooks.fire_after_tool_execution_hooks(&tool_call, &result).await {
+                if let Some(events) = &self.tool_events {
+                    let _ = events.send_warning(format!("工具执行后钩子失败 ({}): {}", tool_name, e));
                 }
             }
+
+            executed.push(ToolCallResult {
+                tool_name,
+                explanation,
+                result,
+            });
         }
+        executed
     }
 
     /// 流式处理用户消息
@@ -181,22 +247,6 @@ impl AIAgent {
 
         Ok(response)
     }
-
-    /// 解析 LLM 响应
-    async fn parse_llm_response(
-        &self,
-        response: &str,
-    ) -> Result<LLMResponse, Box<dyn std::error::Error + Send + Sync>> {
-        // 简化实现：实际应该从 LLM 响应中解析工具调用
-        // 这里模拟返回内容（实际应与 LLM API 格式匹配）
-        Ok(LLMResponse::Content(response.to_string()))
-    }
-}
-
-/// LLM 响应类型
-enum LLMResponse {
-    Content(String),
-    ToolCalls(Vec<ToolCall>),
 }
 
 /// Agent 响应
@@ -211,6 +261,8 @@ pub struct AgentResponse {
 #[derive(Debug, Clone)]
 pub struct ToolCallResult {
     pub tool_name: String,
+    /// explain 模式下的通俗说明
+    pub explanation: Option<String>,
     pub result: crate::tools::ToolResult,
 }
 
@@ -264,14 +316,16 @@ mod tests {
 
     #[tokio::test]
     async fn test_ai_agent_creation() {
-        use crate::ai::config::LLMConfig;
+        use crate::ai::config::{LLMConfig, LLMProvider};
 
         let config = LLMConfig::from_env().unwrap_or_else(|_| LLMConfig {
+            provider: LLMProvider::OpenAI,
             api_key: "test".to_string(),
             base_url: "https://api.openai.com/v1".to_string(),
             model: "gpt-3.5-turbo".to_string(),
             temperature: 0.7,
             max_tokens: 1000,
+            system_message_policy: Default::default(),
         });
 
         let llm_client = Arc::new(LLMClient::new(config));
@@ -282,7 +336,8 @@ mod tests {
         agent.register_standard_tools().await;
 
         // 验证工具已注册
-        let registry = agent.tool_registry().lock().await;
+        let tool_registry = agent.tool_registry();
+        let registry = tool_registry.lock().await;
         assert!(registry.count() > 0);
     }
 }
@@ -15,7 +15,7 @@ use crate::core::{
     TokenCalculator, ContextWindowOptimizer, MessageHistory, HookManager,
 };
 use crate::core::tool_executor::ToolExecutor;
-use crate::core::conversation_engine::ProcessedResponse;
+use crate::core::conversation_engine::{LineRange, ProcessedResponse};
 use std::collections::HashMap;
 
 /// 对话响应
@@ -167,11 +167,14 @@ impl ChatOrchestrator {
             // 文件提及
             let parts: Vec<&str> = input.split_whitespace().collect();
             let mut paths = Vec::new();
+            let mut line_ranges = Vec::new();
             let mut query = String::new();
             
             for part in parts {
                 if part.starts_with("@") {
-                    paths.push(part[1..].to_string());
+                    let (path, range) = LineRange::split_mention(&part[1..]);
+                    paths.push(path.to_string());
+                    line_ranges.push(range);
                 } else {
                     query.push_str(part);
                     query.push(' ');
@@ -180,6 +183,7 @@ impl ChatOrchestrator {
             
             Ok(UserIntent::FileMention {
                 paths,
+                line_ranges,
                 query: query.trim().to_string(),
             })
         } else if input.contains("review") || input.contains("审查") {
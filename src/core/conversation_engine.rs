@@ -13,14 +13,18 @@ use chrono::{DateTime, Local};
 use crate::core::{RetryHandler, RetryConfig, CompositeRouter};
 use crate::core::tool_executor::ToolExecutor;
 use crate::core::HookManager;
-use crate::ai::client::LLMClient;
+use crate::core::message_history::{Message, MessageRole};
+use crate::core::token_calculator::TokenCalculator;
+use crate::ai::client::{ChatMessage, LLMClient};
 
 /// 用户意图类型
 #[derive(Debug, Clone)]
 pub enum UserIntent {
-    /// 文件提及：@path/to/file query
+    /// 文件提及：@path/to/file query，或 @path:10-20 只附加指定行
     FileMention {
         paths: Vec<String>,
+        /// 与 paths 一一对应，None 表示整个文件
+        line_ranges: Vec<Option<LineRange>>,
         query: String,
     },
     
@@ -55,6 +59,33 @@ pub enum UserIntent {
     },
 }
 
+/// 提及中的行范围（从 1 开始，包含两端）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl LineRange {
+    /// 拆分 `path:10-20` 或 `path:10`；后缀不是行号时整个作为路径
+    pub fn split_mention(mention: &str) -> (&str, Option<LineRange>) {
+        let Some((path, suffix)) = mention.rsplit_once(':') else {
+            return (mention, None);
+        };
+        let (start, end) = suffix.split_once('-').unwrap_or((suffix, suffix));
+        match (start.parse::<usize>(), end.parse::<usize>()) {
+            (Ok(start), Ok(end)) if !path.is_empty() => (path, Some(LineRange { start, end })),
+            _ => (mention, None),
+        }
+    }
+}
+
+impl std::fmt::Display for LineRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
 /// 文件内容
 #[derive(Debug, Clone)]
 pub struct FileContent {
@@ -62,6 +93,57 @@ pub struct FileContent {
     pub content: String,
     pub language: String,
     pub line_count: usize,
+    /// 只附加了部分行时的行范围，None 表示整个文件
+    pub range: Option<LineRange>,
+}
+
+impl FileContent {
+    pub fn new(path: impl Into<String>, content: impl Into<String>) -> Self {
+        let path = path.into();
+        let content = content.into();
+        let language = std::path::Path::new(&path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("text")
+            .to_string();
+        let line_count = content.lines().count();
+        Self { path, content, language, line_count, range: None }
+    }
+
+    /// 只保留 range 内的行；范围无效或超出文件长度时返回说明
+    pub fn with_range(path: impl Into<String>, content: &str, range: LineRange) -> Result<Self, String> {
+        let path = path.into();
+        let total = content.lines().count();
+        if range.start == 0 || range.start > range.end {
+            return Err(format!("{}:{} 不是有效的行范围", path, range));
+        }
+        if range.end > total {
+            return Err(format!("{} 只有 {} 行，无法附加第 {} 行", path, total, range));
+        }
+        let slice: Vec<&str> = content.lines().skip(range.start - 1).take(range.end - range.start + 1).collect();
+        let mut file = Self::new(path, slice.join("\n") + "\n");
+        file.range = Some(range);
+        Ok(file)
+    }
+
+    /// 渲染为发送给 LLM 的文件块；line_numbers 为 true 时每行带 `N: ` 前缀（原文件中的行号）
+    pub fn render(&self, line_numbers: bool) -> String {
+        let start_line = self.range.map_or(1, |range| range.start);
+        let body = if line_numbers {
+            self.content
+                .lines()
+                .enumerate()
+                .map(|(i, line)| format!("{}: {}", start_line + i, line))
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            self.content.clone()
+        };
+        match self.range {
+            Some(range) => format!("\n\n<file_content path=\"{}\" lines=\"{}\">\n{}\n</file_content>\n", self.path, range, body),
+            None => format!("\n\n<file_content path=\"{}\">\n{}\n</file_content>\n", self.path, body),
+        }
+    }
 }
 
 /// 对话上下文
@@ -70,9 +152,13 @@ pub struct ConversationContext {
     pub user_input: String,
     pub intent: UserIntent,
     pub files: Vec<FileContent>,
+    /// 附加文件时是否带行号（模型引用行号更准确）
+    pub line_numbers: bool,
     pub rules: String,
     pub timestamp: DateTime<Local>,
     pub metadata: HashMap<String, String>,
+    /// 这一轮的回复，保存到历史后作为后续请求中的 assistant 消息
+    pub response: Option<String>,
 }
 
 impl ConversationContext {
@@ -81,9 +167,11 @@ impl ConversationContext {
             user_input,
             intent,
             files: Vec::new(),
+            line_numbers: false,
             rules: String::new(),
             timestamp: Local::now(),
             metadata: HashMap::new(),
+            response: None,
         }
     }
     
@@ -92,6 +180,16 @@ impl ConversationContext {
         self
     }
     
+    pub fn with_line_numbers(mut self, enabled: bool) -> Self {
+        self.line_numbers = enabled;
+        self
+    }
+
+    /// 按上下文的行号设置渲染所有附加文件
+    pub fn render_files(&self) -> String {
+        self.files.iter().map(|file| file.render(self.line_numbers)).collect()
+    }
+
     pub fn with_rules(mut self, rules: String) -> Self {
         self.rules = rules;
         self
@@ -169,16 +267,19 @@ impl IntentRecognizer {
     }
     
     fn extract_file_mention(input: &str) -> Option<UserIntent> {
-        // 简单的 @path 提取
+        // 简单的 @path 提取，支持 @path:10-20 行范围
         let mut paths = Vec::new();
+        let mut line_ranges = Vec::new();
         let mut query = input.to_string();
         
         for part in input.split_whitespace() {
             if part.starts_with('@') {
-                let path = part.trim_start_matches('@').to_string();
-                if !path.is_empty() {
-                    query = query.replace(&format!("@{}", path), "");
-                    paths.push(path);
+                let mention = part.trim_start_matches('@');
+                if !mention.is_empty() {
+                    query = query.replace(&format!("@{}", mention), "");
+                    let (path, range) = LineRange::split_mention(mention);
+                    paths.push(path.to_string());
+                    line_ranges.push(range);
                 }
             }
         }
@@ -186,6 +287,7 @@ impl IntentRecognizer {
         if !paths.is_empty() {
             Some(UserIntent::FileMention {
                 paths,
+                line_ranges,
                 query: query.trim().to_string(),
             })
         } else {
@@ -307,6 +409,54 @@ impl ContextManager {
 /// 响应处理器
 pub struct ResponseProcessor;
 
+/// 响应后处理规则：去掉模型常见的客套开场白，并在用户要代码时拆掉包裹整条响应的代码围栏
+#[derive(Debug, Clone)]
+pub struct PostProcessConfig {
+    pub enabled: bool,
+    /// 去掉首行的客套话（如 "Sure! Here's the code:"）
+    pub strip_prefixes: bool,
+    /// 整条响应只有一个代码块时，只保留代码本身
+    pub unwrap_code_fence: bool,
+    /// 视为客套话的首行（已编译，不区分大小写，须匹配整行），用 with_boilerplate_patterns 设置
+    pub boilerplate_patterns: Vec<regex::Regex>,
+}
+
+/// 默认的客套话首行规则
+pub const DEFAULT_BOILERPLATE_PATTERNS: &[&str] = &[
+    r"(?:sure|certainly|of course|absolutely|okay)[!.,]?",
+    r"(?:(?:sure|certainly|of course|absolutely|okay)[!.,]?\s*)?here you go[:.!]?",
+    r"(?:(?:sure|certainly|of course|absolutely|okay)[!.,]?\s*)?here(?:'s| is| are) (?:the |your |an? |my )?(?:\w+ ){0,3}(?:code|implementation|solution|version|example|fix|snippet|function|script)s?(?: \w+){0,4}[:.!]?",
+    r"(?:好的|当然|没问题)[！!，,。.]?",
+    r"(?:(?:好的|当然|没问题)[！!，,。.]?\s*)?以下是[^。！？!?:：]*[:：]",
+];
+
+impl PostProcessConfig {
+    /// 替换客套话规则；任一规则无法编译时返回错误并指出是哪一条
+    pub fn with_boilerplate_patterns(mut self, patterns: &[&str]) -> Result<Self, String> {
+        self.boilerplate_patterns = patterns
+            .iter()
+            .map(|pattern| {
+                regex::Regex::new(&format!("(?i)^(?:{})$", pattern))
+                    .map_err(|e| format!("无效的客套话规则 {:?}: {}", pattern, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(self)
+    }
+}
+
+impl Default for PostProcessConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            strip_prefixes: true,
+            unwrap_code_fence: true,
+            boilerplate_patterns: Vec::new(),
+        }
+        .with_boilerplate_patterns(DEFAULT_BOILERPLATE_PATTERNS)
+        .expect("默认客套话规则可以编译")
+    }
+}
+
 impl ResponseProcessor {
     pub fn process(response: &str) -> ProcessedResponse {
         ProcessedResponse {
@@ -317,6 +467,50 @@ impl ResponseProcessor {
             thinking: Self::extract_thinking(response),
         }
     }
+
+    /// 按后处理规则处理响应，content 为清理后的内容
+    pub fn process_with(response: &str, config: &PostProcessConfig, wants_code: bool) -> ProcessedResponse {
+        let mut processed = Self::process(response);
+        processed.content = Self::post_process(response, config, wants_code);
+        processed
+    }
+
+    /// 规则化的响应清理
+    pub fn post_process(response: &str, config: &PostProcessConfig, wants_code: bool) -> String {
+        if !config.enabled {
+            return response.to_string();
+        }
+
+        let mut content = response.trim();
+
+        if config.strip_prefixes {
+            let (first_line, rest) = content.split_once('\n').unwrap_or((content, ""));
+            let first_line = first_line.trim();
+            // 只去掉整行都是客套话的首行，带有实际内容的首行原样保留
+            let is_boilerplate = config.boilerplate_patterns.iter().any(|re| re.is_match(first_line));
+            if is_boilerplate && !rest.trim().is_empty() {
+                content = rest.trim();
+            }
+        }
+
+        if config.unwrap_code_fence && wants_code {
+            if let Some(code) = Self::unwrap_single_fence(content) {
+                return code.to_string();
+            }
+        }
+
+        content.to_string()
+    }
+
+    /// 响应恰好是一个完整的围栏代码块时返回其中的代码
+    fn unwrap_single_fence(content: &str) -> Option<&str> {
+        let inner = content.strip_prefix("```")?.strip_suffix("```")?;
+        let (_language, code) = inner.split_once('\n')?;
+        if code.contains("```") {
+            return None;
+        }
+        Some(code.trim_end_matches('\n'))
+    }
     
     fn extract_modifications(response: &str) -> Vec<CodeModification> {
         // 简单的修改检测
@@ -353,15 +547,22 @@ impl ResponseProcessor {
     }
     
     fn extract_key_points(response: &str) -> Vec<String> {
+        use crate::ui::markdown::{parse_list_item, ListNesting};
+
+        // 列表项作为关键点，嵌套项按层级缩进两格
         let mut points = Vec::new();
-        
-        // 简单的关键点提取
+        let mut nesting = ListNesting::default();
         for line in response.lines() {
-            if line.starts_with("- ") || line.starts_with("• ") {
-                points.push(line.trim_start_matches("- ").trim_start_matches("• ").to_string());
+            match parse_list_item(line) {
+                Some(item) => {
+                    let level = nesting.level(item.indent);
+                    points.push(format!("{}{}", "  ".repeat(level), item.text));
+                }
+                None if !line.trim().is_empty() => nesting.reset(),
+                None => {}
             }
         }
-        
+
         points
     }
     
@@ -382,6 +583,7 @@ pub struct ConversationEngine {
     pub intent_recognizer: IntentRecognizer,
     pub context_manager: ContextManager,
     pub response_processor: ResponseProcessor,
+    pub post_process: PostProcessConfig,
     pub conversation_history: Vec<ConversationContext>,
     
     // 新增：完整流程所需的组件
@@ -398,6 +600,7 @@ impl ConversationEngine {
             intent_recognizer: IntentRecognizer,
             context_manager: ContextManager,
             response_processor: ResponseProcessor,
+            post_process: PostProcessConfig::default(),
             conversation_history: Vec::new(),
             retry_handler: RetryHandler::new(RetryConfig::default()),
             router: CompositeRouter::new(),
@@ -422,6 +625,11 @@ impl ConversationEngine {
         self
     }
     
+    pub fn with_post_process(mut self, config: PostProcessConfig) -> Self {
+        self.post_process = config;
+        self
+    }
+    
     /// 处理用户输入的主方法
     pub fn process_input(&mut self, input: String) -> ConversationContext {
         // 1. 识别意图
@@ -436,9 +644,10 @@ impl ConversationEngine {
         context
     }
     
-    /// 处理 LLM 响应
-    pub fn process_response(&self, response: &str) -> ProcessedResponse {
-        ResponseProcessor::process(response)
+    /// 处理 LLM 响应；intent 是本轮输入的意图（本轮上下文在处理完响应后才保存到历史）
+    pub fn process_response(&self, response: &str, intent: &UserIntent) -> ProcessedResponse {
+        let wants_code = matches!(intent, UserIntent::CodeGeneration { .. });
+        ResponseProcessor::process_with(response, &self.post_process, wants_code)
     }
     
     /// 获取对话历史
@@ -502,7 +711,7 @@ impl ConversationEngine {
         self.validate_response(&response_text)?;
         
         // 7. 处理响应
-        let mut processed = self.process_response(&response_text);
+        let mut processed = self.process_response(&response_text, &intent);
         
         // 8-9. 检测并执行工具调用（递归）
         processed = self.execute_tools_recursive(processed)
@@ -514,6 +723,8 @@ impl ConversationEngine {
             .map_err(|e| format!("After model hook failed: {}", e))?;
         
         // 11. 保存到历史并返回
+        let mut context = context;
+        context.response = Some(processed.content.clone());
         self.conversation_history.push(context);
         
         Ok(processed)
@@ -525,14 +736,52 @@ impl ConversationEngine {
             return Err("LLM client not configured".to_string());
         }
         
-        let _llm_client = self.llm_client.as_ref().unwrap();
-        
-        // 这里应该调用实际的 LLM 客户端
-        // 为了演示，返回模拟响应
-        Ok(format!(
-            "Response to: {}\n\nThis is a mock response from the LLM.",
-            context.user_input
-        ))
+        let llm_client = self.llm_client.as_ref().unwrap();
+        llm_client
+            .generate_completion(self.request_messages(context), None, None)
+            .await
+            .map_err(|e| format!("LLM request failed: {}", e))
+    }
+
+    /// 发送给 LLM 的消息：按上下文窗口裁剪后的 build_messages
+    pub fn request_messages(&self, context: &ConversationContext) -> Vec<ChatMessage> {
+        let mut messages = self.build_messages(context);
+        self.fit_to_context(&mut messages);
+        messages
+            .into_iter()
+            .map(|message| ChatMessage { role: message.role.to_string(), content: message.content })
+            .collect()
+    }
+    
+    /// 本次请求的消息：规则（system）+ 之前各轮的输入和回复 + 当前输入及附加文件
+    fn build_messages(&self, context: &ConversationContext) -> Vec<Message> {
+        let mut messages = Vec::new();
+        if !context.rules.is_empty() {
+            messages.push(Message::new(MessageRole::System, context.rules.clone()));
+        }
+        for previous in &self.conversation_history {
+            messages.push(Message::new(MessageRole::User, previous.user_input.clone()));
+            if let Some(response) = &previous.response {
+                messages.push(Message::new(MessageRole::Assistant, response.clone()));
+            }
+        }
+        messages.push(Message::new(
+            MessageRole::User,
+            format!("{}{}", context.render_files(), context.user_input),
+        ));
+        messages
+    }
+
+    /// 按 LLM 客户端的模型和 max_tokens 裁剪消息，返回丢弃的条数；未配置客户端时不裁剪
+    pub fn fit_to_context(&self, messages: &mut Vec<Message>) -> usize {
+        let Some(client) = &self.llm_client else {
+            return 0;
+        };
+        let config = client.config();
+        let calculator = TokenCalculator::from_model_name(&config.model)
+            .with_output_reserve(config.max_tokens as usize);
+        let model = calculator.get_model_info().clone();
+        calculator.trim_to_fit(messages, &model)
     }
     
     /// 验证响应
@@ -615,6 +864,35 @@ mod tests {
             _ => panic!("Expected FileMention intent"),
         }
     }
+
+    #[test]
+    fn test_file_mention_line_range_attaches_slice() {
+        let intent = IntentRecognizer::recognize("@src/main.rs:10-20 这段在做什么");
+        let UserIntent::FileMention { paths, line_ranges, query } = intent else {
+            panic!("Expected FileMention intent");
+        };
+        assert_eq!(paths, vec!["src/main.rs"]);
+        assert_eq!(line_ranges, vec![Some(LineRange { start: 10, end: 20 })]);
+        assert_eq!(query, "这段在做什么");
+
+        let source = std::fs::read_to_string("src/main.rs").unwrap();
+        let file = FileContent::with_range("src/main.rs", &source, LineRange { start: 10, end: 20 }).unwrap();
+        let expected: Vec<&str> = source.lines().skip(9).take(11).collect();
+        assert_eq!(file.content.lines().collect::<Vec<_>>(), expected);
+        let rendered = file.render(true);
+        assert!(rendered.contains("lines=\"10-20\""));
+        assert!(rendered.contains(&format!("\n10: {}\n", expected[0])));
+        assert!(rendered.contains(&format!("\n20: {}\n", expected[10])));
+        assert!(!rendered.contains("\n21: "));
+
+        let total = source.lines().count();
+        let err = FileContent::with_range("src/main.rs", &source, LineRange { start: total, end: total + 5 }).unwrap_err();
+        assert!(err.contains(&format!("只有 {} 行", total)));
+        assert!(FileContent::with_range("src/main.rs", &source, LineRange { start: 20, end: 10 }).is_err());
+
+        assert_eq!(LineRange::split_mention("notes:todo.md"), ("notes:todo.md", None));
+        assert_eq!(LineRange::split_mention("lib.rs:7"), ("lib.rs", Some(LineRange { start: 7, end: 7 })));
+    }
     
     #[test]
     fn test_intent_recognition_command() {
@@ -650,4 +928,132 @@ mod tests {
         assert_eq!(engine.conversation_history.len(), 1);
         assert!(engine.get_last_context().is_some());
     }
+
+    #[test]
+    fn test_fit_to_context_reserves_max_tokens() {
+        use crate::ai::config::LLMConfig;
+
+        // gpt-4 窗口 8192，预留 8000 后只剩 192 tokens 给提示
+        let config = LLMConfig { model: "gpt-4".to_string(), max_tokens: 8000, ..LLMConfig::default() };
+        let engine = ConversationEngine::new().with_llm_client(Arc::new(LLMClient::new(config)));
+        let mut messages = vec![
+            Message::new(MessageRole::System, "rules"),
+            Message::new(MessageRole::User, "old ".repeat(100)),
+            Message::new(MessageRole::Assistant, "reply ".repeat(200)),
+            Message::new(MessageRole::User, "current question"),
+        ];
+
+        assert_eq!(engine.fit_to_context(&mut messages), 2);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].content, "current question");
+        assert_eq!(ConversationEngine::new().fit_to_context(&mut messages), 0);
+    }
+
+    #[test]
+    fn test_request_messages_include_replies_and_are_trimmed() {
+        use crate::ai::config::LLMConfig;
+
+        let config = LLMConfig { model: "gpt-4".to_string(), max_tokens: 8000, ..LLMConfig::default() };
+        let mut engine = ConversationEngine::new().with_llm_client(Arc::new(LLMClient::new(config)));
+        let mut earlier = ContextManager::build("what is a trait", &UserIntent::Chat { query: String::new(), context_files: vec![] });
+        earlier.response = Some("an interface".to_string());
+        engine.conversation_history.push(earlier);
+        let current = ContextManager::build("and a trait object?", &UserIntent::Chat { query: String::new(), context_files: vec![] });
+
+        let roles: Vec<String> = engine.request_messages(&current).into_iter().map(|m| m.role).collect();
+        assert_eq!(roles, vec!["user", "assistant", "user"]);
+
+        // 超出窗口时发送的是裁剪后的列表
+        let mut long = ContextManager::build(&"old ".repeat(300), &UserIntent::Chat { query: String::new(), context_files: vec![] });
+        long.response = Some("reply ".repeat(300));
+        engine.conversation_history.insert(0, long);
+        let sent = engine.request_messages(&current);
+        assert!(sent.len() < engine.build_messages(&current).len());
+        assert_eq!(sent.last().unwrap().content, "and a trait object?");
+    }
+
+    #[test]
+    fn test_post_process_unwraps_code_when_enabled() {
+        let config = PostProcessConfig { enabled: true, ..Default::default() };
+        let response = "Sure, here you go:\n```rust\nfn main() {\n    println!(\"hi\");\n}\n```";
+
+        let cleaned = ResponseProcessor::post_process(response, &config, true);
+        assert_eq!(cleaned, "fn main() {\n    println!(\"hi\");\n}");
+    }
+
+    #[test]
+    fn test_post_process_disabled_or_not_code_request() {
+        let response = "Sure, here you go:\n```rust\nfn main() {}\n```";
+
+        let disabled = PostProcessConfig::default();
+        assert_eq!(ResponseProcessor::post_process(response, &disabled, true), response);
+
+        // 没有要求代码时只去掉客套话，保留代码围栏
+        let enabled = PostProcessConfig { enabled: true, ..Default::default() };
+        assert_eq!(
+            ResponseProcessor::post_process(response, &enabled, false),
+            "```rust\nfn main() {}\n```"
+        );
+
+        // 多个代码块不拆
+        let multi = "```rust\na\n```\ntext\n```rust\nb\n```";
+        assert_eq!(ResponseProcessor::post_process(multi, &enabled, true), multi);
+    }
+
+    #[test]
+    fn test_invalid_boilerplate_pattern_is_reported() {
+        let err = PostProcessConfig::default().with_boilerplate_patterns(&["sure", "(unclosed"]).unwrap_err();
+        assert!(err.contains("(unclosed"), "{}", err);
+
+        let config = PostProcessConfig { enabled: true, ..Default::default() }
+            .with_boilerplate_patterns(&["alright"])
+            .unwrap();
+        assert_eq!(ResponseProcessor::post_process("Alright\nok", &config, false), "ok");
+        assert_eq!(ResponseProcessor::post_process("Sure!\nok", &config, false), "Sure!\nok");
+    }
+
+    #[test]
+    fn test_process_response_uses_current_intent() {
+        let engine = ConversationEngine::new().with_post_process(PostProcessConfig { enabled: true, ..Default::default() });
+        let mut engine = engine;
+        // 上一轮是普通聊天
+        engine.process_input("what is a trait?".to_string());
+
+        let response = "```rust\nfn main() {}\n```";
+        let code = UserIntent::CodeGeneration { description: "write main".to_string(), language: None };
+        assert_eq!(engine.process_response(response, &code).content, "fn main() {}");
+        let chat = UserIntent::Chat { query: "explain".to_string(), context_files: vec![] };
+        assert_eq!(engine.process_response(response, &chat).content, response);
+    }
+
+    #[test]
+    fn test_post_process_keeps_first_line_with_content() {
+        let config = PostProcessConfig { enabled: true, ..Default::default() };
+
+        for response in [
+            "Sure. The bug is in the loop bound.\nChange `<=` to `<`.",
+            "Here's why the test fails: the lock is held across an await.\nDrop the guard first.",
+            "Of course the borrow checker rejects this.\nThe reference outlives the vector.",
+            "好的问题。这里的生命周期不够长。\n需要返回拥有所有权的值。",
+        ] {
+            assert_eq!(ResponseProcessor::post_process(response, &config, false), response);
+        }
+
+        for (response, cleaned) in [
+            ("Certainly!\nUse `Vec::with_capacity`.", "Use `Vec::with_capacity`."),
+            ("Sure! Here's the updated code:\n`let x = 1;`", "`let x = 1;`"),
+            ("好的，以下是修改后的代码：\n`let x = 1;`", "`let x = 1;`"),
+        ] {
+            assert_eq!(ResponseProcessor::post_process(response, &config, false), cleaned);
+        }
+    }
+
+    #[test]
+    fn test_key_points_keep_nested_list_levels() {
+        let response = "要点：\n- 配置\n  - 设置 API Key\n  1. 选择模型\n• 运行\n说明文字\n    - 独立列表";
+        assert_eq!(
+            ResponseProcessor::extract_key_points(response),
+            vec!["配置", "  设置 API Key", "  选择模型", "运行", "独立列表"]
+        );
+    }
 }
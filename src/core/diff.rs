@@ -0,0 +1,194 @@
+/// 逐行差异
+/// 基于最长公共子序列计算一次差异并分组为 hunk，FileDiff / CodeDiff、格式化工具和聊天中的 diff 显示共用同一结果
+
+/// hunk 前后保留的上下文行数（与 `diff -u` 一致）
+pub const DEFAULT_CONTEXT_LINES: usize = 3;
+
+/// 差异中的一行
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine {
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+impl DiffLine {
+    pub fn is_change(&self) -> bool {
+        !matches!(self, DiffLine::Context(_))
+    }
+
+    /// unified diff 格式的一行（不含换行）
+    pub fn render(&self) -> String {
+        match self {
+            DiffLine::Context(line) => format!(" {}", line),
+            DiffLine::Removed(line) => format!("-{}", line),
+            DiffLine::Added(line) => format!("+{}", line),
+        }
+    }
+}
+
+/// 一组相邻的改动及其上下文；行号从 1 开始
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+impl DiffHunk {
+    /// `@@ -1,3 +1,4 @@`
+    pub fn header(&self) -> String {
+        format!("@@ -{},{} +{},{} @@", self.old_start, self.old_len, self.new_start, self.new_len)
+    }
+}
+
+/// 逐行比较，返回包含未变化行的完整序列
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let (n, m) = (old_lines.len(), new_lines.len());
+
+    // lcs[i][j] = old[i..] 与 new[j..] 的最长公共子序列长度
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_lines[i] == new_lines[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old_lines[i] == new_lines[j] {
+            lines.push(DiffLine::Context(old_lines[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(DiffLine::Removed(old_lines[i].to_string()));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new_lines[j].to_string()));
+            j += 1;
+        }
+    }
+    lines
+}
+
+/// 把差异分组为 hunk，每个 hunk 前后保留 context 行未变化的内容；相距不超过 2 * context 的改动合并为一个 hunk
+pub fn compute_hunks(old: &str, new: &str, context: usize) -> Vec<DiffHunk> {
+    let lines = diff_lines(old, new);
+    let changes: Vec<usize> = lines.iter().enumerate().filter(|(_, l)| l.is_change()).map(|(i, _)| i).collect();
+    let Some(&first) = changes.first() else {
+        return Vec::new();
+    };
+
+    // 每个 hunk 覆盖的 lines 下标范围
+    let mut ranges = vec![(first.saturating_sub(context), first)];
+    for &index in &changes[1..] {
+        let last = ranges.last_mut().expect("ranges is not empty");
+        if index - last.1 <= 2 * context + 1 {
+            last.1 = index;
+        } else {
+            ranges.push((index.saturating_sub(context), index));
+        }
+    }
+
+    // 每个下标之前的旧/新行数，用于计算起始行号
+    let mut old_before = Vec::with_capacity(lines.len());
+    let mut new_before = Vec::with_capacity(lines.len());
+    let (mut old_count, mut new_count) = (0, 0);
+    for line in &lines {
+        old_before.push(old_count);
+        new_before.push(new_count);
+        match line {
+            DiffLine::Context(_) => {
+                old_count += 1;
+                new_count += 1;
+            }
+            DiffLine::Removed(_) => old_count += 1,
+            DiffLine::Added(_) => new_count += 1,
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, last_change)| {
+            let end = (last_change + context + 1).min(lines.len());
+            let hunk_lines = lines[start..end].to_vec();
+            let old_len = hunk_lines.iter().filter(|l| !matches!(l, DiffLine::Added(_))).count();
+            let new_len = hunk_lines.iter().filter(|l| !matches!(l, DiffLine::Removed(_))).count();
+            DiffHunk {
+                // 与 diff -u 一致：范围为空时起始行号是前一行
+                old_start: old_before[start] + usize::from(old_len > 0),
+                old_len,
+                new_start: new_before[start] + usize::from(new_len > 0),
+                new_len,
+                lines: hunk_lines,
+            }
+        })
+        .collect()
+}
+
+/// 渲染为 unified diff 的 hunk 部分（不含 ---/+++ 文件头）
+pub fn render_unified(hunks: &[DiffHunk]) -> String {
+    let mut out = String::new();
+    for hunk in hunks {
+        out.push_str(&hunk.header());
+        out.push('\n');
+        for line in &hunk.lines {
+            out.push_str(&line.render());
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hunks_match_rendered_unified_diff() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\n";
+        let hunks = compute_hunks(old, new, DEFAULT_CONTEXT_LINES);
+
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(
+            hunks[0],
+            DiffHunk {
+                old_start: 1,
+                old_len: 5,
+                new_start: 1,
+                new_len: 5,
+                lines: vec![
+                    DiffLine::Context("a".to_string()),
+                    DiffLine::Removed("b".to_string()),
+                    DiffLine::Added("B".to_string()),
+                    DiffLine::Context("c".to_string()),
+                    DiffLine::Context("d".to_string()),
+                    DiffLine::Context("e".to_string()),
+                ],
+            }
+        );
+        assert_eq!((hunks[1].old_start, hunks[1].old_len, hunks[1].new_start, hunks[1].new_len), (10, 3, 10, 4));
+
+        assert_eq!(
+            render_unified(&hunks),
+            "@@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n@@ -10,3 +10,4 @@\n j\n k\n l\n+m\n"
+        );
+        assert!(compute_hunks("same\n", "same\n", DEFAULT_CONTEXT_LINES).is_empty());
+    }
+
+    #[test]
+    fn test_hunk_for_created_file() {
+        let hunks = compute_hunks("", "fn main() {}\n", DEFAULT_CONTEXT_LINES);
+        assert_eq!(hunks[0].header(), "@@ -0,0 +1,1 @@");
+    }
+}
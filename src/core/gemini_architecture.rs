@@ -257,18 +257,24 @@ impl RoutingStrategy for IntentBasedStrategy {
         _input: &str,
         context: &str,
     ) -> Result<RoutingDecision, String> {
-        let model = if context.contains("review") || context.contains("debug") {
+        let needs_reasoning = context.contains("review") || context.contains("debug");
+        let model = if needs_reasoning {
             "gemini-2.5-pro"
         } else {
             "gemini-2.0-flash"
         };
+        let reasoning = if needs_reasoning {
+            format!("Selected {} based on intent: conversation mentions review/debug", model)
+        } else {
+            format!("Selected {} based on intent: no review/debug intent in conversation", model)
+        };
 
         Ok(RoutingDecision {
             model: model.to_string(),
             metadata: RoutingMetadata {
                 source: "intent_based".to_string(),
                 latency_ms: 0,
-                reasoning: format!("Selected {} based on intent", model),
+                reasoning,
             },
         })
     }
@@ -294,9 +300,11 @@ impl CompositeRouter {
         input: &str,
         context: &str,
     ) -> Result<RoutingDecision, String> {
+        let started = std::time::Instant::now();
         for strategy in &self.strategies {
             match strategy.route(input, context).await {
-                Ok(decision) => {
+                Ok(mut decision) => {
+                    decision.metadata.latency_ms = started.elapsed().as_millis() as u64;
                     return Ok(decision);
                 }
                 Err(_) => continue,
@@ -315,6 +323,46 @@ impl CompositeRouter {
     }
 }
 
+/// 一轮对话的路由记录（/why 显示）
+#[derive(Debug, Clone)]
+pub struct RoutingReport {
+    pub decision: RoutingDecision,
+    /// 路由时预估的输入 token 数
+    pub estimated_tokens: usize,
+    /// LLM 调用尝试次数（1 表示没有重试）
+    pub attempts: u32,
+    /// LLM 调用耗时（毫秒）
+    pub latency_ms: u64,
+}
+
+impl RoutingReport {
+    /// 模型在成本与性能之间的取舍
+    fn tradeoff(model: &str) -> &'static str {
+        if model.contains("pro") {
+            "推理能力更强，但成本更高、速度较慢"
+        } else if model.contains("flash") {
+            "速度快、成本低，适合日常问答"
+        } else {
+            "未知（非内置模型）"
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let metadata = &self.decision.metadata;
+        format!(
+            "🧭 上一轮路由:\n  模型: {}\n  策略: {}\n  决定因素: {}\n  Token 预估: {}\n  成本/性能: {}\n  重试次数: {}\n  路由耗时: {} ms\n  响应耗时: {} ms",
+            self.decision.model,
+            metadata.source,
+            metadata.reasoning,
+            self.estimated_tokens,
+            Self::tradeoff(&self.decision.model),
+            self.attempts.saturating_sub(1),
+            metadata.latency_ms,
+            self.latency_ms,
+        )
+    }
+}
+
 // ============================================================================
 // 4. 对话轮次管理
 // ============================================================================
@@ -405,6 +453,7 @@ pub struct GeminiArchitecture {
     llm_client: Option<Arc<LLMClient>>,
     prompt_builder: PromptBuilder,
     turn_counter: u32,
+    last_routing: Option<RoutingReport>,
 }
 
 impl GeminiArchitecture {
@@ -417,6 +466,7 @@ impl GeminiArchitecture {
             llm_client: None,
             prompt_builder: PromptBuilder::new(),
             turn_counter: 0,
+            last_routing: None,
         }
     }
 
@@ -428,6 +478,40 @@ impl GeminiArchitecture {
         self.prompt_builder = builder;
     }
 
+    /// 最近一轮的路由记录
+    pub fn last_routing(&self) -> Option<&RoutingReport> {
+        self.last_routing.as_ref()
+    }
+
+    /// 记录没有经过路由器的一轮（直接使用配置的模型）
+    pub fn record_routing(&mut self, report: RoutingReport) {
+        self.last_routing = Some(report);
+    }
+
+    /// 回复完成后补上最近一轮的响应耗时
+    pub fn finish_routing(&mut self, latency_ms: u64) {
+        if let Some(report) = self.last_routing.as_mut() {
+            report.latency_ms = latency_ms;
+        }
+    }
+
+    /// 为本轮选择模型并记录路由原因
+    pub async fn route_turn(&mut self, user_input: &str) -> Result<RoutingDecision, String> {
+        let context = self.history.get_context();
+        let decision = self.router.route(user_input, &context).await?;
+        let estimated_tokens = crate::core::TokenCalculator::from_model_name(&decision.model)
+            .estimate_prompt(user_input, &[context])
+            .total_tokens;
+
+        self.last_routing = Some(RoutingReport {
+            decision: decision.clone(),
+            estimated_tokens,
+            attempts: 0,
+            latency_ms: 0,
+        });
+        Ok(decision)
+    }
+
     fn build_chat_messages(&self, user_input: &str) -> Vec<ChatMessage> {
         let prompt_messages: Vec<PromptMessage> = self.prompt_builder.build_messages(user_input);
         prompt_messages
@@ -450,14 +534,19 @@ impl GeminiArchitecture {
         let mut turn = Turn::new(self.turn_counter, user_input.clone());
 
         // 1. 路由决策
-        let routing_decision = self.router.route(&user_input, &self.history.get_context()).await?;
+        let routing_decision = self.route_turn(&user_input).await?;
 
         let messages = self.build_chat_messages(&user_input);
 
         // 2. 调用 LLM
-        let response = self
+        let started = std::time::Instant::now();
+        let result = self
             .call_llm_with_retry(llm_client, messages, routing_decision.model.clone())
-            .await?;
+            .await;
+        if let Some(report) = self.last_routing.as_mut() {
+            report.latency_ms = started.elapsed().as_millis() as u64;
+        }
+        let response = result?;
         turn = turn.with_response(response.clone());
 
         // 3. 验证响应
@@ -482,7 +571,7 @@ impl GeminiArchitecture {
     }
 
     async fn call_llm_with_retry(
-        &self,
+        &mut self,
         llm_client: Arc<LLMClient>,
         messages: Vec<ChatMessage>,
         model: String,
@@ -493,6 +582,9 @@ impl GeminiArchitecture {
         let mut delay = retry_config.initial_delay_ms;
 
         loop {
+            if let Some(report) = self.last_routing.as_mut() {
+                report.attempts = attempt + 1;
+            }
             use std::sync::Mutex;
             let buffer = std::sync::Arc::new(Mutex::new(String::new()));
             let buffer_clone = buffer.clone();
@@ -1,8 +1,38 @@
-use crate::core::message::Message;
+use crate::core::message::{Message, Role};
+use crate::ui::types::MessageMetadata;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
+/// 默认最多保存的消息条数（HISTORY_MAX_MESSAGES 可配置）
+pub const DEFAULT_PERSISTED_MESSAGES: usize = 100;
+
+/// 写入历史文件的一条消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistedMessage {
+    pub role: Role,
+    pub content: String,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// 读取 HISTORY_MAX_MESSAGES，未设置或无法解析时使用默认值
+pub fn persisted_messages_limit() -> usize {
+    std::env::var("HISTORY_MAX_MESSAGES")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(DEFAULT_PERSISTED_MESSAGES)
+}
+
 pub struct ChatHistory {
     messages: VecDeque<Message>,
+    // 与 messages 一一对应的创建时间
+    timestamps: VecDeque<DateTime<Utc>>,
+    // 与 messages 一一对应的置顶标记（/pin）
+    pinned: VecDeque<bool>,
+    // 与 messages 一一对应的回复用量（只有完成的 AI 回复才有）
+    metadata: VecDeque<Option<MessageMetadata>>,
     max_size: usize,
 }
 
@@ -10,15 +40,84 @@ impl ChatHistory {
     pub fn new(max_size: usize) -> Self {
         Self {
             messages: VecDeque::with_capacity(max_size),
+            timestamps: VecDeque::with_capacity(max_size),
+            pinned: VecDeque::with_capacity(max_size),
+            metadata: VecDeque::with_capacity(max_size),
             max_size,
         }
     }
 
+    /// 添加消息。紧跟在 AI 消息之后的 AI 消息会合并到前一条中（以换行分隔），
+    /// 避免流式输出和工具循环产生多个相邻的 AI 气泡
     pub fn add_message(&mut self, message: Message) {
-        if self.messages.len() == self.max_size {
-            self.messages.pop_front();
+        if let Role::Assistant = message.role {
+            if let Some(last) = self.messages.back_mut() {
+                if let Role::Assistant = last.role {
+                    if !last.content.is_empty() {
+                        last.content.push('\n');
+                    }
+                    last.content.push_str(&message.content);
+                    return;
+                }
+            }
+        }
+
+        // 超出容量时淘汰最早的未置顶消息；全部置顶时不淘汰
+        if self.messages.len() >= self.max_size {
+            if let Some(index) = self.pinned.iter().position(|pinned| !pinned) {
+                self.remove(index);
+            }
         }
         self.messages.push_back(message);
+        self.timestamps.push_back(Utc::now());
+        self.pinned.push_back(false);
+        self.metadata.push_back(None);
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.messages.remove(index);
+        self.timestamps.remove(index);
+        self.pinned.remove(index);
+        self.metadata.remove(index);
+    }
+
+    /// 设置第 index 条消息的置顶状态，index 越界时返回 false
+    pub fn set_pinned(&mut self, index: usize, pinned: bool) -> bool {
+        match self.pinned.get_mut(index) {
+            Some(flag) => {
+                *flag = pinned;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_pinned(&self, index: usize) -> bool {
+        self.pinned.get(index).copied().unwrap_or(false)
+    }
+
+    pub fn pinned_count(&self) -> usize {
+        self.pinned.iter().filter(|pinned| **pinned).count()
+    }
+
+    /// 记录第 index 条消息的用量，index 越界时返回 false
+    pub fn set_metadata(&mut self, index: usize, metadata: MessageMetadata) -> bool {
+        match self.metadata.get_mut(index) {
+            Some(slot) => {
+                *slot = Some(metadata);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn metadata(&self, index: usize) -> Option<&MessageMetadata> {
+        self.metadata.get(index).and_then(Option::as_ref)
+    }
+
+    /// 第 index 条消息的创建时间（合并的消息保留第一条的时间）
+    pub fn timestamp(&self, index: usize) -> Option<DateTime<Utc>> {
+        self.timestamps.get(index).copied()
     }
 
     pub fn get_messages(&self) -> &VecDeque<Message> {
@@ -29,11 +128,102 @@ impl ChatHistory {
         &mut self.messages
     }
 
+    /// 移除第 index 条之后的未置顶消息（/retry 丢弃失败的回复）
+    pub fn remove_after(&mut self, index: usize) {
+        for i in (index + 1..self.messages.len()).rev() {
+            if !self.pinned[i] {
+                self.remove(i);
+            }
+        }
+    }
+
+    /// 清空历史，保留置顶消息
     pub fn clear(&mut self) {
+        for index in (0..self.messages.len()).rev() {
+            if !self.pinned[index] {
+                self.remove(index);
+            }
+        }
+    }
+
+    /// 清空全部历史，包括置顶消息
+    pub fn clear_all(&mut self) {
         self.messages.clear();
+        self.timestamps.clear();
+        self.pinned.clear();
+        self.metadata.clear();
     }
 
     pub fn is_empty(&self) -> bool {
         self.messages.is_empty()
     }
+
+    /// 最近的 max 条消息（带时间和置顶标记），用于写入历史文件
+    pub fn to_persisted(&self, max: usize) -> Vec<PersistedMessage> {
+        let skip = self.messages.len().saturating_sub(max);
+        (skip..self.messages.len())
+            .map(|index| PersistedMessage {
+                role: self.messages[index].role.clone(),
+                content: self.messages[index].content.clone(),
+                timestamp: self.timestamps[index],
+                pinned: self.pinned[index],
+            })
+            .collect()
+    }
+
+    /// 用保存的消息替换当前历史（保留原时间戳，不合并相邻 AI 消息），超出容量时只保留最近的部分
+    pub fn restore(&mut self, entries: Vec<PersistedMessage>) {
+        self.clear_all();
+        let skip = entries.len().saturating_sub(self.max_size);
+        for entry in entries.into_iter().skip(skip) {
+            self.messages.push_back(Message { role: entry.role, content: entry.content });
+            self.timestamps.push_back(entry.timestamp);
+            self.pinned.push_back(entry.pinned);
+            self.metadata.push_back(None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consecutive_assistant_messages_merge() {
+        let mut history = ChatHistory::new(10);
+        history.add_message(Message { role: Role::User, content: "hi".to_string() });
+        history.add_message(Message { role: Role::Assistant, content: "first".to_string() });
+        history.add_message(Message { role: Role::Assistant, content: "second".to_string() });
+
+        let messages = history.get_messages();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].content, "first\nsecond");
+
+        history.add_message(Message { role: Role::System, content: "note".to_string() });
+        history.add_message(Message { role: Role::Assistant, content: "third".to_string() });
+        assert_eq!(history.get_messages().len(), 4);
+    }
+
+    #[test]
+    fn test_pinned_message_survives_clear_and_eviction() {
+        let mut history = ChatHistory::new(3);
+        history.add_message(Message { role: Role::User, content: "requirement".to_string() });
+        assert!(history.set_pinned(0, true));
+        for i in 0..5 {
+            history.add_message(Message { role: Role::System, content: format!("note {}", i) });
+        }
+
+        let messages = history.get_messages();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].content, "requirement");
+        assert!(history.is_pinned(0));
+        assert_eq!(messages[2].content, "note 4");
+
+        history.clear();
+        assert_eq!(history.get_messages().len(), 1);
+        assert_eq!(history.get_messages()[0].content, "requirement");
+
+        history.clear_all();
+        assert!(history.is_empty());
+    }
 }
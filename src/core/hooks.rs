@@ -3,9 +3,12 @@
 /// 支持在对话生命周期的关键点执行自定义逻辑
 /// 类似于 fireBeforeModelHook, fireAfterModelHook 等
 
+use std::io::Write;
+use std::path::PathBuf;
 use std::sync::Arc;
 use async_trait::async_trait;
 use crate::core::conversation_engine::{ConversationContext, ProcessedResponse};
+use crate::tools::tool::{ToolCall, ToolResult};
 
 /// 钩子执行结果
 pub type HookResult = Result<(), String>;
@@ -31,7 +34,7 @@ pub trait BeforeToolSelectionHook: Send + Sync {
 /// 工具执行后钩子 - 在工具执行后执行
 #[async_trait]
 pub trait AfterToolExecutionHook: Send + Sync {
-    async fn execute(&self, tool_name: &str, result: &str) -> HookResult;
+    async fn execute(&self, call: &ToolCall, result: &ToolResult) -> HookResult;
 }
 
 /// 重试钩子 - 在重试前执行
@@ -110,9 +113,9 @@ impl HookManager {
     }
 
     /// 执行所有工具执行后钩子
-    pub async fn fire_after_tool_execution_hooks(&self, tool_name: &str, result: &str) -> HookResult {
+    pub async fn fire_after_tool_execution_hooks(&self, call: &ToolCall, result: &ToolResult) -> HookResult {
         for hook in &self.after_tool_execution_hooks {
-            hook.execute(tool_name, result).await?;
+            hook.execute(call, result).await?;
         }
         Ok(())
     }
@@ -170,6 +173,63 @@ impl OnRetryHook for LoggingHook {
     }
 }
 
+/// 审计日志中每条工具输出保留的最大字符数
+pub const DEFAULT_AUDIT_OUTPUT_CHARS: usize = 500;
+
+/// 审计钩子 - 每次工具执行后向文件追加一行 JSON（时间、工具名、参数、是否成功、截断后的输出）
+/// 写入失败时返回 Err，由调用方决定如何提示，不中断对话
+pub struct FileAuditHook {
+    path: PathBuf,
+    max_output_chars: usize,
+}
+
+impl FileAuditHook {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            max_output_chars: DEFAULT_AUDIT_OUTPUT_CHARS,
+        }
+    }
+
+    /// 一次工具执行对应的审计记录
+    fn record(&self, call: &ToolCall, result: &ToolResult) -> serde_json::Value {
+        let output = match (&result.error, &result.data) {
+            (Some(error), _) => error.clone(),
+            (None, serde_json::Value::String(text)) => text.clone(),
+            (None, data) => data.to_string(),
+        };
+        let mut truncated: String = output.chars().take(self.max_output_chars).collect();
+        if truncated.len() < output.len() {
+            truncated.push('…');
+        }
+
+        serde_json::json!({
+            "timestamp": chrono::Local::now().to_rfc3339(),
+            "tool": call.tool_name,
+            "arguments": call.arguments,
+            "success": result.success,
+            "output": truncated,
+        })
+    }
+
+    fn append(&self, line: &str) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", line)
+    }
+}
+
+#[async_trait]
+impl AfterToolExecutionHook for FileAuditHook {
+    async fn execute(&self, call: &ToolCall, result: &ToolResult) -> HookResult {
+        let line = self.record(call, result).to_string();
+        self.append(&line)
+            .map_err(|e| format!("写入工具审计日志失败 ({}): {}", self.path.display(), e))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -195,4 +255,77 @@ mod tests {
         let result = <LoggingHook as BeforeModelHook>::execute(&hook, &context).await;
         assert!(result.is_ok());
     }
+
+    /// 返回固定输出的假工具
+    struct EchoTool;
+
+    impl crate::tools::tool::Tool for EchoTool {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn description(&self) -> &str {
+            "echo text back"
+        }
+
+        fn definition(&self) -> crate::tools::tool::ToolDefinition {
+            crate::tools::tool::ToolDefinition {
+                name: "echo".to_string(),
+                description: "echo text back".to_string(),
+                parameters: vec![],
+            }
+        }
+
+        fn execute(&self, call: ToolCall) -> std::pin::Pin<Box<dyn std::future::Future<Output = ToolResult> + Send + '_>> {
+            Box::pin(async move {
+                let text = call.arguments.get("text").and_then(|v| v.as_str()).unwrap_or_default();
+                ToolResult {
+                    success: true,
+                    data: serde_json::Value::String(text.repeat(3)),
+                    error: None,
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_audit_hook_appends_tool_record() {
+        use crate::tools::tool::Tool;
+
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("audit").join("tools.jsonl");
+        let mut manager = HookManager::new();
+        manager.register_after_tool_execution_hook(Arc::new(FileAuditHook { max_output_chars: 4, ..FileAuditHook::new(&log_path) }));
+
+        let call = ToolCall {
+            tool_name: "echo".to_string(),
+            arguments: [("text".to_string(), serde_json::json!("abc"))].into_iter().collect(),
+        };
+        for _ in 0..2 {
+            let result = EchoTool.execute(call.clone()).await;
+            manager.fire_after_tool_execution_hooks(&call, &result).await.unwrap();
+        }
+
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let record: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(record["tool"], "echo");
+        assert_eq!(record["arguments"]["text"], "abc");
+        assert_eq!(record["success"], true);
+        assert_eq!(record["output"], "abca…");
+        assert!(record["timestamp"].as_str().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_file_audit_hook_reports_write_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        // 目标路径是目录，写入必然失败
+        let hook = FileAuditHook::new(dir.path());
+        let call = ToolCall { tool_name: "echo".to_string(), arguments: Default::default() };
+        let result = ToolResult { success: false, data: serde_json::Value::Null, error: Some("boom".to_string()) };
+
+        let err = hook.execute(&call, &result).await.unwrap_err();
+        assert!(err.contains("写入工具审计日志失败"), "{}", err);
+    }
 }
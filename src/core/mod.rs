@@ -1,6 +1,8 @@
 pub mod buffer;
+pub mod diff;
 pub mod cursor;
 pub mod history;
+pub mod sessions;
 pub mod message;
 pub mod context_optimizer;
 pub mod integration;
@@ -23,6 +25,7 @@ pub mod chat_orchestrator;
 pub mod health_check;
 pub mod vibe_coding;
 pub mod ai_agent;
+pub mod shutdown;
 
 pub use conversation_engine::{ConversationEngine, ConversationContext, UserIntent};
 
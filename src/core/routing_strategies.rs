@@ -7,8 +7,10 @@
 /// - PerformanceStrategy: 根据性能选择模型
 
 use async_trait::async_trait;
+use std::collections::BTreeMap;
 use crate::core::{RoutingStrategy, RoutingDecision, RetryConfig};
 use crate::core::conversation_engine::{ConversationContext, UserIntent};
+use crate::core::token_calculator::TokenCalculator;
 
 /// 降级策略 - 主模型失败时尝试备选模型
 pub struct FallbackStrategy {
@@ -113,28 +115,75 @@ impl RoutingStrategy for ModelSelectionStrategy {
     }
 }
 
-/// 成本优化策略 - 根据成本选择模型
+/// 模型价格（美元 / 1000 tokens）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    pub input_per_1k: f64,
+    pub output_per_1k: f64,
+}
+
+impl ModelPricing {
+    pub fn new(input_per_1k: f64, output_per_1k: f64) -> Self {
+        Self { input_per_1k, output_per_1k }
+    }
+
+    /// 本地 / 自托管模型不计费
+    pub fn free() -> Self {
+        Self::new(0.0, 0.0)
+    }
+
+    /// 一次请求的预估成本
+    pub fn cost(&self, prompt_tokens: usize, output_tokens: usize) -> f64 {
+        prompt_tokens as f64 / 1000.0 * self.input_per_1k + output_tokens as f64 / 1000.0 * self.output_per_1k
+    }
+}
+
+/// 默认为回复预留的 tokens（用于估算成本和判断上下文窗口）
+pub const DEFAULT_EXPECTED_OUTPUT_TOKENS: usize = 1_000;
+
+/// 成本优化策略 - 在上下文窗口放得下提示的模型中选择预估成本最低的
 pub struct CostOptimizationStrategy {
-    cheap_model: String,
-    expensive_model: String,
-    cost_threshold: f32,
+    /// 模型名 → (价格, 上下文窗口)
+    models: BTreeMap<String, (ModelPricing, usize)>,
+    expected_output_tokens: usize,
 }
 
 impl CostOptimizationStrategy {
-    pub fn new(cheap: impl Into<String>, expensive: impl Into<String>, threshold: f32) -> Self {
+    pub fn new() -> Self {
         Self {
-            cheap_model: cheap.into(),
-            expensive_model: expensive.into(),
-            cost_threshold: threshold,
+            models: BTreeMap::new(),
+            expected_output_tokens: DEFAULT_EXPECTED_OUTPUT_TOKENS,
         }
     }
 
     pub fn with_defaults() -> Self {
-        Self {
-            cheap_model: "gemini-2.0-flash".to_string(),
-            expensive_model: "gemini-2.5-pro".to_string(),
-            cost_threshold: 0.5,
-        }
+        Self::new()
+            .with_model("gemini-2.0-flash", ModelPricing::new(0.0001, 0.0004), 1_048_576)
+            .with_model("gemini-2.5-pro", ModelPricing::new(0.00125, 0.01), 1_048_576)
+    }
+
+    /// 注册（或覆盖）模型的价格和上下文窗口；本地模型可用 ModelPricing::free()
+    pub fn with_model(mut self, model: impl Into<String>, pricing: ModelPricing, context_window: usize) -> Self {
+        self.models.insert(model.into(), (pricing, context_window));
+        self
+    }
+
+    /// 设置为回复预留的 tokens
+    pub fn with_expected_output_tokens(mut self, tokens: usize) -> Self {
+        self.expected_output_tokens = tokens;
+        self
+    }
+
+    /// 预估提示的 tokens：输入 + 附加文件
+    fn prompt_tokens(model: &str, context: &ConversationContext) -> usize {
+        let calculator = TokenCalculator::from_model_name(model);
+        calculator.count_tokens(&context.user_input) + calculator.count_tokens(&context.render_files())
+    }
+}
+
+impl Default for CostOptimizationStrategy {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -149,17 +198,33 @@ impl RoutingStrategy for CostOptimizationStrategy {
         context: &ConversationContext,
         _retry: &RetryConfig,
     ) -> Result<Option<RoutingDecision>, String> {
-        // 简单的启发式：长输入使用便宜模型
-        let input_length = context.user_input.len() as f32;
-        let threshold = self.cost_threshold * 1000.0;
-
-        let (model, reason) = if input_length > threshold {
-            (&self.cheap_model, "long input, using cost-optimized model")
-        } else {
-            (&self.expensive_model, "short input, using high-quality model")
-        };
+        let candidates: Vec<(&String, f64, usize, usize)> = self
+            .models
+            .iter()
+            .map(|(model, (pricing, window))| {
+                let prompt_tokens = Self::prompt_tokens(model, context);
+                (model, pricing.cost(prompt_tokens, self.expected_output_tokens), prompt_tokens, *window)
+            })
+            .collect();
+
+        let cheapest = candidates
+            .iter()
+            .filter(|(_, _, prompt_tokens, window)| prompt_tokens + self.expected_output_tokens <= *window)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((model, cost, prompt_tokens, _)) = cheapest {
+            return Ok(Some(RoutingDecision::new(
+                *model,
+                format!("cheapest model that fits {} prompt tokens (est. ${:.4})", prompt_tokens, cost),
+            )));
+        }
 
-        Ok(Some(RoutingDecision::new(model, reason)))
+        // 都放不下时选择上下文窗口最大的模型
+        Ok(candidates
+            .iter()
+            .max_by_key(|(_, _, _, window)| *window)
+            .map(|(model, _, prompt_tokens, _)| {
+                RoutingDecision::new(*model, format!("{} prompt tokens exceed every context window, using the largest", prompt_tokens))
+            }))
     }
 }
 
@@ -262,6 +327,41 @@ mod tests {
         assert!(result.unwrap().is_some());
     }
 
+    fn chat_context(input: &str) -> ConversationContext {
+        ConversationContext::new(
+            input.to_string(),
+            UserIntent::Chat { query: input.to_string(), context_files: vec![] },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_cost_optimization_prefers_cheapest_model_that_fits() {
+        let strategy = CostOptimizationStrategy::new()
+            .with_model("small-cheap", ModelPricing::new(0.1, 0.2), 2_000)
+            .with_model("large-expensive", ModelPricing::new(1.0, 2.0), 100_000)
+            .with_expected_output_tokens(500);
+        let retry = RetryConfig::default();
+
+        let decision = strategy.route(&chat_context("fix this typo"), &retry).await.unwrap().unwrap();
+        assert_eq!(decision.model, "small-cheap");
+
+        // 提示超出便宜模型的上下文窗口时升级
+        let long = "word ".repeat(2_000);
+        let decision = strategy.route(&chat_context(&long), &retry).await.unwrap().unwrap();
+        assert_eq!(decision.model, "large-expensive");
+
+        // 本地模型价格为 0，放得下时优先
+        let strategy = strategy.with_model("local-llama", ModelPricing::free(), 8_000);
+        let decision = strategy.route(&chat_context("fix this typo"), &retry).await.unwrap().unwrap();
+        assert_eq!(decision.model, "local-llama");
+
+        // 都放不下时选择窗口最大的模型
+        let huge = "word ".repeat(100_000);
+        let decision = strategy.route(&chat_context(&huge), &retry).await.unwrap().unwrap();
+        assert_eq!(decision.model, "large-expensive");
+        assert!(CostOptimizationStrategy::new().route(&chat_context("hi"), &retry).await.unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn test_performance_strategy() {
         let strategy = PerformanceStrategy::with_defaults();
@@ -0,0 +1,139 @@
+/// 会话存档
+/// 当前会话自动保存在 ~/.starfellcode/history.json；开始新会话或恢复其他会话时，
+/// 旧会话移到 ~/.starfellcode/sessions/<时间>.json 归档。启动时的会话选择器列出这两处的会话
+
+use crate::core::history::PersistedMessage;
+use crate::core::message::Role;
+use crate::ui::types::ChatSession;
+use std::path::{Path, PathBuf};
+
+/// 存档根目录（相对于 HOME）
+const STORE_DIR: &str = ".starfellcode";
+/// 当前会话的自动保存文件（相对于存档根目录）
+const CURRENT_FILE: &str = "history.json";
+/// 归档会话目录（相对于存档根目录）
+const ARCHIVE_DIR: &str = "sessions";
+/// 当前会话在列表中的 id
+pub const CURRENT_SESSION_ID: &str = "current";
+/// 会话标题（第一条用户消息）的最大字符数
+const TITLE_MAX_CHARS: usize = 40;
+
+/// 启动时的选择
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionChoice {
+    /// 恢复指定 id 的会话
+    Resume(String),
+    /// 开始新会话
+    Fresh,
+}
+
+pub struct SessionStore {
+    root: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// 使用 ~/.starfellcode；没有 HOME 时返回 None
+    pub fn from_env() -> Option<Self> {
+        std::env::var("HOME").ok().map(|home| Self::new(Path::new(&home).join(STORE_DIR)))
+    }
+
+    /// 当前会话的自动保存文件
+    pub fn current_path(&self) -> PathBuf {
+        self.root.join(CURRENT_FILE)
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        if id == CURRENT_SESSION_ID {
+            self.current_path()
+        } else {
+            self.root.join(ARCHIVE_DIR).join(format!("{}.json", id))
+        }
+    }
+
+    /// 读取会话消息；文件不存在、损坏或为空时返回 None
+    pub fn load(&self, id: &str) -> Option<Vec<PersistedMessage>> {
+        let json = std::fs::read_to_string(self.path(id)).ok()?;
+        let entries: Vec<PersistedMessage> = serde_json::from_str(&json).ok()?;
+        (!entries.is_empty()).then_some(entries)
+    }
+
+    /// 所有可恢复的会话，最近更新的在前
+    pub fn list(&self) -> Vec<ChatSession> {
+        let mut ids = vec![CURRENT_SESSION_ID.to_string()];
+        if let Ok(dir) = std::fs::read_dir(self.root.join(ARCHIVE_DIR)) {
+            ids.extend(dir.flatten().filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != "json" {
+                    return None;
+                }
+                path.file_stem()?.to_str().map(str::to_string)
+            }));
+        }
+        let mut sessions: Vec<ChatSession> = ids
+            .into_iter()
+            .filter_map(|id| {
+                let entries = self.load(&id)?;
+                Some(summarize(id, &entries))
+            })
+            .collect();
+        sessions.sort_by(|a, b| b.last_updated.cmp(&a.last_updated));
+        sessions
+    }
+
+    /// 把当前会话移入归档，返回归档后的 id；当前会话为空时不做任何事
+    pub fn archive_current(&self) -> std::io::Result<Option<String>> {
+        let Some(entries) = self.load(CURRENT_SESSION_ID) else {
+            return Ok(None);
+        };
+        let base = entries[0].timestamp.format("%Y%m%d-%H%M%S").to_string();
+        let mut id = base.clone();
+        let mut suffix = 1;
+        while self.path(&id).exists() {
+            suffix += 1;
+            id = format!("{}-{}", base, suffix);
+        }
+        std::fs::create_dir_all(self.root.join(ARCHIVE_DIR))?;
+        std::fs::rename(self.current_path(), self.path(&id))?;
+        Ok(Some(id))
+    }
+
+    /// 取出归档会话：把归档文件移动为当前会话的自动保存文件，恢复后它就是当前会话。
+    /// 调用前当前会话应已归档；移动失败时归档保持不变
+    pub fn take(&self, id: &str) -> std::io::Result<Option<Vec<PersistedMessage>>> {
+        let Some(entries) = self.load(id) else {
+            return Ok(None);
+        };
+        if id != CURRENT_SESSION_ID {
+            std::fs::create_dir_all(&self.root)?;
+            std::fs::rename(self.path(id), self.current_path())?;
+        }
+        Ok(Some(entries))
+    }
+}
+
+/// 标题取第一条用户消息的首行
+fn summarize(id: String, entries: &[PersistedMessage]) -> ChatSession {
+    let title = entries
+        .iter()
+        .find(|entry| entry.role == Role::User)
+        .and_then(|entry| entry.content.lines().find(|line| !line.trim().is_empty()))
+        .map(|line| {
+            let line = line.trim();
+            if line.chars().count() > TITLE_MAX_CHARS {
+                format!("{}…", line.chars().take(TITLE_MAX_CHARS).collect::<String>())
+            } else {
+                line.to_string()
+            }
+        })
+        .unwrap_or_else(|| "(无用户消息)".to_string());
+    ChatSession {
+        id,
+        title,
+        message_count: entries.len(),
+        last_updated: entries.iter().map(|entry| entry.timestamp).max().unwrap_or_default(),
+    }
+}
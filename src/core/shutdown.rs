@@ -0,0 +1,141 @@
+/// 退出时的优雅关闭
+/// 设置取消标记，在超时内等待后台任务结束，超时仍未结束的任务直接中止
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+/// 取消标记（可跨任务克隆）
+#[derive(Clone, Default)]
+pub struct ShutdownToken {
+    cancelled: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl ShutdownToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 请求取消，唤醒所有正在等待 cancelled() 的任务
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// 等待取消
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// 关闭结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// 在超时内正常结束的任务数
+    pub finished: usize,
+    /// 超时后被中止的任务数
+    pub aborted: usize,
+}
+
+/// 跟踪 App 发起的后台任务（聊天请求、工具注册、索引等）
+#[derive(Default)]
+pub struct BackgroundTasks {
+    token: ShutdownToken,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl BackgroundTasks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 后台任务应检查的取消标记
+    pub fn token(&self) -> ShutdownToken {
+        self.token.clone()
+    }
+
+    /// 记录一个已启动的任务（顺便清理已结束的任务）
+    pub fn track(&mut self, handle: JoinHandle<()>) {
+        self.handles.retain(|h| !h.is_finished());
+        self.handles.push(handle);
+    }
+
+    /// 启动任务并记录；取消时任务在下一个等待点被丢弃
+    pub fn spawn(&mut self, future: impl Future<Output = ()> + Send + 'static) {
+        let token = self.token.clone();
+        self.track(tokio::spawn(async move {
+            tokio::select! {
+                _ = future => {}
+                _ = token.cancelled() => {}
+            }
+        }));
+    }
+
+    /// 设置取消标记并在 timeout 内等待所有任务结束，超时的任务会被中止
+    pub async fn shutdown(&mut self, timeout: Duration) -> ShutdownReport {
+        self.token.cancel();
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut report = ShutdownReport { finished: 0, aborted: 0 };
+        for mut handle in self.handles.drain(..) {
+            match tokio::time::timeout_at(deadline, &mut handle).await {
+                Ok(_) => report.finished += 1,
+                Err(_) => {
+                    // 中止后任务持有的子进程（kill_on_drop）随之被回收
+                    handle.abort();
+                    let _ = handle.await;
+                    report.aborted += 1;
+                }
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[tokio::test]
+    async fn test_shutdown_cancels_and_aborts_hung_task() {
+        let mut tasks = BackgroundTasks::new();
+
+        // 协作式任务：收到取消后自行退出
+        let token = tasks.token();
+        let observed = Arc::new(AtomicUsize::new(0));
+        let seen = observed.clone();
+        tasks.track(tokio::spawn(async move {
+            token.cancelled().await;
+            seen.fetch_add(1, Ordering::SeqCst);
+        }));
+
+        // 挂起的任务：忽略取消标记
+        tasks.track(tokio::spawn(async {
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+        }));
+
+        let started = std::time::Instant::now();
+        let report = tasks.shutdown(Duration::from_millis(100)).await;
+
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(tasks.token().is_cancelled());
+        assert_eq!(observed.load(Ordering::SeqCst), 1);
+        assert_eq!(report, ShutdownReport { finished: 1, aborted: 1 });
+        assert!(tasks.handles.is_empty());
+    }
+}
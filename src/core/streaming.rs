@@ -115,10 +115,13 @@ impl Default for StreamHandler {
 }
 
 /// 流式响应缓冲区 - 累积流式内容
+/// 流中途出错时保留原始请求和已收到的部分内容，重试时可以从中断处继续；成功完成后清空
 pub struct StreamBuffer {
     content: String,
     chunks: Vec<String>,
     retry_count: u32,
+    /// 产生当前内容的原始请求
+    request: Option<String>,
 }
 
 impl StreamBuffer {
@@ -127,9 +130,17 @@ impl StreamBuffer {
             content: String::new(),
             chunks: Vec::new(),
             retry_count: 0,
+            request: None,
         }
     }
 
+    /// 开始一个新请求：丢弃之前保留的内容
+    pub fn begin(&mut self, request: impl Into<String>) {
+        self.clear();
+        self.retry_count = 0;
+        self.request = Some(request.into());
+    }
+
     pub fn append(&mut self, chunk: &str) {
         self.content.push_str(chunk);
         self.chunks.push(chunk.to_string());
@@ -145,6 +156,18 @@ impl StreamBuffer {
         self.clear();
     }
 
+    /// 从中断处继续：保留已收到的内容，新的内容接在后面
+    pub fn on_resume(&mut self) {
+        self.retry_count += 1;
+    }
+
+    /// 请求成功完成：清空内容和请求
+    pub fn complete(&mut self) {
+        self.clear();
+        self.retry_count = 0;
+        self.request = None;
+    }
+
     pub fn get_content(&self) -> &str {
         &self.content
     }
@@ -156,6 +179,27 @@ impl StreamBuffer {
     pub fn get_retry_count(&self) -> u32 {
         self.retry_count
     }
+
+    pub fn get_request(&self) -> Option<&str> {
+        self.request.as_deref()
+    }
+
+    /// 是否保留了可以继续的部分回复
+    pub fn has_partial(&self) -> bool {
+        self.request.is_some() && !self.content.is_empty()
+    }
+
+    /// 继续生成的请求：原始请求 + 已收到的部分回复 + 从中断处继续的说明；没有部分回复时返回 None
+    pub fn resume_prompt(&self) -> Option<String> {
+        if !self.has_partial() {
+            return None;
+        }
+        Some(format!(
+            "{}\n\n<partial_response>\n{}\n</partial_response>\n上一次回复在这里中断了。请紧接着上面的内容继续输出，不要重复已经输出的部分。",
+            self.request.as_deref().unwrap_or_default(),
+            self.content
+        ))
+    }
 }
 
 impl Default for StreamBuffer {
@@ -196,6 +240,29 @@ mod tests {
         assert_eq!(buffer.get_content(), "");
     }
 
+    #[test]
+    fn test_stream_buffer_keeps_partial_for_resume() {
+        let mut buffer = StreamBuffer::new();
+        assert_eq!(buffer.resume_prompt(), None);
+
+        buffer.begin("explain ownership");
+        buffer.append("Ownership means");
+        // 出错后保留部分内容，继续时带上原始请求和已收到的内容
+        assert!(buffer.has_partial());
+        let prompt = buffer.resume_prompt().unwrap();
+        assert!(prompt.starts_with("explain ownership\n\n<partial_response>\nOwnership means\n</partial_response>"));
+
+        buffer.on_resume();
+        buffer.append(" each value has one owner.");
+        assert_eq!(buffer.get_content(), "Ownership means each value has one owner.");
+        assert_eq!(buffer.get_retry_count(), 1);
+
+        buffer.complete();
+        assert!(!buffer.has_partial());
+        assert_eq!(buffer.get_request(), None);
+        assert_eq!(buffer.get_content(), "");
+    }
+
     #[test]
     fn test_stream_handler() {
         let (handler, mut receiver) = StreamHandler::new();
@@ -204,7 +271,7 @@ mod tests {
         
         if let Ok(Some(event)) = tokio::runtime::Runtime::new()
             .unwrap()
-            .block_on(async { Ok(receiver.recv().await) })
+            .block_on(async { Ok::<_, ()>(receiver.recv().await) })
         {
             assert!(matches!(event.event_type, StreamEventType::Chunk));
             assert_eq!(event.content, "test");
@@ -84,6 +84,10 @@ pub struct StreamingOptimizer {
     metrics: PerformanceMetrics,
     buffer: Vec<String>,
     last_flush: Instant,
+    /// 统计起点，吞吐量按墙钟时间计算
+    started_at: Instant,
+    /// 收到的原始事件数（未合并）
+    received_events: usize,
 }
 
 impl StreamingOptimizer {
@@ -94,6 +98,8 @@ impl StreamingOptimizer {
             metrics: PerformanceMetrics::new(),
             buffer: Vec::new(),
             last_flush: Instant::now(),
+            started_at: Instant::now(),
+            received_events: 0,
         }
     }
 
@@ -106,6 +112,7 @@ impl StreamingOptimizer {
     pub fn add_event(&mut self, content: String) -> Option<OptimizedStreamEvent> {
         self.buffer.push(content.clone());
         self.metrics.total_bytes += content.len();
+        self.received_events += 1;
 
         // 检查是否应该刷新
         if self.should_flush() {
@@ -177,18 +184,21 @@ impl StreamingOptimizer {
 
     /// 计算吞吐量（事件/秒）
     pub fn calculate_throughput_events_per_sec(&self) -> f64 {
-        if self.metrics.total_time_ms == 0 {
-            return 0.0;
-        }
-        (self.metrics.total_events as f64 / self.metrics.total_time_ms as f64) * 1000.0
+        self.per_second(self.received_events as f64)
     }
 
     /// 计算吞吐量（字节/秒）
     pub fn calculate_throughput_bytes_per_sec(&self) -> f64 {
-        if self.metrics.total_time_ms == 0 {
+        self.per_second(self.metrics.total_bytes as f64)
+    }
+
+    /// 把计数换算成自 started_at 以来的每秒速率
+    fn per_second(&self, count: f64) -> f64 {
+        let elapsed = self.started_at.elapsed().as_secs_f64();
+        if elapsed == 0.0 {
             return 0.0;
         }
-        (self.metrics.total_bytes as f64 / self.metrics.total_time_ms as f64) * 1000.0
+        count / elapsed
     }
 
     /// 计算平均延迟（毫秒）
@@ -212,6 +222,8 @@ impl StreamingOptimizer {
     pub fn reset_metrics(&mut self) {
         self.metrics = PerformanceMetrics::new();
         self.last_flush = Instant::now();
+        self.started_at = Instant::now();
+        self.received_events = 0;
     }
 
     /// 应用背压（如果缓冲区过大则等待）
@@ -6,7 +6,7 @@
 /// - 成本估算
 /// - 模型支持
 
-use crate::core::message_history::Message;
+use crate::core::message_history::{Message, MessageRole};
 
 /// Token 编码方式
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -16,6 +16,37 @@ pub enum TokenEncoding {
     R50kBase,               // 编码
 }
 
+/// Token 计数方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenizerBackend {
+    /// 按字符数估算
+    Heuristic,
+    /// tiktoken-rs 精确计数（`tiktoken` feature，仅 OpenAI 模型）
+    Tiktoken,
+}
+
+impl std::fmt::Display for TokenizerBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenizerBackend::Heuristic => write!(f, "估算"),
+            TokenizerBackend::Tiktoken => write!(f, "tiktoken"),
+        }
+    }
+}
+
+/// 用 OpenAI 模型对应的 tiktoken 编码计数；不是 OpenAI 模型时返回 None
+#[cfg(feature = "tiktoken")]
+fn tiktoken_count(model: &str, text: &str) -> Option<usize> {
+    use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+    let bpe = match get_tokenizer(model)? {
+        Tokenizer::Cl100kBase => tiktoken_rs::cl100k_base_singleton(),
+        Tokenizer::O200kBase => tiktoken_rs::o200k_base_singleton(),
+        _ => return None,
+    };
+    let count = bpe.lock().encode_with_special_tokens(text).len();
+    Some(count)
+}
+
 /// 模型信息
 #[derive(Debug, Clone)]
 pub struct ModelInfo {
@@ -23,6 +54,7 @@ pub struct ModelInfo {
     pub encoding: TokenEncoding,
     pub input_price_per_1k: f64,    // 每 1000 tokens 的输入价格（美元）
     pub output_price_per_1k: f64,   // 每 1000 tokens 的输出价格（美元）
+    pub context_window: usize,      // 上下文窗口大小（tokens）
 }
 
 impl ModelInfo {
@@ -33,6 +65,7 @@ impl ModelInfo {
             encoding: TokenEncoding::Cl100kBase,
             input_price_per_1k: 0.03,
             output_price_per_1k: 0.06,
+            context_window: 8_192,
         }
     }
 
@@ -43,6 +76,7 @@ impl ModelInfo {
             encoding: TokenEncoding::Cl100kBase,
             input_price_per_1k: 0.0005,
             output_price_per_1k: 0.0015,
+            context_window: 16_385,
         }
     }
 
@@ -53,6 +87,7 @@ impl ModelInfo {
             encoding: TokenEncoding::Cl100kBase,
             input_price_per_1k: 0.075 / 1000.0,  // $0.075 per 1M tokens
             output_price_per_1k: 0.30 / 1000.0,  // $0.30 per 1M tokens
+            context_window: 1_048_576,
         }
     }
 
@@ -63,6 +98,7 @@ impl ModelInfo {
             encoding: TokenEncoding::Cl100kBase,
             input_price_per_1k: 0.003,
             output_price_per_1k: 0.015,
+            context_window: 200_000,
         }
     }
 }
@@ -108,15 +144,53 @@ impl TokenStats {
     }
 }
 
+/// 发送前的 Token 预估（输入 + 附加上下文）
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptEstimate {
+    pub input_tokens: usize,
+    pub context_tokens: usize,
+    pub total_tokens: usize,
+    pub context_window: usize,
+}
+
+impl PromptEstimate {
+    /// 是否超过模型上下文窗口
+    pub fn exceeds_window(&self) -> bool {
+        self.total_tokens > self.context_window
+    }
+
+    /// 超过上下文窗口时的警告信息
+    pub fn warning(&self) -> Option<String> {
+        if self.exceeds_window() {
+            Some(format!(
+                "⚠ 预计 {} tokens，超过模型上下文窗口 {} tokens（超出 {}）",
+                self.total_tokens,
+                self.context_window,
+                self.total_tokens - self.context_window
+            ))
+        } else {
+            None
+        }
+    }
+}
+
 /// Token 计算器
 pub struct TokenCalculator {
     model: ModelInfo,
+    /// 为模型回复预留的 tokens（请求的 max_tokens）
+    output_reserve: usize,
 }
 
 impl TokenCalculator {
     /// 创建新的 Token 计算器
     pub fn new(model: ModelInfo) -> Self {
-        Self { model }
+        Self { model, output_reserve: 0 }
+    }
+
+    /// 设置为模型回复预留的 tokens，判断是否放得进上下文窗口时一并计入
+    pub fn with_output_reserve(mut self, max_tokens: usize) -> Self {
+        self.output_reserve = max_tokens;
+        self
     }
 
     /// 使用模型名称创建计算器
@@ -131,8 +205,26 @@ impl TokenCalculator {
         Self::new(model)
     }
 
+    /// 当前模型使用的计数方式：启用 `tiktoken` feature 且是 OpenAI 模型时精确计数，否则估算
+    pub fn backend(&self) -> TokenizerBackend {
+        #[cfg(feature = "tiktoken")]
+        if tiktoken_count(&self.model.name, "").is_some() {
+            return TokenizerBackend::Tiktoken;
+        }
+        TokenizerBackend::Heuristic
+    }
+
     /// 计算文本的 Token 数
     pub fn count_tokens(&self, text: &str) -> usize {
+        #[cfg(feature = "tiktoken")]
+        if let Some(count) = tiktoken_count(&self.model.name, text) {
+            return count;
+        }
+        self.estimate_tokens(text)
+    }
+
+    /// 按编码方式启发式估算文本的 Token 数
+    pub fn estimate_tokens(&self, text: &str) -> usize {
         match self.model.encoding {
             TokenEncoding::Cl100kBase => {
                 // 简单估算：平均每 4 个字符 = 1 token
@@ -187,6 +279,40 @@ impl TokenCalculator {
         stats
     }
 
+    /// 预估一次请求的 Token 数：输入文本 + 将要附加的上下文
+    pub fn estimate_prompt(&self, input: &str, context: &[String]) -> PromptEstimate {
+        let input_tokens = self.count_tokens(input);
+        let context_tokens: usize = context.iter().map(|c| self.count_tokens(c)).sum();
+
+        PromptEstimate {
+            input_tokens,
+            context_tokens,
+            total_tokens: input_tokens + context_tokens,
+            context_window: self.model.context_window,
+        }
+    }
+
+    /// 消息加上回复预留是否放得进模型的上下文窗口
+    pub fn fits_in_context(&self, messages: &[Message], model: &ModelInfo) -> bool {
+        let prompt_tokens: usize = messages.iter().map(|m| self.count_message_tokens(m)).sum();
+        prompt_tokens + self.output_reserve <= model.context_window
+    }
+
+    /// 从最早的非 system 消息开始丢弃，直到放得进上下文窗口，返回丢弃的条数
+    /// system 消息和最后一条消息（当前输入）始终保留，因此裁剪后仍可能放不下
+    pub fn trim_to_fit(&self, messages: &mut Vec<Message>, model: &ModelInfo) -> usize {
+        let mut dropped = 0;
+        while !self.fits_in_context(messages, model) {
+            let last = messages.len().saturating_sub(1);
+            let Some(oldest) = messages[..last].iter().position(|m| m.role != MessageRole::System) else {
+                break;
+            };
+            messages.remove(oldest);
+            dropped += 1;
+        }
+        dropped
+    }
+
     /// 估算成本
     pub fn estimate_cost(&self, stats: &TokenStats) -> f64 {
         let input_cost = (stats.input_tokens as f64 / 1000.0) * self.model.input_price_per_1k;
@@ -312,4 +438,89 @@ mod tests {
         let calculator = TokenCalculator::from_model_name("unknown");
         assert_eq!(calculator.model.name, "gpt-4"); // 默认值
     }
+
+    #[test]
+    fn test_estimate_prompt_sums_components() {
+        let calculator = TokenCalculator::new(ModelInfo::gpt4());
+        let input = "Explain this function";
+        let context = vec!["fn main() {}".to_string(), "struct App;".to_string()];
+
+        let estimate = calculator.estimate_prompt(input, &context);
+        let expected_context = calculator.count_tokens(&context[0]) + calculator.count_tokens(&context[1]);
+        assert_eq!(estimate.input_tokens, calculator.count_tokens(input));
+        assert_eq!(estimate.context_tokens, expected_context);
+        assert_eq!(estimate.total_tokens, estimate.input_tokens + expected_context);
+        assert!(estimate.warning().is_none());
+    }
+
+    #[test]
+    fn test_estimate_prompt_over_window_warns() {
+        let calculator = TokenCalculator::new(ModelInfo::gpt4());
+        let huge = "word ".repeat(10_000);
+
+        let estimate = calculator.estimate_prompt("summarize", &[huge]);
+        assert!(estimate.exceeds_window());
+        assert!(estimate.warning().unwrap().contains("超过模型上下文窗口"));
+    }
+
+    /// 上下文窗口很小的合成模型，便于观察裁剪
+    fn tiny_model(context_window: usize) -> ModelInfo {
+        ModelInfo { context_window, ..ModelInfo::gpt4() }
+    }
+
+    #[test]
+    fn test_fits_in_context_counts_output_reserve() {
+        let calculator = TokenCalculator::new(ModelInfo::gpt4());
+        let messages = vec![Message::new(MessageRole::User, "word ".repeat(20))];
+        let per_message = calculator.count_message_tokens(&messages[0]);
+
+        assert!(calculator.fits_in_context(&messages, &tiny_model(per_message)));
+        let reserved = TokenCalculator::new(ModelInfo::gpt4()).with_output_reserve(1);
+        assert!(!reserved.fits_in_context(&messages, &tiny_model(per_message)));
+    }
+
+    #[test]
+    fn test_trim_to_fit_drops_oldest_non_system_messages() {
+        let mut messages = vec![
+            Message::new(MessageRole::System, "rules"),
+            Message::new(MessageRole::User, "first question"),
+            Message::new(MessageRole::Assistant, "first answer"),
+            Message::new(MessageRole::User, "second question"),
+            Message::new(MessageRole::Assistant, "second answer"),
+            Message::new(MessageRole::User, "current question"),
+        ];
+        let calculator = TokenCalculator::new(ModelInfo::gpt4()).with_output_reserve(10);
+        let tokens = |items: &[Message]| items.iter().map(|m| calculator.count_message_tokens(m)).sum::<usize>();
+        // 窗口只放得下 system + 最后两条 + 预留
+        let window = tokens(&[messages[0].clone(), messages[4].clone(), messages[5].clone()]) + 10;
+        let model = tiny_model(window);
+
+        assert!(!calculator.fits_in_context(&messages, &model));
+        assert_eq!(calculator.trim_to_fit(&mut messages, &model), 3);
+        assert!(calculator.fits_in_context(&messages, &model));
+        let contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["rules", "second answer", "current question"]);
+
+        // 只剩 system 和当前输入时不再丢弃
+        let mut messages = vec![Message::new(MessageRole::System, "rules"), Message::new(MessageRole::User, "current question")];
+        assert_eq!(calculator.trim_to_fit(&mut messages, &tiny_model(1)), 0);
+        assert_eq!(messages.len(), 2);
+    }
+
+    #[test]
+    fn test_non_openai_models_use_heuristic_backend() {
+        let calculator = TokenCalculator::new(ModelInfo::claude3());
+        assert_eq!(calculator.backend(), TokenizerBackend::Heuristic);
+        assert_eq!(calculator.count_tokens("Hello, world!"), calculator.estimate_tokens("Hello, world!"));
+    }
+
+    #[cfg(feature = "tiktoken")]
+    #[test]
+    fn test_tiktoken_counts_openai_models_exactly() {
+        let calculator = TokenCalculator::new(ModelInfo::gpt4());
+        assert_eq!(calculator.backend(), TokenizerBackend::Tiktoken);
+        // cl100k_base: "t" "ik" "token" " is" " great" "!"
+        assert_eq!(calculator.count_tokens("tiktoken is great!"), 6);
+        assert_eq!(calculator.count_tokens("Hello, world!"), 4);
+    }
 }
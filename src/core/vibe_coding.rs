@@ -254,8 +254,12 @@ pub struct VibeWorkflowManager {
     pub stage: VibeStage,
     file_handler: CodeFileHandler,
     changes: Vec<CodeChange>,
+    docs_dir: PathBuf,
 }
 
+/// PRD 和技术设计文档的默认输出目录
+pub const DEFAULT_DOCS_DIR: &str = "docs";
+
 impl VibeWorkflowManager {
     pub fn new() -> Self {
         Self {
@@ -263,23 +267,23 @@ impl VibeWorkflowManager {
             stage: VibeStage::Conceptualization,
             file_handler: CodeFileHandler::new(),
             changes: Vec::new(),
+            docs_dir: PathBuf::from(DEFAULT_DOCS_DIR),
         }
     }
 
+    /// 设置文档输出目录
+    pub fn with_docs_dir(mut self, docs_dir: impl Into<PathBuf>) -> Self {
+        self.docs_dir = docs_dir.into();
+        self
+    }
+
     /// Stage 1: 创建项目并生成 PRD
     pub fn create_project(&mut self, name: String, description: String) -> Result<VibeProject, String> {
         let project = VibeProject::new(name, description);
         let prd = ProductRequirementsDoc::new(project.clone());
 
         // 保存 PRD 文件
-        let prd_path = PathBuf::from(format!("docs/prd_{}.md", project.id));
-        if let Some(parent) = prd_path.parent() {
-            let _ = self.file_handler.create_file(
-                parent.to_str().unwrap(),
-                "# Vibe Project\n\nThis directory was created using Vibe Coding workflow.",
-            );
-        }
-
+        let prd_path = self.docs_dir.join(format!("prd_{}.md", project.id));
         let result = self.file_handler.create_file(
             prd_path.to_str().unwrap(),
             &prd.to_markdown(),
@@ -305,7 +309,10 @@ impl VibeWorkflowManager {
         );
 
         let result = self.file_handler.create_file(
-            &format!("docs/technical_design_{}.md", prd.project.id),
+            self.docs_dir
+                .join(format!("technical_design_{}.md", prd.project.id))
+                .to_str()
+                .unwrap(),
             &design.to_markdown(),
         );
 
@@ -372,13 +379,15 @@ mod tests {
 
     #[test]
     fn test_project_creation() {
-        let mut manager = VibeWorkflowManager::new();
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = VibeWorkflowManager::new().with_docs_dir(dir.path());
         let project = manager.create_project(
             "Test Project".to_string(),
             "A test project for vibe coding".to_string(),
         );
 
-        assert!(project.is_ok());
+        let project = project.unwrap();
+        assert!(dir.path().join(format!("prd_{}.md", project.id)).exists());
         assert_eq!(manager.stage, VibeStage::Conceptualization);
     }
 
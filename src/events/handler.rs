@@ -1,5 +1,4 @@
 use crate::app::{App, AppAction, ModificationChoice};
-use crate::ai::code_modification::{CodeModificationOp, CodeMatcher};
 use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseEvent, MouseEventKind, MouseButton};
 use crate::ui::pixel_layout_v2::extract_text_from_chat_area;
 
@@ -28,6 +27,12 @@ fn estimate_chat_lines(app: &App) -> usize {
 pub struct EventHandler;
 
 impl EventHandler {
+    /// 终端尺寸变化：重新计算滚动范围，下一帧按新尺寸重新布局
+    pub fn handle_resize(app: &mut App, width: u16, height: u16) -> AppAction {
+        app.update_max_scroll(width, height);
+        AppAction::None
+    }
+
     pub fn handle_mouse_event(app: &mut App, mouse: MouseEvent, terminal_size: (u16, u16)) -> AppAction {
         match mouse.kind {
             MouseEventKind::Down(MouseButton::Left) => {
@@ -96,6 +101,12 @@ impl EventHandler {
     }
     
     pub fn handle_chat_event(app: &mut App, key: KeyEvent) -> AppAction {
+        // 生成过程中按下中断键：停止生成，保留已收到的内容，输入框继续可用
+        if app.is_streaming && app.interrupt_key.matches(&key) {
+            app.interrupt_generation();
+            return AppAction::None;
+        }
+
         // 最高优先级：处理 AI 代码修改确认对话
         if app.modification_confirmation_pending && !app.pending_modifications.is_empty() {
             match key.code {
@@ -118,87 +129,9 @@ impl EventHandler {
                     return AppAction::None;
                 }
                 KeyCode::Char('1') => {
-                    // 数字 1 - 确认
+                    // 数字 1 - 确认并立即执行
                     app.modification_choice = ModificationChoice::Confirm;
-                    // 立即执行
-                    if app.modification_choice == ModificationChoice::Confirm {
-                        // 执行修改
-                        for (op, _diff) in &app.pending_modifications {
-                            match op {
-                                crate::ai::code_modification::CodeModificationOp::Create { path, content } => {
-                                    // 创建文件
-                                    match std::fs::write(path, content) {
-                                        Ok(_) => {
-                                            app.chat_history.add_message(crate::core::message::Message {
-                                                role: crate::core::message::Role::System,
-                                                content: format!("✅ 文件已创建: {}", path),
-                                            });
-                                        }
-                                        Err(e) => {
-                                            app.chat_history.add_message(crate::core::message::Message {
-                                                role: crate::core::message::Role::System,
-                                                content: format!("❌ 创建文件失败: {}", e),
-                                            });
-                                        }
-                                    }
-                                }
-                                CodeModificationOp::Modify { path, search, replace } => {
-                                    // 修改文件 - 使用 CodeMatcher 进行模糊匹配
-                                    match CodeMatcher::find_and_replace(&path, &search, &replace) {
-                                        Ok(diff) => {
-                                            match std::fs::write(path, diff.new_content) {
-                                                Ok(_) => {
-                                                    app.chat_history.add_message(crate::core::message::Message {
-                                                        role: crate::core::message::Role::System,
-                                                        content: format!("✅ 文件已修改: {}", path),
-                                                    });
-                                                }
-                                                Err(e) => {
-                                                    app.chat_history.add_message(crate::core::message::Message {
-                                                        role: crate::core::message::Role::System,
-                                                        content: format!("❌ 修改文件失败: {}", e),
-                                                    });
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            app.chat_history.add_message(crate::core::message::Message {
-                                                role: crate::core::message::Role::System,
-                                                content: format!("❌ 代码匹配失败: {}", e),
-                                            });
-                                        }
-                                    }
-                                }
-                                crate::ai::code_modification::CodeModificationOp::Delete { path } => {
-                                    // 删除文件
-                                    match std::fs::remove_file(path) {
-                                        Ok(_) => {
-                                            app.chat_history.add_message(crate::core::message::Message {
-                                                role: crate::core::message::Role::System,
-                                                content: format!("✅ 文件已删除: {}", path),
-                                            });
-                                        }
-                                        Err(e) => {
-                                            app.chat_history.add_message(crate::core::message::Message {
-                                                role: crate::core::message::Role::System,
-                                                content: format!("❌ 删除文件失败: {}", e),
-                                            });
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    } else {
-                        // 取消修改
-                        app.chat_history.add_message(crate::core::message::Message {
-                            role: crate::core::message::Role::System,
-                            content: "✅ 修改已取消".to_string(),
-                        });
-                    }
-                    
-                    // 清空待确认的修改
-                    app.pending_modifications.clear();
-                    app.modification_confirmation_pending = false;
+                    app.apply_pending_modifications();
                     return AppAction::None;
                 }
                 KeyCode::Char('2') | KeyCode::Char('n') | KeyCode::Char('N') => {
@@ -242,70 +175,7 @@ impl EventHandler {
                     // Enter - 执行当前选择
                     match app.modification_choice {
                         ModificationChoice::Confirm => {
-                            // 执行修改
-                            for (op, _diff) in &app.pending_modifications {
-                                match op {
-                                    crate::ai::code_modification::CodeModificationOp::Create { path, content } => {
-                                        match std::fs::write(path, content) {
-                                            Ok(_) => {
-                                                app.chat_history.add_message(crate::core::message::Message {
-                                                    role: crate::core::message::Role::System,
-                                                    content: format!("✅ 文件已创建: {}", path),
-                                                });
-                                            }
-                                            Err(e) => {
-                                                app.chat_history.add_message(crate::core::message::Message {
-                                                    role: crate::core::message::Role::System,
-                                                    content: format!("❌ 创建文件失败: {}", e),
-                                                });
-                                            }
-                                        }
-                                    }
-                                    crate::ai::code_modification::CodeModificationOp::Modify { path, search: _, replace } => {
-                                        match std::fs::read_to_string(path) {
-                                            Ok(content) => {
-                                                let new_content = content.replace(&content, &replace);
-                                                match std::fs::write(path, new_content) {
-                                                    Ok(_) => {
-                                                        app.chat_history.add_message(crate::core::message::Message {
-                                                            role: crate::core::message::Role::System,
-                                                            content: format!("✅ 文件已修改: {}", path),
-                                                        });
-                                                    }
-                                                    Err(e) => {
-                                                        app.chat_history.add_message(crate::core::message::Message {
-                                                            role: crate::core::message::Role::System,
-                                                            content: format!("❌ 修改文件失败: {}", e),
-                                                        });
-                                                    }
-                                                }
-                                            }
-                                            Err(e) => {
-                                                app.chat_history.add_message(crate::core::message::Message {
-                                                    role: crate::core::message::Role::System,
-                                                    content: format!("❌ 读取文件失败: {}", e),
-                                                });
-                                            }
-                                        }
-                                    }
-                                    crate::ai::code_modification::CodeModificationOp::Delete { path } => {
-                                        match std::fs::remove_file(path) {
-                                            Ok(_) => {
-                                                app.chat_history.add_message(crate::core::message::Message {
-                                                    role: crate::core::message::Role::System,
-                                                    content: format!("✅ 文件已删除: {}", path),
-                                                });
-                                            }
-                                            Err(e) => {
-                                                app.chat_history.add_message(crate::core::message::Message {
-                                                    role: crate::core::message::Role::System,
-                                                    content: format!("❌ 删除文件失败: {}", e),
-                                                });
-                                            }
-                                        }
-                                    }
-                                }
-                            }
+                            app.apply_pending_modifications();
                         }
                         ModificationChoice::Cancel | ModificationChoice::Abandon => {
                             // 取消或放弃修改
@@ -412,6 +282,21 @@ impl EventHandler {
             }
         }
 
+        // 模型切换浮层
+        if app.model_switcher.visible {
+            match key.code {
+                KeyCode::Up => app.model_switcher.select_previous(),
+                KeyCode::Down => app.model_switcher.select_next(),
+                KeyCode::Enter => app.apply_model_switcher_selection(),
+                KeyCode::Esc => app.model_switcher.close(),
+                KeyCode::Backspace => app.model_switcher.pop_char(),
+                KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => app.model_switcher.close(),
+                KeyCode::Char(c) if key.kind == KeyEventKind::Press => app.model_switcher.push_char(c),
+                _ => {}
+            }
+            return AppAction::None;
+        }
+
         if app.command_hints.visible {
             match key.code {
                 KeyCode::Up => {
@@ -433,7 +318,7 @@ impl EventHandler {
                     return AppAction::None;
                 }
                 KeyCode::Esc => {
-                    app.command_hints.visible = false;
+                    app.clear_input();
                     return AppAction::None;
                 }
                 _ => {}
@@ -441,8 +326,13 @@ impl EventHandler {
         }
 
         match key.code {
+            KeyCode::Char('o') if key.modifiers == KeyModifiers::CONTROL => {
+                // Ctrl+O - 打开模型切换浮层（Ctrl+M 在终端中与回车是同一个字符，无法区分）
+                app.open_model_switcher();
+                AppAction::None
+            }
             KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
-                // Ctrl+C - 如果有选中文本则复制，否则退出
+                // Ctrl+C - 如果有选中文本则复制；生成中先停止生成，否则退出
                 if !app.selected_text.is_empty() {
                     // 复制到剪贴板
                     if let Ok(mut clipboard) = arboard::Clipboard::new() {
@@ -455,7 +345,7 @@ impl EventHandler {
                     }
                     AppAction::None
                 } else {
-                    AppAction::Quit
+                    app.handle_ctrl_c()
                 }
             }
             KeyCode::Enter => {
@@ -515,6 +405,7 @@ impl EventHandler {
                     if app.input_text.contains('@') {
                         // 使用文件搜索引擎更新
                         app.file_search.update_query(app.input_text.clone());
+                        app.request_semantic_ranking();
                         app.mention_suggestions.suggestions = app.file_search.results.clone();
                         app.mention_suggestions.selected_index = app.file_search.selected_index;
                         app.mention_suggestions.visible = !app.file_search.results.is_empty();
@@ -576,6 +467,11 @@ impl EventHandler {
                 }
                 AppAction::None
             }
+            KeyCode::Esc => {
+                // Esc - 放弃正在输入的消息（历史不受影响）
+                app.clear_input();
+                AppAction::None
+            }
             KeyCode::Left => {
                 // 使用字符索引移动光标
                 app.input_cursor = app.input_cursor.saturating_sub(1);
@@ -622,6 +518,7 @@ impl EventHandler {
                             app.mention_suggestions.activate('@');
                         }
                         app.file_search.update_query(app.input_text.clone());
+                        app.request_semantic_ranking();
                         app.mention_suggestions.suggestions = app.file_search.results.clone();
                         app.mention_suggestions.selected_index = app.file_search.selected_index;
                         app.mention_suggestions.visible = !app.file_search.results.is_empty();
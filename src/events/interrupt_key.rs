@@ -0,0 +1,72 @@
+/// 中断生成的按键
+/// 默认 Esc，可通过环境变量 INTERRUPT_KEY 配置（如 `esc`、`ctrl+g`）
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InterruptKey {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Default for InterruptKey {
+    fn default() -> Self {
+        Self { code: KeyCode::Esc, modifiers: KeyModifiers::NONE }
+    }
+}
+
+impl InterruptKey {
+    /// 解析 `esc`、`ctrl+g`、`alt+x` 之类的描述；Ctrl+C 保留给复制/退出，不能作为中断键
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_lowercase();
+        let (modifiers, key) = match value.rsplit_once('+') {
+            Some(("ctrl", key)) => (KeyModifiers::CONTROL, key),
+            Some(("alt", key)) => (KeyModifiers::ALT, key),
+            Some(_) => return None,
+            None => (KeyModifiers::NONE, value.as_str()),
+        };
+        let code = match key {
+            "esc" | "escape" => KeyCode::Esc,
+            _ => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if modifiers != KeyModifiers::NONE => KeyCode::Char(c),
+                    _ => return None,
+                }
+            }
+        };
+        if code == KeyCode::Char('c') && modifiers == KeyModifiers::CONTROL {
+            return None;
+        }
+        Some(Self { code, modifiers })
+    }
+
+    /// 读取 INTERRUPT_KEY，未设置或无法解析时使用默认的 Esc
+    pub fn from_env() -> Self {
+        std::env::var("INTERRUPT_KEY")
+            .ok()
+            .and_then(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        key.code == self.code && key.modifiers == self.modifiers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interrupt_key() {
+        assert_eq!(InterruptKey::parse("Esc"), Some(InterruptKey::default()));
+        assert_eq!(
+            InterruptKey::parse("ctrl+g"),
+            Some(InterruptKey { code: KeyCode::Char('g'), modifiers: KeyModifiers::CONTROL })
+        );
+        assert_eq!(InterruptKey::parse("ctrl+c"), None);
+        assert_eq!(InterruptKey::parse("g"), None);
+        assert_eq!(InterruptKey::parse("shift+g"), None);
+    }
+}
@@ -1 +1,2 @@
 pub mod file_ops;
+pub mod path_guard;
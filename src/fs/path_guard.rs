@@ -0,0 +1,135 @@
+//! 路径约束模块
+//! 限制写入/删除/创建操作只能发生在项目根目录内
+
+use std::path::{Component, Path, PathBuf};
+
+/// 项目根目录路径守卫
+#[derive(Debug, Clone)]
+pub struct PathGuard {
+    root: PathBuf,
+    allow_outside: bool,
+}
+
+impl PathGuard {
+    /// 以指定目录作为项目根目录
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        let root = root.as_ref();
+        Self {
+            root: root.canonicalize().unwrap_or_else(|_| normalize(root)),
+            allow_outside: false,
+        }
+    }
+
+    /// 是否允许访问项目根目录之外的路径（对应 --allow-outside）
+    pub fn with_allow_outside(mut self, allow: bool) -> Self {
+        self.allow_outside = allow;
+        self
+    }
+
+    /// 切换项目根目录，保留 --allow-outside 设置
+    pub fn rebase(&self, root: impl AsRef<Path>) -> Self {
+        Self::new(root).with_allow_outside(self.allow_outside)
+    }
+
+    /// 检查目标路径，返回解析后的绝对路径；位于项目根目录之外时返回错误
+    pub fn check(&self, path: &str) -> Result<PathBuf, String> {
+        let resolved = resolve(Path::new(path));
+        if self.allow_outside || resolved.starts_with(&self.root) {
+            Ok(resolved)
+        } else {
+            Err(format!(
+                "Path '{}' is outside the project root '{}' (use --allow-outside to permit)",
+                path,
+                self.root.display()
+            ))
+        }
+    }
+}
+
+impl Default for PathGuard {
+    fn default() -> Self {
+        Self::new(std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
+    }
+}
+
+/// 解析路径：相对路径基于当前工作目录，并对最长的已存在前缀做 canonicalize（解析符号链接）
+fn resolve(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join(path)
+    };
+    let normalized = normalize(&absolute);
+
+    // 目标文件可能尚不存在，向上找到第一个存在的祖先目录
+    let mut existing = normalized.as_path();
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            let mut resolved = canonical;
+            for part in rest.iter().rev() {
+                resolved.push(part);
+            }
+            return resolved;
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return normalized,
+        }
+    }
+}
+
+/// 词法规范化：去掉 `.`，并让 `..` 回退一级
+fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            other => result.push(other.as_os_str()),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_path_inside_root_is_allowed() {
+        let temp_dir = tempdir().unwrap();
+        let guard = PathGuard::new(temp_dir.path());
+
+        let target = temp_dir.path().join("src/new_file.rs");
+        assert!(guard.check(target.to_str().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_parent_directory_escape_is_blocked() {
+        let temp_dir = tempdir().unwrap();
+        let project = temp_dir.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        let guard = PathGuard::new(&project);
+
+        let escape = project.join("../outside.txt");
+        assert!(guard.check(escape.to_str().unwrap()).is_err());
+        assert!(guard.check("/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_allow_outside_flag() {
+        let temp_dir = tempdir().unwrap();
+        let guard = PathGuard::new(temp_dir.path()).with_allow_outside(true);
+
+        assert!(guard.check("/etc/passwd").is_ok());
+    }
+}
@@ -17,6 +17,10 @@ use crossterm::{
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
+use std::time::Duration;
+
+/// 退出时等待后台任务结束的最长时间
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -33,20 +37,63 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Set project root to current directory
     let current_dir = std::env::current_dir()
         .unwrap_or_else(|_| std::path::PathBuf::from("."));
-    app.file_search.set_root(current_dir);
+    app.file_search.set_root(current_dir.clone());
+
+    // 写入/删除/创建操作限制在项目根目录内，除非显式传入 --allow-outside
+    let allow_outside = std::env::args().any(|arg| arg == "--allow-outside");
+    app.file_command_handler.set_path_guard(
+        crate::fs::path_guard::PathGuard::new(&current_dir).with_allow_outside(allow_outside),
+    );
     eprintln!("📁 Project root: {}", app.file_search.root_path.display());
 
+    // --safe：只读探索模式，禁用写入/执行类工具
+    if std::env::args().any(|arg| arg == "--safe") {
+        app.safe_mode = true;
+        eprintln!("🔒 Safe mode: write/exec tools disabled");
+    }
+
     // Build file search cache at startup (like Gemini CLI's list_directory)
     // This ensures fast file lookups when user types @
     eprintln!("📁 Building file cache...");
     app.file_search.build_cache();
     eprintln!("✓ File cache built ({} files)", app.file_search.cache.len());
 
+    // 停止生成的按键（默认 Esc）
+    app.interrupt_key = crate::events::interrupt_key::InterruptKey::from_env();
+    app.commit_style = crate::ai::commit_message::CommitStyle::from_env();
+    // 输入指示器动画的帧间隔（TYPING_ANIMATION_MS，0 或 off 关闭动画）
+    app.typing_animation = crate::ui::typing_indicator::TypingAnimation::from_env();
+
+    // 有保存的会话时先让用户选择继续哪一个（当前会话自动保存在 ~/.starfellcode/history.json）
+    app.persisted_history_limit = crate::core::history::persisted_messages_limit();
+    let session_store = crate::core::sessions::SessionStore::from_env();
+    if let Some(store) = &session_store {
+        let sessions = store.list();
+        if !sessions.is_empty() {
+            let mut picker = crate::ui::session_picker::SessionPicker::new(sessions);
+            let choice = run_session_picker(&mut terminal, &mut picker)?;
+            if let Err(e) = app.apply_session_choice(store, &choice) {
+                eprintln!("⚠ Failed to archive previous session: {}", e);
+            }
+        }
+    }
+    app.history_path = session_store.as_ref().map(|store| store.current_path());
+
+    // 工具审计日志：设置 TOOL_AUDIT_LOG 后每次工具执行追加一行 JSON
+    app.tool_audit_log = std::env::var_os("TOOL_AUDIT_LOG").map(std::path::PathBuf::from);
+
+    // 自定义工具：在 tools/plugins.rs 的 register_custom_tools 中注册
+    app.tool_plugins.push(crate::tools::plugins::register_custom_tools);
+
     // Initialize AI client from environment configuration
     match crate::ai::config::LLMConfig::from_env() {
         Ok(config) => {
             app.init_ai_client_with_config(config);
             eprintln!("✓ LLM client initialized successfully");
+            // 项目级模型偏好（.starfell/model）覆盖全局默认
+            if let Some(model) = app.apply_project_model(&current_dir) {
+                eprintln!("✓ Using project model: {}", model);
+            }
         }
         Err(e) => {
             eprintln!("⚠ Warning: Failed to load LLM configuration: {}", e);
@@ -61,6 +108,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Run the application
     let res = run_app(&mut terminal, &mut app).await;
 
+    // 取消进行中的请求和后台任务，等待它们结束并保存历史后再恢复终端
+    let shutdown = app.shutdown(SHUTDOWN_TIMEOUT).await;
+
     // Restore terminal
     disable_raw_mode()?;
     execute!(
@@ -70,6 +120,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     )?;
     terminal.show_cursor()?;
 
+    if let Err(e) = shutdown {
+        eprintln!("⚠ Failed to save chat history: {}", e);
+    }
     if let Err(err) = res {
         println!("{:?}", err);
     }
@@ -77,27 +130,68 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// 进入主循环前显示会话选择器，直到用户做出选择
+fn run_session_picker<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    picker: &mut crate::ui::session_picker::SessionPicker,
+) -> io::Result<crate::core::sessions::SessionChoice> {
+    let theme = crate::ui::pixel_layout_v2::Theme::new();
+    loop {
+        terminal.draw(|f| picker.render(f, &theme))?;
+        if let crossterm::event::Event::Key(key) = crossterm::event::read()? {
+            if key.kind == crossterm::event::KeyEventKind::Press {
+                if let Some(choice) = picker.handle_key(key) {
+                    return Ok(choice);
+                }
+            }
+        }
+    }
+}
+
+/// /compose：挂起 TUI，在外部编辑器中编辑输入框内容，返回后恢复终端并重绘
+fn run_compose<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+
+    let result = app.compose_with_editor(&crate::ui::compose::editor_command());
+
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
+
+    if let Err(e) = result {
+        app.chat_history.add_message(crate::core::message::Message {
+            role: crate::core::message::Role::System,
+            content: format!("❌ 编辑器未能完成: {}", e),
+        });
+        app.scroll_to_bottom();
+    }
+    terminal.draw(|f| app.render(f))?;
+    Ok(())
+}
+
 async fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
 ) -> io::Result<()> {
         use crossterm::event::EventStream;
     use futures_util::StreamExt;
-    use std::time::Duration;
 
     let mut reader = EventStream::new();
-    let mut interval = tokio::time::interval(Duration::from_millis(100));
+    let mut interval = tokio::time::interval(app.typing_animation.redraw_interval(Duration::from_millis(100)));
 
     loop {
         tokio::select! {
             // 渲染 UI
             _ = interval.tick() => {
+                app.poll_semantic_ranking(std::time::Instant::now());
+                app.apply_semantic_ranking();
                 terminal.draw(|f| {
                     app.render(f);
                 })?;
             }
 
-            // 处理终端事件 - 键盘和鼠标
+            // 处理终端事件 - 键盘、鼠标和尺寸变化
             Some(Ok(event)) = reader.next() => {
                 match event {
                     crossterm::event::Event::Key(key) => {
@@ -106,10 +200,14 @@ async fn run_app<B: ratatui::backend::Backend>(
                             match action {
                                 crate::app::AppAction::SubmitChat => {
                                     app.handle_chat_submit().await;
+                                    if std::mem::take(&mut app.compose_requested) {
+                                        // 编辑器运行期间不能让 EventStream 抢读 stdin，结束后重建
+                                        drop(reader);
+                                        run_compose(terminal, app)?;
+                                        reader = EventStream::new();
+                                    }
                                 }
-                                crate::app::AppAction::Quit => {
-                                    return Ok(());
-                                }
+                                crate::app::AppAction::Quit => return Ok(()),
                                 crate::app::AppAction::None => {}
                             }
                         }
@@ -123,6 +221,11 @@ async fn run_app<B: ratatui::backend::Backend>(
                             (terminal_size.width, terminal_size.height)
                         );
                     }
+                    crossterm::event::Event::Resize(width, height) => {
+                        crate::events::handler::EventHandler::handle_resize(app, width, height);
+                        terminal.autoresize()?;
+                        terminal.draw(|f| app.render(f))?;
+                    }
                     _ => {}
                 }
             }
@@ -137,37 +240,67 @@ async fn run_app<B: ratatui::backend::Backend>(
                 }
             } => {
                 if let Some(stream_event) = maybe_stream_event {
-                    match stream_event {
-                        crate::ai::streaming::StreamEvent::Token(t) => {
-                            {
-                                // 将 token 追加到聊天历史中最后一条 AI 消息
-                                if let Some(last_msg) = app.chat_history.get_messages_mut().back_mut() {
-                                    if let crate::core::message::Role::Assistant = last_msg.role {
-                                        last_msg.content.push_str(&t);
-                                    }
-                                }
+                    use crate::ai::streaming::StreamEvent;
+
+                    // 合并所有立即可用的 Token，一次加锁、一次渲染
+                    let (tokens, next_event) = match stream_event {
+                        StreamEvent::Token(first) => match app.stream_handler.as_ref() {
+                            Some(handler) => {
+                                let rx = handler.get_receiver();
+                                let mut rx = rx.lock().await;
+                                crate::ai::streaming::drain_tokens(&mut rx, first)
                             }
-                            // 同步到 streaming_response（保持现有渲染逻辑兼容）
-                            let mut streaming_response = app.streaming_response.lock().unwrap();
-                            streaming_response.append(&t);
-                            drop(streaming_response); // 释放锁
-                            
-                            // 保持自动滚动到底部
-                            app.scroll_to_bottom();
-                            
-                            // 立即触发重新渲染以显示新的 token
-                            terminal.draw(|f| app.render(f)).ok();
-                        }
-                        crate::ai::streaming::StreamEvent::Done => {
+                            None => (vec![first], None),
+                        },
+                        other => (Vec::new(), Some(other)),
+                    };
+
+                    if !tokens.is_empty() {
+                        app.append_stream_tokens(&tokens);
+                        // 保持自动滚动到底部
+                        app.scroll_to_bottom();
+                        terminal.draw(|f| app.render(f)).ok();
+                    }
+
+                    match next_event {
+                        Some(StreamEvent::Done) => {
                             app.finalize_streaming_response().await;
                             // 最终渲染
                             terminal.draw(|f| app.render(f)).ok();
                         }
-                        crate::ai::streaming::StreamEvent::Error(e) => {
+                        Some(StreamEvent::Error(e)) => {
                             eprintln!("Streaming Error: {}", e);
-                            app.finalize_streaming_response().await;
+                            app.handle_stream_error(&e).await;
+                            terminal.draw(|f| app.render(f)).ok();
+                        }
+                        Some(StreamEvent::Interrupted(reason)) => {
+                            app.handle_stream_interrupted(&reason);
+                            terminal.draw(|f| app.render(f)).ok();
+                        }
+                        Some(StreamEvent::Explanation(text)) => {
+                            app.show_tool_explanation(&text);
+                            terminal.draw(|f| app.render(f)).ok();
+                        }
+                        Some(StreamEvent::ToolCall(call)) => {
+                            app.show_tool_call(&call);
+                            terminal.draw(|f| app.render(f)).ok();
+                        }
+                        Some(StreamEvent::ToolResult(result)) => {
+                            app.show_tool_result(&result);
+                            terminal.draw(|f| app.render(f)).ok();
+                        }
+                        Some(StreamEvent::Usage(usage)) => {
+                            app.record_usage(usage);
+                        }
+                        Some(StreamEvent::ScanProgress(files_scanned)) => {
+                            app.scan_progress = Some(files_scanned);
+                            terminal.draw(|f| app.render(f)).ok();
+                        }
+                        Some(StreamEvent::Warning(text)) => {
+                            app.show_warning(&text);
                             terminal.draw(|f| app.render(f)).ok();
                         }
+                        Some(StreamEvent::Token(_)) | None => {}
                     }
                 }
             }
@@ -326,8 +326,9 @@ mod tests {
 
     #[test]
     fn test_prompt_contains_base_elements() {
+        // 基础提示优先取 the-augment.xml，找不到时才用内置默认值
         let prompt = PairProgrammingPrompts.generate(5);
-        assert!(prompt.contains("pair programming assistant"));
+        assert!(prompt.contains(PairProgrammingPrompts::base_prompt().trim()));
         assert!(prompt.contains("code blocks"));
     }
 }
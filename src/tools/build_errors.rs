@@ -0,0 +1,139 @@
+/// 编译错误解析
+/// 从 cargo / rustc 的输出中提取 `error[...]: 消息` 及其后的 `--> 文件:行:列`，供对话附加为上下文
+
+/// 一条带位置的编译错误
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompilerError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    /// 错误码（如 E0308），没有时为 None
+    pub code: Option<String>,
+    pub message: String,
+}
+
+impl CompilerError {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "file": self.file,
+            "line": self.line,
+            "column": self.column,
+            "code": self.code,
+            "message": self.message,
+        })
+    }
+}
+
+/// 解析 cargo 输出中的错误；警告和没有位置的汇总行（如 `could not compile`）会被忽略
+pub fn parse_cargo_errors(output: &str) -> Vec<CompilerError> {
+    let mut errors = Vec::new();
+    let mut pending: Option<(Option<String>, String)> = None;
+
+    for line in output.lines() {
+        if let Some(header) = parse_error_header(line) {
+            pending = Some(header);
+            continue;
+        }
+        if line.starts_with("warning") {
+            pending = None;
+            continue;
+        }
+        let Some(location) = line.trim_start().strip_prefix("--> ") else {
+            continue;
+        };
+        if let Some((code, message)) = pending.take() {
+            if let Some((file, line, column)) = parse_location(location) {
+                errors.push(CompilerError { file, line, column, code, message });
+            }
+        }
+    }
+    errors
+}
+
+/// 把错误列表格式化为附加给模型的上下文
+pub fn format_errors_context(errors: &[CompilerError]) -> String {
+    let mut context = format!("编译失败，共 {} 个错误：\n", errors.len());
+    for error in errors {
+        let code = error.code.as_ref().map(|c| format!("[{}]", c)).unwrap_or_default();
+        context.push_str(&format!(
+            "{}:{}:{}: error{}: {}\n",
+            error.file, error.line, error.column, code, error.message
+        ));
+    }
+    context.trim_end().to_string()
+}
+
+/// `error[E0308]: mismatched types` / `error: expected one of ...`
+fn parse_error_header(line: &str) -> Option<(Option<String>, String)> {
+    let rest = line.strip_prefix("error")?;
+    let (code, rest) = match rest.strip_prefix('[') {
+        Some(bracketed) => {
+            let (code, rest) = bracketed.split_once(']')?;
+            (Some(code.to_string()), rest)
+        }
+        None => (None, rest),
+    };
+    let message = rest.strip_prefix(':')?.trim();
+    Some((code, message.to_string()))
+}
+
+/// `src/main.rs:4:18`（文件名本身可能包含冒号，从右侧切分）
+fn parse_location(location: &str) -> Option<(String, usize, usize)> {
+    let mut parts = location.trim().rsplitn(3, ':');
+    let column = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    let file = parts.next()?.to_string();
+    Some((file, line, column))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cargo_errors_skips_warnings_and_summary() {
+        let output = "\
+   Compiling demo v0.1.0 (/tmp/demo)
+warning: unused variable: `x`
+ --> src/lib.rs:2:9
+  |
+2 |     let x = 1;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_x`
+
+error[E0308]: mismatched types
+  --> src/main.rs:4:18
+   |
+4  |     let n: u32 = \"four\";
+   |            ---   ^^^^^^ expected `u32`, found `&str`
+
+error: expected one of `,` or `}`, found `=`
+ --> src/config.rs:10:5
+
+error: could not compile `demo` (bin \"demo\") due to 2 previous errors
+";
+        let errors = parse_cargo_errors(output);
+        assert_eq!(
+            errors,
+            vec![
+                CompilerError {
+                    file: "src/main.rs".to_string(),
+                    line: 4,
+                    column: 18,
+                    code: Some("E0308".to_string()),
+                    message: "mismatched types".to_string(),
+                },
+                CompilerError {
+                    file: "src/config.rs".to_string(),
+                    line: 10,
+                    column: 5,
+                    code: None,
+                    message: "expected one of `,` or `}`, found `=`".to_string(),
+                },
+            ]
+        );
+
+        let context = format_errors_context(&errors);
+        assert!(context.contains("共 2 个错误"));
+        assert!(context.contains("src/main.rs:4:18: error[E0308]: mismatched types"));
+    }
+}
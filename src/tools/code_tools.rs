@@ -2,6 +2,7 @@
 /// 提供代码搜索、语法分析、结构分析等功能
 
 use super::tool::{Tool, ToolCall, ToolDefinition, ToolParameter, ToolResult, ToolExecutionContext};
+use super::scan_control::{ScanControl, ScanOutcome};
 use regex::Regex;
 use std::fs;
 use std::path::Path;
@@ -27,7 +28,7 @@ impl Tool for CodeSearchTool {
             parameters: vec![
                 ToolParameter {
                     name: "pattern".to_string(),
-                    description: "搜索模式（支持正则表达式）".to_string(),
+                    description: "搜索文本（默认按字面匹配，regex 为 true 时按正则表达式）".to_string(),
                     param_type: "string".to_string(),
                     required: true,
                 },
@@ -49,6 +50,18 @@ impl Tool for CodeSearchTool {
                     param_type: "boolean".to_string(),
                     required: false,
                 },
+                ToolParameter {
+                    name: "regex".to_string(),
+                    description: "是否把 pattern 当作正则表达式（默认false，按字面匹配）".to_string(),
+                    param_type: "boolean".to_string(),
+                    required: false,
+                },
+                ToolParameter {
+                    name: "skip_comments".to_string(),
+                    description: "忽略注释中的匹配（默认false，仅对已知语言生效）".to_string(),
+                    param_type: "boolean".to_string(),
+                    required: false,
+                },
             ],
         }
     }
@@ -77,8 +90,11 @@ impl Tool for CodeSearchTool {
 
             let include_pattern = ctx.get_string("include_pattern");
             let case_sensitive = ctx.get_bool("case_sensitive").unwrap_or(false);
+            let skip_comments = ctx.get_bool("skip_comments").unwrap_or(false);
+            // 与 grok-cli 的 SearchTool 一致：默认按字面匹配，正则需显式开启
+            let regex = ctx.get_bool("regex").unwrap_or(false);
 
-            match search_code(&pattern, &path, include_pattern.as_deref(), case_sensitive) {
+            match search_code(&pattern, &path, include_pattern.as_deref(), case_sensitive, skip_comments, regex) {
                 Ok(results) => ToolResult {
                     success: true,
                     data: serde_json::json!({
@@ -96,6 +112,10 @@ impl Tool for CodeSearchTool {
             }
         })
     }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
 }
 
 /// 函数定义查找工具
@@ -127,11 +147,33 @@ impl Tool for FunctionFinderTool {
                     param_type: "string".to_string(),
                     required: true,
                 },
+                ToolParameter {
+                    name: "max_depth".to_string(),
+                    description: format!("最大递归深度（默认{}）", DEFAULT_FUNCTION_SEARCH_DEPTH),
+                    param_type: "number".to_string(),
+                    required: false,
+                },
+                ToolParameter {
+                    name: "max_results".to_string(),
+                    description: format!("最多返回的函数数（默认{}）", DEFAULT_FUNCTION_SEARCH_RESULTS),
+                    param_type: "number".to_string(),
+                    required: false,
+                },
+                ToolParameter {
+                    name: "include_pattern".to_string(),
+                    description: "只搜索匹配的文件（如*.rs）".to_string(),
+                    param_type: "string".to_string(),
+                    required: false,
+                },
             ],
         }
     }
 
     fn execute(&self, call: ToolCall) -> Pin<Box<dyn Future<Output = ToolResult> + Send + '_>> {
+        self.execute_with_control(call, ScanControl::new())
+    }
+
+    fn execute_with_control(&self, call: ToolCall, control: ScanControl) -> Pin<Box<dyn Future<Output = ToolResult> + Send + '_>> {
         Box::pin(async move {
             let ctx = ToolExecutionContext::new(call.tool_name, call.arguments);
 
@@ -153,13 +195,22 @@ impl Tool for FunctionFinderTool {
                 },
             };
 
-            match find_functions(&path, &language) {
-                Ok(functions) => ToolResult {
+            let options = FunctionSearchOptions {
+                max_depth: ctx.get_number("max_depth").map(|n| n as usize).unwrap_or(DEFAULT_FUNCTION_SEARCH_DEPTH),
+                max_results: ctx.get_number("max_results").map(|n| n as usize).unwrap_or(DEFAULT_FUNCTION_SEARCH_RESULTS),
+                include_pattern: ctx.get_string("include_pattern"),
+            };
+
+            match find_functions_async(path.clone(), language.clone(), options, control).await {
+                Ok(outcome) => ToolResult {
                     success: true,
                     data: serde_json::json!({
                         "path": path,
                         "language": language,
-                        "functions": functions
+                        "functions": outcome.result.functions,
+                        "truncated": outcome.result.truncated,
+                        "files_scanned": outcome.files_scanned,
+                        "cancelled": outcome.cancelled
                     }),
                     error: None,
                 },
@@ -171,6 +222,10 @@ impl Tool for FunctionFinderTool {
             }
         })
     }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
 }
 
 /// 代码结构分析工具
@@ -246,13 +301,17 @@ impl Tool for CodeStructureTool {
             }
         })
     }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
 }
 
-fn search_code(pattern: &str, path: &str, include_pattern: Option<&str>, _case_sensitive: bool) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error>> {
-    let regex = Regex::new(pattern)?;
+fn search_code(pattern: &str, path: &str, include_pattern: Option<&str>, _case_sensitive: bool, skip_comments: bool, is_regex: bool) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error>> {
+    let regex = if is_regex { Regex::new(pattern)? } else { Regex::new(&regex::escape(pattern))? };
     let mut results = Vec::new();
 
-    fn search_in_path(path: &Path, regex: &Regex, include_pattern: Option<&str>, results: &mut Vec<serde_json::Value>) -> Result<(), Box<dyn std::error::Error>> {
+    fn search_in_path(path: &Path, regex: &Regex, include_pattern: Option<&str>, skip_comments: bool, results: &mut Vec<serde_json::Value>) -> Result<(), Box<dyn std::error::Error>> {
         if path.is_file() {
             // 检查文件扩展名
             if let Some(pattern) = include_pattern {
@@ -265,8 +324,13 @@ fn search_code(pattern: &str, path: &str, include_pattern: Option<&str>, _case_s
             }
 
             let content = fs::read_to_string(path)?;
-            for (line_num, line) in content.lines().enumerate() {
-                if regex.is_match(line) {
+            // 匹配时使用去掉注释的代码，结果中仍显示原始行
+            let code_lines = match comment_syntax(path).filter(|_| skip_comments) {
+                Some(syntax) => strip_comments(&content, &syntax),
+                None => content.lines().map(str::to_string).collect(),
+            };
+            for (line_num, (line, code)) in content.lines().zip(&code_lines).enumerate() {
+                if regex.is_match(code) {
                     results.push(serde_json::json!({
                         "file": path.to_string_lossy(),
                         "line": line_num + 1,
@@ -277,16 +341,95 @@ fn search_code(pattern: &str, path: &str, include_pattern: Option<&str>, _case_s
         } else if path.is_dir() {
             for entry in fs::read_dir(path)? {
                 let entry = entry?;
-                search_in_path(&entry.path(), regex, include_pattern, results)?;
+                search_in_path(&entry.path(), regex, include_pattern, skip_comments, results)?;
             }
         }
         Ok(())
     }
 
-    search_in_path(Path::new(path), &regex, include_pattern, &mut results)?;
+    search_in_path(Path::new(path), &regex, include_pattern, skip_comments, &mut results)?;
     Ok(results)
 }
 
+/// 一种语言的注释和字符串语法
+struct CommentSyntax {
+    line: &'static str,
+    block: Option<(&'static str, &'static str)>,
+    /// 字符串引号，引号内的注释标记不生效
+    quotes: &'static [char],
+}
+
+/// 按扩展名识别注释语法，未知语言返回 None（不做剥离）
+fn comment_syntax(path: &Path) -> Option<CommentSyntax> {
+    let ext = path.extension()?.to_str()?;
+    let syntax = match ext {
+        // Rust 的 ' 也用于生命周期，只把 " 当作字符串
+        "rs" => CommentSyntax { line: "//", block: Some(("/*", "*/")), quotes: &['"'] },
+        "c" | "h" | "cc" | "cpp" | "hpp" | "java" | "go" | "cs" | "kt" | "swift" | "scala" => {
+            CommentSyntax { line: "//", block: Some(("/*", "*/")), quotes: &['"'] }
+        }
+        "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" => {
+            CommentSyntax { line: "//", block: Some(("/*", "*/")), quotes: &['"', '\'', '`'] }
+        }
+        "py" | "rb" | "sh" | "bash" | "toml" | "yaml" | "yml" => {
+            CommentSyntax { line: "#", block: None, quotes: &['"', '\''] }
+        }
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+/// 把注释替换为空白，逐行返回（行数与原内容一致）。
+/// 只识别注释标记和简单的字符串字面量，不是完整的词法分析：字符串不跨行，块注释可以跨行
+fn strip_comments(content: &str, syntax: &CommentSyntax) -> Vec<String> {
+    let mut in_block = false;
+    let mut lines = Vec::new();
+
+    for line in content.lines() {
+        let mut code = String::with_capacity(line.len());
+        let mut in_string: Option<char> = None;
+        let mut rest = line;
+
+        while let Some(c) = rest.chars().next() {
+            if in_block {
+                let (_, close) = syntax.block.expect("in_block requires block syntax");
+                match rest.find(close) {
+                    Some(end) => {
+                        rest = &rest[end + close.len()..];
+                        code.push(' ');
+                        in_block = false;
+                    }
+                    None => rest = "",
+                }
+                continue;
+            }
+            if let Some(quote) = in_string {
+                if c == '\\' && rest.len() > 1 {
+                    let escaped = rest[1..].chars().next().map(char::len_utf8).unwrap_or(0);
+                    code.push_str(&rest[..1 + escaped]);
+                    rest = &rest[1 + escaped..];
+                    continue;
+                }
+                if c == quote {
+                    in_string = None;
+                }
+            } else if rest.starts_with(syntax.line) {
+                break;
+            } else if let Some((open, _)) = syntax.block.filter(|(open, _)| rest.starts_with(open)) {
+                rest = &rest[open.len()..];
+                in_block = true;
+                continue;
+            } else if syntax.quotes.contains(&c) {
+                in_string = Some(c);
+            }
+            code.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        lines.push(code);
+    }
+    lines
+}
+
 fn matches_pattern(filename: &str, pattern: &str) -> bool {
     if pattern.starts_with("*.") {
         let ext = &pattern[2..];
@@ -296,7 +439,59 @@ fn matches_pattern(filename: &str, pattern: &str) -> bool {
     }
 }
 
-fn find_functions(path: &str, language: &str) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error>> {
+/// 遍历时跳过的目录（构建产物、依赖、版本控制）
+const IGNORED_DIRS: &[&str] = &[".git", "target", "node_modules", ".vscode", ".idea", "dist", "build", "__pycache__", ".venv"];
+
+/// find_functions 默认的最大递归深度
+const DEFAULT_FUNCTION_SEARCH_DEPTH: usize = 10;
+
+/// find_functions 默认最多返回的函数数
+const DEFAULT_FUNCTION_SEARCH_RESULTS: usize = 200;
+
+/// find_functions 的遍历范围
+#[derive(Debug, Clone)]
+pub struct FunctionSearchOptions {
+    /// 最大递归深度（起始目录下的文件深度为 1）
+    pub max_depth: usize,
+    /// 最多返回的函数数，超出时结果被截断
+    pub max_results: usize,
+    /// 文件名过滤（如 *.rs）
+    pub include_pattern: Option<String>,
+}
+
+impl Default for FunctionSearchOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_FUNCTION_SEARCH_DEPTH,
+            max_results: DEFAULT_FUNCTION_SEARCH_RESULTS,
+            include_pattern: None,
+        }
+    }
+}
+
+/// 找到的函数；truncated 表示达到 max_results 后停止了遍历
+#[derive(Debug, Clone, Default)]
+pub struct FunctionMatches {
+    pub functions: Vec<serde_json::Value>,
+    pub truncated: bool,
+}
+
+/// 在目录树中查找函数定义（在阻塞线程池中运行，不阻塞 UI）
+pub async fn find_functions_async(
+    path: String,
+    language: String,
+    options: FunctionSearchOptions,
+    control: ScanControl,
+) -> Result<ScanOutcome<FunctionMatches>, Box<dyn std::error::Error + Send + Sync>> {
+    tokio::task::spawn_blocking(move || find_functions(&path, &language, &options, &control)).await?
+}
+
+fn find_functions(
+    path: &str,
+    language: &str,
+    options: &FunctionSearchOptions,
+    control: &ScanControl,
+) -> Result<ScanOutcome<FunctionMatches>, Box<dyn std::error::Error + Send + Sync>> {
     let patterns = match language.to_lowercase().as_str() {
         "rust" => vec![r"^(?:pub\s+)?(?:async\s+)?fn\s+(\w+)"],
         "python" => vec![r"^(?:def|class)\s+(\w+)"],
@@ -304,20 +499,40 @@ fn find_functions(path: &str, language: &str) -> Result<Vec<serde_json::Value>,
         _ => return Err(format!("Unsupported language: {}", language).into()),
     };
 
-    let mut functions = Vec::new();
+    let mut matches = FunctionMatches::default();
     let regexes: Vec<Regex> = patterns.iter().map(|p| Regex::new(p)).collect::<Result<_, _>>()?;
 
-    fn search_functions(path: &Path, regexes: &[Regex], functions: &mut Vec<serde_json::Value>) -> Result<(), Box<dyn std::error::Error>> {
+    fn search_functions(
+        path: &Path,
+        depth: usize,
+        regexes: &[Regex],
+        options: &FunctionSearchOptions,
+        matches: &mut FunctionMatches,
+        control: &ScanControl,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if control.is_cancelled() || matches.truncated {
+            return Ok(());
+        }
         if path.is_file() {
+            if let Some(pattern) = &options.include_pattern {
+                let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                if !matches_pattern(file_name, pattern) {
+                    return Ok(());
+                }
+            }
             let content = fs::read_to_string(path)?;
             for (line_num, line) in content.lines().enumerate() {
                 for regex in regexes {
                     if let Some(captures) = regex.captures(line) {
+                        if matches.functions.len() >= options.max_results {
+                            matches.truncated = true;
+                            return Ok(());
+                        }
                         let name = captures.get(1)
                             .or_else(|| captures.get(2))
                             .map(|m| m.as_str())
                             .unwrap_or("unknown");
-                        functions.push(serde_json::json!({
+                        matches.functions.push(serde_json::json!({
                             "file": path.to_string_lossy(),
                             "line": line_num + 1,
                             "name": name,
@@ -326,17 +541,24 @@ fn find_functions(path: &str, language: &str) -> Result<Vec<serde_json::Value>,
                     }
                 }
             }
-        } else if path.is_dir() {
-            for entry in fs::read_dir(path)? {
-                let entry = entry?;
-                search_functions(&entry.path(), regexes, functions)?;
+            control.file_scanned();
+        } else if path.is_dir() && depth < options.max_depth {
+            let mut entries: Vec<_> = fs::read_dir(path)?.collect::<Result<_, _>>()?;
+            entries.sort_by_key(|e| e.path());
+            for entry in entries {
+                let entry_path = entry.path();
+                let ignored = entry_path.is_dir()
+                    && entry_path.file_name().and_then(|n| n.to_str()).is_some_and(|n| IGNORED_DIRS.contains(&n));
+                if !ignored {
+                    search_functions(&entry_path, depth + 1, regexes, options, matches, control)?;
+                }
             }
         }
         Ok(())
     }
 
-    search_functions(Path::new(path), &regexes, &mut functions)?;
-    Ok(functions)
+    search_functions(Path::new(path), 0, &regexes, options, &mut matches, control)?;
+    Ok(ScanOutcome::new(matches, control))
 }
 
 fn analyze_code_structure(path: &str, language: &str) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
@@ -465,4 +687,128 @@ class Calculator:
         let functions = result.data["functions"].as_array().unwrap();
         assert!(functions.len() >= 2); // Should find function and class
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_find_functions_cancel_mid_walk() {
+        let temp_dir = tempdir().unwrap();
+        for i in 0..50 {
+            fs::write(temp_dir.path().join(format!("f{:02}.rs", i)), format!("fn func_{}() {{}}\n", i)).unwrap();
+        }
+
+        // 扫描到第 10 个文件时取消
+        let control = ScanControl::new();
+        let cancel_handle = control.clone();
+        let control = control.with_progress(move |scanned| {
+            if scanned >= 10 {
+                cancel_handle.cancel();
+            }
+        });
+
+        let outcome = find_functions_async(
+            temp_dir.path().to_string_lossy().to_string(),
+            "rust".to_string(),
+            FunctionSearchOptions::default(),
+            control,
+        )
+        .await
+        .unwrap();
+
+        assert!(outcome.cancelled);
+        assert_eq!(outcome.files_scanned, 10);
+        assert_eq!(outcome.result.functions.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_find_functions_caps_results_and_skips_ignored_dirs() {
+        let temp_dir = tempdir().unwrap();
+        let src = temp_dir.path().join("src");
+        let target = temp_dir.path().join("target").join("debug");
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir_all(&target).unwrap();
+        fs::write(src.join("lib.rs"), "fn alpha() {}\nfn beta() {}\nfn gamma() {}\n").unwrap();
+        fs::write(src.join("notes.txt"), "fn not_code() {}\n").unwrap();
+        fs::write(target.join("generated.rs"), "fn generated() {}\n").unwrap();
+
+        let call = |max_results: u32| ToolCall {
+            tool_name: "find_functions".to_string(),
+            arguments: [
+                ("path".to_string(), serde_json::json!(temp_dir.path().to_str())),
+                ("language".to_string(), serde_json::json!("rust")),
+                ("max_results".to_string(), serde_json::json!(max_results)),
+                ("include_pattern".to_string(), serde_json::json!("*.rs")),
+            ].into(),
+        };
+
+        let result = FunctionFinderTool.execute(call(100)).await;
+        assert!(result.success, "{:?}", result.error);
+        let names: Vec<&str> = result.data["functions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["alpha", "beta", "gamma"]);
+        assert_eq!(result.data["truncated"], false);
+
+        let result = FunctionFinderTool.execute(call(2)).await;
+        assert_eq!(result.data["functions"].as_array().unwrap().len(), 2);
+        assert_eq!(result.data["truncated"], true);
+    }
+
+    #[test]
+    fn test_search_skips_rust_comments() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("lib.rs");
+        fs::write(
+            &path,
+            "// legacy_api is deprecated\n/* call legacy_api\n   here */ fn a() {}\nlet url = \"http://x\"; legacy_api();\n",
+        )
+        .unwrap();
+        let dir = temp_dir.path().to_string_lossy().to_string();
+
+        let lines = |results: Vec<serde_json::Value>| results.iter().map(|r| r["line"].as_u64().unwrap()).collect::<Vec<_>>();
+        assert_eq!(lines(search_code("legacy_api", &dir, None, false, false, false).unwrap()), vec![1, 2, 4]);
+        // 字符串中的 // 不是注释，同一行后面的调用仍能匹配
+        assert_eq!(lines(search_code("legacy_api", &dir, None, false, true, false).unwrap()), vec![4]);
+        assert_eq!(lines(search_code("fn a", &dir, None, false, true, false).unwrap()), vec![3]);
+    }
+
+    #[test]
+    fn test_search_skips_python_comments() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("app.py");
+        fs::write(&path, "# TODO: remove load_config\nname = '#load_config'\nload_config()  # load_config here\n").unwrap();
+        let dir = temp_dir.path().to_string_lossy().to_string();
+
+        let results = search_code("load_config", &dir, None, false, false, false).unwrap();
+        assert_eq!(results.len(), 3);
+        let results = search_code("load_config", &dir, None, false, true, false).unwrap();
+        let lines: Vec<u64> = results.iter().map(|r| r["line"].as_u64().unwrap()).collect();
+        assert_eq!(lines, vec![2, 3]);
+        assert_eq!(results[1]["content"], "load_config()  # load_config here");
+    }
+
+    #[tokio::test]
+    async fn test_search_is_literal_unless_regex_flag_set() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "a.b\naxb\n").unwrap();
+
+        let search = |regex: Option<bool>| {
+            let mut arguments: std::collections::HashMap<String, serde_json::Value> = [
+                ("pattern".to_string(), serde_json::json!("a.b")),
+                ("path".to_string(), serde_json::json!(temp_dir.path().to_str())),
+            ].into();
+            if let Some(regex) = regex {
+                arguments.insert("regex".to_string(), serde_json::json!(regex));
+            }
+            CodeSearchTool.execute(ToolCall { tool_name: "search_code".to_string(), arguments })
+        };
+        let contents = |result: ToolResult| {
+            result.data["matches"].as_array().unwrap().iter().map(|m| m["content"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(contents(search(None).await), vec!["a.b"]);
+        assert_eq!(contents(search(Some(false)).await), vec!["a.b"]);
+        assert_eq!(contents(search(Some(true)).await), vec!["a.b", "axb"]);
+    }
+}
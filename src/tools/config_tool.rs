@@ -0,0 +1,194 @@
+/// 配置文件读取工具
+/// 读取 `.env`、`config.toml`、YAML、JSON 等配置文件，并把密钥类字段（API_KEY、TOKEN、SECRET、PASSWORD 等）的值替换为掩码，
+/// 让模型能理解配置结构而不会看到密钥本身
+
+use super::tool::{Tool, ToolCall, ToolDefinition, ToolParameter, ToolResult, ToolExecutionContext};
+use std::pin::Pin;
+use std::future::Future;
+
+/// 替换密钥值的掩码
+const MASK: &str = "********";
+
+/// 键名（统一为大写、下划线分隔后）包含这些片段时视为密钥
+const SECRET_KEY_PATTERNS: &[&str] = &[
+    "API_KEY", "APIKEY", "TOKEN", "SECRET", "PASSWORD", "PASSWD", "PRIVATE_KEY", "CREDENTIAL",
+];
+
+/// 配置文件读取工具
+pub struct ReadConfigTool;
+
+impl Tool for ReadConfigTool {
+    fn name(&self) -> &str {
+        "read_config"
+    }
+
+    fn description(&self) -> &str {
+        "读取环境变量或配置文件（.env、config.toml、YAML、JSON），密钥类字段的值会被掩码"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name().to_string(),
+            description: self.description().to_string(),
+            parameters: vec![ToolParameter {
+                name: "path".to_string(),
+                description: "配置文件路径".to_string(),
+                param_type: "string".to_string(),
+                required: true,
+            }],
+        }
+    }
+
+    fn execute(&self, call: ToolCall) -> Pin<Box<dyn Future<Output = ToolResult> + Send + '_>> {
+        Box::pin(async move {
+            let ctx = ToolExecutionContext::new(call.tool_name, call.arguments);
+
+            let path = match ctx.get_string("path") {
+                Some(p) => p,
+                None => return ToolResult {
+                    success: false,
+                    data: serde_json::json!(null),
+                    error: Some("Missing required parameter: path".to_string()),
+                },
+            };
+
+            match tokio::fs::read_to_string(&path).await {
+                Ok(content) => {
+                    let (masked, masked_keys) = mask_secrets(&content);
+                    ToolResult {
+                        success: true,
+                        data: serde_json::json!({
+                            "path": path,
+                            "content": masked,
+                            "masked_keys": masked_keys,
+                        }),
+                        error: None,
+                    }
+                }
+                Err(e) => ToolResult {
+                    success: false,
+                    data: serde_json::json!({ "path": path }),
+                    error: Some(format!("读取配置文件失败: {}", e)),
+                },
+            }
+        })
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
+
+/// 掩码所有密钥字段的值，返回掩码后的内容和被掩码的键名
+pub fn mask_secrets(content: &str) -> (String, Vec<String>) {
+    let mut masked_keys = Vec::new();
+    let lines: Vec<String> = content
+        .lines()
+        .map(|line| match mask_line(line) {
+            Some((key, masked)) => {
+                masked_keys.push(key);
+                masked
+            }
+            None => line.to_string(),
+        })
+        .collect();
+
+    let mut masked = lines.join("\n");
+    if content.ends_with('\n') {
+        masked.push('\n');
+    }
+    (masked, masked_keys)
+}
+
+/// `KEY=value`、`key = "value"`、`key: value`、`"key": "value",`；
+/// 键是密钥且值非空时返回 (键名, 掩码后的行)
+fn mask_line(line: &str) -> Option<(String, String)> {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with('#') || trimmed.starts_with(';') || trimmed.starts_with("//") {
+        return None;
+    }
+
+    let separator = line.find(['=', ':'])?;
+    let raw_key = line[..separator].trim();
+    let key = raw_key
+        .strip_prefix("export ")
+        .unwrap_or(raw_key)
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'');
+    if !is_secret_key(key) {
+        return None;
+    }
+
+    let value = line[separator + 1..].trim();
+    let (value, trailing_comma) = match value.strip_suffix(',') {
+        Some(v) => (v.trim_end(), ","),
+        None => (value, ""),
+    };
+    // 值为空（如 YAML 的 `secrets:` 小节）时没有需要隐藏的内容
+    if value.is_empty() || value == "\"\"" || value == "''" {
+        return None;
+    }
+
+    let quote = match value.chars().next() {
+        Some(q @ ('"' | '\'')) => q.to_string(),
+        _ => String::new(),
+    };
+    let separator_and_space = &line[separator..line.len() - line[separator + 1..].trim_start().len()];
+    Some((
+        key.to_string(),
+        format!("{}{}{}{}{}{}", &line[..separator], separator_and_space, quote, MASK, quote, trailing_comma),
+    ))
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let normalized = key.to_uppercase().replace(['-', '.', ' '], "_");
+    SECRET_KEY_PATTERNS.iter().any(|pattern| normalized.contains(pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_read_config_masks_secret_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".env");
+        std::fs::write(
+            &path,
+            "# LLM 配置\nOPENAI_API_KEY=sk-live-123456\nMODEL=gpt-4o\nexport GITHUB_TOKEN=\"ghp_abc\"\nDB_PASSWORD = hunter2\nPORT=8080\nEMPTY_SECRET=\n",
+        )
+        .unwrap();
+
+        let mut arguments = HashMap::new();
+        arguments.insert("path".to_string(), serde_json::json!(path.to_string_lossy()));
+        let result = ReadConfigTool
+            .execute(ToolCall { tool_name: "read_config".to_string(), arguments })
+            .await;
+        assert!(result.success);
+
+        let content = result.data["content"].as_str().unwrap();
+        assert!(!content.contains("sk-live-123456"));
+        assert!(!content.contains("ghp_abc"));
+        assert!(!content.contains("hunter2"));
+        assert!(content.contains("OPENAI_API_KEY=********"));
+        assert!(content.contains("export GITHUB_TOKEN=\"********\""));
+        assert!(content.contains("DB_PASSWORD = ********"));
+        assert!(content.contains("MODEL=gpt-4o"));
+        assert!(content.contains("PORT=8080"));
+        assert!(content.contains("EMPTY_SECRET=\n"));
+        assert_eq!(result.data["masked_keys"], serde_json::json!(["OPENAI_API_KEY", "GITHUB_TOKEN", "DB_PASSWORD"]));
+    }
+
+    #[test]
+    fn test_mask_secrets_in_toml_and_json() {
+        let (masked, keys) = mask_secrets("[server]\nhost = \"0.0.0.0\"\nsecret-key = 'abc'\n");
+        assert!(masked.contains("host = \"0.0.0.0\""));
+        assert!(masked.contains("secret-key = '********'"));
+        assert_eq!(keys, vec!["secret-key"]);
+
+        let (masked, _) = mask_secrets("{\n  \"apiKey\": \"xyz\",\n  \"name\": \"demo\"\n}");
+        assert!(masked.contains("\"apiKey\": \"********\","));
+        assert!(masked.contains("\"name\": \"demo\""));
+    }
+}
@@ -2,6 +2,7 @@
 /// 提供文件读取、写入、修改等功能
 
 use super::tool::{Tool, ToolCall, ToolDefinition, ToolParameter, ToolResult, ToolExecutionContext};
+use crate::fs::path_guard::PathGuard;
 use std::fs;
 use std::path::Path;
 use std::pin::Pin;
@@ -99,10 +100,23 @@ impl Tool for FileReadTool {
             }
         })
     }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
 }
 
-/// 文件写入工具
-pub struct FileWriteTool;
+/// 文件写入工具（写入路径受项目根目录约束）
+#[derive(Default)]
+pub struct FileWriteTool {
+    guard: PathGuard,
+}
+
+impl FileWriteTool {
+    pub fn new(guard: PathGuard) -> Self {
+        Self { guard }
+    }
+}
 
 impl Tool for FileWriteTool {
     fn name(&self) -> &str {
@@ -156,6 +170,14 @@ impl Tool for FileWriteTool {
                 },
             };
 
+            if let Err(e) = self.guard.check(&path) {
+                return ToolResult {
+                    success: false,
+                    data: serde_json::json!(null),
+                    error: Some(e),
+                };
+            }
+
             // 确保父目录存在
             if let Some(parent) = Path::new(&path).parent() {
                 if let Err(e) = fs::create_dir_all(parent) {
@@ -252,6 +274,10 @@ impl Tool for FileListTool {
             }
         })
     }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
 }
 
 fn list_directory(path: &str, recursive: bool) -> Result<Vec<serde_json::Value>, std::io::Error> {
@@ -302,7 +328,7 @@ mod tests {
         let file_path = temp_dir.path().join("test.txt");
 
         // 写入文件
-        let write_tool = FileWriteTool;
+        let write_tool = FileWriteTool::new(PathGuard::new(temp_dir.path()));
         let write_call = ToolCall {
             tool_name: "write_file".to_string(),
             arguments: [
@@ -331,6 +357,39 @@ mod tests {
         assert!(content.contains("Second line"));
     }
 
+    #[tokio::test]
+    async fn test_file_write_outside_root_is_blocked() {
+        let temp_dir = tempdir().unwrap();
+        let project = temp_dir.path().join("project");
+        fs::create_dir(&project).unwrap();
+
+        let write_tool = FileWriteTool::new(PathGuard::new(&project));
+
+        let inside = project.join("inside.txt");
+        let inside_call = ToolCall {
+            tool_name: "write_file".to_string(),
+            arguments: [
+                ("path".to_string(), serde_json::json!(inside.to_str())),
+                ("content".to_string(), serde_json::json!("ok")),
+            ].into(),
+        };
+        assert!(write_tool.execute(inside_call).await.success);
+        assert!(inside.exists());
+
+        let outside = project.join("../outside.txt");
+        let outside_call = ToolCall {
+            tool_name: "write_file".to_string(),
+            arguments: [
+                ("path".to_string(), serde_json::json!(outside.to_str())),
+                ("content".to_string(), serde_json::json!("nope")),
+            ].into(),
+        };
+        let result = write_tool.execute(outside_call).await;
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("outside the project root"));
+        assert!(!temp_dir.path().join("outside.txt").exists());
+    }
+
     #[tokio::test]
     async fn test_list_directory() {
         let temp_dir = tempdir().unwrap();
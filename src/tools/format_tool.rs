@@ -0,0 +1,324 @@
+/// 代码格式化工具
+/// 根据文件扩展名或项目类型选择格式化器（cargo fmt / prettier / black），返回格式化前后的差异
+
+use super::tool::{Tool, ToolCall, ToolDefinition, ToolParameter, ToolResult, ToolExecutionContext};
+use crate::core::diff::diff_lines;
+use crate::fs::path_guard::PathGuard;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::future::Future;
+
+/// 支持的格式化器
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Formatter {
+    Cargo,
+    Prettier,
+    Black,
+}
+
+impl Formatter {
+    /// 按文件扩展名选择
+    fn for_file(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Self::Cargo),
+            "js" | "jsx" | "ts" | "tsx" | "json" | "css" | "scss" | "html" | "md" | "yaml" | "yml" => Some(Self::Prettier),
+            "py" => Some(Self::Black),
+            _ => None,
+        }
+    }
+
+    /// 按项目标记文件选择
+    fn for_project(dir: &Path) -> Option<Self> {
+        if dir.join("Cargo.toml").exists() {
+            Some(Self::Cargo)
+        } else if dir.join("package.json").exists() {
+            Some(Self::Prettier)
+        } else if ["pyproject.toml", "setup.py", "requirements.txt"].iter().any(|f| dir.join(f).exists()) {
+            Some(Self::Black)
+        } else {
+            None
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo fmt",
+            Self::Prettier => "prettier",
+            Self::Black => "black",
+        }
+    }
+
+    /// 格式化器会处理的文件（用于对比格式化前后的内容）
+    fn handles(&self, path: &Path) -> bool {
+        Self::for_file(path) == Some(*self)
+    }
+
+    /// 构建命令：(程序, 参数, 工作目录)
+    fn command(&self, target: &Path, is_file: bool, check: bool) -> (&'static str, Vec<String>, PathBuf) {
+        let target_str = target.to_string_lossy().to_string();
+        // 单个文件时 target 可能是相对路径，保持当前工作目录
+        let cwd = if is_file { PathBuf::from(".") } else { target.to_path_buf() };
+
+        match (self, is_file) {
+            (Self::Cargo, true) => {
+                let mut args = vec!["--edition".to_string(), "2021".to_string()];
+                if check {
+                    args.push("--check".to_string());
+                }
+                args.push(target_str);
+                ("rustfmt", args, cwd)
+            }
+            (Self::Cargo, false) => {
+                let args = if check { vec!["fmt".into(), "--".into(), "--check".into()] } else { vec!["fmt".into()] };
+                ("cargo", args, cwd)
+            }
+            (Self::Prettier, _) => {
+                let mode = if check { "--check" } else { "--write" };
+                ("prettier", vec![mode.to_string(), target_str], cwd)
+            }
+            (Self::Black, _) => {
+                let mut args = if check { vec!["--check".to_string(), "--diff".to_string()] } else { Vec::new() };
+                args.push(target_str);
+                ("black", args, cwd)
+            }
+        }
+    }
+}
+
+/// 代码格式化工具（写入路径受项目根目录约束）
+#[derive(Default)]
+pub struct FormatTool {
+    guard: PathGuard,
+}
+
+impl FormatTool {
+    pub fn new(guard: PathGuard) -> Self {
+        Self { guard }
+    }
+}
+
+impl Tool for FormatTool {
+    fn name(&self) -> &str {
+        "format_code"
+    }
+
+    fn description(&self) -> &str {
+        "使用项目的格式化器（cargo fmt / prettier / black）格式化文件或整个项目，返回改动的差异；check 模式只报告不写入"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name().to_string(),
+            description: self.description().to_string(),
+            parameters: vec![
+                ToolParameter {
+                    name: "path".to_string(),
+                    description: "要格式化的文件或项目目录（默认当前目录）".to_string(),
+                    param_type: "string".to_string(),
+                    required: false,
+                },
+                ToolParameter {
+                    name: "check".to_string(),
+                    description: "只检查并报告需要格式化的内容，不写入（默认false）".to_string(),
+                    param_type: "boolean".to_string(),
+                    required: false,
+                },
+            ],
+        }
+    }
+
+    fn execute(&self, call: ToolCall) -> Pin<Box<dyn Future<Output = ToolResult> + Send + '_>> {
+        Box::pin(async move {
+            let ctx = ToolExecutionContext::new(call.tool_name, call.arguments);
+            let path = ctx.get_string("path").unwrap_or_else(|| ".".to_string());
+            let check = ctx.get_bool("check").unwrap_or(false);
+
+            if !check {
+                if let Err(e) = self.guard.check(&path) {
+                    return ToolResult {
+                        success: false,
+                        data: serde_json::json!(null),
+                        error: Some(e),
+                    };
+                }
+            }
+
+            match format_path(Path::new(&path), check).await {
+                Ok(data) => ToolResult {
+                    success: true,
+                    data,
+                    error: None,
+                },
+                Err(e) => ToolResult {
+                    success: false,
+                    data: serde_json::json!({ "path": path }),
+                    error: Some(e),
+                },
+            }
+        })
+    }
+}
+
+/// 收集格式化器会处理的文件内容（格式化前后对比用）
+fn snapshot(target: &Path, formatter: Formatter) -> BTreeMap<PathBuf, String> {
+    let mut files = BTreeMap::new();
+    if target.is_file() {
+        if let Ok(content) = std::fs::read_to_string(target) {
+            files.insert(target.to_path_buf(), content);
+        }
+        return files;
+    }
+
+    for entry in ignore::WalkBuilder::new(target).build().flatten() {
+        let path = entry.path();
+        if path.is_file() && formatter.handles(path) {
+            if let Ok(content) = std::fs::read_to_string(path) {
+                files.insert(path.to_path_buf(), content);
+            }
+        }
+    }
+    files
+}
+
+async fn format_path(target: &Path, check: bool) -> Result<serde_json::Value, String> {
+    if !target.exists() {
+        return Err(format!("路径不存在: {}", target.display()));
+    }
+    let is_file = target.is_file();
+    let formatter = if is_file { Formatter::for_file(target) } else { Formatter::for_project(target) }
+        .ok_or_else(|| format!("无法识别 {} 的项目类型，没有可用的格式化器", target.display()))?;
+
+    let before = if check { BTreeMap::new() } else { snapshot(target, formatter) };
+
+    let (program, args, cwd) = formatter.command(target, is_file, check);
+    let output = match tokio::process::Command::new(program).args(&args).current_dir(&cwd).output().await {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(format!("未安装 {}，无法格式化（请先安装后重试）", formatter.name()));
+        }
+        Err(e) => return Err(format!("运行 {} 失败: {}", formatter.name(), e)),
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if check {
+        // check 模式下退出码 1 表示存在未格式化的文件
+        return match output.status.code() {
+            Some(0) => Ok(serde_json::json!({
+                "formatter": formatter.name(),
+                "check": true,
+                "needs_formatting": false,
+            })),
+            Some(1) => Ok(serde_json::json!({
+                "formatter": formatter.name(),
+                "check": true,
+                "needs_formatting": true,
+                "report": format!("{}{}", stdout, stderr).trim(),
+            })),
+            _ => Err(format!("{} 执行失败: {}", formatter.name(), stderr.trim())),
+        };
+    }
+
+    if !output.status.success() {
+        return Err(format!("{} 执行失败: {}", formatter.name(), stderr.trim()));
+    }
+
+    let mut changed_files = Vec::new();
+    let mut diff = String::new();
+    for (path, old_content) in &before {
+        let Ok(new_content) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        if &new_content != old_content {
+            changed_files.push(path.display().to_string());
+            diff.push_str(&format!("--- {}\n+++ {}\n", path.display(), path.display()));
+            diff.push_str(&line_diff(old_content, &new_content));
+        }
+    }
+
+    Ok(serde_json::json!({
+        "formatter": formatter.name(),
+        "check": false,
+        "changed_files": changed_files,
+        "diff": diff,
+    }))
+}
+
+/// 逐行差异（只输出变化的行）
+fn line_diff(old: &str, new: &str) -> String {
+    diff_lines(old, new)
+        .iter()
+        .filter(|line| line.is_change())
+        .map(|line| format!("{}\n", line.render()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    const MESSY: &str = "fn main(){let x=1;println!(\"{}\",x);}\n";
+
+    fn cargo_project() -> tempfile::TempDir {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"fmt_test\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), MESSY).unwrap();
+        dir
+    }
+
+    fn format_call(path: &Path, check: bool) -> ToolCall {
+        ToolCall {
+            tool_name: "format_code".to_string(),
+            arguments: [
+                ("path".to_string(), serde_json::json!(path.to_str())),
+                ("check".to_string(), serde_json::json!(check)),
+            ].into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cargo_fmt_reformats_project() {
+        let dir = cargo_project();
+        let tool = FormatTool::new(PathGuard::new(dir.path()));
+
+        // 没有安装 cargo fmt 时跳过
+        let result = tool.execute(format_call(dir.path(), false)).await;
+        if result.error.as_deref().is_some_and(|e| e.contains("未安装")) {
+            return;
+        }
+        assert!(result.success, "{:?}", result.error);
+
+        let changed = result.data["changed_files"].as_array().unwrap();
+        assert_eq!(changed.len(), 1);
+        assert!(changed[0].as_str().unwrap().ends_with("main.rs"));
+        assert!(result.data["diff"].as_str().unwrap().contains("-fn main(){let x=1;"));
+
+        let formatted = fs::read_to_string(dir.path().join("src/main.rs")).unwrap();
+        assert!(formatted.contains("let x = 1;"));
+    }
+
+    #[tokio::test]
+    async fn test_check_mode_does_not_write() {
+        let dir = cargo_project();
+        let tool = FormatTool::new(PathGuard::new(dir.path()));
+
+        let result = tool.execute(format_call(dir.path(), true)).await;
+        if result.error.as_deref().is_some_and(|e| e.contains("未安装")) {
+            return;
+        }
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.data["needs_formatting"], true);
+        assert_eq!(fs::read_to_string(dir.path().join("src/main.rs")).unwrap(), MESSY);
+    }
+
+    #[test]
+    fn test_line_diff() {
+        assert_eq!(line_diff("a\nb\nc", "a\nB\nc"), "-b\n+B\n");
+        assert_eq!(line_diff("same", "same"), "");
+    }
+}
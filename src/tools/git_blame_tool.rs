@@ -0,0 +1,260 @@
+/// Git blame 工具
+/// 对文件的指定行范围运行 `git blame -L`，返回每行的作者、提交和日期，用于回答“这段代码是谁写的、为什么”
+
+use super::tool::{Tool, ToolCall, ToolDefinition, ToolParameter, ToolResult, ToolExecutionContext};
+use chrono::{TimeZone, Utc};
+use std::path::Path;
+use std::pin::Pin;
+use std::future::Future;
+
+/// 一行的 blame 信息
+#[derive(Debug, Clone, PartialEq)]
+struct BlameLine {
+    line: usize,
+    commit: String,
+    author: String,
+    email: String,
+    date: String,
+    summary: String,
+    content: String,
+}
+
+impl BlameLine {
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "line": self.line,
+            "commit": self.commit,
+            "author": self.author,
+            "email": self.email,
+            "date": self.date,
+            "summary": self.summary,
+            "content": self.content,
+        })
+    }
+}
+
+/// Git blame 工具
+pub struct GitBlameTool;
+
+impl Tool for GitBlameTool {
+    fn name(&self) -> &str {
+        "git_blame"
+    }
+
+    fn description(&self) -> &str {
+        "查看文件指定行范围的 git blame：每行的作者、提交、日期和提交说明"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name().to_string(),
+            description: self.description().to_string(),
+            parameters: vec![
+                ToolParameter {
+                    name: "path".to_string(),
+                    description: "文件路径".to_string(),
+                    param_type: "string".to_string(),
+                    required: true,
+                },
+                ToolParameter {
+                    name: "start_line".to_string(),
+                    description: "起始行号（可选，从1开始）".to_string(),
+                    param_type: "number".to_string(),
+                    required: false,
+                },
+                ToolParameter {
+                    name: "end_line".to_string(),
+                    description: "结束行号（可选，默认到文件末尾）".to_string(),
+                    param_type: "number".to_string(),
+                    required: false,
+                },
+            ],
+        }
+    }
+
+    fn execute(&self, call: ToolCall) -> Pin<Box<dyn Future<Output = ToolResult> + Send + '_>> {
+        Box::pin(async move {
+            let ctx = ToolExecutionContext::new(call.tool_name, call.arguments);
+
+            let path = match ctx.get_string("path") {
+                Some(p) => p,
+                None => return ToolResult {
+                    success: false,
+                    data: serde_json::json!(null),
+                    error: Some("Missing required parameter: path".to_string()),
+                },
+            };
+            let start_line = ctx.get_number("start_line").map(|n| (n as usize).max(1));
+            let end_line = ctx.get_number("end_line").map(|n| n as usize);
+
+            match blame(Path::new(&path), start_line, end_line).await {
+                Ok(lines) => ToolResult {
+                    success: true,
+                    data: serde_json::json!({
+                        "path": path,
+                        "lines": lines.iter().map(BlameLine::to_json).collect::<Vec<_>>(),
+                    }),
+                    error: None,
+                },
+                Err(e) => ToolResult {
+                    success: false,
+                    data: serde_json::json!({ "path": path }),
+                    error: Some(e),
+                },
+            }
+        })
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
+
+async fn blame(path: &Path, start_line: Option<usize>, end_line: Option<usize>) -> Result<Vec<BlameLine>, String> {
+    if !path.is_file() {
+        return Err(format!("文件不存在: {}", path.display()));
+    }
+    // 在文件所在目录运行，文件不必位于当前工作目录的仓库中
+    let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+
+    let inside_repo = tokio::process::Command::new("git")
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .current_dir(dir)
+        .output()
+        .await
+        .map_err(|e| format!("无法运行 git: {}", e))?;
+    if !inside_repo.status.success() {
+        return Err(format!("{} 不在 git 仓库中，无法查看 blame", path.display()));
+    }
+
+    let mut args = vec!["blame".to_string(), "--line-porcelain".to_string()];
+    match (start_line, end_line) {
+        (Some(start), Some(end)) => args.push(format!("-L{},{}", start, end.max(start))),
+        (Some(start), None) => args.push(format!("-L{},", start)),
+        (None, Some(end)) => args.push(format!("-L1,{}", end.max(1))),
+        (None, None) => {}
+    }
+    args.push("--".to_string());
+    args.push(file_name);
+
+    let output = tokio::process::Command::new("git")
+        .args(&args)
+        .current_dir(dir)
+        .output()
+        .await
+        .map_err(|e| format!("无法运行 git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("git blame 失败: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    Ok(parse_line_porcelain(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// 解析 `git blame --line-porcelain` 输出：每行以 `<sha> <原行号> <行号> [<行数>]` 开头，
+/// 后跟若干 `key value` 头部，最后是以制表符开头的行内容
+fn parse_line_porcelain(output: &str) -> Vec<BlameLine> {
+    let mut lines = Vec::new();
+    let mut current: Option<BlameLine> = None;
+
+    for raw in output.lines() {
+        if let Some(content) = raw.strip_prefix('\t') {
+            if let Some(mut line) = current.take() {
+                line.content = content.to_string();
+                lines.push(line);
+            }
+            continue;
+        }
+
+        let (key, value) = raw.split_once(' ').unwrap_or((raw, ""));
+        match current.as_mut() {
+            None => {
+                let line_number = value.split(' ').nth(1).and_then(|n| n.parse().ok()).unwrap_or(0);
+                current = Some(BlameLine {
+                    line: line_number,
+                    commit: key.chars().take(8).collect(),
+                    author: String::new(),
+                    email: String::new(),
+                    date: String::new(),
+                    summary: String::new(),
+                    content: String::new(),
+                });
+            }
+            Some(line) => match key {
+                "author" => line.author = value.to_string(),
+                "author-mail" => line.email = value.trim_matches(|c| c == '<' || c == '>').to_string(),
+                "author-time" => {
+                    line.date = value
+                        .parse::<i64>()
+                        .ok()
+                        .and_then(|ts| Utc.timestamp_opt(ts, 0).single())
+                        .map(|date| date.format("%Y-%m-%d").to_string())
+                        .unwrap_or_default();
+                }
+                "summary" => line.summary = value.to_string(),
+                _ => {}
+            },
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git").args(args).current_dir(dir).output().unwrap().status;
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    fn blame_call(path: &Path, start: Option<u32>, end: Option<u32>) -> ToolCall {
+        let mut arguments = std::collections::HashMap::new();
+        arguments.insert("path".to_string(), serde_json::json!(path.to_str()));
+        if let Some(start) = start {
+            arguments.insert("start_line".to_string(), serde_json::json!(start));
+        }
+        if let Some(end) = end {
+            arguments.insert("end_line".to_string(), serde_json::json!(end));
+        }
+        ToolCall {
+            tool_name: "git_blame".to_string(),
+            arguments,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blame_attributes_lines_to_commit_author() {
+        let dir = tempfile::tempdir().unwrap();
+        git(dir.path(), &["init", "-q"]);
+        git(dir.path(), &["config", "user.name", "Ada Lovelace"]);
+        git(dir.path(), &["config", "user.email", "ada@example.com"]);
+        let file = dir.path().join("engine.rs");
+        std::fs::write(&file, "fn one() {}\nfn two() {}\nfn three() {}\n").unwrap();
+        git(dir.path(), &["add", "engine.rs"]);
+        git(dir.path(), &["commit", "-q", "-m", "Add analytical engine"]);
+
+        let result = GitBlameTool.execute(blame_call(&file, Some(2), Some(3))).await;
+        assert!(result.success, "{:?}", result.error);
+
+        let lines = result.data["lines"].as_array().unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["line"], 2);
+        assert_eq!(lines[0]["content"], "fn two() {}");
+        assert_eq!(lines[1]["author"], "Ada Lovelace");
+        assert_eq!(lines[1]["email"], "ada@example.com");
+        assert_eq!(lines[1]["summary"], "Add analytical engine");
+    }
+
+    #[tokio::test]
+    async fn test_blame_outside_repo_fails_gracefully() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("loose.txt");
+        std::fs::write(&file, "hello\n").unwrap();
+
+        let result = GitBlameTool.execute(blame_call(&file, None, None)).await;
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("不在 git 仓库中"));
+    }
+}
@@ -42,24 +42,57 @@
 pub mod tool;
 pub mod tool_registry;
 
+/// 扫描控制 - 项目级遍历的取消与进度报告
+pub mod scan_control;
+
 /// 文件操作工具 - 读取、写入、修改文件
 pub mod file_tools;
 
+/// 配置文件读取工具 - 读取 .env 等配置文件并掩码密钥
+pub mod config_tool;
+
 /// 字符串替换编辑器工具 - 替换文件中的文本内容
 pub mod str_replace_tool;
 
 /// 代码分析工具 - 语法分析、代码搜索等
 pub mod code_tools;
 
+/// 符号重命名工具 - 按标识符边界跨文件重命名
+pub mod rename_tool;
+
 /// 终端命令工具 - 执行系统命令
 pub mod terminal_tools;
 
+/// 代码格式化工具 - 调用 cargo fmt / prettier / black
+pub mod format_tool;
+
 /// 项目管理工具 - 项目结构分析、依赖管理等
 pub mod project_tools;
 
+/// 编译错误解析 - 从 cargo 输出提取 (文件, 行, 消息)
+pub mod build_errors;
+
+/// Git blame 工具 - 查看代码行的作者和提交
+pub mod git_blame_tool;
+
 /// Todo 管理工具 - 创建和更新任务列表
 pub mod todo_tool;
 
+/// 草稿本工具 - 会话内记录中间笔记
+pub mod scratchpad_tool;
+
+/// 文件摘要工具 - 请模型概括文件用途并缓存结果
+pub mod summarize_tool;
+
+/// 工具注册入口 - 内置工具与自定义工具的初始化函数
+pub mod plugins;
+
+/// 工具调用说明 - explain 模式下的通俗描述
+pub mod tool_explain;
+
+/// 工具结果显示 - 完整输出或一行摘要（/verbose）
+pub mod tool_display;
+
 /// 工具使用示例
 pub mod tool_examples;
 
@@ -69,8 +102,16 @@ pub use tool_registry::ToolRegistry;
 
 // 重新导出具体工具类，方便使用
 pub use file_tools::{FileReadTool, FileWriteTool, FileListTool};
+pub use config_tool::ReadConfigTool;
 pub use str_replace_tool::StrReplaceTool;
 pub use code_tools::{CodeSearchTool, FunctionFinderTool, CodeStructureTool};
-pub use terminal_tools::{CommandExecuteTool, EnvironmentInfoTool};
+pub use rename_tool::RenameSymbolTool;
+pub use terminal_tools::{CommandExecuteTool, CheckCommandTool, EnvironmentInfoTool};
+pub use format_tool::FormatTool;
 pub use project_tools::{ProjectStructureTool, DependencyAnalyzerTool, BuildTool};
-pub use todo_tool::{CreateTodoListTool, UpdateTodoListTool, TodoItem, TodoStatus};
\ No newline at end of file
+pub use build_errors::{CompilerError, parse_cargo_errors};
+pub use git_blame_tool::GitBlameTool;
+pub use todo_tool::{CreateTodoListTool, UpdateTodoListTool, TodoItem, TodoStatus};
+pub use scratchpad_tool::ScratchpadTool;
+pub use summarize_tool::SummarizeFileTool;
+pub use plugins::{ToolInitFn, register_builtin_tools};
\ No newline at end of file
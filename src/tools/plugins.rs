@@ -0,0 +1,135 @@
+/// 工具注册入口
+///
+/// 内置工具由 `register_builtin_tools` 注册。下游项目或分支要添加自己的工具时，
+/// 在 `register_custom_tools` 中调用 `registry.register(Arc::new(MyTool))` 即可，不需要修改 Agent 或 App：
+///
+/// ```rust,ignore
+/// pub fn register_custom_tools(registry: &mut ToolRegistry) {
+///     registry.register(Arc::new(MyTool));
+/// }
+/// ```
+///
+/// main.rs 在启动时把它加入 `App::tool_plugins`，每个 Agent 注册完内置工具后按顺序调用这些初始化函数；
+/// 与内置工具同名的工具会覆盖内置实现
+
+use super::*;
+use crate::fs::path_guard::PathGuard;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// 工具初始化函数：向注册表中注册额外的工具
+pub type ToolInitFn = fn(&mut ToolRegistry);
+
+/// 注册所有内置工具；会写文件的工具使用 `guard` 约束写入路径（与 /create、/modify 等文件命令相同）
+pub fn register_builtin_tools(
+    registry: &mut ToolRegistry,
+    todo_manager: Arc<Mutex<todo_tool::TodoManager>>,
+    guard: &PathGuard,
+) {
+    // 文件工具
+    registry.register(Arc::new(FileReadTool));
+    registry.register(Arc::new(FileWriteTool::new(guard.clone())));
+    registry.register(Arc::new(FileListTool));
+    registry.register(Arc::new(ReadConfigTool));
+    registry.register(Arc::new(StrReplaceTool::new(guard.clone())));
+
+    // 终端工具
+    registry.register(Arc::new(CommandExecuteTool));
+    registry.register(Arc::new(CheckCommandTool));
+    registry.register(Arc::new(EnvironmentInfoTool));
+
+    // 代码工具
+    registry.register(Arc::new(CodeSearchTool));
+    registry.register(Arc::new(FunctionFinderTool));
+    registry.register(Arc::new(CodeStructureTool));
+    registry.register(Arc::new(RenameSymbolTool::new(guard.clone())));
+    registry.register(Arc::new(FormatTool::new(guard.clone())));
+
+    // 项目工具
+    registry.register(Arc::new(ProjectStructureTool));
+    registry.register(Arc::new(DependencyAnalyzerTool));
+    registry.register(Arc::new(BuildTool));
+    registry.register(Arc::new(GitBlameTool));
+
+    // Todo 工具（需要共享 manager）
+    registry.register(Arc::new(CreateTodoListTool::new(todo_manager.clone())));
+    registry.register(Arc::new(UpdateTodoListTool::new(todo_manager)));
+
+    // 草稿本（内容保存在工具实例上，随 Agent 存在）
+    registry.register(Arc::new(ScratchpadTool::new()));
+}
+
+/// 自定义工具的初始化函数，默认不注册任何工具
+pub fn register_custom_tools(_registry: &mut ToolRegistry) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::tool::Tool;
+    use std::collections::HashMap;
+    use std::future::Future;
+    use std::pin::Pin;
+
+    struct EchoTool;
+
+    impl Tool for EchoTool {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn description(&self) -> &str {
+            "Echo the text argument"
+        }
+
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition {
+                name: self.name().to_string(),
+                description: self.description().to_string(),
+                parameters: vec![ToolParameter {
+                    name: "text".to_string(),
+                    description: "Text to echo".to_string(),
+                    param_type: "string".to_string(),
+                    required: true,
+                }],
+            }
+        }
+
+        fn execute(&self, call: ToolCall) -> Pin<Box<dyn Future<Output = ToolResult> + Send + '_>> {
+            Box::pin(async move {
+                ToolResult {
+                    success: true,
+                    data: call.arguments.get("text").cloned().unwrap_or_default(),
+                    error: None,
+                }
+            })
+        }
+
+        fn is_read_only(&self) -> bool {
+            true
+        }
+    }
+
+    fn register_echo(registry: &mut ToolRegistry) {
+        registry.register(Arc::new(EchoTool));
+    }
+
+    #[tokio::test]
+    async fn test_custom_tool_registered_by_init_function() {
+        let plugins: Vec<ToolInitFn> = vec![register_custom_tools, register_echo];
+        let mut registry = ToolRegistry::new();
+        register_builtin_tools(&mut registry, Arc::new(Mutex::new(todo_tool::TodoManager::new())), &PathGuard::default());
+        let builtin = registry.count();
+        for init in &plugins {
+            init(&mut registry);
+        }
+
+        assert_eq!(registry.count(), builtin + 1);
+        assert!(registry.list_definitions().iter().any(|d| d.name == "echo"));
+
+        let mut arguments = HashMap::new();
+        arguments.insert("text".to_string(), serde_json::json!("hello"));
+        let result = registry.execute(ToolCall { tool_name: "echo".to_string(), arguments }).await;
+        assert!(result.success);
+        assert_eq!(result.data, "hello");
+    }
+}
@@ -2,6 +2,8 @@
 /// 提供项目结构分析、依赖管理、构建工具等功能
 
 use super::tool::{Tool, ToolCall, ToolDefinition, ToolParameter, ToolResult, ToolExecutionContext};
+use super::scan_control::{ScanControl, ScanOutcome};
+use super::build_errors::parse_cargo_errors;
 use std::fs;
 use std::path::Path;
 use std::pin::Pin;
@@ -35,6 +37,10 @@ impl Tool for ProjectStructureTool {
     }
 
     fn execute(&self, call: ToolCall) -> Pin<Box<dyn Future<Output = ToolResult> + Send + '_>> {
+        self.execute_with_control(call, ScanControl::new())
+    }
+
+    fn execute_with_control(&self, call: ToolCall, control: ScanControl) -> Pin<Box<dyn Future<Output = ToolResult> + Send + '_>> {
         Box::pin(async move {
             let ctx = ToolExecutionContext::new(call.tool_name, call.arguments);
 
@@ -47,12 +53,14 @@ impl Tool for ProjectStructureTool {
                 },
             };
 
-            match analyze_project_structure(&path) {
-                Ok(analysis) => ToolResult {
+            match analyze_project_structure_async(path.clone(), control).await {
+                Ok(outcome) => ToolResult {
                     success: true,
                     data: serde_json::json!({
                         "path": path,
-                        "analysis": analysis
+                        "analysis": outcome.result,
+                        "files_scanned": outcome.files_scanned,
+                        "cancelled": outcome.cancelled
                     }),
                     error: None,
                 },
@@ -64,6 +72,10 @@ impl Tool for ProjectStructureTool {
             }
         })
     }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
 }
 
 /// 依赖分析工具
@@ -123,6 +135,10 @@ impl Tool for DependencyAnalyzerTool {
             }
         })
     }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
 }
 
 /// 构建工具
@@ -174,16 +190,25 @@ impl Tool for BuildTool {
             let target = ctx.get_string("target").unwrap_or_else(|| "debug".to_string());
 
             match build_project(&path, &target).await {
-                Ok(result) => ToolResult {
-                    success: result.success,
-                    data: serde_json::json!({
-                        "path": path,
-                        "target": target,
-                        "output": result.output,
-                        "duration_ms": result.duration_ms
-                    }),
-                    error: result.error,
-                },
+                Ok(result) => {
+                    // 失败时附带结构化的编译错误，便于定位和修复
+                    let errors: Vec<_> = if result.success {
+                        Vec::new()
+                    } else {
+                        parse_cargo_errors(&result.output).iter().map(|e| e.to_json()).collect()
+                    };
+                    ToolResult {
+                        success: result.success,
+                        data: serde_json::json!({
+                            "path": path,
+                            "target": target,
+                            "output": result.output,
+                            "errors": errors,
+                            "duration_ms": result.duration_ms
+                        }),
+                        error: result.error,
+                    }
+                }
                 Err(e) => ToolResult {
                     success: false,
                     data: serde_json::json!(null),
@@ -194,7 +219,18 @@ impl Tool for BuildTool {
     }
 }
 
-fn analyze_project_structure(path: &str) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+/// 分析项目结构（在阻塞线程池中运行，不阻塞 UI）
+pub async fn analyze_project_structure_async(
+    path: String,
+    control: ScanControl,
+) -> Result<ScanOutcome<serde_json::Value>, Box<dyn std::error::Error + Send + Sync>> {
+    tokio::task::spawn_blocking(move || analyze_project_structure(&path, &control)).await?
+}
+
+fn analyze_project_structure(
+    path: &str,
+    control: &ScanControl,
+) -> Result<ScanOutcome<serde_json::Value>, Box<dyn std::error::Error + Send + Sync>> {
     let mut analysis = serde_json::json!({
         "languages": {},
         "frameworks": [],
@@ -209,8 +245,15 @@ fn analyze_project_structure(path: &str) -> Result<serde_json::Value, Box<dyn st
         }
     });
 
-    fn analyze_directory(path: &Path, analysis: &mut serde_json::Value) -> Result<(), Box<dyn std::error::Error>> {
+    fn analyze_directory(
+        path: &Path,
+        analysis: &mut serde_json::Value,
+        control: &ScanControl,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         for entry in fs::read_dir(path)? {
+            if control.is_cancelled() {
+                return Ok(());
+            }
             let entry = entry?;
             let path = entry.path();
 
@@ -227,6 +270,7 @@ fn analyze_project_structure(path: &str) -> Result<serde_json::Value, Box<dyn st
 
                 // 检测框架和配置文件
                 detect_frameworks_and_configs(&file_name, analysis);
+                control.file_scanned();
             } else if path.is_dir() {
                 let dir_name = path.file_name()
                     .and_then(|n| n.to_str())
@@ -240,7 +284,7 @@ fn analyze_project_structure(path: &str) -> Result<serde_json::Value, Box<dyn st
 
                 // 递归分析（限制深度）
                 if path.components().count() < 5 {
-                    analyze_directory(&path, analysis)?;
+                    analyze_directory(&path, analysis, control)?;
                 }
             }
         }
@@ -248,8 +292,22 @@ fn analyze_project_structure(path: &str) -> Result<serde_json::Value, Box<dyn st
         Ok(())
     }
 
-    analyze_directory(Path::new(path), &mut analysis)?;
-    Ok(analysis)
+    analyze_directory(Path::new(path), &mut analysis, control)?;
+
+    // 同一工具可能由多个文件检测到（如 Cargo.toml 与 Cargo.lock），去重
+    for key in ["package_managers", "build_tools", "frameworks"] {
+        dedup_preserving_order(&mut analysis[key]);
+    }
+
+    Ok(ScanOutcome::new(analysis, control))
+}
+
+/// 去除 JSON 数组中的重复项，保留首次出现的顺序
+fn dedup_preserving_order(value: &mut serde_json::Value) {
+    if let Some(items) = value.as_array_mut() {
+        let mut seen = std::collections::HashSet::new();
+        items.retain(|item| seen.insert(item.to_string()));
+    }
 }
 
 fn detect_language(file_name: &str, languages: &mut serde_json::Map<String, serde_json::Value>) {
@@ -418,22 +476,43 @@ fn analyze_cargo_deps(path: &str, deps: &mut serde_json::Value) -> Result<(), Bo
     let cargo_toml_path = Path::new(path).join("Cargo.toml");
     let content = fs::read_to_string(cargo_toml_path)?;
 
-    // 简单的 TOML 解析（实际项目中应该使用 toml crate）
-    let direct = deps["direct"].as_array_mut().unwrap();
+    let manifest: toml::Table = content.parse()?;
 
-    for line in content.lines() {
-        let line = line.trim();
-        if line.starts_with('"') && line.contains(" = ") {
-            if let Some(dep_name) = line.split('"').nth(1) {
-                direct.push(serde_json::json!({
-                    "name": dep_name,
-                    "type": "cargo"
+    // [dependencies] 计入 direct；[dev-dependencies] 和 [build-dependencies] 计入 dev
+    // 平台相关的 [target.'cfg(..)'.*dependencies] 按同样规则归类
+    let mut tables = vec![&manifest];
+    if let Some(targets) = manifest.get("target").and_then(|t| t.as_table()) {
+        tables.extend(targets.values().filter_map(|t| t.as_table()));
+    }
+    for table in tables {
+        for (section, bucket, dep_type) in [
+            ("dependencies", "direct", "cargo"),
+            ("dev-dependencies", "dev", "cargo-dev"),
+            ("build-dependencies", "dev", "cargo-build"),
+        ] {
+            let Some(entries) = table.get(section).and_then(|d| d.as_table()) else {
+                continue;
+            };
+            let list = deps[bucket].as_array_mut().unwrap();
+            for (name, spec) in entries {
+                // serde = "1.0" 或 serde = { version = "1.0", ... }；path/git 依赖没有版本
+                let version = match spec {
+                    toml::Value::String(version) => Some(version.as_str()),
+                    toml::Value::Table(table) => table.get("version").and_then(|v| v.as_str()),
+                    _ => None,
+                };
+                list.push(serde_json::json!({
+                    "name": name,
+                    "version": version,
+                    "type": dep_type
                 }));
             }
         }
     }
 
-    deps["total_count"] = serde_json::json!(direct.len());
+    if let Some(direct) = deps["direct"].as_array() {
+        deps["total_count"] = serde_json::json!(direct.len());
+    }
     Ok(())
 }
 
@@ -514,6 +593,7 @@ async fn build_project(path: &str, target: &str) -> Result<BuildResult, Box<dyn
     let output = Command::new(command)
         .args(&args)
         .current_dir(path)
+        .kill_on_drop(true)
         .output()
         .await?;
 
@@ -578,14 +658,69 @@ serde = "1.0"
         assert!(analysis["package_managers"].as_array().unwrap().contains(&serde_json::json!("Cargo")));
     }
 
+    #[tokio::test]
+    async fn test_registry_passes_request_control_to_project_scan() {
+        use crate::tools::ToolRegistry;
+        use std::sync::Arc;
+
+        let temp_dir = tempdir().unwrap();
+        for i in 0..20 {
+            fs::write(temp_dir.path().join(format!("{}.rs", i)), "fn main() {}\n").unwrap();
+        }
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(ProjectStructureTool));
+        let call = || ToolCall {
+            tool_name: "analyze_project".to_string(),
+            arguments: [("path".to_string(), serde_json::json!(temp_dir.path().to_str().unwrap()))].into(),
+        };
+
+        let result = registry.execute(call()).await;
+        assert_eq!(result.data["files_scanned"], 20);
+        assert_eq!(result.data["cancelled"], false);
+
+        // 请求已被取消：扫描不再继续
+        let control = ScanControl::new();
+        control.cancel();
+        let result = registry.execute_with_control(call(), control).await;
+        assert!(result.success);
+        assert_eq!(result.data["files_scanned"], 0);
+        assert_eq!(result.data["cancelled"], true);
+    }
+
+    #[tokio::test]
+    async fn test_package_managers_are_deduplicated() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("Cargo.toml"), "[package]\nname = \"test\"\n").unwrap();
+        fs::write(temp_dir.path().join("Cargo.lock"), "version = 3\n").unwrap();
+        fs::write(temp_dir.path().join("requirements.txt"), "").unwrap();
+        fs::write(temp_dir.path().join("setup.py"), "").unwrap();
+
+        let outcome = analyze_project_structure(temp_dir.path().to_str().unwrap(), &ScanControl::new()).unwrap();
+        let package_managers = outcome.result["package_managers"].as_array().unwrap();
+
+        let count = |name: &str| package_managers.iter().filter(|pm| *pm == name).count();
+        assert_eq!(count("Cargo"), 1);
+        assert_eq!(count("pip"), 1);
+    }
+
     #[tokio::test]
     async fn test_dependency_analysis() {
         let temp_dir = tempdir().unwrap();
 
         fs::write(temp_dir.path().join("Cargo.toml"), r#"
+[package]
+name = "demo"
+
 [dependencies]
 serde = "1.0"
-tokio = "1.0"
+tokio = { version = "1.0", features = ["full"] }
+local = { path = "../local" }
+
+[dev-dependencies]
+tempfile = "3.8"
+
+[build-dependencies]
+cc = "1"
 "#).unwrap();
 
         let dep_tool = DependencyAnalyzerTool;
@@ -600,6 +735,18 @@ tokio = "1.0"
         assert!(result.success);
 
         let deps = &result.data["dependencies"];
-        assert!(deps["direct"].as_array().unwrap().len() > 0);
+        let names = |bucket: &str| -> Vec<String> {
+            deps[bucket].as_array().unwrap().iter().map(|d| d["name"].as_str().unwrap().to_string()).collect()
+        };
+        assert_eq!(names("direct"), vec!["local", "serde", "tokio"]);
+        assert_eq!(names("dev"), vec!["tempfile", "cc"]);
+        assert_eq!(deps["total_count"], 3);
+
+        let direct = deps["direct"].as_array().unwrap();
+        assert_eq!(direct[1]["version"], "1.0");
+        assert_eq!(direct[2]["version"], "1.0");
+        assert!(direct[0]["version"].is_null());
+        assert_eq!(deps["dev"][0]["type"], "cargo-dev");
+        assert_eq!(deps["dev"][1]["type"], "cargo-build");
     }
 }
\ No newline at end of file
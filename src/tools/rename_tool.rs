@@ -0,0 +1,427 @@
+/// 符号重命名工具
+/// 在整个项目中按标识符边界重命名符号，跳过字符串和注释
+
+use super::tool::{Tool, ToolCall, ToolDefinition, ToolParameter, ToolResult, ToolExecutionContext};
+use crate::fs::path_guard::PathGuard;
+use std::fs;
+use std::path::Path;
+use std::pin::Pin;
+use std::future::Future;
+
+/// 源文件的注释/字符串语法
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SourceSyntax {
+    /// Rust：// 与 /* */ 注释，"..." / r#"..."# 字符串，'c' 字符
+    Rust,
+    /// C 系语言（C/C++/Java/Go/JS/TS）：// 与 /* */ 注释，"..." / '...' / `...` 字符串
+    CLike,
+    /// Python：# 注释，'...' / "..." / 三引号字符串
+    Python,
+    /// 未知语言：不跳过任何内容
+    Plain,
+}
+
+impl SourceSyntax {
+    fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?;
+        match ext {
+            "rs" => Some(Self::Rust),
+            "c" | "h" | "cpp" | "hpp" | "cc" | "java" | "go" | "js" | "jsx" | "ts" | "tsx" => Some(Self::CLike),
+            "py" => Some(Self::Python),
+            _ => None,
+        }
+    }
+}
+
+/// 单个文件的重命名结果
+#[derive(Debug, Clone)]
+struct FileRename {
+    path: String,
+    replacements: usize,
+    new_content: String,
+}
+
+/// 符号重命名工具（写入路径受项目根目录约束）
+#[derive(Default)]
+pub struct RenameSymbolTool {
+    guard: PathGuard,
+}
+
+impl RenameSymbolTool {
+    pub fn new(guard: PathGuard) -> Self {
+        Self { guard }
+    }
+}
+
+impl Tool for RenameSymbolTool {
+    fn name(&self) -> &str {
+        "rename_symbol"
+    }
+
+    fn description(&self) -> &str {
+        "在项目源文件中按标识符边界重命名符号（跳过字符串和注释），支持 dry_run 预览受影响的文件"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name().to_string(),
+            description: self.description().to_string(),
+            parameters: vec![
+                ToolParameter {
+                    name: "old_name".to_string(),
+                    description: "原标识符".to_string(),
+                    param_type: "string".to_string(),
+                    required: true,
+                },
+                ToolParameter {
+                    name: "new_name".to_string(),
+                    description: "新标识符".to_string(),
+                    param_type: "string".to_string(),
+                    required: true,
+                },
+                ToolParameter {
+                    name: "path".to_string(),
+                    description: "搜索路径（文件或目录，默认当前目录）".to_string(),
+                    param_type: "string".to_string(),
+                    required: false,
+                },
+                ToolParameter {
+                    name: "dry_run".to_string(),
+                    description: "只预览受影响的文件和替换次数，不写入（默认false）".to_string(),
+                    param_type: "boolean".to_string(),
+                    required: false,
+                },
+            ],
+        }
+    }
+
+    fn execute(&self, call: ToolCall) -> Pin<Box<dyn Future<Output = ToolResult> + Send + '_>> {
+        Box::pin(async move {
+            let ctx = ToolExecutionContext::new(call.tool_name, call.arguments);
+
+            let old_name = match ctx.get_string("old_name") {
+                Some(n) => n,
+                None => return ToolResult {
+                    success: false,
+                    data: serde_json::json!(null),
+                    error: Some("Missing required parameter: old_name".to_string()),
+                },
+            };
+
+            let new_name = match ctx.get_string("new_name") {
+                Some(n) => n,
+                None => return ToolResult {
+                    success: false,
+                    data: serde_json::json!(null),
+                    error: Some("Missing required parameter: new_name".to_string()),
+                },
+            };
+
+            if !is_identifier(&old_name) || !is_identifier(&new_name) {
+                return ToolResult {
+                    success: false,
+                    data: serde_json::json!(null),
+                    error: Some(format!("Invalid identifier: '{}' -> '{}'", old_name, new_name)),
+                };
+            }
+
+            let path = ctx.get_string("path").unwrap_or_else(|| ".".to_string());
+            let dry_run = ctx.get_bool("dry_run").unwrap_or(false);
+
+            let renames = collect_renames(Path::new(&path), &old_name, &new_name);
+
+            if !dry_run {
+                // 先检查所有路径，任何一个在项目根目录之外都不写入，避免只改了一部分文件
+                if let Some(e) = renames.iter().find_map(|rename| self.guard.check(&rename.path).err()) {
+                    return ToolResult {
+                        success: false,
+                        data: serde_json::json!(null),
+                        error: Some(e),
+                    };
+                }
+                for rename in &renames {
+                    if let Err(e) = fs::write(&rename.path, &rename.new_content) {
+                        return ToolResult {
+                            success: false,
+                            data: serde_json::json!(null),
+                            error: Some(format!("Failed to write file '{}': {}", rename.path, e)),
+                        };
+                    }
+                }
+            }
+
+            let total: usize = renames.iter().map(|r| r.replacements).sum();
+            let files: Vec<serde_json::Value> = renames
+                .iter()
+                .map(|r| serde_json::json!({ "path": r.path, "replacements": r.replacements }))
+                .collect();
+
+            ToolResult {
+                success: true,
+                data: serde_json::json!({
+                    "old_name": old_name,
+                    "new_name": new_name,
+                    "dry_run": dry_run,
+                    "files": files,
+                    "total_replacements": total
+                }),
+                error: None,
+            }
+        })
+    }
+}
+
+/// 遍历路径下的源文件（遵循 .gitignore），计算每个文件的重命名结果
+fn collect_renames(path: &Path, old_name: &str, new_name: &str) -> Vec<FileRename> {
+    let mut renames = Vec::new();
+
+    for entry in ignore::WalkBuilder::new(path).build().flatten() {
+        let file_path = entry.path();
+        let Some(syntax) = SourceSyntax::from_path(file_path) else {
+            continue;
+        };
+        let Ok(content) = fs::read_to_string(file_path) else {
+            continue;
+        };
+
+        let (new_content, replacements) = rename_identifier(&content, old_name, new_name, syntax);
+        if replacements > 0 {
+            renames.push(FileRename {
+                path: file_path.to_string_lossy().to_string(),
+                replacements,
+                new_content,
+            });
+        }
+    }
+
+    renames.sort_by(|a, b| a.path.cmp(&b.path));
+    renames
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => chars.all(|c| c.is_alphanumeric() || c == '_'),
+        _ => false,
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// 按标识符边界替换，跳过注释和字符串，返回新内容和替换次数
+fn rename_identifier(source: &str, old_name: &str, new_name: &str, syntax: SourceSyntax) -> (String, usize) {
+    let chars: Vec<char> = source.chars().collect();
+    let mut output = String::with_capacity(source.len());
+    let mut count = 0;
+    let mut i = 0;
+
+    let starts_with = |i: usize, pat: &str| pat.chars().enumerate().all(|(k, c)| chars.get(i + k) == Some(&c));
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+
+        // 注释
+        let line_comment = match syntax {
+            SourceSyntax::Rust | SourceSyntax::CLike => starts_with(i, "//"),
+            SourceSyntax::Python => c == '#',
+            SourceSyntax::Plain => false,
+        };
+        if line_comment {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if matches!(syntax, SourceSyntax::Rust | SourceSyntax::CLike) && starts_with(i, "/*") {
+            i += 2;
+            while i < chars.len() && !starts_with(i, "*/") {
+                i += 1;
+            }
+            i = (i + 2).min(chars.len());
+        }
+        // 字符串
+        else if syntax == SourceSyntax::Python && (starts_with(i, "\"\"\"") || starts_with(i, "'''")) {
+            let quote: String = chars[i..i + 3].iter().collect();
+            i += 3;
+            while i < chars.len() && !starts_with(i, &quote) {
+                i += 1;
+            }
+            i = (i + 3).min(chars.len());
+        } else if syntax != SourceSyntax::Plain && (c == '"' || is_quote_literal(&chars, i, syntax)) {
+            i = skip_quoted(&chars, i);
+        }
+        // 标识符（连同数字字面量一起按整词处理）
+        else if is_ident_char(c) {
+            while i < chars.len() && is_ident_char(chars[i]) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+
+            if syntax == SourceSyntax::Rust && matches!(word.as_str(), "r" | "br") && matches!(chars.get(i), Some('"') | Some('#')) {
+                i = skip_raw_string(&chars, i);
+            } else if word == old_name {
+                output.push_str(new_name);
+                count += 1;
+                continue;
+            }
+        } else {
+            i += 1;
+        }
+
+        output.extend(&chars[start..i]);
+    }
+
+    (output, count)
+}
+
+/// 单引号是否开始一个字面量（Rust 中区分字符字面量与生命周期 'a）
+fn is_quote_literal(chars: &[char], i: usize, syntax: SourceSyntax) -> bool {
+    match (chars[i], syntax) {
+        ('\'', SourceSyntax::Rust) => {
+            chars.get(i + 1) == Some(&'\\') || chars.get(i + 2) == Some(&'\'')
+        }
+        ('\'', _) => true,
+        ('`', SourceSyntax::CLike) => true,
+        _ => false,
+    }
+}
+
+/// 跳过以 chars[i] 为引号的字符串，处理反斜杠转义
+fn skip_quoted(chars: &[char], mut i: usize) -> usize {
+    let quote = chars[i];
+    i += 1;
+    while i < chars.len() && chars[i] != quote {
+        if chars[i] == '\\' {
+            i += 1;
+        }
+        i += 1;
+    }
+    (i + 1).min(chars.len())
+}
+
+/// 跳过 Rust 原始字符串 r#"..."#（i 指向 r 之后）
+fn skip_raw_string(chars: &[char], mut i: usize) -> usize {
+    let mut hashes = 0;
+    while chars.get(i) == Some(&'#') {
+        hashes += 1;
+        i += 1;
+    }
+    if chars.get(i) != Some(&'"') {
+        return i;
+    }
+    i += 1;
+    while i < chars.len() {
+        if chars[i] == '"' && (1..=hashes).all(|k| chars.get(i + k) == Some(&'#')) {
+            return i + 1 + hashes;
+        }
+        i += 1;
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const SOURCE: &str = r#"fn compute_total(a: u32) -> u32 { a }
+
+fn main() {
+    // compute_total 在注释里不改
+    let compute_total_cache = compute_total(1);
+    let label = "compute_total";
+    println!("{} {}", label, compute_total(compute_total_cache));
+}
+"#;
+
+    #[tokio::test]
+    async fn test_rename_rust_function() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("main.rs");
+        fs::write(&file, SOURCE).unwrap();
+
+        let tool = RenameSymbolTool::new(PathGuard::new(temp_dir.path()));
+        let call = ToolCall {
+            tool_name: "rename_symbol".to_string(),
+            arguments: [
+                ("old_name".to_string(), serde_json::json!("compute_total")),
+                ("new_name".to_string(), serde_json::json!("sum_values")),
+                ("path".to_string(), serde_json::json!(temp_dir.path().to_str())),
+            ].into(),
+        };
+
+        let result = tool.execute(call).await;
+        assert!(result.success);
+        assert_eq!(result.data["total_replacements"], 3);
+
+        let content = fs::read_to_string(&file).unwrap();
+        assert!(content.contains("fn sum_values(a: u32)"));
+        assert!(content.contains("= sum_values(1);"));
+        assert!(content.contains("sum_values(compute_total_cache)"));
+        // 不相关的单词、注释、字符串保持不变
+        assert!(content.contains("let compute_total_cache"));
+        assert!(content.contains("// compute_total 在注释里不改"));
+        assert!(content.contains("\"compute_total\""));
+    }
+
+    #[tokio::test]
+    async fn test_rename_dry_run_does_not_write() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("main.rs");
+        fs::write(&file, SOURCE).unwrap();
+
+        let tool = RenameSymbolTool::new(PathGuard::new(temp_dir.path()));
+        let call = ToolCall {
+            tool_name: "rename_symbol".to_string(),
+            arguments: [
+                ("old_name".to_string(), serde_json::json!("compute_total")),
+                ("new_name".to_string(), serde_json::json!("sum_values")),
+                ("path".to_string(), serde_json::json!(temp_dir.path().to_str())),
+                ("dry_run".to_string(), serde_json::json!(true)),
+            ].into(),
+        };
+
+        let result = tool.execute(call).await;
+        assert!(result.success);
+        assert_eq!(result.data["files"].as_array().unwrap().len(), 1);
+        assert_eq!(result.data["files"][0]["replacements"], 3);
+        assert_eq!(fs::read_to_string(&file).unwrap(), SOURCE);
+    }
+
+    #[tokio::test]
+    async fn test_rename_outside_project_writes_nothing() {
+        let temp_dir = tempdir().unwrap();
+        let project = temp_dir.path().join("project");
+        fs::create_dir(&project).unwrap();
+        let inside = project.join("main.rs");
+        let outside = temp_dir.path().join("z_outside.rs");
+        fs::write(&inside, SOURCE).unwrap();
+        fs::write(&outside, SOURCE).unwrap();
+
+        let tool = RenameSymbolTool::new(PathGuard::new(&project));
+        let call = ToolCall {
+            tool_name: "rename_symbol".to_string(),
+            arguments: [
+                ("old_name".to_string(), serde_json::json!("compute_total")),
+                ("new_name".to_string(), serde_json::json!("sum_values")),
+                ("path".to_string(), serde_json::json!(temp_dir.path().to_str())),
+            ].into(),
+        };
+
+        let result = tool.execute(call).await;
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("outside the project root"));
+        // 项目内的文件排在前面，但也没有被修改
+        assert_eq!(fs::read_to_string(&inside).unwrap(), SOURCE);
+        assert_eq!(fs::read_to_string(&outside).unwrap(), SOURCE);
+    }
+
+    #[test]
+    fn test_rust_lifetimes_and_raw_strings() {
+        let source = "fn f<'a>(x: &'a str) -> &'a str { let s = r#\"x\"#; let c = 'x'; x }";
+        let (renamed, count) = rename_identifier(source, "x", "y", SourceSyntax::Rust);
+        assert_eq!(count, 2);
+        assert_eq!(renamed, "fn f<'a>(y: &'a str) -> &'a str { let s = r#\"x\"#; let c = 'x'; y }");
+    }
+}
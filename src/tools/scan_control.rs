@@ -0,0 +1,67 @@
+/// 项目级扫描的取消与进度控制
+/// 供 analyze_project_structure / find_functions 等遍历整个目录树的工具使用
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// 扫描进度回调，参数为已扫描的文件数
+pub type ProgressCallback = Arc<dyn Fn(usize) + Send + Sync>;
+
+/// 扫描控制：取消标记 + 进度回调（可跨线程克隆）
+#[derive(Clone, Default)]
+pub struct ScanControl {
+    cancelled: Arc<AtomicBool>,
+    files_scanned: Arc<AtomicUsize>,
+    progress: Option<ProgressCallback>,
+}
+
+impl ScanControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置进度回调（每扫描一个文件调用一次）
+    pub fn with_progress(mut self, callback: impl Fn(usize) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// 请求取消扫描
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// 记录扫描了一个文件并报告进度
+    pub fn file_scanned(&self) {
+        let count = self.files_scanned.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(progress) = &self.progress {
+            progress(count);
+        }
+    }
+
+    pub fn files_scanned(&self) -> usize {
+        self.files_scanned.load(Ordering::SeqCst)
+    }
+}
+
+/// 扫描结果：被取消时 result 为已收集到的部分结果
+#[derive(Debug, Clone)]
+pub struct ScanOutcome<T> {
+    pub result: T,
+    pub files_scanned: usize,
+    pub cancelled: bool,
+}
+
+impl<T> ScanOutcome<T> {
+    pub fn new(result: T, control: &ScanControl) -> Self {
+        Self {
+            result,
+            files_scanned: control.files_scanned(),
+            cancelled: control.is_cancelled(),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+/// 草稿本工具
+/// 为多步推理提供会话内的临时笔记区，内容只保存在内存中，不写入文件
+
+use super::tool::{Tool, ToolCall, ToolDefinition, ToolParameter, ToolResult, ToolExecutionContext};
+use std::pin::Pin;
+use std::future::Future;
+use std::sync::Mutex;
+
+/// 草稿本工具：支持 write / append / read / clear
+#[derive(Default)]
+pub struct ScratchpadTool {
+    content: Mutex<String>,
+}
+
+impl ScratchpadTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 执行一个草稿本操作，返回操作后的内容
+    fn apply(&self, action: &str, text: Option<String>) -> Result<String, String> {
+        let mut content = self.content.lock().unwrap_or_else(|e| e.into_inner());
+        match action {
+            "write" => {
+                *content = text.ok_or("write 需要 text 参数")?;
+            }
+            "append" => {
+                let text = text.ok_or("append 需要 text 参数")?;
+                if !content.is_empty() && !content.ends_with('\n') {
+                    content.push('\n');
+                }
+                content.push_str(&text);
+            }
+            "read" => {}
+            "clear" => content.clear(),
+            other => return Err(format!("未知操作: {}（可用: write, append, read, clear）", other)),
+        }
+        Ok(content.clone())
+    }
+}
+
+impl Tool for ScratchpadTool {
+    fn name(&self) -> &str {
+        "scratchpad"
+    }
+
+    fn description(&self) -> &str {
+        "会话内的草稿本，用于在多步任务中记录中间发现和笔记。action: write 覆盖写入, append 追加一行, read 读取, clear 清空"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name().to_string(),
+            description: self.description().to_string(),
+            parameters: vec![
+                ToolParameter {
+                    name: "action".to_string(),
+                    description: "操作：write, append, read, clear".to_string(),
+                    param_type: "string".to_string(),
+                    required: true,
+                },
+                ToolParameter {
+                    name: "text".to_string(),
+                    description: "write / append 时写入的文本".to_string(),
+                    param_type: "string".to_string(),
+                    required: false,
+                },
+            ],
+        }
+    }
+
+    fn execute(&self, call: ToolCall) -> Pin<Box<dyn Future<Output = ToolResult> + Send + '_>> {
+        Box::pin(async move {
+            let ctx = ToolExecutionContext::new(call.tool_name, call.arguments);
+
+            let action = match ctx.get_string("action") {
+                Some(a) => a,
+                None => return ToolResult {
+                    success: false,
+                    data: serde_json::json!(null),
+                    error: Some("Missing required parameter: action".to_string()),
+                },
+            };
+
+            match self.apply(&action, ctx.get_string("text")) {
+                Ok(content) => ToolResult {
+                    success: true,
+                    data: serde_json::json!({
+                        "action": action,
+                        "content": content,
+                        "length": content.len()
+                    }),
+                    error: None,
+                },
+                Err(e) => ToolResult {
+                    success: false,
+                    data: serde_json::json!(null),
+                    error: Some(e),
+                },
+            }
+        })
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(action: &str, text: Option<&str>) -> ToolCall {
+        let mut arguments = std::collections::HashMap::new();
+        arguments.insert("action".to_string(), serde_json::json!(action));
+        if let Some(text) = text {
+            arguments.insert("text".to_string(), serde_json::json!(text));
+        }
+        ToolCall {
+            tool_name: "scratchpad".to_string(),
+            arguments,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_append_accumulates() {
+        let tool = ScratchpadTool::new();
+        tool.execute(call("write", Some("found bug in parser"))).await;
+        tool.execute(call("append", Some("lexer is fine"))).await;
+
+        let result = tool.execute(call("read", None)).await;
+        assert!(result.success);
+        assert_eq!(result.data["content"], "found bug in parser\nlexer is fine");
+    }
+
+    #[tokio::test]
+    async fn test_clear_empties_scratchpad() {
+        let tool = ScratchpadTool::new();
+        tool.execute(call("append", Some("note"))).await;
+        tool.execute(call("clear", None)).await;
+
+        let result = tool.execute(call("read", None)).await;
+        assert_eq!(result.data["content"], "");
+
+        let result = tool.execute(call("append", None)).await;
+        assert!(!result.success);
+    }
+}
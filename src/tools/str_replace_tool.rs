@@ -2,13 +2,23 @@
 /// 实现文件文本替换功能（类似 grok-cli 的 str_replace_editor）
 
 use super::tool::{Tool, ToolCall, ToolDefinition, ToolParameter, ToolResult, ToolExecutionContext};
+use crate::fs::path_guard::PathGuard;
 use std::fs;
 use std::io::{Read, Write};
 use std::pin::Pin;
 use std::future::Future;
 
-/// 文本替换编辑器工具
-pub struct StrReplaceTool;
+/// 文本替换编辑器工具（写入路径受项目根目录约束）
+#[derive(Default)]
+pub struct StrReplaceTool {
+    guard: PathGuard,
+}
+
+impl StrReplaceTool {
+    pub fn new(guard: PathGuard) -> Self {
+        Self { guard }
+    }
+}
 
 impl Tool for StrReplaceTool {
     fn name(&self) -> &str {
@@ -85,6 +95,14 @@ impl Tool for StrReplaceTool {
 
             let replace_all = ctx.get_bool("replace_all").unwrap_or(false);
 
+            if let Err(e) = self.guard.check(&path) {
+                return ToolResult {
+                    success: false,
+                    data: serde_json::json!(null),
+                    error: Some(e),
+                };
+            }
+
             // 读取文件内容
             let content = match fs::read_to_string(&path) {
                 Ok(content) => content,
@@ -162,7 +180,7 @@ mod tests {
         fs::write(&file_path, initial_content).unwrap();
 
         // 执行替换
-        let tool = StrReplaceTool;
+        let tool = StrReplaceTool::new(PathGuard::new(temp_dir.path()));
         let call = ToolCall {
             tool_name: "str_replace_editor".to_string(),
             arguments: vec![
@@ -193,7 +211,7 @@ mod tests {
         fs::write(&file_path, initial_content).unwrap();
 
         // 执行替换所有匹配
-        let tool = StrReplaceTool;
+        let tool = StrReplaceTool::new(PathGuard::new(temp_dir.path()));
         let call = ToolCall {
             tool_name: "str_replace_editor".to_string(),
             arguments: vec![
@@ -227,7 +245,7 @@ mod tests {
         fs::write(&file_path, initial_content).unwrap();
 
         // 执行多行替换
-        let tool = StrReplaceTool;
+        let tool = StrReplaceTool::new(PathGuard::new(temp_dir.path()));
         let old_str = r#"    let x = 1;
     let y = 2;"#;
         let new_str = "    let x = 10;\n    let y = 20;";
@@ -250,4 +268,27 @@ mod tests {
         assert!(content.contains("let y = 20;"));
         assert!(!content.contains("let x = 1;"));
     }
+
+    #[tokio::test]
+    async fn test_str_replace_rejects_path_outside_project() {
+        let project = tempdir().unwrap();
+        let outside = tempdir().unwrap();
+        let file_path = outside.path().join("test.txt");
+        fs::write(&file_path, "foo\n").unwrap();
+
+        let tool = StrReplaceTool::new(PathGuard::new(project.path()));
+        let call = ToolCall {
+            tool_name: "str_replace_editor".to_string(),
+            arguments: vec![
+                ("path".to_string(), serde_json::json!(file_path.to_string_lossy())),
+                ("old_str".to_string(), serde_json::json!("foo")),
+                ("new_str".to_string(), serde_json::json!("bar")),
+            ].into_iter().collect(),
+        };
+
+        let result = tool.execute(call).await;
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("outside the project root"));
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "foo\n");
+    }
 }
@@ -0,0 +1,208 @@
+/// 文件摘要工具
+/// 读取文件并请模型用一段话概括它的用途，结果按 (路径, 修改时间) 缓存，文件未改动时重复摘要不再请求模型
+
+use super::tool::{Tool, ToolCall, ToolDefinition, ToolParameter, ToolResult, ToolExecutionContext};
+use crate::ai::client::{ChatCompletion, ChatMessage};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// 发给模型的文件内容最多保留的字节数，超出部分截断
+pub const MAX_SUMMARY_INPUT_BYTES: usize = 32 * 1024;
+
+/// 文件摘要工具
+pub struct SummarizeFileTool {
+    client: Arc<dyn ChatCompletion>,
+    /// 规范化路径 → (修改时间, 摘要)
+    cache: Mutex<HashMap<PathBuf, (SystemTime, String)>>,
+    max_input_bytes: usize,
+}
+
+impl SummarizeFileTool {
+    pub fn new(client: Arc<dyn ChatCompletion>) -> Self {
+        Self {
+            client,
+            cache: Mutex::new(HashMap::new()),
+            max_input_bytes: MAX_SUMMARY_INPUT_BYTES,
+        }
+    }
+
+    /// 返回 (摘要, 是否命中缓存)
+    async fn summarize(&self, path: &str) -> Result<(String, bool), String> {
+        let canonical = std::fs::canonicalize(path).map_err(|e| format!("Failed to read file '{}': {}", path, e))?;
+        let modified = std::fs::metadata(&canonical)
+            .and_then(|meta| meta.modified())
+            .map_err(|e| format!("Failed to read file '{}': {}", path, e))?;
+
+        if let Some((cached_at, summary)) = self.cache.lock().unwrap_or_else(|e| e.into_inner()).get(&canonical) {
+            if *cached_at == modified {
+                return Ok((summary.clone(), true));
+            }
+        }
+
+        let content = std::fs::read_to_string(&canonical).map_err(|e| format!("Failed to read file '{}': {}", path, e))?;
+        let reply = self
+            .client
+            .complete(vec![ChatMessage {
+                role: "user".to_string(),
+                content: self.build_prompt(path, &content),
+            }])
+            .await
+            .map_err(|e| format!("Failed to summarize '{}': {}", path, e))?;
+
+        let summary = reply.split_whitespace().collect::<Vec<_>>().join(" ");
+        if summary.is_empty() {
+            return Err(format!("模型没有返回 {} 的摘要", path));
+        }
+        self.cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(canonical, (modified, summary.clone()));
+        Ok((summary, false))
+    }
+
+    fn build_prompt(&self, path: &str, content: &str) -> String {
+        let (content, note) = if content.len() > self.max_input_bytes {
+            let mut end = self.max_input_bytes;
+            while !content.is_char_boundary(end) {
+                end -= 1;
+            }
+            (&content[..end], format!("\n(File truncated: showing the first {} of {} bytes.)", end, content.len()))
+        } else {
+            (content, String::new())
+        };
+        format!(
+            "Summarize the purpose of the file `{}` in one short paragraph: what it is responsible for \
+             and the main types or functions it provides. Reply with the paragraph only.{}\n\n```\n{}\n```",
+            path, note, content
+        )
+    }
+}
+
+impl Tool for SummarizeFileTool {
+    fn name(&self) -> &str {
+        "summarize_file"
+    }
+
+    fn description(&self) -> &str {
+        "用一段话概括文件的用途和主要内容，适合快速了解项目结构；文件未改动时直接返回缓存的摘要"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name().to_string(),
+            description: self.description().to_string(),
+            parameters: vec![ToolParameter {
+                name: "path".to_string(),
+                description: "文件路径".to_string(),
+                param_type: "string".to_string(),
+                required: true,
+            }],
+        }
+    }
+
+    fn execute(&self, call: ToolCall) -> Pin<Box<dyn Future<Output = ToolResult> + Send + '_>> {
+        Box::pin(async move {
+            let ctx = ToolExecutionContext::new(call.tool_name, call.arguments);
+
+            let path = match ctx.get_string("path") {
+                Some(p) => p,
+                None => return ToolResult {
+                    success: false,
+                    data: serde_json::json!(null),
+                    error: Some("Missing required parameter: path".to_string()),
+                },
+            };
+
+            match self.summarize(&path).await {
+                Ok((summary, cached)) => ToolResult {
+                    success: true,
+                    data: serde_json::json!({
+                        "path": path,
+                        "summary": summary,
+                        "cached": cached
+                    }),
+                    error: None,
+                },
+                Err(e) => ToolResult {
+                    success: false,
+                    data: serde_json::json!(null),
+                    error: Some(e),
+                },
+            }
+        })
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::client::CompletionError;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 返回固定摘要并记录调用次数和最后一次的提示
+    #[derive(Default)]
+    struct CountingClient {
+        calls: AtomicUsize,
+        prompt: Mutex<String>,
+    }
+
+    impl ChatCompletion for CountingClient {
+        fn complete(&self, messages: Vec<ChatMessage>) -> Pin<Box<dyn Future<Output = Result<String, CompletionError>> + Send + '_>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            *self.prompt.lock().unwrap() = messages[0].content.clone();
+            Box::pin(async { Ok("Parses the config file\n and exposes `Config::load`.".to_string()) })
+        }
+
+        fn complete_stream(
+            &self,
+            _messages: Vec<ChatMessage>,
+            _callback: Box<dyn FnMut(String) -> bool + Send>,
+        ) -> Pin<Box<dyn Future<Output = Result<(), CompletionError>> + Send + '_>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn call(path: &std::path::Path) -> ToolCall {
+        let mut arguments = HashMap::new();
+        arguments.insert("path".to_string(), serde_json::json!(path.to_string_lossy()));
+        ToolCall { tool_name: "summarize_file".to_string(), arguments }
+    }
+
+    #[tokio::test]
+    async fn test_summary_is_cached_until_file_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("config.rs");
+        std::fs::write(&file, "pub struct Config;\n".repeat(100)).unwrap();
+
+        let client = Arc::new(CountingClient::default());
+        let tool = SummarizeFileTool { max_input_bytes: 64, ..SummarizeFileTool::new(client.clone()) };
+
+        let first = tool.execute(call(&file)).await;
+        assert!(first.success);
+        assert_eq!(first.data["summary"], "Parses the config file and exposes `Config::load`.");
+        assert_eq!(first.data["cached"], false);
+        let prompt = client.prompt.lock().unwrap().clone();
+        assert!(prompt.contains("File truncated: showing the first 64 of 1900 bytes"));
+
+        let second = tool.execute(call(&file)).await;
+        assert_eq!(second.data["summary"], first.data["summary"]);
+        assert_eq!(second.data["cached"], true);
+        assert_eq!(client.calls.load(Ordering::SeqCst), 1);
+
+        // 修改时间变化后重新摘要
+        let later = SystemTime::now() + std::time::Duration::from_secs(5);
+        std::fs::File::options().write(true).open(&file).unwrap().set_modified(later).unwrap();
+        assert_eq!(tool.execute(call(&file)).await.data["cached"], false);
+        assert_eq!(client.calls.load(Ordering::SeqCst), 2);
+
+        assert!(!tool.execute(call(&dir.path().join("missing.rs"))).await.success);
+    }
+}
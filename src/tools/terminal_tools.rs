@@ -89,6 +89,7 @@ impl Tool for CommandExecuteTool {
                         "command": command,
                         "args": args,
                         "exit_code": result.exit_code,
+                        "output": combined_output(&result.stdout, &result.stderr),
                         "stdout": result.stdout,
                         "stderr": result.stderr,
                         "duration_ms": result.duration_ms
@@ -105,10 +106,120 @@ impl Tool for CommandExecuteTool {
     }
 }
 
+/// 供人阅读的合并输出：stdout 在前，stderr 紧随其后（两者分别保留在 stdout / stderr 字段中）
+fn combined_output(stdout: &str, stderr: &str) -> String {
+    if stdout.is_empty() || stderr.is_empty() || stdout.ends_with('\n') {
+        format!("{}{}", stdout, stderr)
+    } else {
+        format!("{}\n{}", stdout, stderr)
+    }
+}
+
+/// check_command 返回的 stderr 末尾字符数
+const STDERR_TAIL_CHARS: usize = 500;
+
+/// 命令检查工具：只关心命令是否成功，不捕获 stdout
+pub struct CheckCommandTool;
+
+impl Tool for CheckCommandTool {
+    fn name(&self) -> &str {
+        "check_command"
+    }
+
+    fn description(&self) -> &str {
+        "运行命令并只返回是否成功和退出码（附 stderr 末尾），适合 test -f 之类的检查，不返回完整输出"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name().to_string(),
+            description: self.description().to_string(),
+            parameters: vec![
+                ToolParameter {
+                    name: "command".to_string(),
+                    description: "要执行的命令".to_string(),
+                    param_type: "string".to_string(),
+                    required: true,
+                },
+                ToolParameter {
+                    name: "args".to_string(),
+                    description: "命令参数数组".to_string(),
+                    param_type: "array".to_string(),
+                    required: false,
+                },
+                ToolParameter {
+                    name: "working_directory".to_string(),
+                    description: "工作目录".to_string(),
+                    param_type: "string".to_string(),
+                    required: false,
+                },
+                ToolParameter {
+                    name: "timeout_seconds".to_string(),
+                    description: "超时时间（秒，默认30）".to_string(),
+                    param_type: "number".to_string(),
+                    required: false,
+                },
+            ],
+        }
+    }
+
+    fn execute(&self, call: ToolCall) -> Pin<Box<dyn Future<Output = ToolResult> + Send + '_>> {
+        Box::pin(async move {
+            let ctx = ToolExecutionContext::new(call.tool_name, call.arguments);
+
+            let command = match ctx.get_string("command") {
+                Some(c) => c,
+                None => return ToolResult {
+                    success: false,
+                    data: serde_json::json!(null),
+                    error: Some("Missing required parameter: command".to_string()),
+                },
+            };
+
+            if !is_safe_command(&command) {
+                return ToolResult {
+                    success: false,
+                    data: serde_json::json!(null),
+                    error: Some(format!("Command '{}' is not allowed for security reasons", command)),
+                };
+            }
+
+            let args = ctx.arguments.get("args")
+                .and_then(|v| v.as_array())
+                .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect::<Vec<_>>())
+                .unwrap_or_default();
+
+            let working_directory = ctx.get_string("working_directory");
+            let timeout_seconds = ctx.get_number("timeout_seconds").unwrap_or(30.0) as u64;
+
+            match check_command(&command, &args, working_directory.as_deref(), timeout_seconds).await {
+                // 命令运行完成时，失败的退出码本身就是结果，工具调用仍然成功
+                Ok(result) => ToolResult {
+                    success: result.error.is_none(),
+                    data: serde_json::json!({
+                        "command": command,
+                        "success": result.success,
+                        "exit_code": result.exit_code,
+                        "stderr_tail": result.stderr,
+                        "duration_ms": result.duration_ms
+                    }),
+                    error: result.error,
+                },
+                Err(e) => ToolResult {
+                    success: false,
+                    data: serde_json::json!(null),
+                    error: Some(format!("Command execution failed: {}", e)),
+                },
+            }
+        })
+    }
+}
+
 /// 安全命令检查
 fn is_safe_command(command: &str) -> bool {
     let safe_commands = [
         "ls", "dir", "pwd", "echo", "cat", "head", "tail", "grep", "find", "wc", "sort", "uniq",
+        "test", "which",
         "git", "cargo", "npm", "yarn", "python", "python3", "node", "rustc",
         "mkdir", "cp", "mv", "rm", "touch", "chmod", "chown",
         "ps", "top", "df", "du", "free", "uptime",
@@ -150,7 +261,8 @@ async fn execute_command(
     let start_time = Instant::now();
 
     let mut cmd = TokioCommand::new(command);
-    cmd.args(args);
+    // 任务被中止（超时或退出）时一并结束子进程，避免留下僵尸进程
+    cmd.args(args).kill_on_drop(true);
 
     if let Some(cwd) = working_directory {
         cmd.current_dir(cwd);
@@ -192,6 +304,56 @@ async fn execute_command(
     }
 }
 
+/// 运行命令但丢弃 stdout，stderr 只保留末尾 STDERR_TAIL_CHARS 个字符
+async fn check_command(
+    command: &str,
+    args: &[String],
+    working_directory: Option<&str>,
+    timeout_seconds: u64,
+) -> Result<CommandResult, Box<dyn std::error::Error + Send + Sync>> {
+    use std::process::Stdio;
+    use std::time::{Duration, Instant};
+
+    let start_time = Instant::now();
+
+    let mut cmd = TokioCommand::new(command);
+    cmd.args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    if let Some(cwd) = working_directory {
+        cmd.current_dir(cwd);
+    }
+
+    match tokio::time::timeout(Duration::from_secs(timeout_seconds), cmd.output()).await {
+        Ok(result) => {
+            let output = result?;
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let count = stderr.chars().count();
+            let tail: String = stderr.chars().skip(count.saturating_sub(STDERR_TAIL_CHARS)).collect();
+
+            Ok(CommandResult {
+                success: output.status.success(),
+                exit_code: output.status.code(),
+                stdout: String::new(),
+                stderr: tail.trim_end().to_string(),
+                duration_ms: start_time.elapsed().as_millis(),
+                error: None,
+            })
+        }
+        Err(_) => Ok(CommandResult {
+            success: false,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            duration_ms: start_time.elapsed().as_millis(),
+            error: Some(format!("Command timed out after {} seconds", timeout_seconds)),
+        }),
+    }
+}
+
 /// 环境信息工具
 pub struct EnvironmentInfoTool;
 
@@ -287,6 +449,63 @@ mod tests {
         assert!(result.data["stdout"].as_str().unwrap().contains("Hello, World!"));
     }
 
+    #[tokio::test]
+    async fn test_command_output_streams_are_separated() {
+        let dir = tempfile::tempdir().unwrap();
+        let present = dir.path().join("present.txt");
+        std::fs::write(&present, "").unwrap();
+        let missing = dir.path().join("missing.txt");
+        let call = ToolCall {
+            tool_name: "execute_command".to_string(),
+            arguments: [
+                ("command".to_string(), serde_json::json!("ls")),
+                ("args".to_string(), serde_json::json!([present.to_str().unwrap(), missing.to_str().unwrap()])),
+            ].into(),
+        };
+
+        let result = CommandExecuteTool.execute(call).await;
+        assert!(!result.success);
+        assert_ne!(result.data["exit_code"], 0);
+        let stdout = result.data["stdout"].as_str().unwrap();
+        let stderr = result.data["stderr"].as_str().unwrap();
+        assert!(stdout.contains("present.txt") && !stdout.contains("missing.txt"));
+        assert!(stderr.contains("missing.txt") && !stderr.contains("present.txt"));
+        assert_eq!(result.data["output"].as_str().unwrap(), format!("{}{}", stdout, stderr));
+    }
+
+    #[tokio::test]
+    async fn test_check_command_reports_failure_without_stdout() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.txt");
+        let call = ToolCall {
+            tool_name: "check_command".to_string(),
+            arguments: [
+                ("command".to_string(), serde_json::json!("cat")),
+                ("args".to_string(), serde_json::json!([missing.to_str().unwrap()])),
+            ].into(),
+        };
+
+        let result = CheckCommandTool.execute(call).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.data["success"], false);
+        assert_ne!(result.data["exit_code"], 0);
+        let stderr_tail = result.data["stderr_tail"].as_str().unwrap();
+        assert!(stderr_tail.contains("missing.txt"));
+        assert!(stderr_tail.chars().count() <= STDERR_TAIL_CHARS);
+        assert!(result.data.get("stdout").is_none());
+
+        let call = ToolCall {
+            tool_name: "check_command".to_string(),
+            arguments: [
+                ("command".to_string(), serde_json::json!("test")),
+                ("args".to_string(), serde_json::json!(["-d", dir.path().to_str().unwrap()])),
+            ].into(),
+        };
+        let result = CheckCommandTool.execute(call).await;
+        assert_eq!(result.data["success"], true);
+        assert_eq!(result.data["exit_code"], 0);
+    }
+
     #[tokio::test]
     async fn test_environment_info() {
         let env_tool = EnvironmentInfoTool;
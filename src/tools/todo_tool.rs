@@ -171,6 +171,10 @@ impl Tool for CreateTodoListTool {
             }
         })
     }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
 }
 
 /// Update Todo List Tool
@@ -245,6 +249,10 @@ impl Tool for UpdateTodoListTool {
             }
         })
     }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
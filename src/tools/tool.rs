@@ -51,6 +51,20 @@ pub trait Tool: Send + Sync {
 
     /// 执行工具（返回 Future 以支持 dyn trait）
     fn execute(&self, call: ToolCall) -> Pin<Box<dyn Future<Output = ToolResult> + Send + '_>>;
+
+    /// 在请求的扫描控制下执行：遍历整个目录树的工具覆盖它，请求取消时停止扫描；其他工具忽略 control
+    fn execute_with_control(
+        &self,
+        call: ToolCall,
+        _control: super::scan_control::ScanControl,
+    ) -> Pin<Box<dyn Future<Output = ToolResult> + Send + '_>> {
+        self.execute(call)
+    }
+
+    /// 是否只读（不写文件、不启动进程）；安全模式下只保留只读工具
+    fn is_read_only(&self) -> bool {
+        false
+    }
 }
 
 /// 工具执行上下文
@@ -0,0 +1,123 @@
+/// 工具结果在聊天中的显示（/verbose）
+/// verbose 模式显示完整输出；关闭时超过 SUMMARY_MIN_LINES 行的结果折叠为一行摘要，
+/// 如 `read src/main.rs (120 lines)`，完整输出可用 `/verbose show` 查看
+
+use super::tool::{ToolCall, ToolResult};
+
+/// 不超过该行数的输出即使在摘要模式下也完整显示
+pub const SUMMARY_MIN_LINES: usize = 10;
+
+/// 折叠后提示如何查看完整输出
+pub const EXPAND_HINT: &str = "（/verbose show 查看完整输出）";
+
+/// 按 verbose 设置渲染工具结果
+pub fn render_tool_result(call: &ToolCall, result: &ToolResult, verbose: bool) -> String {
+    let full = full_output(call, result);
+    if verbose || !result.success || full.lines().count() <= SUMMARY_MIN_LINES {
+        full
+    } else {
+        format!("▸ {} {}", summary_line(call, result), EXPAND_HINT)
+    }
+}
+
+/// 完整输出
+pub fn full_output(call: &ToolCall, result: &ToolResult) -> String {
+    if !result.success {
+        return format!("❌ {}: {}", call.tool_name, result.error.clone().unwrap_or_default());
+    }
+    let data = &result.data;
+    match call.tool_name.as_str() {
+        "read_file" => data["content"].as_str().unwrap_or_default().to_string(),
+        "execute_command" => format!(
+            "$ {}\n(exit code: {})\n{}",
+            command_line(call),
+            data["exit_code"],
+            data["output"].as_str().unwrap_or_default()
+        ),
+        "search_code" => data["matches"]
+            .as_array()
+            .map(|matches| {
+                matches
+                    .iter()
+                    .map(|m| format!("{}:{}: {}", m["file"].as_str().unwrap_or_default(), m["line"], m["content"].as_str().unwrap_or_default()))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default(),
+        _ => match data {
+            serde_json::Value::String(text) => text.clone(),
+            _ => serde_json::to_string_pretty(data).unwrap_or_default(),
+        },
+    }
+}
+
+/// 一行摘要
+pub fn summary_line(call: &ToolCall, result: &ToolResult) -> String {
+    let data = &result.data;
+    let arg = |name: &str| call.arguments.get(name).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    let count = |key: &str| data[key].as_array().map_or(0, |items| items.len());
+    match call.tool_name.as_str() {
+        "read_file" => format!("read {} ({} lines)", arg("path"), data["total_lines"].as_u64().unwrap_or_default()),
+        "execute_command" => format!(
+            "ran `{}` (exit {}, {} lines)",
+            command_line(call),
+            data["exit_code"],
+            data["output"].as_str().unwrap_or_default().lines().count()
+        ),
+        "search_code" => format!("searched \"{}\" in {} ({} matches)", arg("pattern"), arg("path"), count("matches")),
+        "list_directory" => format!("listed {} ({} entries)", arg("path"), count("entries")),
+        name => format!("{} ({} lines of output)", name, full_output(call, result).lines().count()),
+    }
+}
+
+/// `command args...`
+fn command_line(call: &ToolCall) -> String {
+    let mut parts = vec![call.arguments.get("command").and_then(|v| v.as_str()).unwrap_or_default().to_string()];
+    if let Some(args) = call.arguments.get("args").and_then(|v| v.as_array()) {
+        parts.extend(args.iter().filter_map(|a| a.as_str()).map(str::to_string));
+    }
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search_result(matches: usize) -> (ToolCall, ToolResult) {
+        let call = ToolCall {
+            tool_name: "search_code".to_string(),
+            arguments: [
+                ("pattern".to_string(), serde_json::json!("fn parse")),
+                ("path".to_string(), serde_json::json!("src")),
+            ]
+            .into(),
+        };
+        let matches: Vec<serde_json::Value> = (1..=matches)
+            .map(|i| serde_json::json!({ "file": format!("src/parser_{}.rs", i), "line": i * 10, "content": "fn parse() {" }))
+            .collect();
+        let result = ToolResult {
+            success: true,
+            data: serde_json::json!({ "pattern": "fn parse", "path": "src", "matches": matches }),
+            error: None,
+        };
+        (call, result)
+    }
+
+    #[test]
+    fn test_search_result_full_when_verbose_and_summarized_when_off() {
+        let (call, result) = search_result(25);
+
+        let verbose = render_tool_result(&call, &result, true);
+        assert_eq!(verbose.lines().count(), 25);
+        assert_eq!(verbose.lines().next(), Some("src/parser_1.rs:10: fn parse() {"));
+
+        let summary = render_tool_result(&call, &result, false);
+        assert_eq!(summary, format!("▸ searched \"fn parse\" in src (25 matches) {}", EXPAND_HINT));
+
+        // 短结果和失败结果不折叠
+        let (call, short) = search_result(3);
+        assert_eq!(render_tool_result(&call, &short, false).lines().count(), 3);
+        let failed = ToolResult { success: false, data: serde_json::json!(null), error: Some("bad regex".to_string()) };
+        assert_eq!(render_tool_result(&call, &failed, false), "❌ search_code: bad regex");
+    }
+}
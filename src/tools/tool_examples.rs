@@ -20,7 +20,7 @@ pub fn create_full_tool_registry() -> ToolRegistry {
 
     // 注册文件操作工具
     registry.register(Arc::new(FileReadTool));
-    registry.register(Arc::new(FileWriteTool));
+    registry.register(Arc::new(FileWriteTool::default()));
     registry.register(Arc::new(FileListTool));
 
     // 注册代码分析工具
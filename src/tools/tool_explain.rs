@@ -0,0 +1,80 @@
+/// 工具调用说明（explain 模式）
+/// 在工具执行前，用通俗的语言描述即将进行的操作，模板按工具名称配置
+
+use super::tool::ToolCall;
+
+/// 每个工具的说明模板，`{参数名}` 会被替换为对应参数值
+fn template_for(tool_name: &str) -> Option<&'static str> {
+    let template = match tool_name {
+        "read_file" => "读取 {path} 的内容，了解其中的代码",
+        "write_file" => "将内容写入 {path}",
+        "list_directory" => "列出目录 {path} 中的文件",
+        "read_config" => "读取配置文件 {path}（密钥已隐藏）",
+        "str_replace_editor" => "在 {path} 中替换一段文本",
+        "execute_command" => "执行终端命令 `{command}`",
+        "check_command" => "检查命令 `{command}` 是否执行成功",
+        "get_environment_info" => "查看当前运行环境信息",
+        "search_code" => "在 {path} 中搜索匹配 \"{pattern}\" 的代码",
+        "find_functions" => "在 {path} 中查找 {language} 函数定义",
+        "analyze_structure" => "分析 {path} 的代码结构",
+        "rename_symbol" => "将符号 {old_name} 重命名为 {new_name}",
+        "format_code" => "用项目的格式化器格式化 {path}",
+        "analyze_project" => "分析 {path} 的项目结构",
+        "analyze_dependencies" => "分析 {path} 的项目依赖",
+        "build_project" => "构建 {path} 中的项目",
+        "git_blame" => "查看 {path} 中代码行的作者和提交记录",
+        "create_todo_list" => "创建任务列表，规划接下来的步骤",
+        "update_todo_list" => "更新任务列表的进度",
+        "scratchpad" => "在草稿本中执行 {action} 操作",
+        "summarize_file" => "请模型概括 {path} 的用途",
+        _ => return None,
+    };
+    Some(template)
+}
+
+/// 生成工具调用的通俗说明
+pub fn explain_tool_call(call: &ToolCall) -> String {
+    let Some(template) = template_for(&call.tool_name) else {
+        return format!("调用工具 {}", call.tool_name);
+    };
+
+    let mut description = template.to_string();
+    for (name, value) in &call.arguments {
+        let value = match value {
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        description = description.replace(&format!("{{{}}}", name), &value);
+    }
+
+    // 未提供的可选参数
+    description.replace("{path}", "当前目录")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_search_tool() {
+        let call = ToolCall {
+            tool_name: "search_code".to_string(),
+            arguments: [
+                ("pattern".to_string(), serde_json::json!("fn main")),
+                ("path".to_string(), serde_json::json!("src")),
+            ].into(),
+        };
+
+        assert_eq!(explain_tool_call(&call), "在 src 中搜索匹配 \"fn main\" 的代码");
+    }
+
+    #[test]
+    fn test_explain_unknown_tool_falls_back() {
+        let call = ToolCall {
+            tool_name: "mystery".to_string(),
+            arguments: Default::default(),
+        };
+
+        assert_eq!(explain_tool_call(&call), "调用工具 mystery");
+    }
+}
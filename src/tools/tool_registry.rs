@@ -1,5 +1,6 @@
 /// 工具注册表和管理系统
 
+use super::scan_control::ScanControl;
 use super::tool::{Tool, ToolCall, ToolDefinition, ToolResult};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -7,6 +8,8 @@ use std::sync::Arc;
 /// 工具注册表
 pub struct ToolRegistry {
     tools: HashMap<String, Arc<dyn Tool>>,
+    /// 安全模式：只允许只读工具（不写文件、不启动进程）
+    safe_mode: bool,
 }
 
 impl ToolRegistry {
@@ -14,9 +17,20 @@ impl ToolRegistry {
     pub fn new() -> Self {
         Self {
             tools: HashMap::new(),
+            safe_mode: false,
         }
     }
 
+    /// 开启或关闭安全模式
+    pub fn set_safe_mode(&mut self, safe_mode: bool) {
+        self.safe_mode = safe_mode;
+    }
+
+    /// 工具在当前模式下是否可用
+    fn is_enabled(&self, tool: &dyn Tool) -> bool {
+        !self.safe_mode || tool.is_read_only()
+    }
+
     /// 注册工具
     pub fn register(&mut self, tool: Arc<dyn Tool>) {
         self.tools.insert(tool.name().to_string(), tool);
@@ -27,18 +41,40 @@ impl ToolRegistry {
         self.tools.get(name).cloned()
     }
 
-    /// 列出所有可用工具定义（用于发送给 LLM）
+    /// 列出所有可用工具定义（用于发送给 LLM；安全模式下不包含被禁用的工具）
     pub fn list_definitions(&self) -> Vec<ToolDefinition> {
         self.tools
             .values()
+            .filter(|tool| self.is_enabled(tool.as_ref()))
             .map(|tool| tool.definition())
             .collect()
     }
 
     /// 执行工具调用
     pub async fn execute(&self, call: ToolCall) -> ToolResult {
+        self.execute_with_control(call, ScanControl::new()).await
+    }
+
+    /// 执行工具调用，取消 control 时正在进行的目录扫描会停止
+    pub async fn execute_with_control(&self, call: ToolCall, control: ScanControl) -> ToolResult {
         match self.get(&call.tool_name) {
-            Some(tool) => tool.execute(call).await,
+            Some(tool) if !self.is_enabled(tool.as_ref()) => ToolResult {
+                success: false,
+                data: serde_json::json!(null),
+                error: Some(format!("Tool '{}' is disabled in safe mode", call.tool_name)),
+            },
+            Some(tool) => match validate_arguments(&tool.definition(), &call.arguments) {
+                Ok(()) => tool.execute_with_control(call, control).await,
+                Err(missing) => ToolResult {
+                    success: false,
+                    data: serde_json::json!({ "missing_parameters": missing }),
+                    error: Some(format!(
+                        "Missing required parameter{}: {}",
+                        if missing.len() > 1 { "s" } else { "" },
+                        missing.join(", ")
+                    )),
+                },
+            },
             None => ToolResult {
                 success: false,
                 data: serde_json::json!(null),
@@ -58,6 +94,25 @@ impl ToolRegistry {
     }
 }
 
+/// 按工具定义检查必填参数，一次返回所有缺失（或为 null）的参数名，顺序与定义一致
+pub fn validate_arguments(
+    definition: &ToolDefinition,
+    arguments: &HashMap<String, serde_json::Value>,
+) -> Result<(), Vec<String>> {
+    let missing: Vec<String> = definition
+        .parameters
+        .iter()
+        .filter(|param| param.required)
+        .filter(|param| arguments.get(&param.name).is_none_or(|value| value.is_null()))
+        .map(|param| param.name.clone())
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(missing)
+    }
+}
+
 impl Default for ToolRegistry {
     fn default() -> Self {
         Self::new()
@@ -123,10 +178,61 @@ mod tests {
 
         let call = ToolCall {
             tool_name: "mock_tool".to_string(),
-            arguments: Default::default(),
+            arguments: [("test".to_string(), serde_json::json!("value"))].into(),
         };
 
         let result = registry.execute(call).await;
         assert!(result.success);
     }
+
+    #[tokio::test]
+    async fn test_all_missing_required_parameters_reported_together() {
+        use crate::tools::file_tools::FileWriteTool;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(FileWriteTool::new(crate::fs::path_guard::PathGuard::new(dir.path()))));
+
+        let result = registry.execute(ToolCall {
+            tool_name: "write_file".to_string(),
+            arguments: [("content".to_string(), serde_json::json!(null))].into(),
+        }).await;
+
+        assert!(!result.success);
+        assert_eq!(result.data["missing_parameters"], serde_json::json!(["path", "content"]));
+        assert_eq!(result.error.as_deref(), Some("Missing required parameters: path, content"));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_safe_mode_blocks_write_tools() {
+        use crate::tools::file_tools::{FileReadTool, FileWriteTool};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "hello").unwrap();
+
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(FileReadTool));
+        registry.register(Arc::new(FileWriteTool::new(crate::fs::path_guard::PathGuard::new(dir.path()))));
+        registry.set_safe_mode(true);
+
+        let names: Vec<String> = registry.list_definitions().into_iter().map(|d| d.name).collect();
+        assert_eq!(names, vec!["read_file"]);
+
+        let path_arg = serde_json::json!(path.to_str().unwrap());
+        let write = registry.execute(ToolCall {
+            tool_name: "write_file".to_string(),
+            arguments: [("path".to_string(), path_arg.clone()), ("content".to_string(), serde_json::json!("overwritten"))].into(),
+        }).await;
+        assert!(!write.success);
+        assert!(write.error.unwrap().contains("disabled in safe mode"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello");
+
+        let read = registry.execute(ToolCall {
+            tool_name: "read_file".to_string(),
+            arguments: [("path".to_string(), path_arg)].into(),
+        }).await;
+        assert!(read.success, "{:?}", read.error);
+    }
 }
@@ -0,0 +1,167 @@
+/// 终端颜色能力检测与降级
+///
+/// 主题大量使用 `Color::Rgb`，在不支持 truecolor 的终端上需要映射到
+/// 最接近的 256 色（或 16 色）调色板，否则颜色会显示错乱。
+
+use ratatui::buffer::Buffer;
+use ratatui::style::Color;
+use std::sync::OnceLock;
+
+/// 终端支持的颜色深度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorDepth {
+    /// 检测当前终端的颜色深度（结果缓存）
+    pub fn detect() -> Self {
+        static DEPTH: OnceLock<ColorDepth> = OnceLock::new();
+        *DEPTH.get_or_init(|| {
+            if std::env::var_os("WT_SESSION").is_some() {
+                // Windows Terminal 支持 truecolor 但不设置 COLORTERM
+                return ColorDepth::TrueColor;
+            }
+            Self::from_env(
+                std::env::var("COLORTERM").ok().as_deref(),
+                std::env::var("TERM").ok().as_deref(),
+            )
+        })
+    }
+
+    /// 根据 COLORTERM / TERM 的值判断颜色深度
+    pub fn from_env(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if matches!(colorterm, Some("truecolor") | Some("24bit")) {
+            return ColorDepth::TrueColor;
+        }
+        match term {
+            Some(t) if t.contains("truecolor") || t.contains("direct") => ColorDepth::TrueColor,
+            Some(t) if t.contains("256color") => ColorDepth::Ansi256,
+            Some("linux") | Some("vt100") | Some("dumb") => ColorDepth::Ansi16,
+            _ => ColorDepth::Ansi256,
+        }
+    }
+}
+
+/// xterm 6x6x6 色立方体每个分量的取值
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// 16 色调色板（xterm 默认值）
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let dr = a.0 as i32 - b.0 as i32;
+    let dg = a.1 as i32 - b.1 as i32;
+    let db = a.2 as i32 - b.2 as i32;
+    (dr * dr + dg * dg + db * db) as u32
+}
+
+fn nearest_cube_index(v: u8) -> usize {
+    CUBE_LEVELS
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, level)| (v as i32 - **level as i32).abs())
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// 将 RGB 映射到最接近的 256 色索引（色立方体 16-231 或灰阶 232-255）
+pub fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let (ri, gi, bi) = (nearest_cube_index(r), nearest_cube_index(g), nearest_cube_index(b));
+    let cube_rgb = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    // 灰阶：8, 18, ..., 238
+    let avg = (r as i32 + g as i32 + b as i32) / 3;
+    let gray_step = ((avg - 8 + 5) / 10).clamp(0, 23);
+    let gray_value = (8 + gray_step * 10) as u8;
+    let gray_index = 232 + gray_step as usize;
+
+    if distance((r, g, b), (gray_value, gray_value, gray_value)) < distance((r, g, b), cube_rgb) {
+        gray_index as u8
+    } else {
+        cube_index as u8
+    }
+}
+
+/// 将 RGB 映射到最接近的 16 色
+pub fn rgb_to_ansi16(r: u8, g: u8, b: u8) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, rgb)| distance((r, g, b), *rgb))
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::Reset)
+}
+
+/// 按颜色深度降级单个颜色，非 RGB 颜色原样返回
+pub fn adapt_color(color: Color, depth: ColorDepth) -> Color {
+    match (color, depth) {
+        (Color::Rgb(r, g, b), ColorDepth::Ansi256) => Color::Indexed(rgb_to_ansi256(r, g, b)),
+        (Color::Rgb(r, g, b), ColorDepth::Ansi16) => rgb_to_ansi16(r, g, b),
+        _ => color,
+    }
+}
+
+/// 渲染完成后对整个缓冲区做颜色降级
+pub fn downsample_buffer(buf: &mut Buffer, depth: ColorDepth) {
+    if depth == ColorDepth::TrueColor {
+        return;
+    }
+    for cell in buf.content.iter_mut() {
+        cell.fg = adapt_color(cell.fg, depth);
+        cell.bg = adapt_color(cell.bg, depth);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgb_to_ansi256_known_values() {
+        assert_eq!(rgb_to_ansi256(0, 0, 0), 16);
+        assert_eq!(rgb_to_ansi256(255, 255, 255), 231);
+        assert_eq!(rgb_to_ansi256(255, 0, 0), 196);
+        assert_eq!(rgb_to_ansi256(0, 255, 0), 46);
+        assert_eq!(rgb_to_ansi256(0, 0, 255), 21);
+        // 灰阶
+        assert_eq!(rgb_to_ansi256(128, 128, 128), 244);
+        assert_eq!(rgb_to_ansi256(12, 12, 12), 232);
+        // 主题强调色 #22d3ee
+        assert_eq!(rgb_to_ansi256(34, 211, 238), 45);
+    }
+
+    #[test]
+    fn test_detect_from_env() {
+        assert_eq!(ColorDepth::from_env(Some("truecolor"), Some("xterm")), ColorDepth::TrueColor);
+        assert_eq!(ColorDepth::from_env(None, Some("xterm-256color")), ColorDepth::Ansi256);
+        assert_eq!(ColorDepth::from_env(None, Some("linux")), ColorDepth::Ansi16);
+    }
+
+    #[test]
+    fn test_adapt_color() {
+        assert_eq!(adapt_color(Color::Rgb(255, 0, 0), ColorDepth::TrueColor), Color::Rgb(255, 0, 0));
+        assert_eq!(adapt_color(Color::Rgb(255, 0, 0), ColorDepth::Ansi256), Color::Indexed(196));
+        assert_eq!(adapt_color(Color::Rgb(250, 5, 5), ColorDepth::Ansi16), Color::LightRed);
+        assert_eq!(adapt_color(Color::Yellow, ColorDepth::Ansi16), Color::Yellow);
+    }
+}
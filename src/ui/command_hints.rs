@@ -1,3 +1,4 @@
+use crate::ai::commands::COMMANDS;
 use crate::ui::theme::ModernTheme;
 use ratatui::{
     layout::Rect,
@@ -24,16 +25,13 @@ impl CommandHints {
         Self {
             visible: false,
             input: String::new(),
-            hints: vec![
-                CommandHint { command: "/help".to_string(), description: "Show help".to_string() },
-                CommandHint { command: "/clear".to_string(), description: "Clear chat history".to_string() },
-                CommandHint { command: "/status".to_string(), description: "Show app status".to_string() },
-                CommandHint { command: "/model".to_string(), description: "Set LLM model".to_string() },
-                CommandHint { command: "/provider".to_string(), description: "Set LLM provider".to_string() },
-                CommandHint { command: "/temp".to_string(), description: "Set temperature".to_string() },
-                CommandHint { command: "/tokens".to_string(), description: "Set max tokens".to_string() },
-                CommandHint { command: "/history".to_string(), description: "Show history".to_string() },
-            ],
+            hints: COMMANDS
+                .iter()
+                .map(|spec| CommandHint {
+                    command: format!("/{}", spec.names[0]),
+                    description: spec.description.to_string(),
+                })
+                .collect(),
             selected_index: 0,
         }
     }
@@ -0,0 +1,77 @@
+/// /compose：在外部编辑器中编写长消息
+/// 主循环挂起 TUI 后调用 edit_in_editor，编辑器退出后把内容放回输入框
+
+use std::io;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// 未设置 $VISUAL / $EDITOR 时使用的编辑器
+pub const DEFAULT_EDITOR: &str = "vi";
+
+/// 按 $VISUAL、$EDITOR 的顺序选择编辑器命令
+pub fn editor_command() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string())
+}
+
+/// 把 initial 写入临时文件，用 editor 打开，返回编辑后的内容（去掉末尾换行）
+/// editor 可以带参数，如 "code --wait"；编辑器非零退出时返回错误
+pub fn edit_in_editor(editor: &str, initial: &str) -> io::Result<String> {
+    let mut parts = editor.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "编辑器命令为空"))?;
+
+    let path = temp_file_path();
+    std::fs::write(&path, initial)?;
+
+    let status = Command::new(program).args(parts).arg(&path).status();
+    let result = match status {
+        Ok(status) if status.success() => std::fs::read_to_string(&path),
+        Ok(status) => Err(io::Error::other(format!("编辑器异常退出: {}", status))),
+        Err(e) => Err(io::Error::new(e.kind(), format!("无法启动编辑器 {}: {}", program, e))),
+    };
+    let _ = std::fs::remove_file(&path);
+
+    result.map(|content| content.trim_end_matches(['\n', '\r']).to_string())
+}
+
+fn temp_file_path() -> PathBuf {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    std::env::temp_dir().join(format!("starfell-compose-{}-{}.md", std::process::id(), nanos))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// 写一个把 content 覆盖到文件里的假编辑器脚本，返回可传给 edit_in_editor 的命令
+    pub(crate) fn fake_editor(dir: &std::path::Path, content: &str) -> String {
+        let output = dir.join("composed.txt");
+        std::fs::write(&output, content).unwrap();
+        let script = dir.join("fake-editor.sh");
+        std::fs::write(&script, format!("cp '{}' \"$1\"\n", output.display())).unwrap();
+        format!("sh {}", script.display())
+    }
+
+    #[test]
+    fn test_edit_in_editor_returns_file_written_by_editor() {
+        let dir = tempfile::tempdir().unwrap();
+        let editor = fake_editor(dir.path(), "第一行\nsecond line\n");
+
+        let content = edit_in_editor(&editor, "draft").unwrap();
+        assert_eq!(content, "第一行\nsecond line");
+    }
+
+    #[test]
+    fn test_edit_in_editor_reports_failing_editor() {
+        assert!(edit_in_editor("false", "draft").is_err());
+        assert!(edit_in_editor("   ", "draft").is_err());
+    }
+}
@@ -0,0 +1,40 @@
+/// 聊天显示密度
+/// 由 /density compact|comfortable 控制：紧凑模式去掉消息间空行、隐藏时间戳等元数据并缩小像素头像，
+/// 同一区域可以显示更多消息
+
+/// 显示密度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChatDensity {
+    /// 默认：完整头像、消息头单独一行、消息间留空行
+    #[default]
+    Comfortable,
+    /// 紧凑：小头像、角色标签与内容同行、无空行、不显示时间戳
+    Compact,
+}
+
+impl ChatDensity {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "comfortable" | "normal" => Some(Self::Comfortable),
+            "compact" => Some(Self::Compact),
+            _ => None,
+        }
+    }
+
+    pub fn is_compact(self) -> bool {
+        self == Self::Compact
+    }
+
+    /// 相邻消息之间的空行数
+    pub fn message_spacing(self) -> u16 {
+        match self {
+            Self::Comfortable => 1,
+            Self::Compact => 0,
+        }
+    }
+
+    /// 是否显示时间戳等消息元数据
+    pub fn shows_metadata(self) -> bool {
+        !self.is_compact()
+    }
+}
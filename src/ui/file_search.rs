@@ -1,5 +1,76 @@
 use ignore::WalkBuilder;
-use std::path::PathBuf;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+/// 最多显示的结果数
+const MAX_RESULTS: usize = 20;
+/// 正则查询的前缀，如 `@re:src/.*handler`
+const REGEX_PREFIX: &str = "re:";
+
+/// @ 之后查询的匹配方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    /// 关键词模糊匹配（默认）
+    Fuzzy,
+    /// 含 `*` / `?` / `[` 时按 glob 匹配相对路径，如 `@**/*.rs`
+    Glob,
+    /// `re:` 前缀，按正则匹配相对路径
+    Regex,
+}
+
+impl SearchMode {
+    /// 按查询内容（不含 @）判断匹配方式
+    pub fn detect(query: &str) -> Self {
+        if query.starts_with(REGEX_PREFIX) {
+            SearchMode::Regex
+        } else if query.contains(['*', '?', '[']) {
+            SearchMode::Glob
+        } else {
+            SearchMode::Fuzzy
+        }
+    }
+}
+
+/// 把 glob 转成整段匹配的正则：`**/` 匹配任意层目录，`*` / `?` 不跨越 `/`
+fn glob_to_regex(glob: &str) -> Result<Regex, regex::Error> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    pattern.push_str("(?:.*/)?");
+                } else {
+                    pattern.push_str(".*");
+                }
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            '[' => {
+                pattern.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    pattern.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    if c == '\\' || c == '[' {
+                        pattern.push('\\');
+                    }
+                    pattern.push(c);
+                }
+                pattern.push(']');
+            }
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern)
+}
 
 /// 文件搜索引擎 - 支持实时全文检索和模糊匹配
 #[derive(Debug, Clone)]
@@ -96,12 +167,26 @@ impl FileSearchEngine {
             self.results = self
                 .cache
                 .iter()
-                .take(20)
+                .take(MAX_RESULTS)
                 .map(|p| format!("@{}", p.display()))
                 .collect();
             return;
         }
 
+        match SearchMode::detect(search_query) {
+            SearchMode::Regex => {
+                let pattern = Regex::new(&search_query[REGEX_PREFIX.len()..]);
+                self.results = self.filter_by_pattern(pattern.ok().as_ref());
+                return;
+            }
+            SearchMode::Glob => {
+                let pattern = glob_to_regex(search_query);
+                self.results = self.filter_by_pattern(pattern.ok().as_ref());
+                return;
+            }
+            SearchMode::Fuzzy => {}
+        }
+
         // 分割查询为多个关键词（仅在有空格或 / 时分割）
         let keywords: Vec<&str> = if search_query.contains(' ') || search_query.contains('/') {
             // 有空格或 / 时，按这些分隔符分割
@@ -187,11 +272,32 @@ impl FileSearchEngine {
         // 提取结果并限制数量
         self.results = matches
             .into_iter()
-            .take(20)  // 增加显示数量
+            .take(MAX_RESULTS)
             .map(|(path, _)| path)
             .collect();
     }
 
+    /// 按正则过滤相对于根目录的路径（保持缓存中的顺序）；模式无效时没有结果
+    fn filter_by_pattern(&self, pattern: Option<&Regex>) -> Vec<String> {
+        let Some(pattern) = pattern else {
+            return Vec::new();
+        };
+        self.cache
+            .iter()
+            .filter(|path| pattern.is_match(&self.relative_path(path)))
+            .take(MAX_RESULTS)
+            .map(|path| format!("@{}", path.display()))
+            .collect()
+    }
+
+    /// 相对于根目录、以 `/` 分隔的路径
+    fn relative_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root_path)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+
     /// 向上选择
     pub fn select_previous(&mut self) {
         if self.selected_index > 0 {
@@ -245,4 +351,73 @@ mod tests {
         engine.update_query("@src main".to_string());
         // 结果应该包含同时包含 "src" 和 "main" 的文件
     }
+
+    /// 在临时目录中建立索引
+    fn engine_with(files: &[&str]) -> (tempfile::TempDir, FileSearchEngine) {
+        let dir = tempfile::tempdir().unwrap();
+        for file in files {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        let mut engine = FileSearchEngine::new();
+        engine.set_root(dir.path().to_path_buf());
+        engine.build_cache();
+        (dir, engine)
+    }
+
+    fn relative_results(engine: &FileSearchEngine) -> Vec<String> {
+        engine
+            .results
+            .iter()
+            .map(|r| engine.relative_path(Path::new(r.trim_start_matches('@'))))
+            .collect()
+    }
+
+    const FILES: &[&str] = &["src/main.rs", "src/events/handler.rs", "src/ui/input_handler.rs", "README.md", "notes.txt"];
+
+    #[test]
+    fn test_search_mode_detection() {
+        assert_eq!(SearchMode::detect("src main"), SearchMode::Fuzzy);
+        assert_eq!(SearchMode::detect("**/*.rs"), SearchMode::Glob);
+        assert_eq!(SearchMode::detect("notes.tx?"), SearchMode::Glob);
+        assert_eq!(SearchMode::detect("re:src/.*handler"), SearchMode::Regex);
+    }
+
+    #[test]
+    fn test_glob_query_filters_relative_paths() {
+        let (_dir, mut engine) = engine_with(FILES);
+
+        engine.update_query("@**/*.rs".to_string());
+        assert_eq!(relative_results(&engine), vec!["src/events/handler.rs", "src/main.rs", "src/ui/input_handler.rs"]);
+
+        engine.update_query("@*.md".to_string());
+        assert_eq!(relative_results(&engine), vec!["README.md"]);
+
+        engine.update_query("@src/[!e]*/*.rs".to_string());
+        assert_eq!(relative_results(&engine), vec!["src/ui/input_handler.rs"]);
+        assert_eq!(engine.selected_index, 0);
+    }
+
+    #[test]
+    fn test_regex_query_filters_relative_paths() {
+        let (_dir, mut engine) = engine_with(FILES);
+
+        engine.update_query("@re:src/.*handler".to_string());
+        assert_eq!(relative_results(&engine), vec!["src/events/handler.rs", "src/ui/input_handler.rs"]);
+
+        // 无效的正则没有结果
+        engine.update_query("@re:src/(".to_string());
+        assert!(engine.results.is_empty());
+    }
+
+    #[test]
+    fn test_plain_query_still_fuzzy_matches() {
+        let (_dir, mut engine) = engine_with(FILES);
+
+        engine.update_query("@handler".to_string());
+        let results = relative_results(&engine);
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.ends_with("handler.rs")));
+    }
 }
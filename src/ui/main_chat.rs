@@ -1,5 +1,8 @@
 use crate::ui::types::{MessageStatus, ChatAction};
 use crate::ui::theme::ModernTheme;
+use crate::ui::markdown::{render_markdown_to_lines, MarkdownTheme};
+use crate::ui::timestamps::{format_timestamp, TimestampMode};
+use crate::ui::typing_indicator::TypingAnimation;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect, Alignment},
     style::{Style, Modifier},
@@ -9,8 +12,6 @@ use ratatui::{
 };
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use chrono::{DateTime, Utc};
-use unicode_width::UnicodeWidthStr;
-use arboard::Clipboard;
 
 #[derive(Clone, Debug)]
 pub struct EnhancedChatMessage {
@@ -43,6 +44,8 @@ pub struct MainChatArea {
     pub max_scroll: usize,
     pub auto_scroll: bool,
     pub typing_indicator: Option<TypingIndicator>,
+    pub typing_animation: TypingAnimation,
+    pub timestamp_mode: TimestampMode,
 }
 
 impl MainChatArea {
@@ -55,6 +58,8 @@ impl MainChatArea {
             max_scroll: 0,
             auto_scroll: true,
             typing_indicator: None,
+            typing_animation: TypingAnimation::from_env(),
+            timestamp_mode: TimestampMode::Absolute,
         }
     }
 
@@ -213,26 +218,21 @@ impl MainChatArea {
             _ => ("MSG", theme.colors.text_primary),
         };
 
-        let timestamp_str = message.timestamp.format("%H:%M:%S").to_string();
-        let header_line = Line::from(vec![
-            Span::styled(
-                format!("{}: ", role_text),
-                Style::default().fg(role_color).add_modifier(Modifier::BOLD),
-            ),
-            Span::styled(
-                format!("[{}]", timestamp_str),
-                Style::default().fg(theme.colors.secondary),
-            ),
-        ]);
-        lines.push(header_line);
-
-        // Message content (with word wrapping)
-        let content_lines = self.wrap_text(&message.content, width.saturating_sub(4) as usize);
-        for content_line in content_lines {
-            lines.push(Line::from(Span::styled(
-                format!("  {}", content_line),
-                Style::default().fg(theme.colors.text_primary),
-            )));
+        let mut header_spans = vec![Span::styled(
+            format!("{}: ", role_text),
+            Style::default().fg(role_color).add_modifier(Modifier::BOLD),
+        )];
+        if let Some(timestamp) = format_timestamp(message.timestamp, Utc::now(), self.timestamp_mode) {
+            header_spans.push(Span::styled(timestamp, Style::default().fg(theme.colors.secondary)));
+        }
+        lines.push(Line::from(header_spans));
+
+        // Message content (shared markdown rendering, wrapped to width)
+        let markdown_theme = MarkdownTheme::from(theme);
+        for content_line in render_markdown_to_lines(&message.content, width.saturating_sub(4) as usize, &markdown_theme) {
+            let mut spans = vec![Span::raw("  ")];
+            spans.extend(content_line.spans);
+            lines.push(Line::from(spans));
         }
 
         // Message status indicator
@@ -266,8 +266,11 @@ impl MainChatArea {
     /// Format typing indicator
     fn format_typing_indicator(&self, indicator: &TypingIndicator, theme: &ModernTheme) -> Vec<Line> {
         let animation_chars = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
-        let char_index = indicator.animation_frame % animation_chars.len();
-        let animation_char = animation_chars[char_index];
+        let animation_char = if self.typing_animation.enabled {
+            animation_chars[indicator.animation_frame % animation_chars.len()]
+        } else {
+            "…"
+        };
 
         vec![
             Line::from(vec![
@@ -443,13 +446,24 @@ impl MainChatArea {
 
     /// Update typing indicator animation
     pub fn update_typing_indicator(&mut self) {
-        if let Some(indicator) = &mut self.typing_indicator {
-            let now = Utc::now();
-            if now.signed_duration_since(indicator.last_update).num_milliseconds() > 100 {
-                indicator.animation_frame += 1;
-                indicator.last_update = now;
-            }
+        self.update_typing_indicator_at(Utc::now());
+    }
+
+    /// Advance the animation by one frame once the configured interval has passed.
+    /// Returns whether the frame changed (i.e. a redraw is needed)
+    pub fn update_typing_indicator_at(&mut self, now: DateTime<Utc>) -> bool {
+        if !self.typing_animation.enabled {
+            return false;
+        }
+        let Some(indicator) = &mut self.typing_indicator else {
+            return false;
+        };
+        if now.signed_duration_since(indicator.last_update).num_milliseconds() < self.typing_animation.frame_interval_ms as i64 {
+            return false;
         }
+        indicator.animation_frame = indicator.animation_frame.wrapping_add(1);
+        indicator.last_update = now;
+        true
     }
 
     /// Stop typing indicator
@@ -529,49 +543,39 @@ impl MainChatArea {
         let total_lines = self.messages.len() * 3; // Approximate lines per message
         self.max_scroll = total_lines.saturating_sub(visible_height);
     }
+}
 
-    /// Wrap text to fit within specified width
-    fn wrap_text(&self, text: &str, width: usize) -> Vec<String> {
-        if width == 0 {
-            return vec![text.to_string()];
-        }
+impl Default for MainChatArea {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        let mut lines = Vec::new();
-        let mut current_line = String::new();
-
-        for word in text.split_whitespace() {
-            // 计算当前行宽度时考虑 Unicode 字符宽度
-            let current_width = current_line.width();
-            let word_width = word.width();
-            let space_width = if current_line.is_empty() { 0 } else { 1 }; // 空格宽度
-
-            if current_width + word_width + space_width > width {
-                if !current_line.is_empty() {
-                    lines.push(current_line);
-                    current_line = String::new();
-                }
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            if !current_line.is_empty() {
-                current_line.push(' ');
-            }
-            current_line.push_str(word);
-        }
+    #[test]
+    fn test_typing_indicator_respects_interval_and_disable() {
+        let mut chat = MainChatArea::new();
+        chat.typing_animation = TypingAnimation { frame_interval_ms: 250, enabled: true };
+        chat.start_typing_indicator(None);
+        let started = chat.typing_indicator.as_ref().unwrap().last_update;
 
-        if !current_line.is_empty() {
-            lines.push(current_line);
-        }
+        assert!(!chat.update_typing_indicator_at(started + chrono::Duration::milliseconds(100)));
+        assert_eq!(chat.typing_indicator.as_ref().unwrap().animation_frame, 0);
+        assert!(chat.update_typing_indicator_at(started + chrono::Duration::milliseconds(250)));
+        assert_eq!(chat.typing_indicator.as_ref().unwrap().animation_frame, 1);
 
-        if lines.is_empty() {
-            lines.push(String::new());
-        }
+        chat.typing_animation = TypingAnimation::parse("off").unwrap();
+        assert!(!chat.update_typing_indicator_at(started + chrono::Duration::seconds(10)));
+        assert_eq!(chat.typing_indicator.as_ref().unwrap().animation_frame, 1);
 
-        lines
-    }
-}
+        chat.stop_typing_indicator();
+        assert!(!chat.update_typing_indicator_at(started + chrono::Duration::seconds(20)));
+        assert!(chat.typing_indicator.is_none());
 
-impl Default for MainChatArea {
-    fn default() -> Self {
-        Self::new()
+        assert_eq!(TypingAnimation::parse("40"), Some(TypingAnimation { frame_interval_ms: 40, enabled: true }));
+        assert_eq!(TypingAnimation::parse("fast"), None);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,372 @@
+/// 聊天内容的 Markdown 渲染，所有聊天渲染器共用
+/// 标题加粗、列表按层级缩进、行内代码和代码块变暗（标注了 rust/python/js 的代码块按语法着色），并按显示宽度折行。
+/// 可以直接用于流式输出中途的内容：未闭合的代码块按代码显示，未配对的反引号按普通文本显示
+
+use crate::ui::syntax::{tokenize_line, Language, TokenKind};
+use crate::ui::theme::ModernTheme;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Markdown 渲染使用的颜色
+#[derive(Debug, Clone, Copy)]
+pub struct MarkdownTheme {
+    pub text: Color,
+    pub heading: Color,
+    pub code: Color,
+    pub bullet: Color,
+    pub keyword: Color,
+    pub string: Color,
+    pub comment: Color,
+}
+
+impl MarkdownTheme {
+    /// 正文颜色 + 强调色（标题、列表符号）
+    pub fn new(text: Color, accent: Color) -> Self {
+        Self {
+            text,
+            heading: accent,
+            code: Color::Gray,
+            bullet: accent,
+            keyword: Color::Magenta,
+            string: Color::Green,
+            comment: Color::DarkGray,
+        }
+    }
+
+    fn text_style(&self) -> Style {
+        Style::default().fg(self.text)
+    }
+
+    fn heading_style(&self) -> Style {
+        Style::default().fg(self.heading).add_modifier(Modifier::BOLD)
+    }
+
+    fn code_style(&self) -> Style {
+        Style::default().fg(self.code).add_modifier(Modifier::DIM)
+    }
+
+    fn bullet_style(&self) -> Style {
+        Style::default().fg(self.bullet)
+    }
+
+    fn syntax_style(&self, kind: TokenKind) -> Style {
+        match kind {
+            TokenKind::Plain => self.code_style(),
+            TokenKind::Keyword => Style::default().fg(self.keyword).add_modifier(Modifier::BOLD),
+            TokenKind::String => Style::default().fg(self.string),
+            TokenKind::Comment => Style::default().fg(self.comment).add_modifier(Modifier::ITALIC),
+        }
+    }
+}
+
+impl From<&ModernTheme> for MarkdownTheme {
+    fn from(theme: &ModernTheme) -> Self {
+        Self {
+            text: theme.colors.text_primary,
+            heading: theme.colors.primary,
+            code: theme.colors.text_secondary,
+            bullet: theme.colors.primary,
+            keyword: theme.colors.secondary,
+            string: theme.colors.success,
+            comment: theme.colors.border_inactive,
+        }
+    }
+}
+
+/// 把 Markdown 内容渲染为折行后的行；width 为 0 时不折行
+pub fn render_markdown_to_lines(content: &str, width: usize, theme: &MarkdownTheme) -> Vec<Line<'static>> {
+    render_markdown_with_source(content, width, theme)
+        .into_iter()
+        .map(|(_, line)| line)
+        .collect()
+}
+
+/// 同 render_markdown_to_lines，并附带每行对应的源内容行号（用于按源行高亮）
+pub fn render_markdown_with_source(content: &str, width: usize, theme: &MarkdownTheme) -> Vec<(usize, Line<'static>)> {
+    let mut lines = Vec::new();
+    let mut in_fence = false;
+    // 当前代码块的语言（围栏的 info string），未知语言不高亮
+    let mut fence_language = None;
+    let mut nesting = ListNesting::default();
+
+    for (index, raw) in content.lines().enumerate() {
+        let trimmed = raw.trim_start();
+        let list = if in_fence { None } else { parse_list_item(raw) };
+        if list.is_none() && !trimmed.is_empty() {
+            nesting.reset();
+        }
+
+        let rendered = if let Some(info) = trimmed.strip_prefix("```") {
+            in_fence = !in_fence;
+            fence_language = if in_fence { Language::from_fence_info(info) } else { None };
+            wrap_segments(Vec::new(), vec![(raw.to_string(), theme.code_style())], width, 0)
+        } else if in_fence {
+            let segments = match fence_language {
+                Some(language) => tokenize_line(language, raw)
+                    .into_iter()
+                    .map(|(text, kind)| (text, theme.syntax_style(kind)))
+                    .collect(),
+                None => vec![(raw.to_string(), theme.code_style())],
+            };
+            wrap_segments(Vec::new(), segments, width, 0)
+        } else if let Some(text) = heading_text(trimmed) {
+            wrap_segments(Vec::new(), parse_inline(text, theme.heading_style(), theme), width, 0)
+        } else if let Some(item) = list {
+            let prefix = list_prefix(nesting.level(item.indent), item.marker);
+            let indent = prefix.width();
+            wrap_segments(
+                vec![(prefix, theme.bullet_style())],
+                parse_inline(item.text, theme.text_style(), theme),
+                width,
+                indent,
+            )
+        } else {
+            wrap_segments(Vec::new(), parse_inline(raw, theme.text_style(), theme), width, 0)
+        };
+
+        lines.extend(rendered.into_iter().map(|line| (index, line)));
+    }
+    lines
+}
+
+/// `# 标题` ~ `###### 标题`
+fn heading_text(line: &str) -> Option<&str> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&level) {
+        line[level..].strip_prefix(' ').map(str::trim)
+    } else {
+        None
+    }
+}
+
+/// 无序列表各层级的符号，超过三层时循环使用
+const BULLET_MARKERS: [&str; 3] = ["•", "◦", "▪"];
+
+/// 一个 tab 折算的缩进列数
+const TAB_WIDTH: usize = 4;
+
+/// 列表项的符号：无序列表，或有序列表的编号
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListMarker<'a> {
+    Bullet,
+    Number(&'a str),
+}
+
+/// 一行列表项：前导空白的列数、符号和正文
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListItem<'a> {
+    pub indent: usize,
+    pub marker: ListMarker<'a>,
+    pub text: &'a str,
+}
+
+/// 识别 `- `、`* `、`+ `、`• ` 和 `1. ` 开头的列表项，前导空格和 tab 计入缩进
+pub fn parse_list_item(line: &str) -> Option<ListItem<'_>> {
+    let rest = line.trim_start_matches([' ', '\t']);
+    let indent = line[..line.len() - rest.len()]
+        .chars()
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum();
+
+    if let Some(text) = ["- ", "* ", "+ ", "• "].iter().find_map(|marker| rest.strip_prefix(marker)) {
+        return Some(ListItem { indent, marker: ListMarker::Bullet, text });
+    }
+
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        if let Some(text) = rest[digits..].strip_prefix(". ") {
+            return Some(ListItem { indent, marker: ListMarker::Number(&rest[..digits]), text });
+        }
+    }
+    None
+}
+
+/// 按缩进推算列表层级：缩进比上一级深的列表项下沉一层，回到上级的缩进时恢复
+#[derive(Debug, Default)]
+pub struct ListNesting {
+    // 当前各层级列表项的缩进
+    indents: Vec<usize>,
+}
+
+impl ListNesting {
+    /// 返回该缩进的列表项所在层级（顶层为 0）
+    pub fn level(&mut self, indent: usize) -> usize {
+        while self.indents.last().is_some_and(|&parent| indent <= parent) {
+            self.indents.pop();
+        }
+        self.indents.push(indent);
+        self.indents.len() - 1
+    }
+
+    /// 列表结束（遇到普通段落、标题或代码块）
+    pub fn reset(&mut self) {
+        self.indents.clear();
+    }
+}
+
+/// 列表项显示的前缀：每层缩进两列，无序列表按层级换符号，有序列表保留编号
+fn list_prefix(level: usize, marker: ListMarker) -> String {
+    let marker = match marker {
+        ListMarker::Bullet => BULLET_MARKERS[level % BULLET_MARKERS.len()].to_string(),
+        ListMarker::Number(number) => format!("{}.", number),
+    };
+    format!("{}{} ", "  ".repeat(level), marker)
+}
+
+/// 行内格式：`代码` 和 **加粗**；没有配对的标记按原样显示
+fn parse_inline(text: &str, base: Style, theme: &MarkdownTheme) -> Vec<(String, Style)> {
+    let mut segments: Vec<(String, Style)> = Vec::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let next = [rest.find('`'), rest.find("**")].into_iter().flatten().min();
+        let Some(start) = next else {
+            segments.push((rest.to_string(), base));
+            break;
+        };
+
+        let (marker, style) = if rest[start..].starts_with('`') {
+            ("`", theme.code_style())
+        } else {
+            ("**", base.add_modifier(Modifier::BOLD))
+        };
+        let inner_start = start + marker.len();
+        match rest[inner_start..].find(marker) {
+            Some(len) if len > 0 => {
+                if start > 0 {
+                    segments.push((rest[..start].to_string(), base));
+                }
+                segments.push((rest[inner_start..inner_start + len].to_string(), style));
+                rest = &rest[inner_start + len + marker.len()..];
+            }
+            _ => {
+                // 未配对（如流式输出到一半）：标记作为普通文本
+                segments.push((rest[..inner_start].to_string(), base));
+                rest = &rest[inner_start..];
+            }
+        }
+    }
+    segments
+}
+
+/// 按显示宽度折行；续行缩进 continuation_indent 列，与列表正文对齐
+fn wrap_segments(
+    prefix: Vec<(String, Style)>,
+    segments: Vec<(String, Style)>,
+    width: usize,
+    continuation_indent: usize,
+) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let mut current: Vec<Span<'static>> = prefix.into_iter().map(|(text, style)| Span::styled(text, style)).collect();
+    let mut current_width: usize = current.iter().map(|span| span.width()).sum();
+    // 缩进过宽时放弃续行缩进，保证每行至少能放下一个字符
+    let indent = if continuation_indent < width { continuation_indent } else { 0 };
+
+    for (text, style) in segments {
+        for c in text.chars() {
+            let w = c.width().unwrap_or(0);
+            if width > 0 && current_width + w > width && current_width > indent {
+                lines.push(Line::from(std::mem::take(&mut current)));
+                current_width = indent;
+                if indent > 0 {
+                    current.push(Span::raw(" ".repeat(indent)));
+                }
+            }
+            match current.last_mut() {
+                Some(span) if span.style == style => span.content.to_mut().push(c),
+                _ => current.push(Span::styled(c.to_string(), style)),
+            }
+            current_width += w;
+        }
+    }
+    lines.push(Line::from(current));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn theme() -> MarkdownTheme {
+        MarkdownTheme::new(Color::White, Color::Cyan)
+    }
+
+    fn texts(line: &Line) -> Vec<String> {
+        line.spans.iter().map(|span| span.content.to_string()).collect()
+    }
+
+    #[test]
+    fn test_markdown_structure_and_styles() {
+        let content = "# Setup\nRun `cargo build` first.\n- one\n  - nested **item**\n2. second\n```rust\nfn main() {}\n```";
+        let lines = render_markdown_to_lines(content, 80, &theme());
+        assert_eq!(lines.len(), 8);
+
+        // 标题：去掉 #，加粗
+        assert_eq!(texts(&lines[0]), vec!["Setup"]);
+        assert!(lines[0].spans[0].style.add_modifier.contains(Modifier::BOLD));
+
+        // 行内代码变暗
+        assert_eq!(texts(&lines[1]), vec!["Run ", "cargo build", " first."]);
+        assert!(lines[1].spans[1].style.add_modifier.contains(Modifier::DIM));
+        assert!(!lines[1].spans[0].style.add_modifier.contains(Modifier::DIM));
+
+        // 列表：统一符号，按层级缩进
+        assert_eq!(texts(&lines[2]), vec!["• ", "one"]);
+        assert_eq!(texts(&lines[3]), vec!["  ◦ ", "nested ", "item"]);
+        assert!(lines[3].spans[2].style.add_modifier.contains(Modifier::BOLD));
+        assert_eq!(texts(&lines[4]), vec!["2. ", "second"]);
+
+        // 代码块围栏变暗，rust 代码按语法着色
+        assert_eq!(texts(&lines[6]), vec!["fn", " main() {}"]);
+        assert!([5, 7].iter().all(|&i| lines[i].spans[0].style.add_modifier.contains(Modifier::DIM)));
+        assert_eq!(lines[6].spans[0].style.fg, Some(theme().keyword));
+        assert!(lines[6].spans[1].style.add_modifier.contains(Modifier::DIM));
+    }
+
+    #[test]
+    fn test_nested_lists_indent_one_level_per_depth() {
+        let content = "- fruits\n    - apple\n    - pear\n\t  1. ripe\n- vegetables\n   * kale\n1. first\n   2. sub\nafter\n  - fresh";
+        let lines = render_markdown_to_lines(content, 80, &theme());
+        let prefixes: Vec<String> = lines.iter().map(|line| line.spans[0].content.to_string()).collect();
+
+        assert_eq!(
+            prefixes,
+            vec!["• ", "  ◦ ", "  ◦ ", "    1. ", "• ", "  ◦ ", "1. ", "  2. ", "after", "• "]
+        );
+        assert_eq!(texts(&lines[1])[1], "apple");
+    }
+
+    #[test]
+    fn test_code_block_highlight_follows_fence_language() {
+        let content = "Example:\n```python\nprint('hi')  # greet\n```\n```haskell\nmain = print \"hi\"\n```\nplain 'text'";
+        let lines = render_markdown_to_lines(content, 80, &theme());
+
+        assert_eq!(texts(&lines[2]), vec!["print(", "'hi'", ")  ", "# greet"]);
+        assert_eq!(lines[2].spans[1].style.fg, Some(theme().string));
+        assert_eq!(lines[2].spans[3].style.fg, Some(theme().comment));
+
+        // 未知语言和代码块外的文本不高亮
+        assert_eq!(texts(&lines[5]), vec!["main = print \"hi\""]);
+        assert_eq!(texts(&lines[7]), vec!["plain 'text'"]);
+    }
+
+    #[test]
+    fn test_wrapping_and_partial_stream() {
+        let lines = render_markdown_to_lines("- alpha beta gamma delta", 12, &theme());
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|l| l.width() <= 12));
+        // 续行与列表正文对齐
+        assert_eq!(lines[1].spans[0].content, "  ");
+
+        // 流式输出到一半：未闭合的代码块和反引号
+        let lines = render_markdown_to_lines("Try `unwrap\n```rust\nlet x", 80, &theme());
+        assert_eq!(texts(&lines[0]), vec!["Try `unwrap"]);
+        assert_eq!(texts(&lines[2]), vec!["let", " x"]);
+        assert_eq!(lines[2].spans[0].style.fg, Some(theme().keyword));
+
+        let sourced = render_markdown_with_source("a\n- b c d e f g h", 6, &theme());
+        assert_eq!(sourced.first().unwrap().0, 0);
+        assert!(sourced[1..].iter().all(|(index, _)| *index == 1));
+    }
+}
@@ -10,11 +10,19 @@ pub mod layout;
 pub mod sidebar;
 pub mod info_panel;
 pub mod theme;
+pub mod color_support;
 pub mod focus;
 pub mod types;
 pub mod command_hints;
 pub mod mention_suggestions;
+pub mod model_switcher;
+pub mod session_picker;
+pub mod timestamps;
+pub mod density;
+pub mod markdown;
+pub mod syntax;
 pub mod file_search;
+pub mod semantic_search;
 pub mod render_cache;
 pub mod optimized_renderer;
 pub mod render_engine;
@@ -26,6 +34,8 @@ pub mod smart_chat_display;
 pub mod vibe_panel;
 pub mod filename_suggestion;
 pub mod input_area;
+pub mod compose;
+pub mod typing_indicator;
 
 // pub use smart_chat_display::{
 //     SmartChatDisplay, SmartMessage, MessageRole, MessageType,
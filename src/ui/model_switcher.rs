@@ -0,0 +1,167 @@
+/// 模型切换浮层（Ctrl+O）
+/// 列出可用模型，支持模糊过滤和上下键选择，Enter 应用选择
+
+use crate::ai::config::LLMProvider;
+use crate::ui::pixel_layout_v2::Theme;
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem},
+    Frame,
+};
+
+/// 各提供商的常用模型（无法在线获取模型列表时使用）
+pub fn known_models(provider: &LLMProvider) -> Vec<String> {
+    let models: &[&str] = match provider {
+        LLMProvider::OpenAI => &["gpt-4o", "gpt-4o-mini", "gpt-4-turbo", "gpt-3.5-turbo"],
+        LLMProvider::Gemini => &["gemini-2.5-pro", "gemini-2.5-flash", "gemini-1.5-pro", "gemini-1.5-flash"],
+        LLMProvider::Claude => &["claude-3-5-sonnet", "claude-3-opus", "claude-3-sonnet", "claude-3-haiku"],
+        LLMProvider::Ollama => &["llama3", "mistral", "qwen2.5-coder", "codellama"],
+        LLMProvider::DeepSeek => &["deepseek-chat", "deepseek-coder", "deepseek-reasoner"],
+        LLMProvider::LocalServer => &["liquid/lfm2-1.2b"],
+    };
+    models.iter().map(|m| m.to_string()).collect()
+}
+
+/// 模糊匹配：query 的字符按顺序出现在 candidate 中（忽略大小写）
+fn fuzzy_match(query: &str, candidate: &str) -> bool {
+    let candidate = candidate.to_lowercase();
+    let mut chars = candidate.chars();
+    query
+        .to_lowercase()
+        .chars()
+        .all(|q| chars.any(|c| c == q))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ModelSwitcher {
+    pub visible: bool,
+    pub query: String,
+    models: Vec<String>,
+    selected_index: usize,
+}
+
+impl ModelSwitcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 打开浮层，当前模型排在最前并默认选中
+    pub fn open(&mut self, mut models: Vec<String>, current: &str) {
+        models.retain(|m| m != current);
+        models.insert(0, current.to_string());
+        self.models = models;
+        self.query.clear();
+        self.selected_index = 0;
+        self.visible = true;
+    }
+
+    pub fn close(&mut self) {
+        self.visible = false;
+        self.query.clear();
+        self.selected_index = 0;
+    }
+
+    pub fn filtered(&self) -> Vec<&String> {
+        self.models
+            .iter()
+            .filter(|m| fuzzy_match(&self.query, m))
+            .collect()
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        self.query.push(c);
+        self.selected_index = 0;
+    }
+
+    pub fn pop_char(&mut self) {
+        self.query.pop();
+        self.selected_index = 0;
+    }
+
+    pub fn select_next(&mut self) {
+        let count = self.filtered().len();
+        if count > 0 {
+            self.selected_index = (self.selected_index + 1) % count;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        let count = self.filtered().len();
+        if count > 0 {
+            self.selected_index = if self.selected_index == 0 { count - 1 } else { self.selected_index - 1 };
+        }
+    }
+
+    pub fn selected(&self) -> Option<String> {
+        self.filtered().get(self.selected_index).map(|m| m.to_string())
+    }
+
+    /// 渲染在 anchor（输入区）上方
+    pub fn render(&self, f: &mut Frame, anchor: Rect, theme: &Theme) {
+        if !self.visible {
+            return;
+        }
+
+        let filtered = self.filtered();
+        let height = (filtered.len().clamp(1, 8) as u16 + 2).min(anchor.y);
+        let area = Rect {
+            x: anchor.x,
+            y: anchor.y.saturating_sub(height),
+            width: anchor.width.min(50),
+            height,
+        };
+
+        let items: Vec<ListItem> = if filtered.is_empty() {
+            vec![ListItem::new(Span::styled("没有匹配的模型", Style::default().fg(theme.diff_rem_text)))]
+        } else {
+            filtered
+                .iter()
+                .enumerate()
+                .map(|(i, model)| {
+                    let item = ListItem::new(Line::from(model.as_str()));
+                    if i == self.selected_index {
+                        item.style(Style::default().fg(theme.bg).bg(theme.accent_ai).add_modifier(Modifier::BOLD))
+                    } else {
+                        item
+                    }
+                })
+                .collect()
+        };
+
+        let title = format!(" 切换模型: {}_ ", self.query);
+        let list = List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.accent_ai))
+                .title(title)
+                .style(Style::default().bg(theme.panel_bg)),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(list, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_filter_and_selection() {
+        let mut switcher = ModelSwitcher::new();
+        switcher.open(known_models(&LLMProvider::OpenAI), "gpt-3.5-turbo");
+        assert_eq!(switcher.selected().as_deref(), Some("gpt-3.5-turbo"));
+
+        for c in "4om".chars() {
+            switcher.push_char(c);
+        }
+        let filtered: Vec<&str> = switcher.filtered().iter().map(|m| m.as_str()).collect();
+        assert_eq!(filtered, vec!["gpt-4o-mini"]);
+
+        switcher.pop_char();
+        switcher.pop_char();
+        switcher.select_next();
+        assert_eq!(switcher.selected().as_deref(), Some("gpt-4o-mini"));
+    }
+}
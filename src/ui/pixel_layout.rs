@@ -37,10 +37,12 @@ use ratatui::{
     layout::{Alignment, Rect},
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Wrap},
+    widgets::{Block, Borders, Paragraph},
     Frame,
 };
 use crate::app::App;
+use crate::ui::density::ChatDensity;
+use crate::ui::markdown::{render_markdown_to_lines, MarkdownTheme};
 
 /// 像素化头像数据
 pub struct PixelAvatar {
@@ -101,6 +103,39 @@ impl PixelAvatar {
         }
         lines
     }
+
+    /// 紧凑模式的半尺寸头像（4x4）：每 2x2 像素合并为一个，眼睛优先保留
+    pub fn render_compact_lines(&self, color: Color) -> Vec<Line<'static>> {
+        let mut lines = vec![];
+        for row in 0..4 {
+            let mut spans = vec![];
+            for col in 0..4 {
+                let pixels: Vec<u8> = [(0, 0), (0, 1), (1, 0), (1, 1)]
+                    .iter()
+                    .map(|(dr, dc)| self.map[(row * 2 + dr) * 8 + col * 2 + dc])
+                    .collect();
+                let pixel_style = if pixels.contains(&2) {
+                    Style::default().bg(Color::White)
+                } else if pixels.iter().filter(|&&p| p == 1).count() >= 2 {
+                    Style::default().bg(color)
+                } else {
+                    Style::default()
+                };
+                spans.push(Span::styled("  ", pixel_style));
+            }
+            lines.push(Line::from(spans));
+        }
+        lines
+    }
+
+    /// 按显示密度渲染头像
+    pub fn render_for_density(&self, color: Color, density: ChatDensity) -> Vec<Line<'static>> {
+        if density.is_compact() {
+            self.render_compact_lines(color)
+        } else {
+            self.render_lines(color)
+        }
+    }
 }
 
 /// 主布局渲染函数
@@ -158,20 +193,43 @@ fn render_status_bar(f: &mut Frame, _app: &App, area: Rect) {
 }
 
 /// 渲染聊天历史区域 - 使用水平布局实现两列
+/// chat_scroll_offset 表示从底部向上滚动的行数，为 0 时显示最新消息
 fn render_history_area(f: &mut Frame, app: &App, area: Rect) {
     use crate::core::message::Role;
     use ratatui::layout::{Constraint, Direction, Layout};
 
     let theme = &V2_THEME;
     let messages = app.chat_history.get_messages();
+    let density = app.chat_density;
+
+    // 每条消息占头像高度 + 消息间隔（紧凑模式下头像减半且没有间隔）
+    let block_height = |msg: &crate::core::message::Message| -> u16 {
+        let avatar = match msg.role {
+            Role::User => PixelAvatar::user(),
+            Role::Assistant => PixelAvatar::ai(),
+            Role::System => PixelAvatar::sys(),
+        };
+        avatar.render_for_density(Color::Reset, density).len() as u16 + density.message_spacing()
+    };
 
-    // 计算每条消息需要的高度并渲染
-    let mut current_y = app.chat_scroll_offset as u16;
-    
+    // 与 render_history 一致：内容超出可见区域时底部对齐，再按偏移向上滚动
+    let total_height: u16 = messages.iter().map(|msg| block_height(msg)).fold(0, u16::saturating_add);
+    let view_top = total_height
+        .saturating_sub(area.height)
+        .saturating_sub(app.chat_scroll_offset.min(u16::MAX as usize) as u16);
+    let view_bottom = view_top.saturating_add(area.height);
+
+    let mut block_top: u16 = 0;
     for msg in messages {
-        if current_y >= area.height {
+        let height = block_height(msg);
+        let block_bottom = block_top.saturating_add(height);
+        if block_top >= view_bottom {
             break;
         }
+        if block_bottom <= view_top {
+            block_top = block_bottom;
+            continue;
+        }
 
         let (avatar, role_label, role_color) = match msg.role {
             Role::User => (PixelAvatar::user(), "USER", theme.accent_user),
@@ -179,49 +237,60 @@ fn render_history_area(f: &mut Frame, app: &App, area: Rect) {
             Role::System => (PixelAvatar::sys(), "SYSTEM", Color::Yellow),
         };
 
-        let avatar_lines = avatar.render_lines(role_color);
-        let avatar_height = avatar_lines.len() as u16;
+        let avatar_lines = avatar.render_for_density(role_color, density);
+        let avatar_width = avatar_lines.first().map(|l| l.width() as u16).unwrap_or(0);
+
+        // 顶部被滚出可见区域的行数
+        let skip = view_top.saturating_sub(block_top);
+        let y = block_top + skip - view_top;
 
         // 创建消息区域
         let msg_area = Rect {
             x: area.x,
-            y: area.y + current_y,
+            y: area.y + y,
             width: area.width,
-            height: (avatar_height + 1).min(area.height - current_y),
+            height: (height - skip).min(area.height - y),
         };
 
         // 水平分割：头像列 + 内容列
         let h_layout = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([Constraint::Length(16), Constraint::Min(20)])
+            .constraints([Constraint::Length(avatar_width), Constraint::Min(20)])
             .split(msg_area);
 
         let avatar_area = h_layout[0];
         let content_area = h_layout[1];
 
-        // 渲染头像（8 行）
-        let avatar_para = Paragraph::new(avatar_lines);
+        // 渲染头像（舒适模式 8 行，紧凑模式 4 行）
+        let avatar_para = Paragraph::new(avatar_lines).scroll((skip, 0));
         f.render_widget(avatar_para, avatar_area);
 
-        // 渲染内容（角色标签 + 消息）
-        let mut content_lines = vec![Line::from(vec![Span::styled(
+        // 渲染内容（角色标签 + 消息）；紧凑模式下标签与第一行内容同行
+        let label = Span::styled(
             role_label,
             Style::default()
                 .fg(role_color)
                 .add_modifier(Modifier::BOLD),
-        )])];
-
-        for line in msg.content.lines() {
-            content_lines.push(Line::from(line));
-        }
+        );
+        let markdown_theme = MarkdownTheme::new(Color::Rgb(220, 220, 220), role_color);
+        let mut body = render_markdown_to_lines(&msg.content, content_area.width as usize, &markdown_theme).into_iter();
+        let mut content_lines = if density.is_compact() {
+            let mut header = vec![label, Span::raw(" ")];
+            header.extend(body.next().map(|line| line.spans).unwrap_or_default());
+            vec![Line::from(header)]
+        } else {
+            vec![Line::from(vec![label])]
+        };
+        content_lines.extend(body);
 
+        // 内容已按宽度折行，不再交给 Paragraph 折行（会丢失列表缩进）
         let content_para = Paragraph::new(content_lines)
-            .wrap(Wrap { trim: true })
+            .scroll((skip, 0))
             .style(Style::default().fg(Color::Rgb(220, 220, 220)));
 
         f.render_widget(content_para, content_area);
 
-        current_y += avatar_height + 1;
+        block_top = block_bottom;
     }
 }
 
@@ -298,3 +367,67 @@ fn render_input_area(f: &mut Frame, app: &App, area: Rect) {
         f.set_cursor(cursor_x, cursor_y);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::message::{Message, Role};
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn buffer_text(terminal: &Terminal<TestBackend>) -> String {
+        let buffer = terminal.backend().buffer();
+        buffer.content.iter().map(|cell| cell.symbol()).collect()
+    }
+
+    #[test]
+    fn test_history_shows_latest_message_by_default() {
+        let mut app = App::new();
+        app.chat_history.clear();
+        for i in 0..30 {
+            app.chat_history.add_message(Message {
+                role: if i % 2 == 0 { Role::User } else { Role::Assistant },
+                content: format!("message-{:02}", i),
+            });
+        }
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 40)).unwrap();
+        terminal.draw(|f| render_pixel_layout(f, &app)).unwrap();
+        let text = buffer_text(&terminal);
+        assert!(text.contains("message-29"));
+        assert!(!text.contains("message-00"));
+
+        // 向上滚动后能看到更早的消息
+        app.chat_scroll_offset = 9 * 28;
+        terminal.draw(|f| render_pixel_layout(f, &app)).unwrap();
+        let text = buffer_text(&terminal);
+        assert!(text.contains("message-00"));
+        assert!(!text.contains("message-29"));
+    }
+
+    #[test]
+    fn test_compact_density_fits_more_messages() {
+        let mut app = App::new();
+        app.chat_history.clear();
+        for i in 0..30 {
+            app.chat_history.add_message(Message {
+                role: if i % 2 == 0 { Role::User } else { Role::Assistant },
+                content: format!("message-{:02}", i),
+            });
+        }
+
+        let visible_messages = |app: &App| {
+            let mut terminal = Terminal::new(TestBackend::new(80, 40)).unwrap();
+            terminal.draw(|f| render_pixel_layout(f, app)).unwrap();
+            let text = buffer_text(&terminal);
+            (0..30).filter(|i| text.contains(&format!("message-{:02}", i))).count()
+        };
+
+        let comfortable = visible_messages(&app);
+        app.chat_density = ChatDensity::Compact;
+        let compact = visible_messages(&app);
+
+        assert!(comfortable > 0);
+        assert!(compact > comfortable, "compact {} vs comfortable {}", compact, comfortable);
+    }
+}
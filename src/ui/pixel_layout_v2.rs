@@ -12,6 +12,7 @@ use crate::app::App;
 use crate::core::message::Role as AppRole;
 use crate::ui::avatar::PixelData;
 use crate::ui::input_area::render_input_area;
+use crate::ui::markdown::{render_markdown_with_source, MarkdownTheme};
 use std::collections::HashMap;
 
 // ============================================================================
@@ -190,17 +191,39 @@ pub fn render_pixel_layout(f: &mut Frame, app: &App) {
         .split(size);
 
     render_history_with_avatars(f, app, chunks[0], &theme);
-    render_status_bar(f, chunks[1], &theme);
+    render_status_bar(f, app, chunks[1], &theme);
     render_input_area(f, app, chunks[2], &theme);
+    app.model_switcher.render(f, chunks[2], &theme);
+
+    // 不支持 truecolor 的终端：将 RGB 颜色降级到 256/16 色
+    crate::ui::color_support::downsample_buffer(
+        f.buffer_mut(),
+        crate::ui::color_support::ColorDepth::detect(),
+    );
 }
 
 
-/// 渲染历史区域(带头像)
-fn render_history_with_avatars(f: &mut Frame, app: &App, area: Rect, theme: &Theme) {
+/// 历史区域在整个终端中的位置（与 render_pixel_layout 的布局一致）
+pub fn history_area(size: Rect) -> Rect {
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(12), Constraint::Length(1), Constraint::Length(3)])
+        .split(size)[0]
+}
+
+/// 给定终端尺寸下聊天历史最多可以向上滚动的行数
+pub fn max_chat_scroll(app: &App, size: Rect) -> usize {
+    let area = history_area(size);
+    let (lines, _) = build_history_lines(app, area.width, &Theme::new());
+    lines.len().saturating_sub(area.height as usize)
+}
+
+/// 按区域宽度折行后的历史内容，以及每行所属的消息索引
+fn build_history_lines(app: &App, width: u16, theme: &Theme) -> (Vec<Line<'static>>, Vec<usize>) {
     let messages = app.chat_history.get_messages();
 
     // 构建所有消息的行内容
-    let mut all_lines: Vec<Line> = Vec::new();
+    let mut all_lines: Vec<Line<'static>> = Vec::new();
     let mut line_to_msg_map: Vec<usize> = Vec::new(); // 记录每行属于哪个消息
 
     for (msg_idx, msg) in messages.iter().enumerate() {
@@ -217,25 +240,70 @@ fn render_history_with_avatars(f: &mut Frame, app: &App, area: Rect, theme: &The
             AppRole::System => "⚙️  ",
         };
 
-        all_lines.push(Line::from(Span::styled(
+        let mut header = vec![Span::styled(
             avatar_symbol,
             Style::default().fg(role_color).add_modifier(Modifier::BOLD),
-        )));
+        )];
+        if app.chat_history.is_pinned(msg_idx) {
+            header.push(Span::raw("📌 "));
+        }
+        // 紧凑模式不显示时间戳
+        let timestamp = app.chat_history.timestamp(msg_idx).filter(|_| app.chat_density.shows_metadata()).and_then(|ts| {
+            crate::ui::timestamps::format_timestamp(ts, chrono::Utc::now(), app.timestamp_mode)
+        });
+        if let Some(timestamp) = timestamp {
+            header.push(Span::styled(timestamp, Style::default().fg(Color::DarkGray)));
+        }
+        let usage = app.chat_history.metadata(msg_idx).filter(|_| app.chat_density.shows_metadata()).and_then(|metadata| metadata.usage_label());
+        if let Some(usage) = usage {
+            header.push(Span::styled(format!("  {}", usage), Style::default().fg(Color::DarkGray)));
+        }
+
+        // 内容缩进 2 列，右侧留 1 列给滚动条
+        let markdown_theme = MarkdownTheme::new(Color::Reset, role_color);
+        let content_width = (width as usize).saturating_sub(3);
+        let mut content = render_markdown_with_source(&msg.content, content_width, &markdown_theme).into_iter();
+
+        // 紧凑模式下头像与第一行内容同行
+        if app.chat_density.is_compact() {
+            if let Some((_, first)) = content.next() {
+                header.extend(first.spans);
+            }
+        }
+        all_lines.push(Line::from(header));
         line_to_msg_map.push(msg_idx);
 
-        // 添加消息内容
-        for line in msg.content.lines() {
-            all_lines.push(Line::from(format!("  {}", line)));
+        // 添加消息内容（/block 跳转的代码块高亮显示）
+        for (line_idx, line) in content {
+            let highlighted = matches!(
+                &app.highlighted_block,
+                Some((idx, range)) if *idx == msg_idx && range.contains(&line_idx)
+            );
+            let mut spans = vec![Span::raw("  ")];
+            spans.extend(line.spans);
+            let line = Line::from(spans);
+            if highlighted {
+                all_lines.push(line.patch_style(Style::default().bg(theme.border)));
+            } else {
+                all_lines.push(line);
+            }
             line_to_msg_map.push(msg_idx);
         }
 
-        // 消息间空行（除了最后一条消息）
-        if msg_idx < messages.len() - 1 {
+        // 消息间空行（除了最后一条消息；紧凑模式不留空行）
+        if msg_idx < messages.len() - 1 && app.chat_density.message_spacing() > 0 {
             all_lines.push(Line::from(""));
             line_to_msg_map.push(msg_idx);
         }
     }
 
+    (all_lines, line_to_msg_map)
+}
+
+/// 渲染历史区域(带头像)
+fn render_history_with_avatars(f: &mut Frame, app: &App, area: Rect, theme: &Theme) {
+    let (all_lines, _) = build_history_lines(app, area.width, theme);
+
     // 计算滚动偏移量 - 确保显示底部最新消息
     let total_lines = all_lines.len() as u16;
     let visible_lines = area.height;
@@ -254,9 +322,8 @@ fn render_history_with_avatars(f: &mut Frame, app: &App, area: Rect, theme: &The
     let history_block = Block::default()
         .bg(theme.panel_bg);
 
-    // 使用 Paragraph 的 scroll 方法渲染
+    // 使用 Paragraph 的 scroll 方法渲染（内容已按宽度折行，行数与 total_lines 一致）
     let paragraph = Paragraph::new(all_lines.clone())
-        .wrap(Wrap { trim: true })
         .scroll((scroll_offset, 0))
         .block(history_block.clone());
 
@@ -319,16 +386,34 @@ fn render_history(f: &mut Frame, app: &App, area: Rect, theme: &Theme) {
 }
 
 /// 渲染状态栏
-fn render_status_bar(f: &mut Frame, area: Rect, _theme: &Theme) {
+fn render_status_bar(f: &mut Frame, app: &App, area: Rect, _theme: &Theme) {
+    // 停止生成后等待第二次 Ctrl+C 时高亮提示
+    let (exit_hint, exit_color) = if app.quit_pending() {
+        ("PRESS CTRL+C AGAIN to EXIT", Color::Rgb(255, 200, 87))
+    } else {
+        ("CTRL+C to EXIT", Color::Rgb(119, 119, 119))
+    };
+    // 生成回复期间显示输入指示器（帧间隔和开关由 TYPING_ANIMATION_MS 控制）
+    let status = match app.streaming_started_at.filter(|_| app.is_streaming) {
+        Some(started_at) => {
+            let symbol = app.typing_animation.symbol(started_at.elapsed());
+            match app.scan_progress {
+                Some(files_scanned) => format!("{} STATUS: GENERATING · {} files scanned", symbol, files_scanned),
+                None => format!("{} STATUS: GENERATING", symbol),
+            }
+        }
+        None => "STATUS: CONNECTED".to_string(),
+    };
+    let status_width = status.chars().count() as u16;
     let status_line = Line::from(vec![
         Span::styled(
-            "STATUS: CONNECTED",
+            status,
             Style::default().fg(Color::Rgb(119, 119, 119)),
         ),
-        Span::raw(" ".repeat(area.width.saturating_sub(30) as usize)),
+        Span::raw(" ".repeat(area.width.saturating_sub(status_width + exit_hint.len() as u16) as usize)),
         Span::styled(
-            "CTRL+C to EXIT",
-            Style::default().fg(Color::Rgb(119, 119, 119)),
+            exit_hint,
+            Style::default().fg(exit_color),
         ),
     ]);
 
@@ -3,6 +3,7 @@
 /// 这是 Ratatui 高效重构的核心模块
 
 use crate::app::App;
+use crate::ui::markdown::{render_markdown_to_lines, MarkdownTheme};
 use crate::ui::optimized_renderer::{
     get_style_cache, CodeBlockRenderer, DiffRenderer, MessageLineGenerator,
 };
@@ -184,29 +185,12 @@ impl RenderEngine {
             ),
         ]));
 
-        // 消息内容
-        let mut in_code_block = false;
-        for content_line in msg.content.lines() {
-            if content_line.trim_start().starts_with("```") {
-                if !in_code_block {
-                    in_code_block = true;
-                    let code_lang = content_line.trim_start()[3..].to_string();
-                    lines.push(self.code_renderer.generate_start_line(&code_lang));
-                } else {
-                    in_code_block = false;
-                    lines.push(self.code_renderer.generate_end_line());
-                }
-            } else if in_code_block {
-                lines.push(self.code_renderer.generate_code_line(content_line));
-            } else {
-                lines.push(Line::from(vec![
-                    ratatui::text::Span::styled(
-                        "  ".to_string(),
-                        ratatui::style::Style::default().fg(color),
-                    ),
-                    ratatui::text::Span::raw(content_line.to_string()),
-                ]));
-            }
+        // 消息内容（与其他渲染器共用 Markdown 渲染）
+        let markdown_theme = MarkdownTheme::new(ratatui::style::Color::Reset, color);
+        for line in render_markdown_to_lines(&msg.content, (width as usize).saturating_sub(2), &markdown_theme) {
+            let mut spans = vec![ratatui::text::Span::raw("  ")];
+            spans.extend(line.spans);
+            lines.push(Line::from(spans));
         }
 
         // 消息底部
@@ -237,17 +221,15 @@ impl RenderEngine {
             ),
         ]));
 
-        for content_line in content.lines() {
-            lines.push(Line::from(vec![
-                ratatui::text::Span::styled(
-                    "  ".to_string(),
-                    style_cache.ai_content,
-                ),
-                ratatui::text::Span::styled(
-                    content_line.to_string(),
-                    style_cache.streaming_content,
-                ),
-            ]));
+        // 流式内容可能停在代码块或行内代码中间，Markdown 渲染会按已到达的部分显示
+        let markdown_theme = MarkdownTheme::new(
+            style_cache.streaming_content.fg.unwrap_or(ratatui::style::Color::Reset),
+            style_cache.ai_prefix.fg.unwrap_or(ratatui::style::Color::Reset),
+        );
+        for line in render_markdown_to_lines(content, 0, &markdown_theme) {
+            let mut spans = vec![ratatui::text::Span::styled("  ".to_string(), style_cache.ai_content)];
+            spans.extend(line.spans);
+            lines.push(Line::from(spans));
         }
 
         lines.push(Line::from(vec![
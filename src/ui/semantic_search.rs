@@ -0,0 +1,181 @@
+/// @ 提及的语义排序
+/// 对项目文件（路径 + 开头片段）建立向量索引，按与查询的相似度排序；
+/// 向量不可用时由调用方退回到 FileSearchEngine 的模糊匹配
+
+use crate::ai::embeddings::{cosine_similarity, EmbeddingClient};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// 参与索引的文件数上限的默认值（/mentions semantic N 或 MENTION_INDEX_MAX_FILES 可修改）
+pub const DEFAULT_MAX_INDEXED_FILES: usize = 500;
+/// 每个文件参与向量化的开头片段长度（字节）
+const SNIPPET_BYTES: usize = 512;
+/// 每次请求向量化的文本数
+const EMBED_BATCH_SIZE: usize = 64;
+
+/// 语义文件排序器（可克隆，克隆共享同一份向量缓存）
+#[derive(Clone)]
+pub struct SemanticFileRanker {
+    client: Arc<dyn EmbeddingClient>,
+    cache: Arc<Mutex<HashMap<PathBuf, Vec<f32>>>>,
+    unavailable: Arc<AtomicBool>,
+    max_files: usize,
+}
+
+impl SemanticFileRanker {
+    pub fn new(client: Arc<dyn EmbeddingClient>) -> Self {
+        Self {
+            client,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+            unavailable: Arc::new(AtomicBool::new(false)),
+            max_files: DEFAULT_MAX_INDEXED_FILES,
+        }
+    }
+
+    /// 设置参与索引的文件数上限，超出的文件不参与语义排序
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    pub fn max_files(&self) -> usize {
+        self.max_files
+    }
+
+    /// 向量接口是否已确认不可用（请求失败过一次后不再重试）
+    pub fn is_unavailable(&self) -> bool {
+        self.unavailable.load(Ordering::Relaxed)
+    }
+
+    /// 为尚未缓存的文件生成向量
+    pub async fn index(&self, files: &[PathBuf]) -> Result<(), String> {
+        // 持有锁直到索引完成，避免并发请求重复向量化同一批文件
+        let mut cache = self.cache.lock().await;
+        let pending: Vec<&PathBuf> = files
+            .iter()
+            .filter(|path| path.is_file() && !cache.contains_key(*path))
+            .take(self.max_files.saturating_sub(cache.len()))
+            .collect();
+
+        for batch in pending.chunks(EMBED_BATCH_SIZE) {
+            let inputs = batch.iter().map(|path| file_text(path)).collect();
+            let vectors = self.embed(inputs).await?;
+            for (path, vector) in batch.iter().zip(vectors) {
+                cache.insert((*path).clone(), vector);
+            }
+        }
+        Ok(())
+    }
+
+    /// 按与查询的语义相似度对文件排序（只返回已索引的文件）
+    pub async fn rank(&self, query: &str, files: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+        self.index(files).await?;
+        let query_vector = self
+            .embed(vec![query.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| "embeddings 响应为空".to_string())?;
+
+        let cache = self.cache.lock().await;
+        let mut scored: Vec<(f32, &PathBuf)> = files
+            .iter()
+            .filter_map(|path| cache.get(path).map(|v| (cosine_similarity(&query_vector, v), path)))
+            .collect();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        Ok(scored.into_iter().map(|(_, path)| path.clone()).collect())
+    }
+
+    async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+        let expected = inputs.len();
+        match self.client.embed(inputs).await {
+            Ok(vectors) if vectors.len() == expected => Ok(vectors),
+            Ok(vectors) => {
+                self.unavailable.store(true, Ordering::Relaxed);
+                Err(format!("embeddings 数量不匹配: 期望 {}，实际 {}", expected, vectors.len()))
+            }
+            Err(e) => {
+                self.unavailable.store(true, Ordering::Relaxed);
+                Err(e.to_string())
+            }
+        }
+    }
+}
+
+/// 文件的向量化文本：路径 + 开头片段
+fn file_text(path: &PathBuf) -> String {
+    let content = std::fs::read_to_string(path).unwrap_or_default();
+    let mut end = content.len().min(SNIPPET_BYTES);
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n{}", path.display(), &content[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::client::CompletionError;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::AtomicUsize;
+
+    /// 按关键词生成向量的假客户端：[认证, 数据库, 渲染]
+    #[derive(Default)]
+    struct KeywordEmbeddings {
+        embedded_texts: AtomicUsize,
+    }
+
+    impl EmbeddingClient for KeywordEmbeddings {
+        fn embed(&self, inputs: Vec<String>) -> Pin<Box<dyn Future<Output = Result<Vec<Vec<f32>>, CompletionError>> + Send + '_>> {
+            self.embedded_texts.fetch_add(inputs.len(), Ordering::SeqCst);
+            Box::pin(async move {
+                Ok(inputs
+                    .iter()
+                    .map(|text| {
+                        let text = text.to_lowercase();
+                        let has = |words: &[&str]| if words.iter().any(|w| text.contains(w)) { 1.0 } else { 0.0 };
+                        vec![has(&["auth", "login", "password"]), has(&["database", "sql"]), has(&["render", "widget"]), 0.1]
+                    })
+                    .collect())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_semantically_closest_file_ranks_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let files: Vec<PathBuf> = [
+            ("a.rs", "fn draw() { render_widget(); }"),
+            ("b.rs", "fn query() { run_sql(\"select\"); }"),
+            ("c.rs", "fn check_password(user: &str) -> bool { true }"),
+        ]
+        .iter()
+        .map(|(name, content)| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        })
+        .collect();
+
+        let client = Arc::new(KeywordEmbeddings::default());
+        let ranker = SemanticFileRanker::new(client.clone());
+
+        let ranked = ranker.rank("user login", &files).await.unwrap();
+        assert_eq!(ranked[0], files[2]);
+
+        let ranked = ranker.rank("database access", &files).await.unwrap();
+        assert_eq!(ranked[0], files[1]);
+
+        // 文件向量已缓存：两次查询只向量化 3 个文件 + 2 个查询
+        assert_eq!(client.embedded_texts.load(Ordering::SeqCst), 5);
+
+        // 超出上限的文件不参与排序
+        let capped = SemanticFileRanker::new(Arc::new(KeywordEmbeddings::default())).with_max_files(2);
+        let ranked = capped.rank("user login", &files).await.unwrap();
+        assert_eq!(ranked.len(), 2);
+        assert!(!ranked.contains(&files[2]));
+    }
+}
@@ -0,0 +1,103 @@
+/// 启动时的会话选择器
+/// 有保存的会话时，进入主循环前列出最近的会话（标题、消息数、更新时间），上下键选择，Enter 确认；
+/// 列表最后一项是"开始新会话"，Esc / n 直接开始新会话
+
+use crate::core::sessions::SessionChoice;
+use crate::ui::pixel_layout_v2::Theme;
+use crate::ui::types::ChatSession;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem},
+    Frame,
+};
+
+/// 最多列出的会话数
+pub const MAX_LISTED_SESSIONS: usize = 10;
+
+pub struct SessionPicker {
+    sessions: Vec<ChatSession>,
+    /// 等于 sessions.len() 时选中"开始新会话"
+    selected_index: usize,
+}
+
+impl SessionPicker {
+    /// sessions 应按更新时间倒序排列；默认选中最近的会话
+    pub fn new(mut sessions: Vec<ChatSession>) -> Self {
+        sessions.truncate(MAX_LISTED_SESSIONS);
+        Self { sessions, selected_index: 0 }
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected_index = (self.selected_index + 1) % (self.sessions.len() + 1);
+    }
+
+    pub fn select_previous(&mut self) {
+        let count = self.sessions.len() + 1;
+        self.selected_index = (self.selected_index + count - 1) % count;
+    }
+
+    pub fn selected(&self) -> SessionChoice {
+        match self.sessions.get(self.selected_index) {
+            Some(session) => SessionChoice::Resume(session.id.clone()),
+            None => SessionChoice::Fresh,
+        }
+    }
+
+    /// 处理按键，做出选择时返回 Some
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<SessionChoice> {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => self.select_next(),
+            KeyCode::Enter => return Some(self.selected()),
+            KeyCode::Esc | KeyCode::Char('n') => return Some(SessionChoice::Fresh),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn render(&self, f: &mut Frame, theme: &Theme) {
+        let size = f.size();
+        let width = size.width.min(72);
+        let height = (self.sessions.len() as u16 + 3).min(size.height);
+        let area = Rect {
+            x: size.x + (size.width - width) / 2,
+            y: size.y + (size.height - height) / 2,
+            width,
+            height,
+        };
+
+        let highlight = Style::default().fg(theme.bg).bg(theme.accent_ai).add_modifier(Modifier::BOLD);
+        let mut items: Vec<ListItem> = self
+            .sessions
+            .iter()
+            .map(|session| {
+                let updated = session.last_updated.with_timezone(&chrono::Local).format("%m-%d %H:%M");
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{}  ", updated)),
+                    Span::styled(session.title.clone(), Style::default().add_modifier(Modifier::BOLD)),
+                    Span::styled(format!("  ({} 条消息)", session.message_count), Style::default().fg(theme.border)),
+                ]))
+            })
+            .collect();
+        items.push(ListItem::new(Span::styled("+ 开始新会话", Style::default().fg(theme.accent_user))));
+        let items: Vec<ListItem> = items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| if i == self.selected_index { item.style(highlight) } else { item })
+            .collect();
+
+        f.render_widget(
+            List::new(items).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.accent_ai))
+                    .title(" 继续之前的会话？ ↑↓ 选择 · Enter 确认 · Esc 新会话 ")
+                    .style(Style::default().bg(theme.panel_bg)),
+            ),
+            area,
+        );
+    }
+}
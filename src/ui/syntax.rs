@@ -0,0 +1,191 @@
+/// 代码块的简单语法高亮：按 ``` 围栏标注的语言，把关键字、字符串、注释分开着色
+/// 逐行处理，不跟踪跨行的块注释和多行字符串；未知语言不高亮
+
+/// 支持高亮的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Rust,
+    Python,
+    JavaScript,
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn",
+    "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self",
+    "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+];
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+    "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "nonlocal",
+    "not", "or", "pass", "raise", "return", "self", "try", "while", "with", "yield",
+];
+
+const JAVASCRIPT_KEYWORDS: &[&str] = &[
+    "async", "await", "break", "case", "catch", "class", "const", "continue", "default", "delete", "do", "else",
+    "export", "extends", "false", "finally", "for", "from", "function", "if", "import", "in", "instanceof", "let",
+    "new", "null", "of", "return", "static", "super", "switch", "this", "throw", "true", "try", "typeof",
+    "undefined", "var", "void", "while", "yield",
+];
+
+impl Language {
+    /// 按围栏的 info string 识别语言，如 ```rust、```py title="x.py"；未知语言返回 None
+    pub fn from_fence_info(info: &str) -> Option<Self> {
+        let name = info
+            .trim()
+            .split(|c: char| c.is_whitespace() || c == ',' || c == '{')
+            .next()?
+            .to_lowercase();
+        match name.as_str() {
+            "rust" | "rs" => Some(Language::Rust),
+            "python" | "py" | "python3" => Some(Language::Python),
+            "javascript" | "js" | "jsx" | "mjs" | "typescript" | "ts" | "tsx" => Some(Language::JavaScript),
+            _ => None,
+        }
+    }
+
+    fn keywords(&self) -> &'static [&'static str] {
+        match self {
+            Language::Rust => RUST_KEYWORDS,
+            Language::Python => PYTHON_KEYWORDS,
+            Language::JavaScript => JAVASCRIPT_KEYWORDS,
+        }
+    }
+
+    fn line_comment(&self) -> &'static str {
+        match self {
+            Language::Python => "#",
+            Language::Rust | Language::JavaScript => "//",
+        }
+    }
+
+    fn is_quote(&self, c: char) -> bool {
+        match self {
+            Language::Rust => c == '"',
+            Language::Python => c == '"' || c == '\'',
+            Language::JavaScript => c == '"' || c == '\'' || c == '`',
+        }
+    }
+}
+
+/// 代码片段的类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Plain,
+    Keyword,
+    String,
+    Comment,
+}
+
+/// 把一行代码切分为 (文本, 类别)，相邻的同类片段合并
+pub fn tokenize_line(language: Language, line: &str) -> Vec<(String, TokenKind)> {
+    let mut tokens: Vec<(String, TokenKind)> = Vec::new();
+    let mut push = |text: &str, kind: TokenKind| match tokens.last_mut() {
+        Some((last, last_kind)) if *last_kind == kind => last.push_str(text),
+        _ => tokens.push((text.to_string(), kind)),
+    };
+
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        let len = if rest.starts_with(language.line_comment()) {
+            push(rest, TokenKind::Comment);
+            break;
+        } else if let Some(len) = (language == Language::Rust).then(|| rust_char_literal_len(rest)).flatten() {
+            push(&rest[..len], TokenKind::String);
+            len
+        } else if language.is_quote(c) {
+            let len = string_len(rest, c);
+            push(&rest[..len], TokenKind::String);
+            len
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            let word = &rest[..len];
+            let kind = if language.keywords().contains(&word) { TokenKind::Keyword } else { TokenKind::Plain };
+            push(word, kind);
+            len
+        } else {
+            push(&rest[..c.len_utf8()], TokenKind::Plain);
+            c.len_utf8()
+        };
+        rest = &rest[len..];
+    }
+    tokens
+}
+
+/// 从开头的引号到配对的引号（含转义）的字节长度；没有闭合时到行尾
+fn string_len(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == quote => return i + c.len_utf8(),
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// Rust 字符字面量（'a'、'\n'）的字节长度；生命周期（'a）返回 None
+fn rust_char_literal_len(text: &str) -> Option<usize> {
+    let body = text.strip_prefix('\'')?;
+    let mut chars = body.char_indices();
+    let (_, first) = chars.next()?;
+    if first == '\\' {
+        // 转义：'\n'、'\''、'\u{1F600}'
+        chars.next()?;
+        let (i, _) = chars.find(|&(_, c)| c == '\'')?;
+        return Some(i + 2);
+    }
+    let (i, c) = chars.next()?;
+    (c == '\'').then_some(i + 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_tokens(language: Language, line: &str, expected: &[(&str, TokenKind)]) {
+        let expected: Vec<(String, TokenKind)> = expected.iter().map(|&(text, kind)| (text.to_string(), kind)).collect();
+        assert_eq!(tokenize_line(language, line), expected);
+    }
+
+    #[test]
+    fn test_fence_info_selects_language() {
+        assert_eq!(Language::from_fence_info("rust"), Some(Language::Rust));
+        assert_eq!(Language::from_fence_info(" py title=\"main.py\""), Some(Language::Python));
+        assert_eq!(Language::from_fence_info("TS"), Some(Language::JavaScript));
+        assert_eq!(Language::from_fence_info("haskell"), None);
+        assert_eq!(Language::from_fence_info(""), None);
+    }
+
+    #[test]
+    fn test_keywords_strings_and_comments() {
+        use TokenKind::*;
+
+        assert_tokens(
+            Language::Rust,
+            "let s = \"a \\\"b\\\"\"; // note",
+            &[("let", Keyword), (" s = ", Plain), ("\"a \\\"b\\\"\"", String), ("; ", Plain), ("// note", Comment)],
+        );
+        // 生命周期不是字符字面量
+        assert_tokens(
+            Language::Rust,
+            "fn f<'a>(c: char) -> bool { c == '\\n' }",
+            &[("fn", Keyword), (" f<'a>(c: char) -> bool { c == ", Plain), ("'\\n'", String), (" }", Plain)],
+        );
+        assert_tokens(
+            Language::Python,
+            "def greet(): return 'hello'  # done",
+            &[
+                ("def", Keyword), (" greet(): ", Plain), ("return", Keyword), (" ", Plain),
+                ("'hello'", String), ("  ", Plain), ("# done", Comment),
+            ],
+        );
+        assert_tokens(
+            Language::JavaScript,
+            "const url = `${base}/api`;",
+            &[("const", Keyword), (" url = ", Plain), ("`${base}/api`", String), (";", Plain)],
+        );
+    }
+}
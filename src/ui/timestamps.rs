@@ -0,0 +1,67 @@
+/// 聊天消息时间戳显示
+/// 由 /timestamps on|off|relative 控制，各渲染器共用同一个格式化函数
+
+use chrono::{DateTime, Local, Utc};
+
+/// 时间戳显示模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampMode {
+    /// 不显示
+    #[default]
+    Off,
+    /// 绝对时间 [HH:MM:SS]
+    Absolute,
+    /// 相对时间（如 2m ago）
+    Relative,
+}
+
+impl TimestampMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(Self::Off),
+            "on" | "absolute" => Some(Self::Absolute),
+            "relative" => Some(Self::Relative),
+            _ => None,
+        }
+    }
+}
+
+/// 按模式格式化时间戳，Off 时返回 None
+pub fn format_timestamp(timestamp: DateTime<Utc>, now: DateTime<Utc>, mode: TimestampMode) -> Option<String> {
+    match mode {
+        TimestampMode::Off => None,
+        TimestampMode::Absolute => Some(format!("[{}]", timestamp.with_timezone(&Local).format("%H:%M:%S"))),
+        TimestampMode::Relative => {
+            let seconds = (now - timestamp).num_seconds().max(0);
+            let text = match seconds {
+                0..=9 => "just now".to_string(),
+                10..=59 => format!("{}s ago", seconds),
+                60..=3599 => format!("{}m ago", seconds / 60),
+                3600..=86399 => format!("{}h ago", seconds / 3600),
+                _ => format!("{}d ago", seconds / 86400),
+            };
+            Some(format!("[{}]", text))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn test_absolute_vs_relative() {
+        let sent = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let now = sent + Duration::seconds(150);
+
+        let absolute = sent.with_timezone(&Local).format("[%H:%M:%S]").to_string();
+        assert_eq!(format_timestamp(sent, now, TimestampMode::Absolute), Some(absolute));
+        assert_eq!(format_timestamp(sent, now, TimestampMode::Relative).as_deref(), Some("[2m ago]"));
+        assert_eq!(format_timestamp(sent, now, TimestampMode::Off), None);
+
+        let later = sent + Duration::hours(5);
+        assert_eq!(format_timestamp(sent, later, TimestampMode::Relative).as_deref(), Some("[5h ago]"));
+        assert_eq!(format_timestamp(sent, sent, TimestampMode::Relative).as_deref(), Some("[just now]"));
+    }
+}
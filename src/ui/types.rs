@@ -157,17 +157,52 @@ pub enum MessageStatus {
 
 #[derive(Clone, Debug)]
 pub struct MessageMetadata {
+    /// 回复的 tokens（completion）
     pub tokens: Option<u32>,
+    /// 请求的 tokens（prompt）
+    pub prompt_tokens: Option<u32>,
     pub model: Option<String>,
     pub processing_time: Option<std::time::Duration>,
+    /// 提供商返回的结束原因，"length" 表示回复被截断
+    pub finish_reason: Option<String>,
+    /// 用量是估算值（提供商没有返回 usage）
+    pub estimated_usage: bool,
 }
 
 impl Default for MessageMetadata {
     fn default() -> Self {
         Self {
             tokens: None,
+            prompt_tokens: None,
             model: None,
             processing_time: None,
+            finish_reason: None,
+            estimated_usage: false,
+        }
+    }
+}
+
+impl MessageMetadata {
+    /// 回复标题行显示的用量，如 "12 → 256 tokens"；估算值前加 "~"，被截断时附加提示
+    pub fn usage_label(&self) -> Option<String> {
+        let (prompt, completion) = (self.prompt_tokens?, self.tokens?);
+        let approx = if self.estimated_usage { "~" } else { "" };
+        let mut label = format!("{}{} → {} tokens", approx, prompt, completion);
+        if self.finish_reason.as_deref() == Some("length") {
+            label.push_str(" · ⚠ 回复已截断");
+        }
+        Some(label)
+    }
+}
+
+impl From<&crate::ai::client::CompletionMetadata> for MessageMetadata {
+    fn from(completion: &crate::ai::client::CompletionMetadata) -> Self {
+        Self {
+            tokens: Some(completion.completion_tokens as u32),
+            prompt_tokens: Some(completion.prompt_tokens as u32),
+            finish_reason: completion.finish_reason.clone(),
+            estimated_usage: completion.estimated,
+            ..Self::default()
         }
     }
 }
@@ -0,0 +1,97 @@
+/// 生成回复时的输入指示器动画
+/// TYPING_ANIMATION_MS 设置帧间隔（毫秒），`0` 或 `off` 关闭动画，改为显示静态指示器
+
+use std::time::Duration;
+
+/// 默认的动画帧间隔
+pub const DEFAULT_TYPING_FRAME_INTERVAL_MS: u64 = 100;
+
+const ANIMATION_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// 关闭动画时显示的静态指示器
+const STATIC_FRAME: &str = "…";
+
+/// 输入指示器动画设置
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TypingAnimation {
+    pub frame_interval_ms: u64,
+    pub enabled: bool,
+}
+
+impl Default for TypingAnimation {
+    fn default() -> Self {
+        Self { frame_interval_ms: DEFAULT_TYPING_FRAME_INTERVAL_MS, enabled: true }
+    }
+}
+
+impl TypingAnimation {
+    pub fn disabled() -> Self {
+        Self { enabled: false, ..Self::default() }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "off" | "0" => Some(Self::disabled()),
+            value => value
+                .parse::<u64>()
+                .ok()
+                .map(|ms| Self { frame_interval_ms: ms, enabled: true }),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        std::env::var("TYPING_ANIMATION_MS")
+            .ok()
+            .and_then(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    /// 开始生成 elapsed 之后的帧序号；每满一个帧间隔前进一帧，关闭动画时始终为 0
+    pub fn frame_index(&self, elapsed: Duration) -> usize {
+        if !self.enabled {
+            return 0;
+        }
+        (elapsed.as_millis() / self.frame_interval_ms as u128) as usize
+    }
+
+    /// 开始生成 elapsed 之后应显示的指示符
+    pub fn symbol(&self, elapsed: Duration) -> &'static str {
+        if !self.enabled {
+            return STATIC_FRAME;
+        }
+        ANIMATION_FRAMES[self.frame_index(elapsed) % ANIMATION_FRAMES.len()]
+    }
+
+    /// 主循环的重绘间隔：动画帧间隔比默认重绘间隔更短时按帧间隔重绘
+    pub fn redraw_interval(&self, default: Duration) -> Duration {
+        if self.enabled {
+            default.min(Duration::from_millis(self.frame_interval_ms))
+        } else {
+            default
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_respects_interval_and_disable() {
+        let animation = TypingAnimation { frame_interval_ms: 250, enabled: true };
+        assert_eq!(animation.frame_index(Duration::from_millis(100)), 0);
+        assert_eq!(animation.symbol(Duration::from_millis(100)), "⠋");
+        assert_eq!(animation.frame_index(Duration::from_millis(250)), 1);
+        assert_eq!(animation.symbol(Duration::from_millis(250)), "⠙");
+
+        let disabled = TypingAnimation::parse("off").unwrap();
+        assert_eq!(disabled.frame_index(Duration::from_secs(10)), 0);
+        assert_eq!(disabled.symbol(Duration::from_secs(10)), "…");
+        assert_eq!(disabled.redraw_interval(Duration::from_millis(100)), Duration::from_millis(100));
+
+        assert_eq!(TypingAnimation::parse("40"), Some(TypingAnimation { frame_interval_ms: 40, enabled: true }));
+        assert_eq!(TypingAnimation::parse("40").unwrap().redraw_interval(Duration::from_millis(100)), Duration::from_millis(40));
+        assert_eq!(TypingAnimation::parse("0"), Some(TypingAnimation::disabled()));
+        assert_eq!(TypingAnimation::parse("fast"), None);
+    }
+}